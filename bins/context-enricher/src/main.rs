@@ -1,10 +1,10 @@
 use anyhow::Result;
 use metadata_svc::models::ColumnMetadata;
-use metadata_svc::{Annotation, MetadataService, Schema, TableMetadata};
+use metadata_svc::{Annotation, GlossaryTerm, MetadataService, Schema, TableMetadata};
 use rag_engine::VectorIndex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableContext {
@@ -15,6 +15,7 @@ pub struct TableContext {
     pub primary_key: Option<Vec<String>>,
     pub annotations: Vec<Annotation>,
     pub lineage_dependencies: Vec<String>,
+    pub glossary: Vec<GlossaryTerm>,
     pub sample_values: Vec<HashMap<String, serde_json::Value>>,
 }
 
@@ -24,6 +25,7 @@ pub struct ColumnInfo {
     pub data_type: String,
     pub nullable: bool,
     pub comment: Option<String>,
+    pub annotations: Vec<Annotation>,
     pub sample_values: Vec<serde_json::Value>,
 }
 
@@ -63,6 +65,7 @@ impl ContextEnricher {
                     data_type: c.data_type.clone(),
                     nullable: c.nullable,
                     comment: c.comment.clone(),
+                    annotations: vec![],
                 })
                 .collect();
 
@@ -94,27 +97,32 @@ impl ContextEnricher {
                 description: None,
             };
 
-            let _ = self.metadata.add_table("main", table_meta).await;
-            tables.push(TableMetadata {
-                name: table_name,
-                schema_name: Some("public".to_string()),
-                columns: vec![],
-                primary_key: None,
-                annotations: vec![],
-                description: None,
-            });
+            tables.push(table_meta);
         }
 
-        let full_schema = Schema {
-            id: Some(1),
-            name: "main".to_string(),
-            source: "warehouse".to_string(),
-            tables,
-            created_at: None,
-            updated_at: None,
-        };
+        let synced = self
+            .metadata
+            .refresh_schema(Schema {
+                id: None,
+                name: "main".to_string(),
+                source: "warehouse".to_string(),
+                tables,
+                created_at: None,
+                updated_at: None,
+            })
+            .await?;
+        if let Some(diff) = &synced.diff {
+            warn!(
+                "Schema drift since v{}: {} added, {} removed, {} renamed, {} changed tables",
+                diff.from_version,
+                diff.added_tables.len(),
+                diff.removed_tables.len(),
+                diff.renamed_tables.len(),
+                diff.changed_tables.len()
+            );
+        }
 
-        Ok(full_schema)
+        Ok(synced.snapshot.schema)
     }
 
     pub async fn generate_table_context(
@@ -131,6 +139,15 @@ impl ContextEnricher {
             .await
             .unwrap_or_default();
 
+        let glossary: Vec<GlossaryTerm> = self
+            .metadata
+            .list_glossary_terms()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|term| term.references_table(&table.name))
+            .collect();
+
         let description = self.generate_table_description(&table);
 
         let columns: Vec<ColumnInfo> = table
@@ -141,6 +158,7 @@ impl ContextEnricher {
                 data_type: c.data_type.clone(),
                 nullable: c.nullable,
                 comment: c.comment.clone(),
+                annotations: c.annotations.clone(),
                 sample_values: vec![],
             })
             .collect();
@@ -153,6 +171,7 @@ impl ContextEnricher {
             primary_key: table.primary_key,
             annotations: table.annotations,
             lineage_dependencies: lineage_deps,
+            glossary,
             sample_values: vec![],
         })
     }
@@ -177,6 +196,9 @@ impl ContextEnricher {
                     "\n  - {} ({}) {}",
                     col.name, col.data_type, comment
                 ));
+                for ann in &col.annotations {
+                    desc.push_str(&format!("\n      {}: {}", ann.key, ann.value));
+                }
             }
         }
 
@@ -220,6 +242,15 @@ impl ContextEnricher {
                     blob.push_str(&format!("Schema: {}\n", schema));
                 }
                 blob.push_str(&format!("{}\n", ctx.description));
+                if !ctx.glossary.is_empty() {
+                    blob.push_str("Glossary:\n");
+                    for term in &ctx.glossary {
+                        blob.push_str(&format!("  {}: {}\n", term.term, term.definition));
+                        if let Some(ref sql) = term.sql {
+                            blob.push_str(&format!("    SQL: {}\n", sql));
+                        }
+                    }
+                }
                 blob
             })
             .collect::<Vec<_>>()
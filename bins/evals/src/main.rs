@@ -1,11 +1,17 @@
+use metadata_svc::SchemaDiff;
+use rag_engine::{Embedder, HashingEmbedder, OpenAIEmbedder, RAGService, SourceType};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use tracing::info;
+use std::sync::Arc;
+use tracing::{info, warn};
+use warehouse_conn::{transpile, Backend, TranspileIssue};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EvalDataset {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub dialect: Option<String>,
     pub test_cases: Vec<TestCase>,
 }
 
@@ -54,6 +60,34 @@ pub struct EvalSummary {
     pub results: Vec<EvalResult>,
 }
 
+/// Documents to index and questions labelled with the ids that should be retrieved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalDataset {
+    pub name: String,
+    pub documents: Vec<RetrievalDocument>,
+    pub queries: Vec<RetrievalQuery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalDocument {
+    pub id: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalQuery {
+    pub question: String,
+    pub relevant: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalMetrics {
+    pub mode: String,
+    pub k: usize,
+    pub recall_at_k: f32,
+    pub mrr: f32,
+}
+
 pub fn load_dataset(path: &str) -> Result<EvalDataset, String> {
     let content =
         std::fs::read_to_string(path).map_err(|e| format!("Failed to read dataset: {}", e))?;
@@ -61,6 +95,140 @@ pub fn load_dataset(path: &str) -> Result<EvalDataset, String> {
     serde_json::from_str(&content).map_err(|e| format!("Failed to parse dataset: {}", e))
 }
 
+pub type DatasetIssues = Vec<(String, Vec<TranspileIssue>)>;
+
+pub fn transpile_dataset(
+    dataset: &EvalDataset,
+    target: Backend,
+) -> Result<(EvalDataset, DatasetIssues), String> {
+    let source: Backend = dataset
+        .dialect
+        .as_deref()
+        .unwrap_or("postgres")
+        .parse()
+        .map_err(|e: warehouse_conn::Error| e.to_string())?;
+
+    let mut transpiled = dataset.clone();
+    let mut issues = Vec::new();
+
+    for test_case in &mut transpiled.test_cases {
+        let result = transpile(&test_case.golden_sql, source, target)
+            .map_err(|e| format!("Failed to transpile {}: {}", test_case.id, e))?;
+        if !result.is_lossless() {
+            issues.push((test_case.id.clone(), result.issues.clone()));
+        }
+        test_case.golden_sql = result.sql;
+    }
+
+    transpiled.dialect = Some(target.name().to_string());
+    Ok((transpiled, issues))
+}
+
+/// Test cases whose golden SQL refers to tables or columns dropped by `diffs`.
+pub fn stale_test_cases(dataset: &EvalDataset, diffs: &[SchemaDiff]) -> Vec<(String, Vec<String>)> {
+    dataset
+        .test_cases
+        .iter()
+        .filter_map(|test_case| {
+            let references: Vec<String> = diffs
+                .iter()
+                .flat_map(|diff| diff.stale_references(&test_case.golden_sql))
+                .collect();
+            (!references.is_empty()).then(|| (test_case.id.clone(), references))
+        })
+        .collect()
+}
+
+pub fn load_schema_diffs(path: &str) -> Result<Vec<SchemaDiff>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read schema diff: {}", e))?;
+    serde_json::from_str::<Vec<SchemaDiff>>(&content)
+        .or_else(|_| serde_json::from_str::<SchemaDiff>(&content).map(|diff| vec![diff]))
+        .map_err(|e| format!("Failed to parse schema diff: {}", e))
+}
+
+pub fn load_retrieval_dataset(path: &str) -> Result<RetrievalDataset, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read retrieval dataset: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse retrieval dataset: {}", e))
+}
+
+/// The embedding model from `QUERYSMITH_EMBEDDING_URL`, `_MODEL` and
+/// `_DIMENSION`, or the offline hashing embedder when no URL is set.
+pub fn embedder_from_env() -> Result<Arc<dyn Embedder>, String> {
+    let Ok(url) = std::env::var("QUERYSMITH_EMBEDDING_URL") else {
+        return Ok(Arc::new(HashingEmbedder::new(256)));
+    };
+    let model = std::env::var("QUERYSMITH_EMBEDDING_MODEL")
+        .unwrap_or_else(|_| "text-embedding-3-small".to_string());
+    let dimension = std::env::var("QUERYSMITH_EMBEDDING_DIMENSION")
+        .unwrap_or_else(|_| "1536".to_string())
+        .parse()
+        .map_err(|e| format!("Invalid QUERYSMITH_EMBEDDING_DIMENSION: {}", e))?;
+    let mut embedder = OpenAIEmbedder::new(&url, &model, dimension);
+    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+        embedder = embedder.with_api_key(&api_key);
+    }
+    Ok(Arc::new(embedder))
+}
+
+/// Recall@k and mean reciprocal rank of the labelled documents, using
+/// `embedder` and the given fusion weights.
+pub async fn evaluate_retrieval(
+    dataset: &RetrievalDataset,
+    embedder: Arc<dyn Embedder>,
+    mode: &str,
+    vector_weight: f32,
+    lexical_weight: f32,
+    k: usize,
+) -> Result<RetrievalMetrics, rag_engine::Error> {
+    let mut rag = RAGService::with_embedder(embedder.clone())
+        .with_fusion_weights(vector_weight, lexical_weight);
+    let documents = dataset
+        .documents
+        .iter()
+        .map(|doc| (doc.id.clone(), doc.content.clone(), serde_json::json!({})))
+        .collect();
+    rag.index_texts(SourceType::Documentation, documents)
+        .await?;
+
+    let mut recall = 0.0;
+    let mut reciprocal_rank = 0.0;
+    for query in &dataset.queries {
+        let vector = embedder.embed(&query.question).await?;
+        let result = rag.retrieve(&query.question, &vector, k, None).await;
+        let ids: Vec<&str> = result.chunks.iter().map(|c| c.id.as_str()).collect();
+
+        let found = query
+            .relevant
+            .iter()
+            .filter(|id| ids.contains(&id.as_str()))
+            .count();
+        recall += found as f32 / query.relevant.len().max(1) as f32;
+        if let Some(rank) = ids
+            .iter()
+            .position(|id| query.relevant.iter().any(|r| r == id))
+        {
+            reciprocal_rank += 1.0 / (rank + 1) as f32;
+        }
+    }
+
+    let queries = dataset.queries.len().max(1) as f32;
+    Ok(RetrievalMetrics {
+        mode: mode.to_string(),
+        k,
+        recall_at_k: recall / queries,
+        mrr: reciprocal_rank / queries,
+    })
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
 pub fn compare_results(golden: &ExecResult, actual: &ExecResult) -> f32 {
     if golden.row_count == 0 && actual.row_count == 0 {
         return 1.0;
@@ -149,14 +317,73 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("QuerySmith Evaluation Harness");
     println!("Usage: evals --dataset <path> --sql <sql-to-test>");
     println!("Or: evals --run-all --dataset <path>");
+    println!("Or: evals --dataset <path> --target <postgres|sqlite|duckdb|mysql>");
+    println!("Or: evals --dataset <path> --schema-diff <diff.json>");
+    println!("Or: evals --retrieval <retrieval.json> [--k <n>]  (e.g. datasets/retrieval.json)");
 
     let args: Vec<String> = std::env::args().collect();
 
+    if let (Some(path), Some(target)) =
+        (arg_value(&args, "--dataset"), arg_value(&args, "--target"))
+    {
+        let dataset = load_dataset(&path)?;
+        let target: Backend = target.parse()?;
+        let (transpiled, issues) = transpile_dataset(&dataset, target)?;
+        for (id, issues) in &issues {
+            for issue in issues {
+                warn!("{}: {} ({})", id, issue.message, issue.construct);
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&transpiled)?);
+        return Ok(());
+    }
+
+    if let (Some(path), Some(diff_path)) = (
+        arg_value(&args, "--dataset"),
+        arg_value(&args, "--schema-diff"),
+    ) {
+        let dataset = load_dataset(&path)?;
+        let diffs = load_schema_diffs(&diff_path)?;
+        let stale = stale_test_cases(&dataset, &diffs);
+        for (id, references) in &stale {
+            warn!("{}: references dropped {}", id, references.join(", "));
+        }
+        println!(
+            "{} of {} test cases reference dropped schema objects",
+            stale.len(),
+            dataset.test_cases.len()
+        );
+        return Ok(());
+    }
+
+    if let Some(path) = arg_value(&args, "--retrieval") {
+        let dataset = load_retrieval_dataset(&path)?;
+        let k = arg_value(&args, "--k")
+            .and_then(|k| k.parse().ok())
+            .unwrap_or(5);
+        let embedder = embedder_from_env()?;
+        println!("=== Retrieval: {} ({}) ===", dataset.name, embedder.model());
+        for (mode, vector, lexical) in [
+            ("vector", 1.0, 0.0),
+            ("bm25", 0.0, 1.0),
+            ("hybrid", 1.0, 1.0),
+        ] {
+            let metrics =
+                evaluate_retrieval(&dataset, embedder.clone(), mode, vector, lexical, k).await?;
+            println!(
+                "{:<8} recall@{}: {:.3}  MRR: {:.3}",
+                metrics.mode, metrics.k, metrics.recall_at_k, metrics.mrr
+            );
+        }
+        return Ok(());
+    }
+
     if args.len() < 2 {
         println!("\nExample dataset format:");
         let example = EvalDataset {
             name: "example".to_string(),
             description: "Example dataset".to_string(),
+            dialect: Some("postgres".to_string()),
             test_cases: vec![TestCase {
                 id: "test_1".to_string(),
                 question: "How many users are there?".to_string(),
@@ -171,3 +398,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retrieval_dataset() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/datasets/retrieval.json");
+        let dataset = load_retrieval_dataset(path).unwrap();
+        let embedder: Arc<dyn Embedder> = Arc::new(HashingEmbedder::new(256));
+
+        let bm25 = evaluate_retrieval(&dataset, embedder.clone(), "bm25", 0.0, 1.0, 5)
+            .await
+            .unwrap();
+        let hybrid = evaluate_retrieval(&dataset, embedder, "hybrid", 1.0, 1.0, 5)
+            .await
+            .unwrap();
+        assert!(bm25.recall_at_k > 0.5);
+        assert!(hybrid.recall_at_k > 0.5);
+        assert!(hybrid.mrr > 0.0 && hybrid.mrr <= 1.0);
+    }
+}
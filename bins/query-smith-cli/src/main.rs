@@ -1,3 +1,4 @@
+use agent_core::Tool;
 use clap::{Parser, Subcommand};
 use std::io::{self, Write};
 use std::sync::Arc;
@@ -10,9 +11,37 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    #[arg(short, long, default_value = "postgres://localhost/querysmith", env = "DATABASE_URL")]
+    #[arg(
+        short,
+        long,
+        default_value = "postgres://localhost/querysmith",
+        env = "DATABASE_URL"
+    )]
     database: String,
 
+    #[arg(long, env = "QUERYSMITH_WAREHOUSES")]
+    warehouses: Option<String>,
+
+    #[arg(long, env = "QUERYSMITH_POLICY_FILE")]
+    policy: Option<String>,
+
+    #[arg(long, env = "QUERYSMITH_PII_CONFIG")]
+    pii: Option<String>,
+
+    #[arg(long, env = "QUERYSMITH_METADATA_URL")]
+    metadata: Option<String>,
+
+    /// Semantic model YAML with the metrics and dimensions for `query_metric`
+    #[arg(long, env = "QUERYSMITH_SEMANTIC_MODEL")]
+    semantic_model: Option<String>,
+
+    #[arg(short, long, default_value = "cli", env = "QUERYSMITH_USER")]
+    user: String,
+
+    /// Groups of `--user`, matched by group policies
+    #[arg(long, env = "QUERYSMITH_GROUPS", value_delimiter = ',')]
+    groups: Vec<String>,
+
     #[arg(short, long, default_value = "minimax-m2.5", env = "LLM_MODEL")]
     model: String,
 
@@ -35,19 +64,42 @@ enum Commands {
     Script {
         #[arg(short, long)]
         file: String,
+
+        #[arg(short, long)]
+        connection: Option<String>,
+
+        #[arg(long, help = "Show values hidden by PII masking, if authorized")]
+        unmasked: bool,
     },
 
     #[command(about = "List available tables")]
-    Tables,
+    Tables {
+        #[arg(short, long)]
+        connection: Option<String>,
+    },
 
     #[command(about = "Show table schema")]
-    Schema { table: String },
+    Schema {
+        table: String,
+
+        #[arg(short, long)]
+        connection: Option<String>,
+    },
 
     #[command(about = "Show memory context")]
     Memory {
         #[arg(short, long, default_value = "10")]
         limit: usize,
     },
+
+    #[command(about = "Serve the metadata REST API")]
+    MetadataApi {
+        #[arg(long, default_value = "127.0.0.1:3001")]
+        addr: std::net::SocketAddr,
+        /// Bearer token required for writes; without it the API is read-only
+        #[arg(long, env = "QUERYSMITH_API_TOKEN")]
+        token: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -56,37 +108,119 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
-    let agent = Arc::new(agent_core::AgentRuntime::new(
-        cli.model,
-        agent_core::ToolRegistry::new(),
-    ));
+    let policy = match &cli.policy {
+        Some(path) => Some(Arc::new(warehouse_conn::PolicyEngine::from_file(path)?)),
+        None => None,
+    };
+    let identity = warehouse_conn::Identity::new(&cli.user).with_groups(cli.groups.clone());
+
+    let metadata = Arc::new(match &cli.metadata {
+        Some(url) => metadata_svc::MetadataService::connect(url).await?,
+        None => metadata_svc::MetadataService::new(),
+    });
+    if let Some(path) = &cli.semantic_model {
+        let model = metadata_svc::SemanticModel::from_yaml(&std::fs::read_to_string(path)?)?;
+        metadata.set_semantic_model(model).await?;
+    }
+    let masker = Arc::new(load_pii_masker(cli.pii.as_deref(), &metadata).await?);
+
+    let mut tools = agent_core::ToolRegistry::new();
+    let mut run_sql = None;
+    let warehouses = load_warehouses(&cli.database, cli.warehouses.as_deref()).map(Arc::new);
+    let dialect = match &warehouses {
+        Ok(warehouses) => {
+            let mut tool =
+                agent_core::RunSqlTool::new(warehouses.clone()).with_pii_masker(masker.clone());
+            if let Some(policy) = &policy {
+                tool = tool.with_policy(policy.clone());
+            }
+            tools.register(tool.clone());
+            tools.register(agent_core::QueryMetricTool::new(
+                metadata.clone(),
+                warehouses.clone(),
+            ));
+            run_sql = Some(tool);
+            warehouses.get(None).map_err(anyhow::Error::from)
+        }
+        Err(e) => Err(anyhow::anyhow!("{}", e)),
+    };
+    let mut agent = agent_core::AgentRuntime::new(cli.model, tools).with_identity(identity.clone());
+    let mut backend = None;
+    match dialect {
+        Ok(warehouse) => {
+            backend = Some(warehouse.dialect().backend());
+            agent = agent.with_dialect(warehouse.dialect());
+        }
+        Err(e) => tracing::warn!("No default warehouse, SQL dialect unknown: {}", e),
+    }
+    let agent = Arc::new(agent);
+    let secured = |warehouse: Arc<dyn warehouse_conn::Warehouse>| match &policy {
+        Some(policy) => Arc::new(policy.wrap(warehouse, identity.clone())),
+        None => warehouse,
+    };
 
     let memory = Arc::new(memory_svc::MemoryService::new());
+    flag_stale_memories(&metadata, memory.clone());
 
     match cli.command {
         Commands::Repl => {
-            run_repl(agent, memory).await?;
+            run_repl(agent, memory, backend).await?;
         }
         Commands::Query { question } => {
             println!("Question: {}", question);
             println!("Response: (Connect to LLM for actual response)");
             println!("This is a placeholder - integrate with LLM client for real responses.");
         }
-        Commands::Script { file } => {
+        Commands::Script {
+            file,
+            connection,
+            unmasked,
+        } => {
+            let Some(run_sql) = run_sql else {
+                return Err(warehouses
+                    .err()
+                    .unwrap_or_else(|| anyhow::anyhow!("no warehouse")));
+            };
+            let context = agent_core::ToolContext::for_identity(identity.clone());
             println!("Running script: {}", file);
             let content = std::fs::read_to_string(&file)?;
             for line in content.lines() {
                 let line = line.trim();
-                if !line.is_empty() && !line.starts_with("--") {
-                    println!("Executing: {}", line);
+                if line.is_empty() || line.starts_with("--") {
+                    continue;
                 }
+                println!("Executing: {}", line);
+                let mut params = std::collections::HashMap::new();
+                params.insert("sql".to_string(), serde_json::json!(line));
+                if let Some(connection) = &connection {
+                    params.insert("database".to_string(), serde_json::json!(connection));
+                }
+                let result = run_sql
+                    .execute_with_context(params, &context)
+                    .await
+                    .map_err(anyhow::Error::msg)?;
+                print_result(&result, unmasked);
             }
         }
-        Commands::Tables => {
-            println!("Available tables: (Connect to database to list)");
+        Commands::Tables { connection } => {
+            let warehouse = secured(warehouses?.get(connection.as_deref())?);
+            let tables = warehouse.list_tables().await?;
+            println!("Available tables ({}):", tables.len());
+            for table in tables {
+                println!("  - {}", table);
+            }
         }
-        Commands::Schema { table } => {
-            println!("Schema for {}: (Connect to database to fetch)", table);
+        Commands::Schema { table, connection } => {
+            let warehouse = secured(warehouses?.get(connection.as_deref())?);
+            let schema = warehouse.get_schema(&table).await?;
+            println!("Schema for {}:", schema.name);
+            for column in &schema.columns {
+                let nullable = if column.nullable { "NULL" } else { "NOT NULL" };
+                println!("  - {} {} {}", column.name, column.data_type, nullable);
+            }
+            if let Some(pk) = &schema.primary_key {
+                println!("Primary Key: {}", pk.join(", "));
+            }
         }
         Commands::Memory { limit } => {
             let all_memory = memory.get_all().await?;
@@ -100,14 +234,118 @@ async fn main() -> anyhow::Result<()> {
                 );
             }
         }
+        Commands::MetadataApi { addr, token } => {
+            metadata_svc::api::serve(metadata, addr, token).await?;
+        }
     }
 
     Ok(())
 }
 
+/// Flags memories that mention tables or columns dropped by a schema sync.
+fn flag_stale_memories(
+    metadata: &metadata_svc::MetadataService,
+    memory: Arc<memory_svc::MemoryService>,
+) {
+    metadata.on_drift(move |diff| {
+        let memory = memory.clone();
+        async move {
+            let flagged = memory.flag_stale(|text| diff.stale_references(text)).await;
+            if !flagged.is_empty() {
+                tracing::info!(
+                    "Flagged {} memories as stale after drift in '{}'",
+                    flagged.len(),
+                    diff.schema_name
+                );
+            }
+        }
+    });
+}
+
+fn load_warehouses(
+    database_url: &str,
+    config_path: Option<&str>,
+) -> anyhow::Result<warehouse_conn::WarehouseRegistry> {
+    let config = match config_path {
+        Some(path) => warehouse_conn::RegistryConfig::from_file(path)?,
+        None => warehouse_conn::RegistryConfig::single("default", database_url),
+    };
+    Ok(warehouse_conn::WarehouseRegistry::from_config(&config)?)
+}
+
+/// The PII masker from `QUERYSMITH_PII_CONFIG`, also masking every column
+/// tagged as PII in the metadata catalog.
+async fn load_pii_masker(
+    config_path: Option<&str>,
+    metadata: &metadata_svc::MetadataService,
+) -> anyhow::Result<warehouse_conn::PiiMasker> {
+    let config = match config_path {
+        Some(path) => warehouse_conn::PiiConfig::from_yaml(&std::fs::read_to_string(path)?)?,
+        None => warehouse_conn::PiiConfig::default(),
+    };
+    Ok(warehouse_conn::PiiMasker::new(config).with_pii_columns(metadata.pii_columns().await))
+}
+
+fn print_result(result: &agent_core::ToolResult, unmasked: bool) {
+    if !result.success {
+        println!(
+            "Error: {}",
+            result.error.as_deref().unwrap_or("query failed")
+        );
+        return;
+    }
+    let Some(masked) = &result.data else {
+        return;
+    };
+    let data = match (&result.unmasked, unmasked) {
+        (Some(raw), true) => raw,
+        _ => masked,
+    };
+
+    if let Some(columns) = data["columns"].as_array() {
+        let header: Vec<String> = columns
+            .iter()
+            .map(|c| c.as_str().unwrap_or("").to_string())
+            .collect();
+        println!("{}", header.join("\t"));
+    }
+    for row in data["rows"].as_array().into_iter().flatten() {
+        let values: Vec<String> = row
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|value| match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+        println!("{}", values.join("\t"));
+    }
+
+    if let Some(findings) = masked["pii_masked"].as_array() {
+        let columns: Vec<&str> = findings
+            .iter()
+            .filter_map(|f| f["column"].as_str())
+            .collect();
+        match (&result.unmasked, unmasked) {
+            (Some(_), true) => println!("(unmasked: showing raw values of {})", columns.join(", ")),
+            (Some(_), false) => println!(
+                "(PII masked in {}; rerun with --unmasked to show raw values)",
+                columns.join(", ")
+            ),
+            (None, true) => println!(
+                "(PII masked in {}; not authorized to view raw values)",
+                columns.join(", ")
+            ),
+            (None, false) => println!("(PII masked in {})", columns.join(", ")),
+        }
+    }
+}
+
 async fn run_repl(
     _agent: Arc<agent_core::AgentRuntime>,
     memory: Arc<memory_svc::MemoryService>,
+    backend: Option<warehouse_conn::Backend>,
 ) -> anyhow::Result<()> {
     println!("QuerySmith REPL (v0.1.0)");
     println!("Type 'help' for commands, 'exit' to quit\n");
@@ -159,9 +397,22 @@ async fn run_repl(
             }
             _ => {
                 let user_memory_scope = memory_svc::MemoryScope::user("cli");
-                let context = memory
-                    .inject_into_prompt(input, Some(user_memory_scope.clone()))
-                    .await?;
+                let context = match backend {
+                    Some(backend) => {
+                        memory
+                            .inject_into_prompt_for_dialect(
+                                input,
+                                Some(user_memory_scope.clone()),
+                                backend,
+                            )
+                            .await?
+                    }
+                    None => {
+                        memory
+                            .inject_into_prompt(input, Some(user_memory_scope.clone()))
+                            .await?
+                    }
+                };
 
                 println!("Processing: {}", input);
                 if !context.is_empty() {
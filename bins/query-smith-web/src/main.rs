@@ -3,7 +3,7 @@ use axum::{
         ws::{Message, WebSocket, WebSocketUpgrade},
         State,
     },
-    http::{header, Method},
+    http::{header, Method, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
@@ -20,12 +20,17 @@ use tower_http::cors::{Any, CorsLayer};
 struct AppState {
     agent: Arc<agent_core::AgentRuntime>,
     memory: Arc<memory_svc::MemoryService>,
+    warehouses: Arc<warehouse_conn::WarehouseRegistry>,
+    cache: Arc<warehouse_conn::ResultCache>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ChatRequest {
     message: String,
     user_id: Option<String>,
+    /// Groups of `user_id`, as asserted by the fronting auth layer.
+    #[serde(default)]
+    groups: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,10 +47,16 @@ struct ApiResponse<T> {
 }
 
 async fn chat_handler(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Json(payload): Json<ChatRequest>,
 ) -> impl IntoResponse {
-    let _user_id = payload.user_id.unwrap_or_else(|| "anonymous".to_string());
+    let identity = match &payload.user_id {
+        Some(user_id) => warehouse_conn::Identity::new(user_id).with_groups(payload.groups),
+        None => warehouse_conn::Identity::anonymous(),
+    };
+    let mut orchestrator =
+        agent_core::AgentOrchestrator::new(state.agent.clone()).with_identity(identity);
+    orchestrator.ask(payload.message).await;
 
     Json(ApiResponse {
         success: true,
@@ -75,19 +86,352 @@ async fn handle_socket(socket: WebSocket) {
     }
 }
 
-async fn health_handler() -> impl IntoResponse {
-    Json(ApiResponse {
-        success: true,
-        data: Some(serde_json::json!({"status": "healthy"})),
-        error: None,
-    })
+async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let connections = state.warehouses.health().await;
+    let healthy = connections.values().all(|status| status.healthy);
+    let code = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        code,
+        Json(ApiResponse {
+            success: healthy,
+            data: Some(serde_json::json!({
+                "status": if healthy { "healthy" } else { "degraded" },
+                "connections": connections,
+            })),
+            error: None,
+        }),
+    )
+}
+
+type PoolGauge = (
+    &'static str,
+    &'static str,
+    fn(&warehouse_conn::PoolStats) -> f64,
+);
+
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = String::new();
+    let gauges: [PoolGauge; 8] = [
+        ("querysmith_pool_size", "gauge", |s| s.size as f64),
+        ("querysmith_pool_idle", "gauge", |s| s.idle as f64),
+        ("querysmith_pool_max_connections", "gauge", |s| {
+            s.max_connections as f64
+        }),
+        ("querysmith_pool_waiters", "gauge", |s| s.waiters as f64),
+        ("querysmith_pool_acquire_total", "counter", |s| {
+            s.acquire_count as f64
+        }),
+        ("querysmith_pool_acquire_avg_ms", "gauge", |s| {
+            s.avg_acquire_ms
+        }),
+        ("querysmith_pool_acquire_max_ms", "gauge", |s| {
+            s.max_acquire_ms
+        }),
+        ("querysmith_pool_reconnects_total", "counter", |s| {
+            s.reconnects as f64
+        }),
+    ];
+
+    let mut stats = Vec::new();
+    for name in state.warehouses.names() {
+        if let Ok(warehouse) = state.warehouses.get(Some(&name)) {
+            if let Some(pool) = warehouse.pool_stats().await {
+                stats.push((name, pool));
+            }
+        }
+    }
+
+    for (metric, kind, value) in gauges {
+        body.push_str(&format!("# TYPE {} {}\n", metric, kind));
+        for (name, pool) in &stats {
+            body.push_str(&format!(
+                "{}{{connection=\"{}\"}} {}\n",
+                metric,
+                name,
+                value(pool)
+            ));
+        }
+    }
+
+    let cache = state.cache.stats().await;
+    let cache_metrics = [
+        ("querysmith_cache_hits_total", "counter", cache.hits as f64),
+        (
+            "querysmith_cache_misses_total",
+            "counter",
+            cache.misses as f64,
+        ),
+        (
+            "querysmith_cache_evictions_total",
+            "counter",
+            cache.evictions as f64,
+        ),
+        ("querysmith_cache_entries", "gauge", cache.entries as f64),
+        ("querysmith_cache_bytes", "gauge", cache.bytes as f64),
+    ];
+    for (metric, kind, value) in cache_metrics {
+        body.push_str(&format!(
+            "# TYPE {} {}\n{} {}\n",
+            metric, kind, metric, value
+        ));
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+fn load_warehouses() -> anyhow::Result<warehouse_conn::WarehouseRegistry> {
+    let config = match std::env::var("QUERYSMITH_WAREHOUSES") {
+        Ok(path) => warehouse_conn::RegistryConfig::from_file(&path)?,
+        Err(_) => {
+            let url = std::env::var("DATABASE_URL")
+                .unwrap_or_else(|_| "postgres://localhost/querysmith".to_string());
+            warehouse_conn::RegistryConfig::single("default", &url)
+        }
+    };
+    Ok(warehouse_conn::WarehouseRegistry::from_config(&config)?)
+}
+
+fn load_policy() -> anyhow::Result<Option<Arc<warehouse_conn::PolicyEngine>>> {
+    match std::env::var("QUERYSMITH_POLICY_FILE") {
+        Ok(path) => Ok(Some(Arc::new(warehouse_conn::PolicyEngine::from_file(
+            &path,
+        )?))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The PII masker from `QUERYSMITH_PII_CONFIG`, also masking every column
+/// tagged as PII in the metadata catalog.
+async fn load_pii_masker(
+    metadata: &metadata_svc::MetadataService,
+) -> anyhow::Result<warehouse_conn::PiiMasker> {
+    let config = match std::env::var("QUERYSMITH_PII_CONFIG") {
+        Ok(path) => warehouse_conn::PiiConfig::from_yaml(&std::fs::read_to_string(path)?)?,
+        Err(_) => warehouse_conn::PiiConfig::default(),
+    };
+    Ok(warehouse_conn::PiiMasker::new(config).with_pii_columns(metadata.pii_columns().await))
+}
+
+/// Replaces the stored semantic model with the YAML in
+/// `QUERYSMITH_SEMANTIC_MODEL`, if set.
+async fn load_semantic_model(metadata: &metadata_svc::MetadataService) -> anyhow::Result<()> {
+    if let Ok(path) = std::env::var("QUERYSMITH_SEMANTIC_MODEL") {
+        let model = metadata_svc::SemanticModel::from_yaml(&std::fs::read_to_string(path)?)?;
+        metadata.set_semantic_model(model).await?;
+    }
+    Ok(())
+}
+
+/// A workflow engine running queries against `warehouses`, with every
+/// workflow YAML in `QUERYSMITH_WORKFLOWS_DIR` registered.
+async fn load_workflows(
+    warehouses: Arc<warehouse_conn::WarehouseRegistry>,
+    cache: Arc<warehouse_conn::ResultCache>,
+) -> anyhow::Result<workflow_engine::WorkflowEngine> {
+    let engine = workflow_engine::WorkflowEngine::new()
+        .with_query_handler(warehouses)
+        .with_result_cache(cache);
+    let Ok(dir) = std::env::var("QUERYSMITH_WORKFLOWS_DIR") else {
+        return Ok(engine);
+    };
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml")
+        ) {
+            continue;
+        }
+        let definition =
+            workflow_engine::WorkflowDefinition::from_yaml(&std::fs::read_to_string(&path)?)?;
+        engine
+            .register(workflow_engine::Workflow::new(definition))
+            .await?;
+    }
+    Ok(engine)
+}
+
+/// Records lineage from the views of every warehouse and the query steps of
+/// every loaded workflow. Failures are logged so startup continues.
+async fn extract_lineage(
+    metadata: &metadata_svc::MetadataService,
+    warehouses: &warehouse_conn::WarehouseRegistry,
+    workflows: &workflow_engine::WorkflowEngine,
+) {
+    for name in warehouses.names() {
+        let result = match warehouses.get(Some(&name)) {
+            Ok(warehouse) => metadata.extract_lineage(warehouse.as_ref()).await,
+            Err(e) => Err(metadata_svc::Error::Database(e.to_string())),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Skipping view lineage for {}: {}", name, e);
+        }
+    }
+    for workflow in workflows.list().await {
+        if let Err(e) = metadata
+            .extract_workflow_lineage(&workflow.definition, Some(warehouses))
+            .await
+        {
+            tracing::warn!(
+                "Skipping lineage for workflow {}: {}",
+                workflow.definition.name,
+                e
+            );
+        }
+    }
 }
 
+fn embedder() -> anyhow::Result<Arc<dyn rag_engine::Embedder>> {
+    let Ok(url) = std::env::var("QUERYSMITH_EMBEDDING_URL") else {
+        return Ok(Arc::new(rag_engine::HashingEmbedder::new(256)));
+    };
+    let model = std::env::var("QUERYSMITH_EMBEDDING_MODEL")
+        .unwrap_or_else(|_| "text-embedding-3-small".to_string());
+    let dimension = std::env::var("QUERYSMITH_EMBEDDING_DIMENSION")
+        .unwrap_or_else(|_| "1536".to_string())
+        .parse()?;
+    let mut embedder = rag_engine::OpenAIEmbedder::new(&url, &model, dimension);
+    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+        embedder = embedder.with_api_key(&api_key);
+    }
+    Ok(Arc::new(embedder))
+}
+
+/// The chat model at `QUERYSMITH_LLM_URL`, OpenAI by default, authenticated
+/// with `OPENAI_API_KEY` if set.
+fn llm_client(model: &str) -> agent_core::LlmClient {
+    let url = std::env::var("QUERYSMITH_LLM_URL")
+        .unwrap_or_else(|_| "https://api.openai.com/v1".to_string());
+    let client = agent_core::LlmClient::new(&url, model);
+    match std::env::var("OPENAI_API_KEY") {
+        Ok(api_key) => client.with_api_key(&api_key),
+        Err(_) => client,
+    }
+}
+
+/// Reranks with `llm` when `QUERYSMITH_LLM_RERANK` is `true` or `1`, and
+/// heuristically otherwise.
+fn reranker(llm: Arc<agent_core::LlmClient>) -> Arc<dyn rag_engine::Reranker> {
+    if !matches!(
+        std::env::var("QUERYSMITH_LLM_RERANK").as_deref(),
+        Ok("true" | "1")
+    ) {
+        return Arc::new(rag_engine::HeuristicReranker::new());
+    }
+    Arc::new(rag_engine::LlmReranker::new(move |prompt: String| {
+        let llm = llm.clone();
+        async move { llm.complete(&prompt).await.map_err(|e| e.to_string()) }
+    }))
+}
+
+/// A shared RAG service when `QUERYSMITH_RAG_URL` is set, authenticated with
+/// `QUERYSMITH_API_TOKEN` for writes, otherwise an in-process index with
+/// [`reranker`], persisted to `QUERYSMITH_RAG_STORE` if given.
+async fn load_rag(
+    llm: Arc<agent_core::LlmClient>,
+) -> anyhow::Result<Arc<dyn rag_engine::RagBackend>> {
+    if let Ok(url) = std::env::var("QUERYSMITH_RAG_URL") {
+        let mut client = rag_engine::RemoteRagClient::new(&url);
+        if let Ok(token) = std::env::var("QUERYSMITH_API_TOKEN") {
+            client = client.with_token(&token);
+        }
+        return Ok(Arc::new(client));
+    }
+    let embedder = embedder()?;
+    let rag = match std::env::var("QUERYSMITH_RAG_STORE") {
+        Ok(url) => {
+            let store =
+                rag_engine::VectorStore::open(&url, embedder.model(), embedder.dimension()).await?;
+            rag_engine::RAGService::open(embedder, Arc::new(store)).await?
+        }
+        Err(_) => rag_engine::RAGService::with_embedder(embedder),
+    };
+    let rag = rag.with_reranker(reranker(llm));
+    Ok(Arc::new(tokio::sync::RwLock::new(rag)))
+}
+
+/// Seeds the query examples from `pg_stat_statements` of the default
+/// warehouse, when it is PostgreSQL, and from the SQL log or script in
+/// `QUERYSMITH_QUERY_LOG`. Failures only disable the affected source.
+async fn seed_query_log(
+    query_log: &rag_engine::QueryLog,
+    warehouses: &warehouse_conn::WarehouseRegistry,
+) {
+    let Ok(warehouse) = warehouses.get(None) else {
+        return;
+    };
+    let backend = warehouse.dialect().backend();
+    if backend == warehouse_conn::Backend::Postgres {
+        let indexed = match rag_engine::read_pg_stat_statements(warehouse.as_ref(), 500).await {
+            Ok(records) => query_log.record(&records, backend).await,
+            Err(e) => Err(e),
+        };
+        match indexed {
+            Ok(count) => tracing::info!("Indexed {} queries from pg_stat_statements", count),
+            Err(e) => tracing::warn!("Failed to read pg_stat_statements: {}", e),
+        }
+    }
+    if let Ok(path) = std::env::var("QUERYSMITH_QUERY_LOG") {
+        let indexed = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                query_log
+                    .record(&rag_engine::parse_sql_log(&text), backend)
+                    .await
+            }
+            Err(e) => Err(rag_engine::Error::Rag(e.to_string())),
+        };
+        match indexed {
+            Ok(count) => tracing::info!("Indexed {} queries from {}", count, path),
+            Err(e) => tracing::warn!("Failed to index query log {}: {}", path, e),
+        }
+    }
+}
+
+/// Any origin may read and chat; writes from a browser are only allowed for
+/// the origins listed in `QUERYSMITH_CORS_ORIGINS`.
 fn cors() -> CorsLayer {
+    let origins: Vec<header::HeaderValue> = std::env::var("QUERYSMITH_CORS_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
+    if origins.is_empty() {
+        return CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::CONTENT_TYPE]);
+    }
     CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST])
-        .allow_headers([header::CONTENT_TYPE])
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+}
+
+/// Flags memories that mention tables or columns dropped by a schema sync.
+fn flag_stale_memories(
+    metadata: &metadata_svc::MetadataService,
+    memory: Arc<memory_svc::MemoryService>,
+) {
+    metadata.on_drift(move |diff| {
+        let memory = memory.clone();
+        async move {
+            let flagged = memory.flag_stale(|text| diff.stale_references(text)).await;
+            if !flagged.is_empty() {
+                tracing::info!(
+                    "Flagged {} memories as stale after drift in '{}'",
+                    flagged.len(),
+                    diff.schema_name
+                );
+            }
+        }
+    });
 }
 
 #[tokio::main]
@@ -95,26 +439,131 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let model = std::env::var("LLM_MODEL").unwrap_or_else(|_| "minimax-m2.5".to_string());
-    let _api_key = std::env::var("OPENAI_API_KEY").ok();
+    let llm = Arc::new(llm_client(&model));
+
+    let memory = Arc::new(memory_svc::MemoryService::new());
+
+    let ttl = std::env::var("QUERYSMITH_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(300);
+    let mut cache = warehouse_conn::ResultCache::new(std::time::Duration::from_secs(ttl));
+    if let Ok(dir) = std::env::var("QUERYSMITH_CACHE_DIR") {
+        cache = cache.with_disk(dir);
+    }
+    if let Err(e) = cache.load().await {
+        tracing::warn!("Failed to load result cache: {}", e);
+    }
+    let cache = Arc::new(cache);
+
+    let warehouses = Arc::new(
+        load_warehouses()
+            .expect("Failed to load warehouse connections")
+            .with_result_cache(cache.clone()),
+    );
 
-    let agent = Arc::new(agent_core::AgentRuntime::new(
-        model,
-        agent_core::ToolRegistry::new(),
+    let metadata = match std::env::var("QUERYSMITH_METADATA_URL") {
+        Ok(url) => metadata_svc::MetadataService::connect(&url)
+            .await
+            .expect("Failed to connect metadata store"),
+        Err(_) => metadata_svc::MetadataService::new(),
+    };
+    let metadata = Arc::new(metadata);
+    flag_stale_memories(&metadata, memory.clone());
+
+    let workflows = load_workflows(warehouses.clone(), cache.clone())
+        .await
+        .expect("Failed to load workflows");
+    extract_lineage(&metadata, &warehouses, &workflows).await;
+    metadata.trigger_workflows(Arc::new(workflows));
+
+    let rag = load_rag(llm).await.expect("Failed to load RAG index");
+    match agent_core::index_glossary(&metadata, rag.as_ref()).await {
+        Ok(count) => tracing::info!("Indexed {} glossary terms", count),
+        Err(e) => tracing::warn!("Failed to index glossary: {}", e),
+    }
+    if let Ok(dir) = std::env::var("QUERYSMITH_DOCS_DIR") {
+        match rag_engine::ingest_directory(rag.as_ref(), std::path::Path::new(&dir)).await {
+            Ok(report) => {
+                tracing::info!(
+                    "Ingested docs from {}: {} added, {} updated, {} removed, {} unchanged",
+                    dir,
+                    report.added.len(),
+                    report.updated.len(),
+                    report.removed.len(),
+                    report.unchanged
+                );
+                for (path, error) in &report.failed {
+                    tracing::warn!("Failed to ingest {}: {}", path, error);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to ingest docs from {}: {}", dir, e),
+        }
+    }
+    let query_log = Arc::new(rag_engine::QueryLog::new(rag.clone()));
+    seed_query_log(&query_log, &warehouses).await;
+
+    load_semantic_model(&metadata)
+        .await
+        .expect("Failed to load semantic model");
+    let masker = load_pii_masker(&metadata)
+        .await
+        .expect("Failed to load PII masking config");
+    let policy = load_policy().expect("Failed to load access policies");
+    let mut run_sql = agent_core::RunSqlTool::new(warehouses.clone())
+        .with_pii_masker(Arc::new(masker))
+        .with_query_log(query_log);
+    let mut query_examples = agent_core::QueryExamplesContext::new(rag.clone());
+    if let Some(policy) = policy {
+        run_sql = run_sql.with_policy(policy.clone());
+        query_examples = query_examples.with_policy(policy);
+    }
+    let mut tools = agent_core::ToolRegistry::new();
+    tools.register(run_sql);
+    tools.register(agent_core::QueryMetricTool::new(
+        metadata.clone(),
+        warehouses.clone(),
     ));
 
-    let memory = Arc::new(memory_svc::MemoryService::new());
+    let mut agent = agent_core::AgentRuntime::new(model, tools)
+        .with_context_provider(metadata.clone())
+        .with_context_provider(Arc::new(agent_core::RagContext::new(rag.clone())))
+        .with_context_provider(Arc::new(query_examples));
+    match warehouses.get(None) {
+        Ok(warehouse) => agent = agent.with_dialect(warehouse.dialect()),
+        Err(e) => tracing::warn!("No default warehouse, SQL dialect unknown: {}", e),
+    }
+    let agent = Arc::new(agent);
+
+    let api_token = std::env::var("QUERYSMITH_API_TOKEN").ok();
+    let metadata_api = metadata_svc::api::require_write_token(
+        metadata_svc::api::router(metadata),
+        api_token.clone(),
+    );
+    let rag_api = rag_engine::api::router(rag, api_token);
 
-    let state = AppState { agent, memory };
+    let state = AppState {
+        agent,
+        memory,
+        warehouses,
+        cache,
+    };
 
     let app = Router::new()
         .route("/", get(|| async { "QuerySmith API" }))
         .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
         .route("/chat", post(chat_handler))
         .route("/ws", get(ws_handler))
+        .nest_service("/metadata", metadata_api)
+        .nest_service("/rag", rag_api)
         .layer(ServiceBuilder::new().layer(cors()))
         .with_state(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    let addr: SocketAddr = std::env::var("QUERYSMITH_BIND")
+        .unwrap_or_else(|_| "127.0.0.1:3000".to_string())
+        .parse()
+        .expect("QUERYSMITH_BIND must be a socket address");
 
     tracing::info!("Starting QuerySmith API on {}", addr);
 
@@ -12,6 +12,7 @@ struct SlackBotState {
     agent: Arc<agent_core::AgentRuntime>,
     memory: Arc<memory_svc::MemoryService>,
     conversations: Arc<RwLock<HashMap<String, ConversationState>>>,
+    backend: Option<warehouse_conn::Backend>,
 }
 
 #[derive(Clone)]
@@ -111,11 +112,18 @@ async fn handle_slash_command(
     match command.as_str() {
         "/query" | "/querysmith" => {
             let user_memory_scope = MemoryScope::user(&user_id);
-            let context = state
-                .memory
-                .inject_into_prompt(&text, Some(user_memory_scope))
-                .await
-                .unwrap_or_default();
+            let context = match state.backend {
+                Some(backend) => state
+                    .memory
+                    .inject_into_prompt_for_dialect(&text, Some(user_memory_scope), backend)
+                    .await
+                    .unwrap_or_default(),
+                None => state
+                    .memory
+                    .inject_into_prompt(&text, Some(user_memory_scope))
+                    .await
+                    .unwrap_or_default(),
+            };
 
             let _full_prompt = if context.is_empty() {
                 text.clone()
@@ -149,6 +157,29 @@ async fn handle_slash_command(
     }
 }
 
+/// Engine of the default warehouse, so corrections in memories are
+/// transpiled to the dialect the answers will run on.
+fn default_backend() -> Option<warehouse_conn::Backend> {
+    let config = match std::env::var("QUERYSMITH_WAREHOUSES") {
+        Ok(path) => warehouse_conn::RegistryConfig::from_file(&path),
+        Err(_) => {
+            let url = std::env::var("DATABASE_URL")
+                .unwrap_or_else(|_| "postgres://localhost/querysmith".to_string());
+            Ok(warehouse_conn::RegistryConfig::single("default", &url))
+        }
+    };
+    let warehouse = config
+        .and_then(|config| warehouse_conn::WarehouseRegistry::from_config(&config))
+        .and_then(|warehouses| warehouses.get(None));
+    match warehouse {
+        Ok(warehouse) => Some(warehouse.dialect().backend()),
+        Err(e) => {
+            tracing::warn!("No default warehouse, SQL dialect unknown: {}", e);
+            None
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
@@ -170,6 +201,7 @@ async fn main() {
         agent,
         memory,
         conversations,
+        backend: default_backend(),
     };
 
     let app = Router::new()
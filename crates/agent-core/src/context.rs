@@ -0,0 +1,306 @@
+use async_trait::async_trait;
+use metadata_svc::{GlossaryTerm, MetadataService};
+use rag_engine::{
+    ContextAssembler, IndexItem, RagBackend, RetrieveRequest, RetrievedChunk, SourceType,
+};
+use std::sync::Arc;
+use warehouse_conn::{Identity, PolicyEngine};
+
+use crate::error::Error;
+
+/// A source of prompt context for a user question, appended to the agent's
+/// system prompt by [`AgentRuntime::system_message_as`](crate::AgentRuntime::system_message_as).
+#[async_trait]
+pub trait ContextProvider: Send + Sync {
+    /// Prompt section relevant to `question` asked by `identity`, empty when
+    /// there is none.
+    async fn context(&self, question: &str, identity: &Identity) -> Result<String, Error>;
+}
+
+/// The business glossary terms used in the question.
+#[async_trait]
+impl ContextProvider for MetadataService {
+    async fn context(&self, question: &str, _identity: &Identity) -> Result<String, Error> {
+        self.glossary_context(question)
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))
+    }
+}
+
+/// Chunks retrieved for the question, deduplicated and fitted into a token
+/// budget by a [`ContextAssembler`].
+pub struct RagContext {
+    rag: Arc<dyn RagBackend>,
+    k: usize,
+    assembler: ContextAssembler,
+}
+
+impl RagContext {
+    pub fn new(rag: Arc<dyn RagBackend>) -> Self {
+        Self {
+            rag,
+            k: 8,
+            assembler: ContextAssembler::new(2048),
+        }
+    }
+
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    pub fn with_assembler(mut self, assembler: ContextAssembler) -> Self {
+        self.assembler = assembler;
+        self
+    }
+}
+
+#[async_trait]
+impl ContextProvider for RagContext {
+    async fn context(&self, question: &str, _identity: &Identity) -> Result<String, Error> {
+        let result = self
+            .rag
+            .retrieve(RetrieveRequest::new(question, self.k))
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))?;
+        if result.chunks.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(format!("\n\n{}", self.assembler.assemble(&result).text))
+    }
+}
+
+/// Past queries similar to the question, as few-shot examples.
+pub struct QueryExamplesContext {
+    rag: Arc<dyn RagBackend>,
+    k: usize,
+    policy: Option<Arc<PolicyEngine>>,
+}
+
+impl QueryExamplesContext {
+    pub fn new(rag: Arc<dyn RagBackend>) -> Self {
+        Self {
+            rag,
+            k: 3,
+            policy: None,
+        }
+    }
+
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Leaves out examples over tables the asking identity cannot read.
+    pub fn with_policy(mut self, policy: Arc<PolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    fn is_visible(&self, chunk: &RetrievedChunk, identity: &Identity) -> bool {
+        let Some(policy) = &self.policy else {
+            return true;
+        };
+        let tables = chunk.metadata.get("tables").and_then(|t| t.as_array());
+        tables.is_some_and(|tables| {
+            tables
+                .iter()
+                .filter_map(|table| table.as_str())
+                .all(|table| policy.can_read(identity, table))
+        })
+    }
+}
+
+#[async_trait]
+impl ContextProvider for QueryExamplesContext {
+    async fn context(&self, question: &str, identity: &Identity) -> Result<String, Error> {
+        // Over-fetch so that examples the policy removes leave room for others.
+        let fetch = if self.policy.is_some() {
+            self.k * 4
+        } else {
+            self.k
+        };
+        let request =
+            RetrieveRequest::new(question, fetch).with_sources(vec![SourceType::QueryExample]);
+        let result = self
+            .rag
+            .retrieve(request)
+            .await
+            .map_err(|e| Error::Agent(e.to_string()))?;
+        let chunks: Vec<_> = result
+            .chunks
+            .iter()
+            .filter(|chunk| self.is_visible(chunk, identity))
+            .take(self.k)
+            .collect();
+        if chunks.is_empty() {
+            return Ok(String::new());
+        }
+        let mut context = String::from("\n\nSimilar past queries:\n");
+        for chunk in chunks {
+            context.push_str(&format!("```sql\n{}\n```\n", chunk.content));
+        }
+        Ok(context)
+    }
+}
+
+/// Indexes every glossary term as documentation under `glossary:<term>`, so
+/// retrieval can match questions that paraphrase a term.
+pub async fn index_glossary(
+    metadata: &MetadataService,
+    rag: &dyn RagBackend,
+) -> Result<usize, Error> {
+    let terms = metadata
+        .list_glossary_terms()
+        .await
+        .map_err(|e| Error::Agent(e.to_string()))?;
+    if terms.is_empty() {
+        return Ok(0);
+    }
+    let items = terms.iter().map(glossary_item).collect();
+    rag.upsert(SourceType::Documentation, items)
+        .await
+        .map_err(|e| Error::Agent(e.to_string()))
+}
+
+fn glossary_item(term: &GlossaryTerm) -> IndexItem {
+    let mut content = format!("{}: {}", term.term, term.definition);
+    if !term.synonyms.is_empty() {
+        content.push_str(&format!("\nSynonyms: {}", term.synonyms.join(", ")));
+    }
+    if !term.tables.is_empty() || !term.columns.is_empty() {
+        let sources: Vec<&str> = term
+            .tables
+            .iter()
+            .chain(&term.columns)
+            .map(String::as_str)
+            .collect();
+        content.push_str(&format!("\nUses: {}", sources.join(", ")));
+    }
+    if let Some(sql) = &term.sql {
+        content.push_str(&format!("\nSQL: {}", sql));
+    }
+    IndexItem {
+        id: format!("glossary:{}", term.term.to_lowercase()),
+        content,
+        metadata: serde_json::json!({
+            "kind": "glossary",
+            "term": term.term,
+            "tables": term.tables,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rag_engine::{HashingEmbedder, RAGService};
+    use tokio::sync::RwLock;
+    use warehouse_conn::Warehouse;
+
+    #[tokio::test]
+    async fn test_glossary_reaches_prompt_and_index() {
+        let metadata = Arc::new(MetadataService::new());
+        metadata
+            .save_glossary_term(
+                GlossaryTerm::new("ARR", "Annual recurring revenue").with_sql("SUM(mrr) * 12"),
+            )
+            .await
+            .unwrap();
+
+        let runtime = crate::AgentRuntime::new("test".to_string(), crate::ToolRegistry::new())
+            .with_context_provider(metadata.clone());
+        let prompt = runtime.system_message_for("What is our ARR?").await.content;
+        assert!(prompt.contains("ARR: Annual recurring revenue"));
+        assert!(!runtime
+            .system_message_for("list users")
+            .await
+            .content
+            .contains("glossary"));
+
+        let rag: Arc<dyn RagBackend> = Arc::new(RwLock::new(RAGService::with_embedder(Arc::new(
+            HashingEmbedder::new(64),
+        ))));
+        assert_eq!(index_glossary(&metadata, rag.as_ref()).await.unwrap(), 1);
+        let result = rag
+            .retrieve(RetrieveRequest::new("annual recurring revenue", 1))
+            .await
+            .unwrap();
+        assert_eq!(result.chunks[0].id, "glossary:arr");
+
+        let runtime = crate::AgentRuntime::new("test".to_string(), crate::ToolRegistry::new())
+            .with_context_provider(Arc::new(RagContext::new(rag).with_k(1)));
+        let prompt = runtime
+            .system_message_for("annual recurring revenue")
+            .await
+            .content;
+        assert!(prompt.contains("## Relevant Context"));
+        assert!(prompt.contains("[1] ARR: Annual recurring revenue"));
+    }
+
+    #[tokio::test]
+    async fn test_agent_queries_become_examples() {
+        let rag: Arc<dyn RagBackend> = Arc::new(RwLock::new(RAGService::with_embedder(Arc::new(
+            HashingEmbedder::new(64),
+        ))));
+        let sqlite =
+            warehouse_conn::SqliteWarehouse::new("sqlite::memory:").with_max_connections(1);
+        sqlite
+            .execute("CREATE TABLE accounts (id INTEGER)")
+            .await
+            .unwrap();
+        let registry = warehouse_conn::WarehouseRegistry::single("default", Arc::new(sqlite));
+        let tool = crate::RunSqlTool::new(Arc::new(registry))
+            .with_query_log(Arc::new(rag_engine::QueryLog::new(rag.clone())));
+        let mut tools = crate::ToolRegistry::new();
+        tools.register(tool);
+
+        let policy = warehouse_conn::PolicyConfig::from_yaml(
+            "policies:\n  - name: interns\n    users: [intern]\n    hidden_tables: [accounts]\n",
+        )
+        .unwrap();
+        let runtime =
+            crate::AgentRuntime::new("test".to_string(), tools).with_context_provider(Arc::new(
+                QueryExamplesContext::new(rag.clone())
+                    .with_policy(Arc::new(PolicyEngine::new(policy))),
+            ));
+        let context = crate::ToolContext::for_identity(Identity::new("analyst"))
+            .with_question("how many accounts are there");
+        runtime
+            .execute_tool_with_context(
+                "run_sql",
+                serde_json::json!({ "sql": "SELECT count(*) FROM accounts WHERE id > 10" }),
+                &context,
+            )
+            .await
+            .unwrap();
+
+        // The example is recorded in the background.
+        for _ in 0..100 {
+            if !rag
+                .metadata(SourceType::QueryExample)
+                .await
+                .unwrap()
+                .is_empty()
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let metadata = rag.metadata(SourceType::QueryExample).await.unwrap();
+        let example = metadata.values().next().unwrap();
+        assert_eq!(example["users"], serde_json::json!(["analyst"]));
+
+        let prompt = runtime
+            .system_message_as("how many accounts", &Identity::new("analyst"))
+            .await
+            .content;
+        assert!(prompt.contains("Similar past queries:"));
+        assert!(prompt.contains("-- question: how many accounts are there"));
+        let prompt = runtime
+            .system_message_as("how many accounts", &Identity::new("intern"))
+            .await
+            .content;
+        assert!(!prompt.contains("Similar past queries:"));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod context;
 pub mod error;
 pub mod llm;
 pub mod orchestrator;
@@ -6,9 +7,11 @@ pub mod runtime;
 pub mod tools;
 pub mod traits;
 
+pub use context::{index_glossary, ContextProvider, QueryExamplesContext, RagContext};
 pub use error::Error;
+pub use llm::LlmClient;
 pub use orchestrator::AgentOrchestrator;
 pub use registry::ToolRegistry;
 pub use runtime::AgentRuntime;
-pub use tools::{DebugQueryTool, RunSqlTool, SearchTablesTool};
-pub use traits::{Tool, ToolResult};
+pub use tools::{DebugQueryTool, QueryMetricTool, RunSqlTool, SearchTablesTool};
+pub use traits::{Tool, ToolContext, ToolResult};
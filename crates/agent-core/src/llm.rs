@@ -1,10 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: MessageRole,
     pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
 }
 
@@ -28,8 +32,11 @@ pub struct ToolCall {
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 }
 
@@ -53,3 +60,79 @@ pub struct Usage {
     pub completion_tokens: usize,
     pub total_tokens: usize,
 }
+
+/// Client for an OpenAI-compatible chat completions endpoint.
+pub struct LlmClient {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl LlmClient {
+    /// `base_url` includes the version prefix, e.g. `https://api.openai.com/v1`.
+    pub fn new(base_url: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key: None,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn chat(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, Error> {
+        let mut http = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(request);
+        if let Some(api_key) = &self.api_key {
+            http = http.bearer_auth(api_key);
+        }
+
+        let response = http.send().await.map_err(|e| Error::Llm(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Llm(format!(
+                "chat request failed with {}: {}",
+                status, body
+            )));
+        }
+        response.json().await.map_err(|e| Error::Llm(e.to_string()))
+    }
+
+    /// Sends `prompt` as a single user message and returns the reply.
+    pub async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![ChatMessage {
+                role: MessageRole::User,
+                content: prompt.to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            }],
+            tools: None,
+            temperature: Some(0.0),
+            stream: None,
+        };
+        let response = self.chat(&request).await?;
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| Error::Llm("chat response has no choices".to_string()))
+    }
+}
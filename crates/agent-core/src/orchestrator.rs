@@ -2,20 +2,37 @@ use std::sync::Arc;
 
 use crate::llm::{ChatCompletionResponse, MessageRole, ToolCall};
 use crate::runtime::AgentRuntime;
+use crate::traits::ToolContext;
+use warehouse_conn::Identity;
 
 pub struct AgentOrchestrator {
     runtime: Arc<AgentRuntime>,
+    system: Option<crate::llm::ChatMessage>,
+    question: Option<String>,
+    identity: Identity,
     messages: Vec<crate::llm::ChatMessage>,
 }
 
 impl AgentOrchestrator {
-    pub fn new(runtime: AgentRuntime) -> Self {
+    /// Takes an owned or shared runtime; tool calls run as the runtime's
+    /// identity until [`with_identity`](Self::with_identity) is set.
+    pub fn new(runtime: impl Into<Arc<AgentRuntime>>) -> Self {
+        let runtime = runtime.into();
         Self {
-            runtime: Arc::new(runtime),
+            identity: runtime.identity().clone(),
+            runtime,
+            system: None,
+            question: None,
             messages: Vec::new(),
         }
     }
 
+    /// The user this conversation is for, e.g. from the chat request.
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identity = identity;
+        self
+    }
+
     pub fn with_initial_message(mut self, content: String) -> Self {
         self.add_user_message(content);
         self
@@ -34,11 +51,35 @@ impl AgentOrchestrator {
         self.add_message(MessageRole::User, content);
     }
 
+    /// Adds `question` as a user message and builds the system message with
+    /// the context retrieved for it.
+    pub async fn ask(&mut self, question: String) {
+        self.system = Some(
+            self.runtime
+                .system_message_as(&question, &self.identity)
+                .await,
+        );
+        self.question = Some(question.clone());
+        self.add_user_message(question);
+    }
+
+    /// Tool context carrying the conversation's identity and the question
+    /// passed to [`ask`](Self::ask).
+    pub fn tool_context(&self) -> ToolContext {
+        let context = ToolContext::for_identity(self.identity.clone());
+        match &self.question {
+            Some(question) => context.with_question(question),
+            None => context,
+        }
+    }
+
     pub fn messages(&self) -> &[crate::llm::ChatMessage] {
         &self.messages
     }
 
     pub fn clear(&mut self) {
+        self.system = None;
+        self.question = None;
         self.messages.clear();
     }
 
@@ -85,12 +126,10 @@ impl AgentOrchestrator {
     }
 
     pub fn get_messages_for_llm(&self) -> Vec<crate::llm::ChatMessage> {
-        let mut msgs = vec![crate::llm::ChatMessage {
-            role: MessageRole::System,
-            content: self.runtime.system_prompt.clone(),
-            tool_calls: None,
-            tool_call_id: None,
-        }];
+        let mut msgs = vec![self
+            .system
+            .clone()
+            .unwrap_or_else(|| self.runtime.build_system_message())];
         msgs.extend(self.messages.clone());
         msgs
     }
@@ -139,10 +178,11 @@ impl SelfCorrectingAgent {
                     let arguments = tool_call.arguments;
                     let tool_call_id = &tool_call.id;
 
+                    let context = self.orchestrator.tool_context();
                     let result = self
                         .orchestrator
                         .runtime
-                        .execute_tool(tool_name, arguments)
+                        .execute_tool_with_context(tool_name, arguments, &context)
                         .await;
 
                     match result {
@@ -1,14 +1,19 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::context::ContextProvider;
 use crate::llm::{ChatMessage, MessageRole};
 use crate::registry::ToolRegistry;
+use crate::traits::ToolContext;
+use warehouse_conn::{Dialect, Identity};
 
 pub struct AgentRuntime {
     pub model: String,
     pub tools: Arc<ToolRegistry>,
     pub max_retries: usize,
     pub system_prompt: String,
+    context: Vec<Arc<dyn ContextProvider>>,
+    identity: Identity,
 }
 
 impl AgentRuntime {
@@ -18,6 +23,8 @@ impl AgentRuntime {
             tools: Arc::new(tools),
             max_retries: 3,
             system_prompt: Self::default_system_prompt(),
+            context: Vec::new(),
+            identity: Identity::anonymous(),
         }
     }
 
@@ -26,6 +33,33 @@ impl AgentRuntime {
         self
     }
 
+    pub fn with_dialect(mut self, dialect: &dyn Dialect) -> Self {
+        self.system_prompt.push('\n');
+        self.system_prompt.push_str(&dialect.cheat_sheet());
+        self.system_prompt
+            .push_str("Write all SQL for this dialect only.\n");
+        self
+    }
+
+    /// Adds a source of question-specific context, see
+    /// [`system_message_as`](Self::system_message_as).
+    pub fn with_context_provider(mut self, provider: Arc<dyn ContextProvider>) -> Self {
+        self.context.push(provider);
+        self
+    }
+
+    /// Identity that tool calls run as unless an
+    /// [`AgentOrchestrator`](crate::AgentOrchestrator) overrides it, so
+    /// access policies apply to agent-generated SQL.
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
     pub fn with_max_retries(mut self, retries: usize) -> Self {
         self.max_retries = retries;
         self
@@ -38,9 +72,11 @@ Your capabilities:
 1. Search for relevant tables using the search_tables tool
 2. Run SQL queries using the run_sql tool
 3. Debug and fix failed queries using the debug_query tool
+4. Compile governed business metrics using the query_metric tool
 
 Guidelines:
 - Always explore available tables before writing complex queries
+- For business metrics such as revenue, prefer the SQL from query_metric over writing your own
 - Provide clear explanations of your SQL
 - If a query fails, use the debug_query tool to analyze the error
 - Return results in a user-friendly format
@@ -58,6 +94,26 @@ When you need to use a tool, respond with a JSON object containing tool_calls.
         }
     }
 
+    /// The system message for `question` asked as the runtime's identity, see
+    /// [`system_message_as`](Self::system_message_as).
+    pub async fn system_message_for(&self, question: &str) -> ChatMessage {
+        self.system_message_as(question, &self.identity).await
+    }
+
+    /// The system message for `question` asked by `identity`: the system
+    /// prompt followed by the context from every provider. A failing provider
+    /// is skipped.
+    pub async fn system_message_as(&self, question: &str, identity: &Identity) -> ChatMessage {
+        let mut message = self.build_system_message();
+        for provider in &self.context {
+            match provider.context(question, identity).await {
+                Ok(context) => message.content.push_str(&context),
+                Err(e) => tracing::warn!("Failed to load prompt context: {}", e),
+            }
+        }
+        message
+    }
+
     pub fn get_tool_schemas(&self) -> Vec<serde_json::Value> {
         self.tools.to_json_schemas()
     }
@@ -66,6 +122,17 @@ When you need to use a tool, respond with a JSON object containing tool_calls.
         &self,
         tool_name: &str,
         arguments: serde_json::Value,
+    ) -> Result<String, String> {
+        let context = ToolContext::for_identity(self.identity.clone());
+        self.execute_tool_with_context(tool_name, arguments, &context)
+            .await
+    }
+
+    pub async fn execute_tool_with_context(
+        &self,
+        tool_name: &str,
+        arguments: serde_json::Value,
+        context: &ToolContext,
     ) -> Result<String, String> {
         let tool = self
             .tools
@@ -75,7 +142,7 @@ When you need to use a tool, respond with a JSON object containing tool_calls.
         let params: HashMap<String, serde_json::Value> =
             serde_json::from_value(arguments).map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let result = tool.execute(params).await;
+        let result = tool.execute_with_context(params, context).await;
 
         match result {
             Ok(tool_result) => {
@@ -113,4 +180,16 @@ mod tests {
         assert_eq!(runtime.model, "minimax-m2.5");
         assert_eq!(runtime.max_retries(), 3);
     }
+
+    #[test]
+    fn test_dialect_in_system_prompt() {
+        let runtime = AgentRuntime::new("minimax-m2.5".to_string(), ToolRegistry::new())
+            .with_dialect(&warehouse_conn::SqliteDialect);
+
+        assert!(runtime.system_prompt.contains("SQL Dialect: SQLite"));
+        assert!(runtime
+            .build_system_message()
+            .content
+            .contains("start of month"));
+    }
 }
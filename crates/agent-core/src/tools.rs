@@ -1,7 +1,9 @@
 pub mod debug_query;
+pub mod query_metric;
 pub mod run_sql;
 pub mod search_tables;
 
 pub use debug_query::DebugQueryTool;
+pub use query_metric::QueryMetricTool;
 pub use run_sql::RunSqlTool;
 pub use search_tables::SearchTablesTool;
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::traits::{Tool, ToolParameters, ToolProperty, ToolResult};
+use metadata_svc::MetadataService;
+use warehouse_conn::WarehouseRegistry;
+
+/// Compiles requests for governed metrics into SQL for the connected dialect.
+pub struct QueryMetricTool {
+    metadata: Arc<MetadataService>,
+    warehouses: Arc<WarehouseRegistry>,
+}
+
+impl QueryMetricTool {
+    pub fn new(metadata: Arc<MetadataService>, warehouses: Arc<WarehouseRegistry>) -> Self {
+        Self {
+            metadata,
+            warehouses,
+        }
+    }
+}
+
+impl Tool for QueryMetricTool {
+    fn name(&self) -> &str {
+        "query_metric"
+    }
+
+    fn description(&self) -> &str {
+        "Compile a request for a governed business metric (e.g. \"revenue by region last quarter\") into SQL using the official metric and dimension definitions. Use this before writing metric SQL by hand, then run the returned SQL with run_sql."
+    }
+
+    fn parameters(&self) -> ToolParameters {
+        let mut props = HashMap::new();
+        props.insert(
+            "request".to_string(),
+            ToolProperty {
+                prop_type: "string".to_string(),
+                description: "Metric request, e.g. \"monthly revenue by region this year\""
+                    .to_string(),
+            },
+        );
+        props.insert(
+            "database".to_string(),
+            ToolProperty {
+                prop_type: "string".to_string(),
+                description: format!(
+                    "Database connection whose dialect to compile for (optional). Available: {}",
+                    self.warehouses.names().join(", ")
+                ),
+            },
+        );
+        ToolParameters {
+            param_type: "object".to_string(),
+            properties: props,
+            required: vec!["request".to_string()],
+        }
+    }
+
+    fn execute(
+        &self,
+        params: HashMap<String, serde_json::Value>,
+    ) -> Pin<Box<dyn Future<Output = Result<ToolResult, String>> + Send>> {
+        let metadata = self.metadata.clone();
+        let warehouses = self.warehouses.clone();
+
+        Box::pin(async move {
+            let request = params
+                .get("request")
+                .and_then(|v| v.as_str())
+                .ok_or("Missing required parameter: request")?;
+            let database = params.get("database").and_then(|v| v.as_str());
+
+            let warehouse = match warehouses.get(database) {
+                Ok(warehouse) => warehouse,
+                Err(e) => return Ok(ToolResult::error(e.to_string())),
+            };
+
+            match metadata
+                .compile_metric_request(request, warehouse.dialect())
+                .await
+            {
+                Ok(compiled) => Ok(ToolResult::success(serde_json::json!({
+                    "sql": compiled.sql,
+                    "metric": compiled.metric.name,
+                    "definition": compiled.metric.description,
+                    "dimensions": compiled.query.dimensions,
+                    "time_grain": compiled.query.time_grain,
+                    "time_range": compiled.query.time_range,
+                }))),
+                Err(e) => Ok(ToolResult::error(e.to_string())),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metadata_svc::SemanticModel;
+
+    #[tokio::test]
+    async fn test_query_metric_compiles_for_dialect() {
+        let metadata = Arc::new(MetadataService::new());
+        metadata
+            .set_semantic_model(
+                SemanticModel::from_yaml(
+                    r#"
+metrics:
+  - name: revenue
+    description: Completed order value
+    expression: SUM(orders.amount)
+    table: orders
+    time_column: created_at
+"#,
+                )
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        let warehouses = Arc::new(
+            WarehouseRegistry::from_config(&warehouse_conn::RegistryConfig::single(
+                "analytics",
+                "sqlite::memory:",
+            ))
+            .unwrap(),
+        );
+        let tool = QueryMetricTool::new(metadata, warehouses);
+
+        let mut params = HashMap::new();
+        params.insert(
+            "request".to_string(),
+            serde_json::json!("monthly revenue this year"),
+        );
+        let result = tool.execute(params).await.unwrap();
+        let sql = result.data.unwrap()["sql"].as_str().unwrap().to_string();
+        assert!(sql.contains("DATE(orders.created_at, 'start of month')"));
+        assert!(sql.contains("DATE(DATE('now'), 'start of year')"));
+
+        let mut params = HashMap::new();
+        params.insert("request".to_string(), serde_json::json!("headcount"));
+        let result = tool.execute(params).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Available metrics: revenue"));
+    }
+}
@@ -3,37 +3,169 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
-use crate::traits::{Tool, ToolParameters, ToolResult};
-use warehouse_conn::{PostgresWarehouse, SqliteWarehouse, Warehouse};
+use crate::traits::{Tool, ToolContext, ToolParameters, ToolResult};
+use rag_engine::{QueryLog, QueryRecord};
+use warehouse_conn::{
+    Identity, PiiMasker, PolicyEngine, PostgresWarehouse, QueryResult, ResultCache,
+    SqliteWarehouse, Warehouse, WarehouseRegistry,
+};
 
+#[derive(Clone)]
 pub struct RunSqlTool {
-    warehouse: Arc<dyn Warehouse>,
+    warehouses: Arc<WarehouseRegistry>,
+    policy: Option<Arc<PolicyEngine>>,
+    masker: Option<Arc<PiiMasker>>,
+    cache: Option<Arc<ResultCache>>,
+    query_log: Option<Arc<QueryLog>>,
 }
 
 impl RunSqlTool {
-    pub fn new_postgres(connection_string: &str) -> Self {
-        let warehouse = PostgresWarehouse::new(connection_string);
+    pub fn new(warehouses: Arc<WarehouseRegistry>) -> Self {
         Self {
-            warehouse: Arc::new(warehouse),
+            warehouses,
+            policy: None,
+            masker: None,
+            cache: None,
+            query_log: None,
         }
     }
 
+    pub fn with_policy(mut self, policy: Arc<PolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn with_pii_masker(mut self, masker: Arc<PiiMasker>) -> Self {
+        self.masker = Some(masker);
+        self
+    }
+
+    pub fn with_result_cache(mut self, cache: Arc<ResultCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Records every successful query, with the question it answered, as a
+    /// retrievable query example.
+    pub fn with_query_log(mut self, query_log: Arc<QueryLog>) -> Self {
+        self.query_log = Some(query_log);
+        self
+    }
+
+    pub fn new_postgres(connection_string: &str) -> Self {
+        let warehouse = PostgresWarehouse::new(connection_string);
+        Self::new(Arc::new(WarehouseRegistry::single(
+            "default",
+            Arc::new(warehouse),
+        )))
+    }
+
     pub fn new_sqlite(connection_string: &str) -> Self {
         let warehouse = SqliteWarehouse::new(connection_string);
-        Self {
-            warehouse: Arc::new(warehouse),
-        }
+        Self::new(Arc::new(WarehouseRegistry::single(
+            "default",
+            Arc::new(warehouse),
+        )))
     }
 
-    pub async fn execute_query(&self, sql: &str) -> Result<ToolResult, String> {
-        match self.warehouse.execute(sql).await {
-            Ok(result) => Ok(ToolResult::success(serde_json::json!({
-                "columns": result.columns,
-                "rows": result.rows,
-                "row_count": result.row_count
-            }))),
-            Err(e) => Ok(ToolResult::error(e.to_string())),
+    pub async fn execute_query(
+        &self,
+        sql: &str,
+        database: Option<&str>,
+    ) -> Result<ToolResult, String> {
+        self.clone()
+            .run(
+                ToolContext {
+                    identity: Identity::anonymous(),
+                    question: None,
+                },
+                sql.to_string(),
+                database.map(|s| s.to_string()),
+            )
+            .await
+    }
+
+    async fn run(
+        self,
+        context: ToolContext,
+        sql: String,
+        database: Option<String>,
+    ) -> Result<ToolResult, String> {
+        let identity = context.identity;
+        let warehouse = match self.warehouses.get(database.as_deref()) {
+            Ok(warehouse) => warehouse,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        let warehouse: Arc<dyn Warehouse> = match &self.policy {
+            Some(policy) => Arc::new(policy.wrap(warehouse, identity.clone())),
+            None => warehouse,
+        };
+
+        let (result, cache_hit) = match &self.cache {
+            Some(cache) => {
+                let mut scope = database
+                    .or_else(|| self.warehouses.default_name().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                if self.policy.is_some() {
+                    let mut groups = identity.groups.clone();
+                    groups.sort();
+                    scope = format!("{}#{}:{}", scope, identity.user_id, groups.join(","));
+                }
+                match cache.execute(&scope, warehouse.as_ref(), &sql).await {
+                    Ok((result, hit)) => (result, Some(hit)),
+                    Err(e) => return Ok(ToolResult::error(e.to_string())),
+                }
+            }
+            None => match warehouse.execute(&sql).await {
+                Ok(result) => (result, None),
+                Err(e) => return Ok(ToolResult::error(e.to_string())),
+            },
+        };
+
+        let (mut data, unmasked) = match &self.masker {
+            Some(masker) => {
+                let masked = masker.mask_query_result(&sql, warehouse.dialect(), &result);
+                let mut data = Self::to_json(&masked.result);
+                if !masked.findings.is_empty() {
+                    data["pii_masked"] = serde_json::json!(masked.findings);
+                }
+                let unmasked = masker
+                    .is_authorized(&identity)
+                    .then(|| Self::to_json(&result));
+                (data, unmasked)
+            }
+            None => (Self::to_json(&result), None),
+        };
+        if let Some(hit) = cache_hit {
+            data["cache"] = serde_json::json!(if hit { "hit" } else { "miss" });
+        }
+        if let Some(query_log) = self.query_log.clone() {
+            let mut record = QueryRecord::new(&sql, "agent").with_user(&identity.user_id);
+            if let Some(question) = &context.question {
+                record = record.with_question(question);
+            }
+            let backend = warehouse.dialect().backend();
+            // Indexing embeds the example, so it stays off the request path.
+            tokio::spawn(async move {
+                if let Err(e) = query_log.record(&[record], backend).await {
+                    tracing::warn!("Failed to record query example: {}", e);
+                }
+            });
         }
+
+        let tool_result = ToolResult::success(data);
+        Ok(match unmasked {
+            Some(unmasked) => tool_result.with_unmasked(unmasked),
+            None => tool_result,
+        })
+    }
+
+    fn to_json(result: &QueryResult) -> serde_json::Value {
+        serde_json::json!({
+            "columns": result.columns,
+            "rows": result.rows,
+            "row_count": result.row_count
+        })
     }
 }
 
@@ -55,6 +187,16 @@ impl Tool for RunSqlTool {
                 description: "SQL query to execute".to_string(),
             },
         );
+        props.insert(
+            "database".to_string(),
+            crate::traits::ToolProperty {
+                prop_type: "string".to_string(),
+                description: format!(
+                    "Name of the database connection to query (optional). Available: {}",
+                    self.warehouses.names().join(", ")
+                ),
+            },
+        );
         ToolParameters {
             param_type: "object".to_string(),
             properties: props,
@@ -66,22 +208,178 @@ impl Tool for RunSqlTool {
         &self,
         params: HashMap<String, serde_json::Value>,
     ) -> Pin<Box<dyn Future<Output = Result<ToolResult, String>> + Send>> {
-        let warehouse = self.warehouse.clone();
+        self.execute_with_context(params, &ToolContext::default())
+    }
+
+    fn execute_with_context(
+        &self,
+        params: HashMap<String, serde_json::Value>,
+        context: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<ToolResult, String>> + Send>> {
+        let tool = self.clone();
+        let context = context.clone();
         let sql = params
             .get("sql")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
+        let database = params
+            .get("database")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
 
         Box::pin(async move {
             let sql = sql.ok_or("Missing required parameter: sql")?;
-            match warehouse.execute(&sql).await {
-                Ok(result) => Ok(ToolResult::success(serde_json::json!({
-                    "columns": result.columns,
-                    "rows": result.rows,
-                    "row_count": result.row_count
-                }))),
-                Err(e) => Ok(ToolResult::error(e.to_string())),
-            }
+            tool.run(context, sql, database).await
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatCompletionResponse, ChatMessage, Choice, MessageRole, ToolCall};
+
+    #[tokio::test]
+    async fn test_run_sql_selects_database() {
+        let config = warehouse_conn::RegistryConfig::single("local", "sqlite::memory:");
+        let registry = WarehouseRegistry::from_config(&config).unwrap();
+        let tool = RunSqlTool::new(Arc::new(registry));
+
+        let mut params = HashMap::new();
+        params.insert("sql".to_string(), serde_json::json!("SELECT 1 AS one"));
+        params.insert("database".to_string(), serde_json::json!("local"));
+        let result = tool.execute(params).await.unwrap();
+        assert!(result.success);
+        assert!(result.data.unwrap().get("cache").is_none());
+
+        let mut params = HashMap::new();
+        params.insert("sql".to_string(), serde_json::json!("SELECT 1"));
+        params.insert("database".to_string(), serde_json::json!("missing"));
+        let result = tool.execute(params).await.unwrap();
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_run_sql_applies_policy_for_identity() {
+        let sqlite = SqliteWarehouse::new("sqlite::memory:").with_max_connections(1);
+        sqlite
+            .execute("CREATE TABLE accounts (id INTEGER, owner TEXT)")
+            .await
+            .unwrap();
+        sqlite
+            .execute("INSERT INTO accounts VALUES (1, 'alice'), (2, 'bob')")
+            .await
+            .unwrap();
+
+        let policy = PolicyEngine::from_yaml(
+            r#"
+policies:
+  - name: own-accounts
+    users: ["*"]
+    tables:
+      accounts:
+        row_filter: "owner = {user_id}"
+"#,
+        )
+        .unwrap();
+        let tool = RunSqlTool::new(Arc::new(WarehouseRegistry::single(
+            "default",
+            Arc::new(sqlite),
+        )))
+        .with_policy(Arc::new(policy));
+
+        let mut params = HashMap::new();
+        params.insert(
+            "sql".to_string(),
+            serde_json::json!("SELECT id FROM accounts"),
+        );
+        let context = ToolContext::for_user("bob", Vec::new());
+        let result = tool.execute_with_context(params, &context).await.unwrap();
+        let data = result.data.unwrap();
+        assert_eq!(data["row_count"], 1);
+        assert_eq!(data["rows"][0][0], 2);
+
+        let mut tools = crate::ToolRegistry::new();
+        tools.register(tool);
+        let runtime = Arc::new(crate::AgentRuntime::new("test".to_string(), tools));
+        let orchestrator = crate::AgentOrchestrator::new(runtime)
+            .with_identity(Identity::new("alice"))
+            .with_initial_message("which accounts are mine?".to_string());
+        let mut agent = crate::orchestrator::SelfCorrectingAgent::new(orchestrator);
+        let reply = |message: ChatMessage| ChatCompletionResponse {
+            id: "test".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message,
+                finish_reason: None,
+            }],
+            usage: None,
+        };
+        let answer = agent
+            .execute_with_retry(|messages: Vec<ChatMessage>| async move {
+                let last = messages.last().unwrap();
+                Ok(match last.role {
+                    MessageRole::Tool => reply(ChatMessage {
+                        role: MessageRole::Assistant,
+                        content: last.content.clone(),
+                        tool_calls: None,
+                        tool_call_id: None,
+                    }),
+                    _ => reply(ChatMessage {
+                        role: MessageRole::Assistant,
+                        content: String::new(),
+                        tool_calls: Some(vec![ToolCall {
+                            id: "call-1".to_string(),
+                            name: "run_sql".to_string(),
+                            arguments: serde_json::json!({ "sql": "SELECT id FROM accounts" }),
+                        }]),
+                        tool_call_id: None,
+                    }),
+                })
+            })
+            .await
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_str(&answer).unwrap();
+        assert_eq!(data["rows"], serde_json::json!([[1]]));
+    }
+
+    #[tokio::test]
+    async fn test_run_sql_masks_pii() {
+        let config = warehouse_conn::RegistryConfig::single("local", "sqlite::memory:");
+        let masker = PiiMasker::new(warehouse_conn::PiiConfig {
+            authorized_users: vec!["auditor".to_string()],
+            ..Default::default()
+        });
+        let tool = RunSqlTool::new(Arc::new(WarehouseRegistry::from_config(&config).unwrap()))
+            .with_pii_masker(Arc::new(masker));
+
+        let mut params = HashMap::new();
+        params.insert(
+            "sql".to_string(),
+            serde_json::json!("SELECT 'jane@example.com' AS email"),
+        );
+        let result = tool.execute(params.clone()).await.unwrap();
+        assert_eq!(result.data.unwrap()["rows"][0][0], "j***@example.com");
+        assert!(result.unmasked.is_none());
+
+        let context = ToolContext::for_user("auditor", Vec::new());
+        let result = tool.execute_with_context(params, &context).await.unwrap();
+        assert_eq!(result.unmasked.unwrap()["rows"][0][0], "jane@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_run_sql_reports_cache_hits() {
+        let config = warehouse_conn::RegistryConfig::single("local", "sqlite::memory:");
+        let cache = Arc::new(ResultCache::new(std::time::Duration::from_secs(60)));
+        let tool = RunSqlTool::new(Arc::new(WarehouseRegistry::from_config(&config).unwrap()))
+            .with_result_cache(cache.clone());
+
+        let mut params = HashMap::new();
+        params.insert("sql".to_string(), serde_json::json!("SELECT 1 AS one"));
+        let first = tool.execute(params.clone()).await.unwrap();
+        assert_eq!(first.data.unwrap()["cache"], "miss");
+        let second = tool.execute(params).await.unwrap();
+        assert_eq!(second.data.unwrap()["cache"], "hit");
+        assert_eq!(cache.stats().await.hits, 1);
+    }
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use warehouse_conn::Identity;
 
 pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
@@ -11,6 +12,40 @@ pub trait Tool: Send + Sync {
         &self,
         params: HashMap<String, serde_json::Value>,
     ) -> Pin<Box<dyn Future<Output = Result<ToolResult, String>> + Send>>;
+
+    fn execute_with_context(
+        &self,
+        params: HashMap<String, serde_json::Value>,
+        _context: &ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<ToolResult, String>> + Send>> {
+        self.execute(params)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolContext {
+    pub identity: Identity,
+    /// The user question the tool call serves, if known.
+    #[serde(default)]
+    pub question: Option<String>,
+}
+
+impl ToolContext {
+    pub fn for_user(user_id: &str, groups: Vec<String>) -> Self {
+        Self::for_identity(Identity::new(user_id).with_groups(groups))
+    }
+
+    pub fn for_identity(identity: Identity) -> Self {
+        Self {
+            identity,
+            question: None,
+        }
+    }
+
+    pub fn with_question(mut self, question: &str) -> Self {
+        self.question = Some(question.to_string());
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +68,8 @@ pub struct ToolResult {
     pub success: bool,
     pub data: Option<serde_json::Value>,
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unmasked: Option<serde_json::Value>,
 }
 
 impl ToolResult {
@@ -41,6 +78,7 @@ impl ToolResult {
             success: true,
             data: Some(data),
             error: None,
+            unmasked: None,
         }
     }
 
@@ -49,8 +87,14 @@ impl ToolResult {
             success: false,
             data: None,
             error: Some(message),
+            unmasked: None,
         }
     }
+
+    pub fn with_unmasked(mut self, data: serde_json::Value) -> Self {
+        self.unmasked = Some(data);
+        self
+    }
 }
 
 pub fn search_tables_params() -> ToolParameters {
@@ -78,6 +122,13 @@ pub fn run_sql_params() -> ToolParameters {
             description: "SQL query to execute".to_string(),
         },
     );
+    props.insert(
+        "database".to_string(),
+        ToolProperty {
+            prop_type: "string".to_string(),
+            description: "Name of the database connection to query".to_string(),
+        },
+    );
     ToolParameters {
         param_type: "object".to_string(),
         properties: props,
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use warehouse_conn::{transpile, Backend};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MemoryScope {
@@ -87,6 +88,8 @@ pub struct Correction {
     pub error_message: String,
     pub explanation: String,
     pub table_involved: Vec<String>,
+    #[serde(default)]
+    pub dialect: Option<String>,
 }
 
 impl Correction {
@@ -102,6 +105,7 @@ impl Correction {
             error_message,
             explanation,
             table_involved: Vec::new(),
+            dialect: None,
         }
     }
 
@@ -110,11 +114,46 @@ impl Correction {
         self
     }
 
+    pub fn with_dialect(mut self, dialect: &str) -> Self {
+        self.dialect = Some(dialect.to_string());
+        self
+    }
+
+    pub fn transpile(&self, target: Backend) -> Result<Correction, String> {
+        let source: Backend = match &self.dialect {
+            Some(dialect) => dialect
+                .parse()
+                .map_err(|e: warehouse_conn::Error| e.to_string())?,
+            None => return Ok(self.clone()),
+        };
+
+        let original =
+            transpile(&self.original_query, source, target).map_err(|e| e.to_string())?;
+        let corrected =
+            transpile(&self.corrected_query, source, target).map_err(|e| e.to_string())?;
+        if let Some(issue) = original.issues.iter().chain(corrected.issues.iter()).next() {
+            return Err(issue.message.clone());
+        }
+
+        Ok(Correction {
+            original_query: original.sql,
+            corrected_query: corrected.sql,
+            error_message: self.error_message.clone(),
+            explanation: self.explanation.clone(),
+            table_involved: self.table_involved.clone(),
+            dialect: Some(target.name().to_string()),
+        })
+    }
+
     pub fn to_memory(&self, scope: MemoryScope) -> Memory {
         let content = format!(
             "Query Correction:\nOriginal: {}\nCorrected: {}\nError: {}\nExplanation: {}",
             self.original_query, self.corrected_query, self.error_message, self.explanation
         );
-        Memory::new(scope, content, MemoryType::Correction)
+        let memory = Memory::new(scope, content, MemoryType::Correction);
+        match &self.dialect {
+            Some(dialect) => memory.with_metadata("dialect", serde_json::json!(dialect)),
+            None => memory,
+        }
     }
 }
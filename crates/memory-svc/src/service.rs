@@ -4,6 +4,7 @@ use tokio::sync::RwLock;
 
 use crate::error::Error;
 use crate::models::{Correction, Memory, MemoryScope, MemoryType};
+use warehouse_conn::Backend;
 
 pub struct MemoryService {
     memories: Arc<RwLock<HashMap<String, Vec<Memory>>>>,
@@ -144,18 +145,89 @@ impl MemoryService {
         scope: Option<MemoryScope>,
     ) -> Result<String, Error> {
         let memories = self.retrieve(query, scope, 5).await?;
+        Ok(self.format_memories(&memories, None))
+    }
 
+    pub async fn inject_into_prompt_for_dialect(
+        &self,
+        query: &str,
+        scope: Option<MemoryScope>,
+        target: Backend,
+    ) -> Result<String, Error> {
+        let memories = self.retrieve(query, scope, 5).await?;
+        Ok(self.format_memories(&memories, Some(target)))
+    }
+
+    fn format_memories(&self, memories: &[Memory], target: Option<Backend>) -> String {
         if memories.is_empty() {
-            return Ok(String::new());
+            return String::new();
         }
 
         let mut context = String::from("\n\nRelevant memories:\n");
 
-        for memory in &memories {
-            context.push_str(&format!("- {}\n", memory.content));
+        for memory in memories {
+            let content = match target {
+                Some(target) => self.adapt_to_dialect(memory, target),
+                None => memory.content.clone(),
+            };
+            match memory
+                .metadata
+                .get("stale_references")
+                .and_then(|r| r.as_array())
+            {
+                Some(references) => {
+                    let references: Vec<&str> =
+                        references.iter().filter_map(|r| r.as_str()).collect();
+                    context.push_str(&format!(
+                        "- {} (may be outdated: {} no longer exists)\n",
+                        content,
+                        references.join(", ")
+                    ));
+                }
+                None => context.push_str(&format!("- {}\n", content)),
+            }
+        }
+
+        context
+    }
+
+    fn adapt_to_dialect(&self, memory: &Memory, target: Backend) -> String {
+        let dialect = match memory.metadata.get("dialect").and_then(|d| d.as_str()) {
+            Some(dialect) if memory.memory_type == MemoryType::Correction => dialect,
+            _ => return memory.content.clone(),
+        };
+
+        let correction = match self.parse_correction(&memory.content) {
+            Some(correction) => correction.with_dialect(dialect),
+            None => return memory.content.clone(),
+        };
+
+        match correction.transpile(target) {
+            Ok(transpiled) => transpiled.to_memory(memory.scope.clone()).content,
+            Err(_) => format!("{} (written for {})", memory.content, dialect),
         }
+    }
 
-        Ok(context)
+    /// Marks memories that `check` reports as referring to dropped schema objects,
+    /// e.g. `|text| diff.stale_references(text)`. Returns the newly flagged memories.
+    pub async fn flag_stale<F>(&self, check: F) -> Vec<Memory>
+    where
+        F: Fn(&str) -> Vec<String>,
+    {
+        let mut memories = self.memories.write().await;
+        let mut flagged = Vec::new();
+
+        for memory in memories.values_mut().flatten() {
+            let references = check(&memory.content);
+            if references.is_empty() {
+                continue;
+            }
+            memory.metadata["stale"] = serde_json::json!(true);
+            memory.metadata["stale_references"] = serde_json::json!(references);
+            flagged.push(memory.clone());
+        }
+
+        flagged
     }
 
     pub async fn delete(&self, scope: &MemoryScope, memory_id: i64) -> Result<(), Error> {
@@ -230,4 +302,68 @@ mod tests {
             .unwrap();
         assert!(!corrections.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_inject_correction_for_other_dialect() {
+        let service = MemoryService::new();
+
+        let correction = Correction::new(
+            "SELECT * FROM users WHERE email ILIKE '%@corp.com'".to_string(),
+            "SELECT * FROM users WHERE email ILIKE '%@corp.com' AND active".to_string(),
+            "missing filter".to_string(),
+            "Only active users count".to_string(),
+        )
+        .with_dialect("postgres");
+
+        service
+            .save_correction(correction, MemoryScope::global())
+            .await
+            .unwrap();
+
+        let context = service
+            .inject_into_prompt_for_dialect("users", None, Backend::Sqlite)
+            .await
+            .unwrap();
+        assert!(context.contains("LOWER(email) LIKE LOWER('%@corp.com')"));
+        assert!(!context.contains("ILIKE"));
+    }
+
+    #[tokio::test]
+    async fn test_flag_stale_memories() {
+        let service = MemoryService::new();
+        service
+            .save(Memory::new(
+                MemoryScope::table("users"),
+                "Use users.age for demographic questions".to_string(),
+                MemoryType::Fact,
+            ))
+            .await
+            .unwrap();
+        service
+            .save(Memory::new(
+                MemoryScope::global(),
+                "Revenue is in orders.total".to_string(),
+                MemoryType::Fact,
+            ))
+            .await
+            .unwrap();
+
+        let flagged = service
+            .flag_stale(|text| {
+                if text.contains("users.age") {
+                    vec!["users.age".to_string()]
+                } else {
+                    vec![]
+                }
+            })
+            .await;
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].metadata["stale"], serde_json::json!(true));
+
+        let prompt = service
+            .inject_into_prompt("users age", Some(MemoryScope::table("users")))
+            .await
+            .unwrap();
+        assert!(prompt.contains("may be outdated: users.age no longer exists"));
+    }
 }
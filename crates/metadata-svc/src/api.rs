@@ -0,0 +1,691 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::slice;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::lineage::{JoinStep, LineageGraph, LineageHop};
+use crate::models::{Annotation, GlossaryTerm, Schema, TableMetadata};
+use crate::service::MetadataService;
+use crate::versioning::SchemaDiff;
+
+pub use warehouse_conn::auth::require_write_token;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+type ApiResult<T> = Result<T, Error>;
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Metadata(_) => StatusCode::BAD_REQUEST,
+            Error::Database(message) => {
+                tracing::error!("Metadata store error: {}", message);
+                let body = json!({ "error": "Internal database error" });
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response();
+            }
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl PageParams {
+    /// The requested offset and the limit clamped to the allowed page size.
+    fn window(&self) -> (usize, usize) {
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        (self.offset.unwrap_or(0), limit)
+    }
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, params: &PageParams) -> Self {
+        let (offset, limit) = params.window();
+        let total = items.len();
+        Self {
+            items: items.into_iter().skip(offset).take(limit).collect(),
+            total,
+            offset,
+            limit,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffParams {
+    since: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TraverseParams {
+    node: String,
+    depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PathParams {
+    from: String,
+    to: String,
+}
+
+/// Routes for schemas, tables, annotations, glossary terms, schema diffs and
+/// lineage, plus `/openapi.json`. Mount with `Router::nest` or run with
+/// [`serve`].
+pub fn router(service: Arc<MetadataService>) -> Router {
+    Router::new()
+        .route("/openapi.json", get(|| async { Json(openapi()) }))
+        .route("/schemas", get(list_schemas).post(create_schema))
+        .route(
+            "/schemas/:schema",
+            get(get_schema).put(put_schema).delete(delete_schema),
+        )
+        .route("/schemas/:schema/diffs", get(list_diffs))
+        .route(
+            "/schemas/:schema/tables",
+            get(list_tables).post(create_table),
+        )
+        .route(
+            "/schemas/:schema/tables/:table",
+            get(get_table).put(put_table).delete(delete_table),
+        )
+        .route(
+            "/schemas/:schema/tables/:table/annotations",
+            get(list_annotations).post(add_annotation),
+        )
+        .route(
+            "/schemas/:schema/tables/:table/columns/:column/annotations",
+            get(list_column_annotations).post(add_column_annotation),
+        )
+        .route("/glossary", get(list_glossary).post(create_glossary_term))
+        .route(
+            "/glossary/:term",
+            get(get_glossary_term)
+                .put(put_glossary_term)
+                .delete(delete_glossary_term),
+        )
+        .route("/lineage", get(get_lineage))
+        .route("/lineage/upstream", get(upstream))
+        .route("/lineage/downstream", get(downstream))
+        .route("/lineage/path", get(join_path))
+        .with_state(service)
+}
+
+/// Serves [`router`] under `/metadata`, matching [`openapi`], with writes
+/// guarded by [`require_write_token`].
+pub async fn serve(
+    service: Arc<MetadataService>,
+    addr: SocketAddr,
+    write_token: Option<String>,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Starting metadata API on {}", addr);
+    let app = Router::new().nest(
+        "/metadata",
+        require_write_token(router(service), write_token),
+    );
+    axum::serve(listener, app).await
+}
+
+async fn list_schemas(
+    State(service): State<Arc<MetadataService>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<Page<Schema>>> {
+    let (offset, limit) = page.window();
+    let (items, total) = service.list_schemas_page(offset, limit).await?;
+    Ok(Json(Page {
+        items,
+        total,
+        offset,
+        limit,
+    }))
+}
+
+async fn create_schema(
+    State(service): State<Arc<MetadataService>>,
+    Json(schema): Json<Schema>,
+) -> ApiResult<(StatusCode, Json<Schema>)> {
+    Ok((
+        StatusCode::CREATED,
+        Json(service.create_schema(schema).await?),
+    ))
+}
+
+async fn get_schema(
+    State(service): State<Arc<MetadataService>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<Schema>> {
+    Ok(Json(service.get_schema(&name).await?))
+}
+
+async fn put_schema(
+    State(service): State<Arc<MetadataService>>,
+    Path(name): Path<String>,
+    Json(schema): Json<Schema>,
+) -> ApiResult<Json<Schema>> {
+    let synced = service.sync_schema(Schema { name, ..schema }).await?;
+    Ok(Json(synced.snapshot.schema))
+}
+
+async fn delete_schema(
+    State(service): State<Arc<MetadataService>>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    service.delete_schema(&name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_diffs(
+    State(service): State<Arc<MetadataService>>,
+    Path(name): Path<String>,
+    Query(params): Query<DiffParams>,
+) -> ApiResult<Json<Page<SchemaDiff>>> {
+    let diffs = service
+        .list_diffs(Some(&name), params.since.as_deref())
+        .await?;
+    let page = PageParams {
+        offset: params.offset,
+        limit: params.limit,
+    };
+    Ok(Json(Page::new(diffs, &page)))
+}
+
+async fn list_tables(
+    State(service): State<Arc<MetadataService>>,
+    Path(schema): Path<String>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<Page<TableMetadata>>> {
+    let schema = service.get_schema(&schema).await?;
+    Ok(Json(Page::new(schema.tables, &page)))
+}
+
+async fn create_table(
+    State(service): State<Arc<MetadataService>>,
+    Path(schema): Path<String>,
+    Json(table): Json<TableMetadata>,
+) -> ApiResult<(StatusCode, Json<TableMetadata>)> {
+    service.create_table(&schema, table.clone()).await?;
+    Ok((StatusCode::CREATED, Json(table)))
+}
+
+async fn get_table(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table)): Path<(String, String)>,
+) -> ApiResult<Json<TableMetadata>> {
+    Ok(Json(service.get_table(&schema, &table).await?))
+}
+
+async fn put_table(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, name)): Path<(String, String)>,
+    Json(table): Json<TableMetadata>,
+) -> ApiResult<Json<TableMetadata>> {
+    let table = TableMetadata { name, ..table };
+    service.save_table(&schema, table.clone()).await?;
+    Ok(Json(table))
+}
+
+async fn delete_table(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    service.delete_table(&schema, &table).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_annotations(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table)): Path<(String, String)>,
+) -> ApiResult<Json<Vec<Annotation>>> {
+    Ok(Json(service.get_annotations(&schema, &table).await?))
+}
+
+async fn add_annotation(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table)): Path<(String, String)>,
+    Json(annotation): Json<Annotation>,
+) -> ApiResult<(StatusCode, Json<Annotation>)> {
+    service
+        .add_annotation(&schema, &table, annotation.clone())
+        .await?;
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
+
+async fn list_column_annotations(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table, column)): Path<(String, String, String)>,
+) -> ApiResult<Json<Vec<Annotation>>> {
+    Ok(Json(
+        service
+            .get_column_annotations(&schema, &table, &column)
+            .await?,
+    ))
+}
+
+async fn add_column_annotation(
+    State(service): State<Arc<MetadataService>>,
+    Path((schema, table, column)): Path<(String, String, String)>,
+    Json(annotation): Json<Annotation>,
+) -> ApiResult<(StatusCode, Json<Annotation>)> {
+    service
+        .add_column_annotation(&schema, &table, &column, annotation.clone())
+        .await?;
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
+
+async fn list_glossary(
+    State(service): State<Arc<MetadataService>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<Page<GlossaryTerm>>> {
+    Ok(Json(Page::new(service.list_glossary_terms().await?, &page)))
+}
+
+async fn create_glossary_term(
+    State(service): State<Arc<MetadataService>>,
+    Json(term): Json<GlossaryTerm>,
+) -> ApiResult<(StatusCode, Json<GlossaryTerm>)> {
+    service.create_glossary_term(term.clone()).await?;
+    Ok((StatusCode::CREATED, Json(term)))
+}
+
+async fn get_glossary_term(
+    State(service): State<Arc<MetadataService>>,
+    Path(term): Path<String>,
+) -> ApiResult<Json<GlossaryTerm>> {
+    Ok(Json(service.get_glossary_term(&term).await?))
+}
+
+async fn put_glossary_term(
+    State(service): State<Arc<MetadataService>>,
+    Path(name): Path<String>,
+    Json(term): Json<GlossaryTerm>,
+) -> ApiResult<Json<GlossaryTerm>> {
+    let term = GlossaryTerm { term: name, ..term };
+    service.save_glossary_term(term.clone()).await?;
+    Ok(Json(term))
+}
+
+async fn delete_glossary_term(
+    State(service): State<Arc<MetadataService>>,
+    Path(term): Path<String>,
+) -> ApiResult<StatusCode> {
+    service.delete_glossary_term(&term).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_lineage(State(service): State<Arc<MetadataService>>) -> ApiResult<Json<LineageGraph>> {
+    Ok(Json(service.get_lineage().await?))
+}
+
+async fn upstream(
+    State(service): State<Arc<MetadataService>>,
+    Query(params): Query<TraverseParams>,
+) -> ApiResult<Json<Vec<LineageHop>>> {
+    Ok(Json(
+        service.get_upstream(&params.node, params.depth).await?,
+    ))
+}
+
+async fn downstream(
+    State(service): State<Arc<MetadataService>>,
+    Query(params): Query<TraverseParams>,
+) -> ApiResult<Json<Vec<LineageHop>>> {
+    Ok(Json(
+        service.get_downstream(&params.node, params.depth).await?,
+    ))
+}
+
+async fn join_path(
+    State(service): State<Arc<MetadataService>>,
+    Query(params): Query<PathParams>,
+) -> ApiResult<Json<Vec<JoinStep>>> {
+    Ok(Json(service.join_path(&params.from, &params.to).await?))
+}
+
+fn param(name: &str, location: &str, required: bool, kind: &str) -> Value {
+    json!({ "name": name, "in": location, "required": required, "schema": { "type": kind } })
+}
+
+fn operation(summary: &str, params: &[Value], body: Option<&str>, ok: (&str, &str)) -> Value {
+    let error = json!({ "$ref": "#/components/responses/Error" });
+    let mut responses = json!({ "400": error, "404": error, "500": error });
+    responses[ok.0] = if ok.1.is_empty() {
+        json!({ "description": "Success" })
+    } else {
+        json!({
+            "description": "Success",
+            "content": { "application/json": { "schema": { "$ref": ok.1 } } }
+        })
+    };
+
+    let mut operation = json!({ "summary": summary, "parameters": params });
+    if let Some(schema) = body {
+        responses["409"] = error;
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": { "$ref": schema } } }
+        });
+    }
+    operation["responses"] = responses;
+    operation
+}
+
+/// OpenAPI 3 description of [`router`], mounted under `/metadata`.
+pub fn openapi() -> Value {
+    let schema = param("schema", "path", true, "string");
+    let table = param("table", "path", true, "string");
+    let column = param("column", "path", true, "string");
+    let term = param("term", "path", true, "string");
+    let page = [
+        param("offset", "query", false, "integer"),
+        param("limit", "query", false, "integer"),
+    ];
+    let traverse = [
+        param("node", "query", true, "string"),
+        param("depth", "query", false, "integer"),
+    ];
+    let with_page =
+        |params: &[Value]| -> Vec<Value> { params.iter().chain(&page).cloned().collect() };
+    let object = |properties: Value| json!({ "type": "object", "properties": properties });
+    let page_of = |item: &str| {
+        json!({
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": { "$ref": item } },
+                "total": { "type": "integer" },
+                "offset": { "type": "integer" },
+                "limit": { "type": "integer" }
+            }
+        })
+    };
+
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": "QuerySmith Metadata API", "version": env!("CARGO_PKG_VERSION") },
+        "servers": [{ "url": "/metadata" }],
+        "security": [{ "bearer": [] }],
+        "paths": {
+            "/schemas": {
+                "get": operation("List schemas", &page, None, ("200", "#/components/schemas/SchemaPage")),
+                "post": operation("Create a schema", &[], Some("#/components/schemas/Schema"), ("201", "#/components/schemas/Schema"))
+            },
+            "/schemas/{schema}": {
+                "get": operation("Get a schema", slice::from_ref(&schema), None, ("200", "#/components/schemas/Schema")),
+                "put": operation("Create or replace a schema, recording a snapshot", slice::from_ref(&schema), Some("#/components/schemas/Schema"), ("200", "#/components/schemas/Schema")),
+                "delete": operation("Delete a schema", slice::from_ref(&schema), None, ("204", ""))
+            },
+            "/schemas/{schema}/diffs": {
+                "get": operation("Schema diff history", &with_page(&[schema.clone(), param("since", "query", false, "string")]), None, ("200", "#/components/schemas/SchemaDiffPage"))
+            },
+            "/schemas/{schema}/tables": {
+                "get": operation("List tables", &with_page(slice::from_ref(&schema)), None, ("200", "#/components/schemas/TablePage")),
+                "post": operation("Add a table", slice::from_ref(&schema), Some("#/components/schemas/Table"), ("201", "#/components/schemas/Table"))
+            },
+            "/schemas/{schema}/tables/{table}": {
+                "get": operation("Get a table", &[schema.clone(), table.clone()], None, ("200", "#/components/schemas/Table")),
+                "put": operation("Create or replace a table", &[schema.clone(), table.clone()], Some("#/components/schemas/Table"), ("200", "#/components/schemas/Table")),
+                "delete": operation("Delete a table", &[schema.clone(), table.clone()], None, ("204", ""))
+            },
+            "/schemas/{schema}/tables/{table}/annotations": {
+                "get": operation("List table annotations", &[schema.clone(), table.clone()], None, ("200", "#/components/schemas/Annotations")),
+                "post": operation("Annotate a table", &[schema.clone(), table.clone()], Some("#/components/schemas/Annotation"), ("201", "#/components/schemas/Annotation"))
+            },
+            "/schemas/{schema}/tables/{table}/columns/{column}/annotations": {
+                "get": operation("List column annotations", &[schema.clone(), table.clone(), column.clone()], None, ("200", "#/components/schemas/Annotations")),
+                "post": operation("Annotate a column", &[schema, table, column], Some("#/components/schemas/Annotation"), ("201", "#/components/schemas/Annotation"))
+            },
+            "/glossary": {
+                "get": operation("List glossary terms", &page, None, ("200", "#/components/schemas/GlossaryPage")),
+                "post": operation("Create a glossary term", &[], Some("#/components/schemas/GlossaryTerm"), ("201", "#/components/schemas/GlossaryTerm"))
+            },
+            "/glossary/{term}": {
+                "get": operation("Get a glossary term by name or synonym", slice::from_ref(&term), None, ("200", "#/components/schemas/GlossaryTerm")),
+                "put": operation("Create or replace a glossary term", slice::from_ref(&term), Some("#/components/schemas/GlossaryTerm"), ("200", "#/components/schemas/GlossaryTerm")),
+                "delete": operation("Delete a glossary term", &[term], None, ("204", ""))
+            },
+            "/lineage": {
+                "get": operation("Full lineage graph", &[], None, ("200", "#/components/schemas/LineageGraph"))
+            },
+            "/lineage/upstream": {
+                "get": operation("Nodes a node depends on", &traverse, None, ("200", "#/components/schemas/LineageHops"))
+            },
+            "/lineage/downstream": {
+                "get": operation("Nodes depending on a node", &traverse, None, ("200", "#/components/schemas/LineageHops"))
+            },
+            "/lineage/path": {
+                "get": operation("Join path between two tables", &[param("from", "query", true, "string"), param("to", "query", true, "string")], None, ("200", "#/components/schemas/JoinPath"))
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" }
+            },
+            "responses": {
+                "Error": {
+                    "description": "Error",
+                    "content": { "application/json": { "schema": object(json!({ "error": { "type": "string" } })) } }
+                }
+            },
+            "schemas": {
+                "Annotation": object(json!({
+                    "key": { "type": "string" },
+                    "value": { "type": "string" },
+                    "source": { "type": "string", "nullable": true }
+                })),
+                "Annotations": { "type": "array", "items": { "$ref": "#/components/schemas/Annotation" } },
+                "Column": object(json!({
+                    "name": { "type": "string" },
+                    "data_type": { "type": "string" },
+                    "nullable": { "type": "boolean" },
+                    "comment": { "type": "string", "nullable": true },
+                    "annotations": { "$ref": "#/components/schemas/Annotations" }
+                })),
+                "Table": object(json!({
+                    "name": { "type": "string" },
+                    "schema_name": { "type": "string", "nullable": true },
+                    "columns": { "type": "array", "items": { "$ref": "#/components/schemas/Column" } },
+                    "primary_key": { "type": "array", "items": { "type": "string" }, "nullable": true },
+                    "annotations": { "$ref": "#/components/schemas/Annotations" },
+                    "description": { "type": "string", "nullable": true }
+                })),
+                "Schema": object(json!({
+                    "id": { "type": "integer", "nullable": true },
+                    "name": { "type": "string" },
+                    "source": { "type": "string" },
+                    "tables": { "type": "array", "items": { "$ref": "#/components/schemas/Table" } },
+                    "created_at": { "type": "string", "nullable": true },
+                    "updated_at": { "type": "string", "nullable": true }
+                })),
+                "GlossaryTerm": object(json!({
+                    "term": { "type": "string" },
+                    "definition": { "type": "string" },
+                    "synonyms": { "type": "array", "items": { "type": "string" } },
+                    "tables": { "type": "array", "items": { "type": "string" } },
+                    "columns": { "type": "array", "items": { "type": "string" } },
+                    "sql": { "type": "string", "nullable": true }
+                })),
+                "SchemaDiff": object(json!({
+                    "schema_name": { "type": "string" },
+                    "from_version": { "type": "integer" },
+                    "to_version": { "type": "integer" },
+                    "created_at": { "type": "string" },
+                    "added_tables": { "type": "array", "items": { "type": "string" } },
+                    "removed_tables": { "type": "array", "items": { "type": "string" } },
+                    "renamed_tables": { "type": "array", "items": { "type": "object" } },
+                    "changed_tables": { "type": "array", "items": { "type": "object" } }
+                })),
+                "LineageGraph": object(json!({
+                    "nodes": { "type": "array", "items": { "type": "object" } },
+                    "relationships": { "type": "array", "items": { "type": "object" } }
+                })),
+                "LineageHops": {
+                    "type": "array",
+                    "items": object(json!({ "node": { "type": "string" }, "depth": { "type": "integer" } }))
+                },
+                "JoinPath": {
+                    "type": "array",
+                    "items": object(json!({
+                        "from": { "type": "string" },
+                        "to": { "type": "string" },
+                        "condition": { "type": "string", "nullable": true }
+                    }))
+                },
+                "SchemaPage": page_of("#/components/schemas/Schema"),
+                "TablePage": page_of("#/components/schemas/Table"),
+                "GlossaryPage": page_of("#/components/schemas/GlossaryTerm"),
+                "SchemaDiffPage": page_of("#/components/schemas/SchemaDiff")
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn call(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_schema_crud_and_errors() {
+        let app = router(Arc::new(MetadataService::new()));
+        let schema = json!({
+            "id": null, "name": "main", "source": "postgres", "created_at": null, "updated_at": null,
+            "tables": [{ "name": "users", "schema_name": null, "primary_key": null, "annotations": [],
+                         "description": null,
+                         "columns": [{ "name": "email", "data_type": "text", "nullable": false, "comment": null }] }]
+        });
+
+        assert_eq!(
+            call(&app, "POST", "/schemas", Some(schema.clone())).await.0,
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            call(&app, "POST", "/schemas", Some(schema)).await.0,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            call(&app, "GET", "/schemas/missing", None).await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        let annotation = json!({ "key": "pii", "value": "true", "source": null });
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/schemas/main/tables/users/columns/email/annotations",
+            Some(annotation),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (_, annotations) = call(
+            &app,
+            "GET",
+            "/schemas/main/tables/users/columns/email/annotations",
+            None,
+        )
+        .await;
+        assert_eq!(annotations[0]["key"], "pii");
+
+        let (status, page) = call(&app, "GET", "/schemas/main/tables?limit=1", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["name"], "users");
+
+        assert_eq!(
+            call(&app, "DELETE", "/schemas/main/tables/users", None)
+                .await
+                .0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(&app, "GET", "/schemas/main/tables/users", None)
+                .await
+                .0,
+            StatusCode::NOT_FOUND
+        );
+        let (_, spec) = call(&app, "GET", "/openapi.json", None).await;
+        assert!(spec["paths"]["/lineage/path"]["get"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_write_token_and_unknown_lineage_node() {
+        let service = Arc::new(MetadataService::new());
+        let app = require_write_token(router(service.clone()), Some("secret".to_string()));
+        let term = json!({ "term": "ARR", "definition": "Annual recurring revenue" });
+        assert_eq!(
+            call(&app, "POST", "/glossary", Some(term.clone())).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        let request = Request::builder()
+            .method("POST")
+            .uri("/glossary")
+            .header("content-type", "application/json")
+            .header("authorization", "Bearer secret")
+            .body(Body::from(term.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let read_only = require_write_token(router(service), None);
+        assert_eq!(
+            call(&read_only, "POST", "/glossary", Some(term)).await.0,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call(&read_only, "GET", "/lineage/upstream?node=missing", None)
+                .await
+                .0,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -9,4 +9,6 @@ pub enum Error {
     Database(String),
     #[error("Not found: {0}")]
     NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
 }
@@ -0,0 +1,619 @@
+use sqlparser::ast::{
+    visit_expressions, visit_relations, CreateTable, Expr, GroupByExpr, Insert, JoinConstraint,
+    JoinOperator, ObjectName, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+};
+use sqlparser::dialect::{Dialect as SqlDialect, GenericDialect};
+use sqlparser::parser::Parser;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use warehouse_conn::{parser_dialect, Backend, Warehouse, WarehouseRegistry};
+use workflow_engine::{Action, WorkflowDefinition};
+
+use crate::error::Error;
+use crate::lineage::{
+    table_id, LineageGraph, LineageNode, LineageRelationship, NodeType, RelationshipType,
+};
+
+const AGGREGATES: &[&str] = &[
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "array_agg",
+    "string_agg",
+    "group_concat",
+    "bool_and",
+    "bool_or",
+    "stddev",
+    "variance",
+];
+
+const POSTGRES_VIEWS: &str = "SELECT schemaname || '.' || viewname AS name, definition \
+     FROM pg_views WHERE schemaname NOT IN ('pg_catalog', 'information_schema')";
+
+const SQLITE_VIEWS: &str = "SELECT name, sql FROM sqlite_master WHERE type = 'view'";
+
+/// Lineage of every `CREATE VIEW`, `CREATE TABLE ... AS` and `INSERT ... SELECT`
+/// in `sql`. Bare queries are attributed to `target` and skipped without one.
+pub fn lineage_from_sql(
+    sql: &str,
+    dialect: &dyn SqlDialect,
+    target: Option<(&str, NodeType)>,
+) -> Result<LineageGraph, Error> {
+    let statements = Parser::parse_sql(dialect, sql)
+        .map_err(|e| Error::Metadata(format!("failed to parse SQL: {}", e)))?;
+
+    let mut graph = LineageGraph::default();
+    for statement in &statements {
+        let (name, node_type, columns, query) = match statement {
+            Statement::CreateView {
+                name,
+                columns,
+                query,
+                ..
+            } => (
+                object_name(name),
+                NodeType::View,
+                columns
+                    .iter()
+                    .map(|c| c.name.value.to_lowercase())
+                    .collect(),
+                query.as_ref(),
+            ),
+            Statement::CreateTable(CreateTable {
+                name,
+                columns,
+                query: Some(query),
+                ..
+            }) => (
+                object_name(name),
+                NodeType::Table,
+                columns
+                    .iter()
+                    .map(|c| c.name.value.to_lowercase())
+                    .collect(),
+                query.as_ref(),
+            ),
+            Statement::Insert(Insert {
+                table_name,
+                columns,
+                source: Some(query),
+                ..
+            }) => (
+                object_name(table_name),
+                NodeType::Table,
+                columns.iter().map(|c| c.value.to_lowercase()).collect(),
+                query.as_ref(),
+            ),
+            Statement::Query(query) => match target {
+                Some((name, node_type)) => {
+                    (name.to_string(), node_type, Vec::new(), query.as_ref())
+                }
+                None => continue,
+            },
+            _ => continue,
+        };
+        graph.merge(query_lineage(
+            &name,
+            node_type,
+            &columns,
+            query,
+            &statement.to_string(),
+        ));
+    }
+    Ok(graph)
+}
+
+/// Lineage of the views defined in `warehouse`, read from `pg_views` on
+/// PostgreSQL and `sqlite_master` on SQLite.
+pub async fn lineage_from_warehouse(warehouse: &dyn Warehouse) -> Result<LineageGraph, Error> {
+    let backend = warehouse.dialect().backend();
+    let sql = match backend {
+        Backend::Postgres => POSTGRES_VIEWS,
+        Backend::Sqlite => SQLITE_VIEWS,
+        _ => {
+            return Err(Error::Metadata(format!(
+                "lineage extraction is not supported for {}",
+                warehouse.dialect().name()
+            )))
+        }
+    };
+    let result = warehouse
+        .execute(sql)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    let dialect = parser_dialect(backend);
+    let mut graph = LineageGraph::default();
+    for row in &result.rows {
+        let (Some(name), Some(definition)) = (
+            row.first().and_then(|v| v.as_str()),
+            row.get(1).and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let name = table_id(name);
+        match lineage_from_sql(definition, dialect.as_ref(), Some((&name, NodeType::View))) {
+            Ok(view) => graph.merge(view.with_origin("warehouse")),
+            Err(e) => tracing::warn!("Skipping lineage for view {}: {}", name, e),
+        }
+    }
+    Ok(graph)
+}
+
+/// Lineage of the `query` steps in `workflow`. Steps that only read data
+/// become `Query` nodes with id `workflow:<workflow>/<step>`. Each step's SQL
+/// is parsed in the dialect of its `database` in `warehouses`, falling back
+/// to a generic dialect when the connection is unknown.
+pub fn lineage_from_workflow(
+    workflow: &WorkflowDefinition,
+    warehouses: Option<&WarehouseRegistry>,
+) -> LineageGraph {
+    let mut graph = LineageGraph::default();
+    for step in &workflow.steps {
+        let Action::Query { sql, database } = &step.action else {
+            continue;
+        };
+        let id = format!("workflow:{}/{}", workflow.name, step.name);
+        let dialect: Box<dyn SqlDialect> =
+            match warehouses.and_then(|registry| registry.get(database.as_deref()).ok()) {
+                Some(warehouse) => parser_dialect(warehouse.dialect().backend()),
+                None => Box::new(GenericDialect {}),
+            };
+        match lineage_from_sql(sql, dialect.as_ref(), Some((&id, NodeType::Query))) {
+            Ok(step_graph) => graph.merge(step_graph.with_origin(&id)),
+            Err(e) => tracing::warn!("Skipping lineage for step {}: {}", id, e),
+        }
+    }
+    graph
+}
+
+/// Lineage of `query` into `target`. Output columns are named by `columns`
+/// when given, e.g. an `INSERT`'s column list, and otherwise by the first
+/// `SELECT` of the query; every branch of a set operation maps onto them by
+/// position.
+fn query_lineage(
+    target: &str,
+    node_type: NodeType,
+    columns: &[String],
+    query: &Query,
+    sql: &str,
+) -> LineageGraph {
+    let mut graph = LineageGraph::default();
+    graph.add_node(table_node(target, node_type));
+
+    let ctes: HashSet<String> = query
+        .with
+        .iter()
+        .flat_map(|with| &with.cte_tables)
+        .map(|cte| cte.alias.name.value.to_lowercase())
+        .collect();
+    let mut sources: Vec<String> = Vec::new();
+    let _ = visit_relations(query, |relation| {
+        let name = object_name(relation);
+        if !ctes.contains(&name) && name != target && !sources.contains(&name) {
+            sources.push(name);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let mut selects = Vec::new();
+    collect_selects(query.body.as_ref(), &mut selects);
+    let relationship_type = if node_type == NodeType::Query {
+        RelationshipType::DependsOn
+    } else if selects.iter().any(|select| is_aggregated(select)) {
+        RelationshipType::AggregatedFrom
+    } else {
+        RelationshipType::DerivedFrom
+    };
+    for source in &sources {
+        graph.add_node(table_node(source, NodeType::Table));
+        graph.add_relationship(LineageRelationship {
+            from_node: target.to_string(),
+            to_node: source.clone(),
+            relationship_type,
+            transform: Some(sql.to_string()),
+            origin: None,
+        });
+    }
+
+    let output_names: Vec<Option<String>> = if columns.is_empty() {
+        selects
+            .first()
+            .map(|select| select.projection.iter().map(projection_name).collect())
+            .unwrap_or_default()
+    } else {
+        columns.iter().cloned().map(Some).collect()
+    };
+
+    for select in selects {
+        let aliases = table_aliases(select, &sources);
+        let tables: HashSet<&String> = aliases.values().collect();
+        let single_table = if tables.len() == 1 {
+            tables.into_iter().next().cloned()
+        } else {
+            None
+        };
+
+        for join in select.from.iter().flat_map(|from| &from.joins) {
+            let (Some(table), Some(condition)) =
+                (relation_name(&join.relation), join_condition(join))
+            else {
+                continue;
+            };
+            if !sources.contains(&table) {
+                continue;
+            }
+            for (other, _) in column_refs(condition, &aliases, None) {
+                if other != table {
+                    graph.add_relationship(LineageRelationship {
+                        from_node: other,
+                        to_node: table.clone(),
+                        relationship_type: RelationshipType::JoinedWith,
+                        transform: Some(condition.to_string()),
+                        origin: None,
+                    });
+                }
+            }
+        }
+
+        if node_type == NodeType::Query {
+            continue;
+        }
+        for (position, item) in select.projection.iter().enumerate() {
+            let (SelectItem::ExprWithAlias { expr, .. } | SelectItem::UnnamedExpr(expr)) = item
+            else {
+                continue;
+            };
+            let Some(Some(name)) = output_names.get(position) else {
+                continue;
+            };
+            let inputs = column_refs(expr, &aliases, single_table.as_deref());
+            if inputs.is_empty() {
+                continue;
+            }
+            let output = column_node(target, name);
+            let output_id = output.id.clone();
+            graph.add_node(output);
+            let relationship_type = if is_aggregate(expr) {
+                RelationshipType::AggregatedFrom
+            } else {
+                RelationshipType::DerivedFrom
+            };
+            for (table, column) in inputs {
+                let input = column_node(&table, &column);
+                let input_id = input.id.clone();
+                graph.add_node(input);
+                graph.add_relationship(LineageRelationship {
+                    from_node: output_id.clone(),
+                    to_node: input_id,
+                    relationship_type,
+                    transform: Some(expr.to_string()),
+                    origin: None,
+                });
+            }
+        }
+    }
+    graph
+}
+
+/// Every `SELECT` in a query body, including both sides of set operations.
+fn collect_selects<'a>(body: &'a SetExpr, selects: &mut Vec<&'a Select>) {
+    match body {
+        SetExpr::Select(select) => selects.push(select),
+        SetExpr::Query(query) => collect_selects(&query.body, selects),
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_selects(left, selects);
+            collect_selects(right, selects);
+        }
+        _ => {}
+    }
+}
+
+/// The output column name of a projection item: its alias or the name of the
+/// column it selects.
+fn projection_name(item: &SelectItem) -> Option<String> {
+    match item {
+        SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.to_lowercase()),
+        SelectItem::UnnamedExpr(Expr::Identifier(ident)) => Some(ident.value.to_lowercase()),
+        SelectItem::UnnamedExpr(Expr::CompoundIdentifier(parts)) => {
+            parts.last().map(|ident| ident.value.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+fn object_name(name: &ObjectName) -> String {
+    table_id(
+        &name
+            .0
+            .iter()
+            .map(|ident| ident.value.as_str())
+            .collect::<Vec<_>>()
+            .join("."),
+    )
+}
+
+fn table_node(id: &str, node_type: NodeType) -> LineageNode {
+    LineageNode {
+        id: id.to_string(),
+        name: id.rsplit(['.', '/']).next().unwrap_or(id).to_string(),
+        node_type,
+        metadata: HashMap::new(),
+    }
+}
+
+fn column_node(table: &str, column: &str) -> LineageNode {
+    LineageNode {
+        id: format!("{}.{}", table, column),
+        name: column.to_string(),
+        node_type: NodeType::Column,
+        metadata: HashMap::from([("table".to_string(), table.to_string())]),
+    }
+}
+
+fn relation_name(relation: &TableFactor) -> Option<String> {
+    match relation {
+        TableFactor::Table { name, .. } => Some(object_name(name)),
+        _ => None,
+    }
+}
+
+/// Maps every alias, full name and unqualified name in the FROM clause to the
+/// source table it refers to. CTEs and subqueries are left out.
+fn table_aliases(select: &Select, sources: &[String]) -> HashMap<String, String> {
+    let mut aliases = HashMap::new();
+    let relations = select.from.iter().flat_map(|from| {
+        std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation))
+    });
+    for relation in relations {
+        let TableFactor::Table { name, alias, .. } = relation else {
+            continue;
+        };
+        let table = object_name(name);
+        if !sources.contains(&table) {
+            continue;
+        }
+        if let Some(alias) = alias {
+            aliases.insert(alias.name.value.to_lowercase(), table.clone());
+        }
+        if let Some(last) = name.0.last() {
+            aliases.insert(last.value.to_lowercase(), table.clone());
+        }
+        aliases.insert(table.clone(), table);
+    }
+    aliases
+}
+
+fn join_condition(join: &sqlparser::ast::Join) -> Option<&Expr> {
+    match &join.join_operator {
+        JoinOperator::Inner(JoinConstraint::On(condition))
+        | JoinOperator::LeftOuter(JoinConstraint::On(condition))
+        | JoinOperator::RightOuter(JoinConstraint::On(condition))
+        | JoinOperator::FullOuter(JoinConstraint::On(condition)) => Some(condition),
+        _ => None,
+    }
+}
+
+/// `(table, column)` pairs referenced by `expr`. Unqualified columns resolve
+/// to `default_table` when the query reads a single table.
+fn column_refs(
+    expr: &Expr,
+    aliases: &HashMap<String, String>,
+    default_table: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut refs = Vec::new();
+    let _ = visit_expressions(expr, |e| {
+        let resolved = match e {
+            Expr::Identifier(ident) => {
+                default_table.map(|table| (table.to_string(), ident.value.to_lowercase()))
+            }
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+                let qualifier = parts[parts.len() - 2].value.to_lowercase();
+                aliases
+                    .get(&qualifier)
+                    .map(|table| (table.clone(), parts[parts.len() - 1].value.to_lowercase()))
+            }
+            _ => None,
+        };
+        if let Some(column) = resolved {
+            if !refs.contains(&column) {
+                refs.push(column);
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    refs
+}
+
+fn is_aggregate(expr: &Expr) -> bool {
+    visit_expressions(expr, |e| match e {
+        Expr::Function(function)
+            if function.over.is_none()
+                && function.name.0.last().is_some_and(|ident| {
+                    AGGREGATES.contains(&ident.value.to_lowercase().as_str())
+                }) =>
+        {
+            ControlFlow::Break(())
+        }
+        _ => ControlFlow::Continue(()),
+    })
+    .is_break()
+}
+
+fn is_aggregated(select: &Select) -> bool {
+    let grouped = match &select.group_by {
+        GroupByExpr::All(_) => true,
+        GroupByExpr::Expressions(exprs, _) => !exprs.is_empty(),
+    };
+    grouped
+        || select.projection.iter().any(|item| match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                is_aggregate(expr)
+            }
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse_conn::SqliteWarehouse;
+
+    fn has_edge(graph: &LineageGraph, from: &str, to: &str, kind: RelationshipType) -> bool {
+        graph
+            .relationships
+            .iter()
+            .any(|r| r.from_node == from && r.to_node == to && r.relationship_type == kind)
+    }
+
+    #[tokio::test]
+    async fn test_lineage_from_sqlite_views() {
+        let warehouse = SqliteWarehouse::new("sqlite::memory:").with_max_connections(1);
+        for sql in [
+            "CREATE TABLE customers (id INTEGER, region TEXT)",
+            "CREATE TABLE orders (id INTEGER, customer_id INTEGER, total REAL)",
+            "CREATE VIEW region_revenue AS SELECT c.region, SUM(o.total) AS revenue \
+             FROM orders o JOIN customers c ON o.customer_id = c.id GROUP BY c.region",
+        ] {
+            warehouse.execute(sql).await.unwrap();
+        }
+
+        let graph = lineage_from_warehouse(&warehouse).await.unwrap();
+        assert_eq!(
+            graph.node("region_revenue").unwrap().node_type,
+            NodeType::View
+        );
+        assert!(has_edge(
+            &graph,
+            "region_revenue",
+            "orders",
+            RelationshipType::AggregatedFrom
+        ));
+        assert!(has_edge(
+            &graph,
+            "orders",
+            "customers",
+            RelationshipType::JoinedWith
+        ));
+        assert!(has_edge(
+            &graph,
+            "region_revenue.revenue",
+            "orders.total",
+            RelationshipType::AggregatedFrom
+        ));
+        assert!(has_edge(
+            &graph,
+            "region_revenue.region",
+            "customers.region",
+            RelationshipType::DerivedFrom
+        ));
+        graph.validate().unwrap();
+    }
+
+    #[test]
+    fn test_insert_columns_and_union_branches() {
+        let graph = lineage_from_sql(
+            "INSERT INTO contacts (contact_email, source) \
+             SELECT email, 'user' FROM users UNION ALL SELECT c.mail, 'lead' FROM leads c",
+            &GenericDialect {},
+            None,
+        )
+        .unwrap();
+
+        for input in ["users.email", "leads.mail"] {
+            assert!(has_edge(
+                &graph,
+                "contacts.contact_email",
+                input,
+                RelationshipType::DerivedFrom
+            ));
+        }
+        assert!(graph.node("contacts.email").is_none());
+        assert!(graph.node("contacts.source").is_none());
+
+        let graph = lineage_from_sql(
+            "CREATE VIEW public.paid AS SELECT o.id FROM public.orders o JOIN sales.refunds r ON r.id = o.id",
+            &GenericDialect {},
+            None,
+        )
+        .unwrap();
+        assert!(has_edge(
+            &graph,
+            "paid",
+            "orders",
+            RelationshipType::DerivedFrom
+        ));
+        assert!(has_edge(
+            &graph,
+            "paid.id",
+            "orders.id",
+            RelationshipType::DerivedFrom
+        ));
+        assert!(graph.node("sales.refunds").is_some());
+    }
+
+    #[test]
+    fn test_lineage_from_workflow() {
+        let workflow: WorkflowDefinition = serde_yaml::from_str(
+            r#"
+name: nightly
+version: "1"
+trigger:
+  type: schedule
+  schedule: "0 2 * * *"
+steps:
+  - name: load
+    action:
+      type: query
+      sql: "INSERT INTO daily_totals SELECT day, total FROM staging_totals"
+  - name: backfill
+    action:
+      type: query
+      sql: "INSERT INTO daily_totals SELECT day, total FROM archived_totals"
+  - name: check
+    action:
+      type: query
+      sql: "SELECT COUNT(*) FROM daily_totals"
+"#,
+        )
+        .unwrap();
+
+        let mut graph = LineageGraph::default();
+        graph.add_node(table_node("daily_totals", NodeType::Table));
+        graph.add_node(table_node("raw_totals", NodeType::Table));
+        graph.add_relationship(LineageRelationship {
+            from_node: "daily_totals".to_string(),
+            to_node: "raw_totals".to_string(),
+            relationship_type: RelationshipType::DerivedFrom,
+            transform: None,
+            origin: Some("dbt".to_string()),
+        });
+        graph.merge(lineage_from_workflow(&workflow, None));
+        for upstream in ["staging_totals", "archived_totals", "raw_totals"] {
+            assert!(has_edge(
+                &graph,
+                "daily_totals",
+                upstream,
+                RelationshipType::DerivedFrom
+            ));
+        }
+        assert!(has_edge(
+            &graph,
+            "daily_totals.total",
+            "staging_totals.total",
+            RelationshipType::DerivedFrom
+        ));
+        assert!(has_edge(
+            &graph,
+            "workflow:nightly/check",
+            "daily_totals",
+            RelationshipType::DependsOn
+        ));
+        assert_eq!(
+            graph.node("workflow:nightly/check").unwrap().node_type,
+            NodeType::Query
+        );
+    }
+}
@@ -0,0 +1,696 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::Error;
+use crate::lineage::{
+    table_id, LineageGraph, LineageNode, LineageRelationship, NodeType, RelationshipType,
+};
+use crate::models::{Annotation, ColumnMetadata, Schema, TableMetadata};
+
+/// Schemas and lineage read from an external tool. Lineage node ids are
+/// `schema.table` and `schema.table.column`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataImport {
+    pub schemas: Vec<Schema>,
+    pub lineage: LineageGraph,
+}
+
+impl MetadataImport {
+    fn add_table(&mut self, schema_name: &str, source: &str, table: TableMetadata) {
+        let index = match self.schemas.iter().position(|s| s.name == schema_name) {
+            Some(index) => index,
+            None => {
+                self.schemas.push(Schema {
+                    id: None,
+                    name: schema_name.to_string(),
+                    source: source.to_string(),
+                    tables: vec![],
+                    created_at: None,
+                    updated_at: None,
+                });
+                self.schemas.len() - 1
+            }
+        };
+        let tables = &mut self.schemas[index].tables;
+        match tables.iter_mut().find(|t| t.name == table.name) {
+            Some(existing) => *existing = table,
+            None => tables.push(table),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DbtManifest {
+    #[serde(default)]
+    nodes: BTreeMap<String, DbtNode>,
+    #[serde(default)]
+    sources: BTreeMap<String, DbtNode>,
+}
+
+#[derive(Deserialize)]
+struct DbtNode {
+    resource_type: String,
+    name: String,
+    #[serde(default)]
+    schema: String,
+    alias: Option<String>,
+    identifier: Option<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    columns: BTreeMap<String, DbtColumn>,
+    #[serde(default)]
+    depends_on: DbtDependsOn,
+    #[serde(default)]
+    config: DbtConfig,
+    #[serde(default)]
+    tags: Vec<String>,
+    compiled_code: Option<String>,
+    test_metadata: Option<DbtTestMetadata>,
+    attached_node: Option<String>,
+    column_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DbtColumn {
+    name: String,
+    #[serde(default)]
+    description: String,
+    data_type: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct DbtDependsOn {
+    #[serde(default)]
+    nodes: Vec<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct DbtConfig {
+    materialized: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DbtTestMetadata {
+    name: String,
+    #[serde(default)]
+    kwargs: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Default, Deserialize)]
+struct DbtCatalog {
+    #[serde(default)]
+    nodes: HashMap<String, DbtCatalogEntry>,
+    #[serde(default)]
+    sources: HashMap<String, DbtCatalogEntry>,
+}
+
+#[derive(Deserialize)]
+struct DbtCatalogEntry {
+    #[serde(default)]
+    columns: HashMap<String, DbtCatalogColumn>,
+}
+
+#[derive(Deserialize)]
+struct DbtCatalogColumn {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    index: usize,
+    comment: Option<String>,
+}
+
+impl DbtNode {
+    fn relation(&self) -> String {
+        let table = self
+            .alias
+            .as_ref()
+            .or(self.identifier.as_ref())
+            .unwrap_or(&self.name);
+        table_id(&format!("{}.{}", self.schema, table))
+    }
+
+    fn node_type(&self) -> NodeType {
+        match (
+            self.resource_type.as_str(),
+            self.config.materialized.as_deref(),
+        ) {
+            ("source", _) => NodeType::Source,
+            (_, Some("view")) => NodeType::View,
+            (_, Some("ephemeral")) => NodeType::Query,
+            _ => NodeType::Table,
+        }
+    }
+}
+
+/// Reads dbt's `manifest.json` and, when available, `catalog.json`. Models,
+/// seeds, snapshots and sources become tables with their docs; data tests
+/// become `test` annotations and model dependencies become lineage.
+pub fn import_dbt(manifest: &str, catalog: Option<&str>) -> Result<MetadataImport, Error> {
+    let manifest: DbtManifest = serde_json::from_str(manifest)
+        .map_err(|e| Error::Metadata(format!("invalid dbt manifest: {}", e)))?;
+    let catalog: DbtCatalog = match catalog {
+        Some(catalog) => serde_json::from_str(catalog)
+            .map_err(|e| Error::Metadata(format!("invalid dbt catalog: {}", e)))?,
+        None => DbtCatalog::default(),
+    };
+
+    let relations: BTreeMap<&str, &DbtNode> = manifest
+        .nodes
+        .iter()
+        .chain(&manifest.sources)
+        .filter(|(_, node)| {
+            matches!(
+                node.resource_type.as_str(),
+                "model" | "seed" | "snapshot" | "source"
+            )
+        })
+        .map(|(id, node)| (id.as_str(), node))
+        .collect();
+
+    let mut tables: BTreeMap<&str, TableMetadata> = BTreeMap::new();
+    for (&id, node) in &relations {
+        if node.node_type() == NodeType::Query {
+            continue;
+        }
+        let catalog_columns = catalog
+            .nodes
+            .get(id)
+            .or_else(|| catalog.sources.get(id))
+            .map(|entry| {
+                let mut columns: Vec<&DbtCatalogColumn> = entry.columns.values().collect();
+                columns.sort_by_key(|c| c.index);
+                columns
+            })
+            .unwrap_or_default();
+        let documented = |name: &str| {
+            node.columns
+                .values()
+                .find(|c| c.name.eq_ignore_ascii_case(name))
+        };
+
+        let mut columns: Vec<ColumnMetadata> = catalog_columns
+            .iter()
+            .map(|c| {
+                dbt_column(
+                    &c.name,
+                    Some(&c.data_type),
+                    c.comment.as_deref(),
+                    documented(&c.name),
+                )
+            })
+            .collect();
+        for doc in node.columns.values() {
+            if !columns
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(&doc.name))
+            {
+                columns.push(dbt_column(&doc.name, None, None, Some(doc)));
+            }
+        }
+
+        let relation = node.relation();
+        tables.insert(
+            id,
+            TableMetadata {
+                name: relation.rsplit('.').next().unwrap_or(&relation).to_string(),
+                schema_name: Some(node.schema.to_lowercase()),
+                columns,
+                primary_key: None,
+                annotations: node
+                    .tags
+                    .iter()
+                    .map(|tag| Annotation::new(Annotation::TAG, tag).with_source("dbt"))
+                    .collect(),
+                description: non_empty(&node.description),
+            },
+        );
+    }
+
+    for test in manifest
+        .nodes
+        .values()
+        .filter(|n| n.resource_type == "test")
+    {
+        let Some(metadata) = &test.test_metadata else {
+            continue;
+        };
+        let attached = test
+            .attached_node
+            .as_deref()
+            .or_else(|| test.depends_on.nodes.first().map(String::as_str));
+        let Some(table) = attached.and_then(|id| tables.get_mut(id)) else {
+            continue;
+        };
+        let mut annotations =
+            vec![Annotation::new(Annotation::TEST, &metadata.name).with_source("dbt")];
+        if let Some(values) = metadata.kwargs.get("values").and_then(|v| v.as_array()) {
+            let values: Vec<String> = values
+                .iter()
+                .map(|v| v.as_str().map_or_else(|| v.to_string(), String::from))
+                .collect();
+            annotations.push(
+                Annotation::new(Annotation::ALLOWED_VALUES, &values.join(", ")).with_source("dbt"),
+            );
+        }
+        let column_name = test
+            .column_name
+            .as_deref()
+            .or_else(|| metadata.kwargs.get("column_name").and_then(|v| v.as_str()));
+        match column_name.and_then(|name| {
+            table
+                .columns
+                .iter_mut()
+                .find(|c| c.name.eq_ignore_ascii_case(name))
+        }) {
+            Some(column) => {
+                if metadata.name == "not_null" {
+                    column.nullable = false;
+                }
+                for annotation in annotations {
+                    push_annotation(&mut column.annotations, annotation);
+                }
+            }
+            None => {
+                for annotation in annotations {
+                    push_annotation(&mut table.annotations, annotation);
+                }
+            }
+        }
+    }
+
+    let mut import = MetadataImport::default();
+    for (id, node) in &relations {
+        let relation = node.relation();
+        let mut metadata = HashMap::from([("dbt_unique_id".to_string(), id.to_string())]);
+        if let Some(materialized) = &node.config.materialized {
+            metadata.insert("materialized".to_string(), materialized.clone());
+        }
+        import.lineage.add_node(LineageNode {
+            id: relation.clone(),
+            name: node.name.clone(),
+            node_type: node.node_type(),
+            metadata,
+        });
+        for upstream in node
+            .depends_on
+            .nodes
+            .iter()
+            .filter_map(|id| relations.get(id.as_str()))
+        {
+            import.lineage.add_relationship(LineageRelationship {
+                from_node: relation.clone(),
+                to_node: upstream.relation(),
+                relationship_type: RelationshipType::DerivedFrom,
+                transform: node.compiled_code.clone(),
+                origin: Some("dbt".to_string()),
+            });
+        }
+    }
+    for table in tables.into_values() {
+        let schema_name = table.schema_name.clone().unwrap_or_default();
+        import.add_table(&schema_name, "dbt", table);
+    }
+    Ok(import)
+}
+
+fn dbt_column(
+    name: &str,
+    data_type: Option<&str>,
+    comment: Option<&str>,
+    doc: Option<&DbtColumn>,
+) -> ColumnMetadata {
+    ColumnMetadata {
+        name: name.to_lowercase(),
+        data_type: data_type
+            .or_else(|| doc.and_then(|d| d.data_type.as_deref()))
+            .unwrap_or("unknown")
+            .to_lowercase(),
+        nullable: true,
+        comment: doc
+            .and_then(|d| non_empty(&d.description))
+            .or_else(|| comment.and_then(non_empty)),
+        annotations: doc
+            .map(|d| {
+                d.tags
+                    .iter()
+                    .map(|tag| Annotation::new(Annotation::TAG, tag).with_source("dbt"))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RunEvent {
+    event_type: Option<String>,
+    job: Option<Job>,
+    #[serde(default)]
+    inputs: Vec<Dataset>,
+    #[serde(default)]
+    outputs: Vec<Dataset>,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    namespace: String,
+    name: String,
+    #[serde(default)]
+    facets: JobFacets,
+}
+
+#[derive(Default, Deserialize)]
+struct JobFacets {
+    sql: Option<SqlFacet>,
+}
+
+#[derive(Deserialize)]
+struct SqlFacet {
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct Dataset {
+    namespace: String,
+    name: String,
+    #[serde(default)]
+    facets: DatasetFacets,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DatasetFacets {
+    schema: Option<SchemaFacet>,
+    documentation: Option<DocumentationFacet>,
+    column_lineage: Option<ColumnLineageFacet>,
+}
+
+#[derive(Deserialize)]
+struct SchemaFacet {
+    #[serde(default)]
+    fields: Vec<SchemaField>,
+}
+
+#[derive(Deserialize)]
+struct SchemaField {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DocumentationFacet {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct ColumnLineageFacet {
+    #[serde(default)]
+    fields: BTreeMap<String, ColumnLineageField>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ColumnLineageField {
+    #[serde(default)]
+    input_fields: Vec<InputField>,
+    transformation_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InputField {
+    name: String,
+    field: String,
+    #[serde(default)]
+    transformations: Vec<Transformation>,
+}
+
+#[derive(Deserialize)]
+struct Transformation {
+    subtype: Option<String>,
+}
+
+impl Dataset {
+    fn id(&self) -> String {
+        table_id(&self.name)
+    }
+}
+
+/// Reads OpenLineage run events given as a JSON object, a JSON array or one
+/// event per line. Failed and aborted runs are ignored; dataset schema facets
+/// become tables and column lineage facets become column-level edges.
+pub fn import_openlineage(events: &str) -> Result<MetadataImport, Error> {
+    let mut parsed = Vec::new();
+    for value in serde_json::Deserializer::from_str(events).into_iter::<serde_json::Value>() {
+        let value =
+            value.map_err(|e| Error::Metadata(format!("invalid OpenLineage event: {}", e)))?;
+        let values = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            let event: RunEvent = serde_json::from_value(value)
+                .map_err(|e| Error::Metadata(format!("invalid OpenLineage event: {}", e)))?;
+            parsed.push(event);
+        }
+    }
+
+    let mut import = MetadataImport::default();
+    for event in &parsed {
+        if matches!(event.event_type.as_deref(), Some("FAIL" | "ABORT")) {
+            continue;
+        }
+        let sql = event
+            .job
+            .as_ref()
+            .and_then(|job| job.facets.sql.as_ref())
+            .map(|facet| facet.query.clone());
+
+        for dataset in event.inputs.iter().chain(&event.outputs) {
+            import.lineage.add_node(dataset_node(dataset, None));
+            if let Some(schema) = &dataset.facets.schema {
+                let name = dataset.name.to_lowercase();
+                let (schema_name, table) = match name.rsplit_once('.') {
+                    Some((prefix, table)) => (
+                        prefix.rsplit('.').next().unwrap_or(prefix).to_string(),
+                        table.to_string(),
+                    ),
+                    None => (dataset.namespace.clone(), name.clone()),
+                };
+                let table = TableMetadata {
+                    name: table,
+                    schema_name: Some(schema_name.clone()),
+                    columns: schema
+                        .fields
+                        .iter()
+                        .map(|field| ColumnMetadata {
+                            name: field.name.to_lowercase(),
+                            data_type: field
+                                .data_type
+                                .as_deref()
+                                .unwrap_or("unknown")
+                                .to_lowercase(),
+                            nullable: true,
+                            comment: field.description.clone(),
+                            annotations: vec![],
+                        })
+                        .collect(),
+                    primary_key: None,
+                    annotations: vec![],
+                    description: dataset
+                        .facets
+                        .documentation
+                        .as_ref()
+                        .map(|doc| doc.description.clone()),
+                };
+                import.add_table(&schema_name, &dataset.namespace, table);
+            }
+        }
+
+        for output in &event.outputs {
+            import
+                .lineage
+                .add_node(dataset_node(output, event.job.as_ref()));
+            for input in &event.inputs {
+                import.lineage.add_relationship(LineageRelationship {
+                    from_node: output.id(),
+                    to_node: input.id(),
+                    relationship_type: RelationshipType::DerivedFrom,
+                    transform: sql.clone(),
+                    origin: Some("openlineage".to_string()),
+                });
+            }
+
+            let Some(column_lineage) = &output.facets.column_lineage else {
+                continue;
+            };
+            for (column, field) in &column_lineage.fields {
+                let output_column = column_node(&output.id(), column);
+                let output_id = output_column.id.clone();
+                import.lineage.add_node(output_column);
+                for input in &field.input_fields {
+                    let input_column = column_node(&table_id(&input.name), &input.field);
+                    let input_id = input_column.id.clone();
+                    let aggregated = input
+                        .transformations
+                        .iter()
+                        .any(|t| t.subtype.as_deref() == Some("AGGREGATION"));
+                    import.lineage.add_node(LineageNode {
+                        id: table_id(&input.name),
+                        name: input.name.clone(),
+                        node_type: NodeType::Table,
+                        metadata: HashMap::new(),
+                    });
+                    import.lineage.add_node(input_column);
+                    import.lineage.add_relationship(LineageRelationship {
+                        from_node: output_id.clone(),
+                        to_node: input_id,
+                        relationship_type: if aggregated {
+                            RelationshipType::AggregatedFrom
+                        } else {
+                            RelationshipType::DerivedFrom
+                        },
+                        transform: field.transformation_description.clone(),
+                        origin: Some("openlineage".to_string()),
+                    });
+                }
+            }
+        }
+    }
+    Ok(import)
+}
+
+fn dataset_node(dataset: &Dataset, job: Option<&Job>) -> LineageNode {
+    let mut metadata = HashMap::from([("namespace".to_string(), dataset.namespace.clone())]);
+    if let Some(job) = job {
+        metadata.insert("job".to_string(), format!("{}/{}", job.namespace, job.name));
+    }
+    LineageNode {
+        id: dataset.id(),
+        name: dataset.name.clone(),
+        node_type: NodeType::Table,
+        metadata,
+    }
+}
+
+fn column_node(table: &str, column: &str) -> LineageNode {
+    LineageNode {
+        id: format!("{}.{}", table, column.to_lowercase()),
+        name: column.to_lowercase(),
+        node_type: NodeType::Column,
+        metadata: HashMap::from([("table".to_string(), table.to_string())]),
+    }
+}
+
+fn push_annotation(annotations: &mut Vec<Annotation>, annotation: Annotation) {
+    if !annotations
+        .iter()
+        .any(|a| a.key == annotation.key && a.value == annotation.value)
+    {
+        annotations.push(annotation);
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "nodes": {
+            "model.shop.orders": {
+                "resource_type": "model", "name": "orders", "schema": "analytics",
+                "alias": "orders", "description": "One row per order",
+                "config": {"materialized": "table"}, "tags": ["finance"],
+                "columns": {"status": {"name": "status", "description": "Order status"}},
+                "depends_on": {"nodes": ["source.shop.raw.orders"]},
+                "compiled_code": "select * from raw.orders"
+            },
+            "test.shop.accepted_values_orders_status": {
+                "resource_type": "test", "name": "accepted_values_orders_status",
+                "attached_node": "model.shop.orders", "column_name": "status",
+                "test_metadata": {"name": "accepted_values",
+                                  "kwargs": {"values": ["placed", "shipped"]}}
+            },
+            "test.shop.not_null_orders_id": {
+                "resource_type": "test", "name": "not_null_orders_id",
+                "attached_node": "model.shop.orders", "column_name": "id",
+                "test_metadata": {"name": "not_null", "kwargs": {}}
+            }
+        },
+        "sources": {
+            "source.shop.raw.orders": {
+                "resource_type": "source", "name": "orders", "schema": "raw",
+                "identifier": "orders"
+            }
+        }
+    }"#;
+
+    const CATALOG: &str = r#"{
+        "nodes": {
+            "model.shop.orders": {"columns": {
+                "ID": {"name": "ID", "type": "integer", "index": 1},
+                "STATUS": {"name": "STATUS", "type": "text", "index": 2}
+            }}
+        }
+    }"#;
+
+    #[test]
+    fn test_import_dbt() {
+        let import = import_dbt(MANIFEST, Some(CATALOG)).unwrap();
+        let analytics = import
+            .schemas
+            .iter()
+            .find(|s| s.name == "analytics")
+            .unwrap();
+        let orders = &analytics.tables[0];
+        assert_eq!(orders.description.as_deref(), Some("One row per order"));
+        assert_eq!(orders.columns[0].name, "id");
+        assert!(!orders.columns[0].nullable);
+        let status = &orders.columns[1];
+        assert_eq!(status.comment.as_deref(), Some("Order status"));
+        assert_eq!(status.annotation(Annotation::TEST), Some("accepted_values"));
+        assert_eq!(
+            status.annotation(Annotation::ALLOWED_VALUES),
+            Some("placed, shipped")
+        );
+
+        assert_eq!(
+            import.lineage.get_table_dependencies("analytics.orders"),
+            vec!["raw.orders"]
+        );
+        assert_eq!(
+            import.lineage.node("raw.orders").unwrap().node_type,
+            NodeType::Source
+        );
+    }
+
+    #[test]
+    fn test_import_openlineage() {
+        let events = r#"
+{"eventType": "COMPLETE", "job": {"namespace": "airflow", "name": "daily_revenue", "facets": {"sql": {"query": "INSERT INTO public.revenue SELECT day, SUM(total) FROM public.orders GROUP BY day"}}}, "inputs": [{"namespace": "postgres://db", "name": "public.orders", "facets": {"schema": {"fields": [{"name": "day", "type": "DATE"}, {"name": "total", "type": "NUMERIC"}]}}}], "outputs": [{"namespace": "postgres://db", "name": "public.revenue", "facets": {"columnLineage": {"fields": {"revenue": {"inputFields": [{"namespace": "postgres://db", "name": "public.orders", "field": "total", "transformations": [{"type": "INDIRECT", "subtype": "AGGREGATION"}]}]}}}}}]}
+{"eventType": "FAIL", "job": {"namespace": "airflow", "name": "broken"}, "inputs": [{"namespace": "postgres://db", "name": "public.x"}], "outputs": []}
+"#;
+        let import = import_openlineage(events).unwrap();
+        assert_eq!(import.schemas[0].name, "public");
+        assert_eq!(import.schemas[0].tables[0].columns[1].data_type, "numeric");
+        assert!(import.lineage.node("x").is_none());
+        assert!(import.lineage.relationships.iter().any(|r| {
+            r.from_node == "revenue.revenue"
+                && r.to_node == "orders.total"
+                && r.relationship_type == RelationshipType::AggregatedFrom
+        }));
+        assert_eq!(
+            import.lineage.get_table_dependencies("revenue"),
+            vec!["orders"]
+        );
+    }
+}
@@ -1,9 +1,29 @@
+pub mod api;
 pub mod error;
+pub mod extract;
+pub mod import;
 pub mod lineage;
 pub mod models;
+pub mod search;
+pub mod semantic;
 pub mod service;
+pub mod sql_store;
+pub mod store;
+pub mod versioning;
 
 pub use error::Error;
-pub use lineage::{LineageGraph, LineageNode, LineageRelationship};
-pub use models::{Annotation, Schema, TableMetadata};
+pub use extract::{lineage_from_sql, lineage_from_warehouse, lineage_from_workflow};
+pub use import::{import_dbt, import_openlineage, MetadataImport};
+pub use lineage::{
+    table_id, ImpactReport, JoinStep, LineageGraph, LineageHop, LineageNode, LineageRelationship,
+    NodeType, RelationshipType,
+};
+pub use models::{Annotation, ColumnMetadata, GlossaryTerm, Schema, TableMetadata};
+pub use search::{SearchHit, SearchHitKind};
+pub use semantic::{CompiledQuery, Dimension, Join, Metric, MetricQuery, SemanticModel, TimeRange};
 pub use service::MetadataService;
+pub use sql_store::{PostgresMetadataStore, SqliteMetadataStore};
+pub use store::{InMemoryMetadataStore, MetadataStore};
+pub use versioning::{
+    ColumnChange, SchemaDiff, SchemaEvent, SchemaSnapshot, SchemaSync, TableDiff, TableRename,
+};
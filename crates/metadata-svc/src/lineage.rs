@@ -1,7 +1,25 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::error::Error;
+
+/// Schemas left out of table node ids, so `public.orders`, `main.orders`
+/// and `orders` are one node.
+const DEFAULT_SCHEMAS: &[&str] = &["public", "main"];
+
+/// The lineage node id of a possibly qualified table name: lowercase, without
+/// a database qualifier and without a default schema. Column ids append
+/// `.<column>` to it.
+pub fn table_id(name: &str) -> String {
+    let name = name.to_lowercase();
+    let parts: Vec<&str> = name.split('.').collect();
+    match &parts[parts.len().saturating_sub(2)..] {
+        [schema, table] if DEFAULT_SCHEMAS.contains(schema) => table.to_string(),
+        parts => parts.join("."),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LineageGraph {
     pub nodes: Vec<LineageNode>,
     pub relationships: Vec<LineageRelationship>,
@@ -15,12 +33,14 @@ pub struct LineageNode {
     pub metadata: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NodeType {
     Table,
     Column,
     View,
     Source,
+    /// A query run outside the warehouse catalog, e.g. a workflow step.
+    Query,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,9 +49,13 @@ pub struct LineageRelationship {
     pub to_node: String,
     pub relationship_type: RelationshipType,
     pub transform: Option<String>,
+    /// What recorded the edge, e.g. `dbt` or a workflow step. Merges only
+    /// replace edges from the same origin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RelationshipType {
     DependsOn,
     DerivedFrom,
@@ -39,17 +63,152 @@ pub enum RelationshipType {
     AggregatedFrom,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageHop {
+    pub node: String,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinStep {
+    pub from: String,
+    pub to: String,
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImpactReport {
+    pub node: String,
+    pub affected_columns: Vec<String>,
+    pub affected_tables: Vec<String>,
+}
+
+impl RelationshipType {
+    /// Whether `from_node` consumes `to_node`, i.e. `to_node` is upstream.
+    pub fn is_dependency(&self) -> bool {
+        !matches!(self, RelationshipType::JoinedWith)
+    }
+}
+
+impl LineageNode {
+    /// Table a column node belongs to, from `metadata["table"]` or the `table.column` id.
+    pub fn table(&self) -> Option<&str> {
+        if self.node_type != NodeType::Column {
+            return None;
+        }
+        self.metadata
+            .get("table")
+            .map(String::as_str)
+            .or_else(|| self.id.rsplit_once('.').map(|(table, _)| table))
+    }
+}
+
 impl LineageGraph {
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(json)
     }
 
+    /// Adds `node`, replacing any existing node with the same id and keeping
+    /// metadata keys the new node doesn't set.
     pub fn add_node(&mut self, node: LineageNode) {
-        self.nodes.push(node);
+        match self.nodes.iter_mut().find(|n| n.id == node.id) {
+            Some(existing) => {
+                let mut metadata = std::mem::take(&mut existing.metadata);
+                metadata.extend(node.metadata.clone());
+                *existing = LineageNode { metadata, ..node };
+            }
+            None => self.nodes.push(node),
+        }
     }
 
     pub fn add_relationship(&mut self, rel: LineageRelationship) {
-        self.relationships.push(rel);
+        let duplicate = self.relationships.iter().any(|r| {
+            r.from_node == rel.from_node
+                && r.to_node == rel.to_node
+                && r.relationship_type == rel.relationship_type
+        });
+        if !duplicate {
+            self.relationships.push(rel);
+        }
+    }
+
+    /// Tags every relationship without an origin with `origin`.
+    pub fn with_origin(mut self, origin: &str) -> Self {
+        for rel in &mut self.relationships {
+            rel.origin.get_or_insert_with(|| origin.to_string());
+        }
+        self
+    }
+
+    /// Merges `other` into this graph. Dependencies of every node `other`
+    /// derives are replaced rather than appended when they come from the same
+    /// origin, so re-extracting a view drops edges to tables it no longer
+    /// reads while edges recorded by other sources stay. A plain `Table` node
+    /// never downgrades a node already known to be a view or query.
+    pub fn merge(&mut self, other: LineageGraph) {
+        let derived: HashSet<(&str, Option<&str>)> = other
+            .relationships
+            .iter()
+            .filter(|r| r.relationship_type.is_dependency())
+            .map(|r| (r.from_node.as_str(), r.origin.as_deref()))
+            .collect();
+        self.relationships.retain(|r| {
+            !(r.relationship_type.is_dependency()
+                && derived.contains(&(r.from_node.as_str(), r.origin.as_deref())))
+        });
+
+        for node in other.nodes {
+            let node = match self.node(&node.id) {
+                Some(existing) if node.node_type == NodeType::Table => LineageNode {
+                    node_type: existing.node_type,
+                    ..node
+                },
+                _ => node,
+            };
+            self.add_node(node);
+        }
+        for rel in other.relationships {
+            self.add_relationship(rel);
+        }
+    }
+
+    pub fn node(&self, id: &str) -> Option<&LineageNode> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// Checks for duplicate node ids, relationships pointing at unknown nodes
+    /// and dependency cycles.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = Vec::new();
+
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if !ids.insert(node.id.as_str()) {
+                problems.push(format!("duplicate node '{}'", node.id));
+            }
+        }
+        for rel in &self.relationships {
+            for endpoint in [&rel.from_node, &rel.to_node] {
+                if !ids.contains(endpoint.as_str()) {
+                    problems.push(format!(
+                        "relationship {} -> {} references unknown node '{}'",
+                        rel.from_node, rel.to_node, endpoint
+                    ));
+                }
+            }
+        }
+        if let Some(cycle) = self.find_cycle() {
+            problems.push(format!("dependency cycle {}", cycle.join(" -> ")));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Metadata(format!(
+                "Invalid lineage graph: {}",
+                problems.join("; ")
+            )))
+        }
     }
 
     pub fn get_table_dependencies(&self, table_id: &str) -> Vec<String> {
@@ -65,4 +224,285 @@ impl LineageGraph {
             .map(|r| r.to_node.clone())
             .collect()
     }
+
+    fn neighbours(&self, id: &str, upstream: bool) -> Vec<&str> {
+        self.relationships
+            .iter()
+            .filter(|r| r.relationship_type.is_dependency())
+            .filter_map(|r| match upstream {
+                true if r.from_node == id => Some(r.to_node.as_str()),
+                false if r.to_node == id => Some(r.from_node.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn traverse(&self, id: &str, upstream: bool, max_depth: Option<usize>) -> Vec<LineageHop> {
+        let mut seen = HashSet::from([id]);
+        let mut queue = VecDeque::from([(id, 0)]);
+        let mut hops = Vec::new();
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for next in self.neighbours(current, upstream) {
+                if seen.insert(next) {
+                    hops.push(LineageHop {
+                        node: next.to_string(),
+                        depth: depth + 1,
+                    });
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        hops
+    }
+
+    /// Everything `id` transitively depends on, nearest first.
+    pub fn upstream(&self, id: &str, max_depth: Option<usize>) -> Vec<LineageHop> {
+        self.traverse(id, true, max_depth)
+    }
+
+    /// Everything that transitively depends on `id`, nearest first.
+    pub fn downstream(&self, id: &str, max_depth: Option<usize>) -> Vec<LineageHop> {
+        self.traverse(id, false, max_depth)
+    }
+
+    pub fn columns_of(&self, table_id: &str) -> Vec<&LineageNode> {
+        self.nodes
+            .iter()
+            .filter(|n| n.table() == Some(table_id))
+            .collect()
+    }
+
+    /// Shortest chain of `JoinedWith` relationships between two tables,
+    /// treating joins as undirected. The join condition is the relationship's
+    /// `transform`.
+    pub fn join_path(&self, from: &str, to: &str) -> Option<Vec<JoinStep>> {
+        let mut previous: HashMap<&str, (&str, &LineageRelationship)> = HashMap::new();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut steps = Vec::new();
+                let mut node = to;
+                while let Some((prev, rel)) = previous.get(node) {
+                    steps.push(JoinStep {
+                        from: prev.to_string(),
+                        to: node.to_string(),
+                        condition: rel.transform.clone(),
+                    });
+                    node = prev;
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+            for rel in self
+                .relationships
+                .iter()
+                .filter(|r| r.relationship_type == RelationshipType::JoinedWith)
+            {
+                let next = if rel.from_node == current {
+                    rel.to_node.as_str()
+                } else if rel.to_node == current {
+                    rel.from_node.as_str()
+                } else {
+                    continue;
+                };
+                if seen.insert(next) {
+                    previous.insert(next, (current, rel));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// A dependency cycle, if any, as the node ids along it with the first
+    /// node repeated at the end.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        fn visit<'a>(
+            graph: &'a LineageGraph,
+            node: &'a str,
+            stack: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Option<Vec<String>> {
+            if let Some(i) = stack.iter().position(|n| *n == node) {
+                let mut cycle: Vec<String> = stack[i..].iter().map(|n| n.to_string()).collect();
+                cycle.push(node.to_string());
+                return Some(cycle);
+            }
+            if !done.insert(node) {
+                return None;
+            }
+            stack.push(node);
+            for next in graph.neighbours(node, true) {
+                if let Some(cycle) = visit(graph, next, stack, done) {
+                    return Some(cycle);
+                }
+            }
+            stack.pop();
+            None
+        }
+
+        let mut done = HashSet::new();
+        let starts: BTreeSet<&str> = self
+            .relationships
+            .iter()
+            .map(|r| r.from_node.as_str())
+            .collect();
+        starts
+            .into_iter()
+            .find_map(|start| visit(self, start, &mut Vec::new(), &mut done))
+    }
+
+    /// What breaks if `id` is dropped: every downstream column, plus every
+    /// table or view that depends on it directly or owns an affected column.
+    pub fn impact(&self, id: &str) -> ImpactReport {
+        let mut columns = BTreeSet::new();
+        let mut tables = BTreeSet::new();
+
+        for hop in self.downstream(id, None) {
+            match self.node(&hop.node) {
+                Some(node) if node.node_type == NodeType::Column => {
+                    if let Some(table) = node.table() {
+                        tables.insert(table.to_string());
+                    }
+                    columns.insert(hop.node);
+                }
+                _ => {
+                    tables.insert(hop.node);
+                }
+            }
+        }
+        if let Some(own_table) = self.node(id).and_then(|n| n.table()) {
+            tables.remove(own_table);
+        }
+
+        ImpactReport {
+            node: id.to_string(),
+            affected_columns: columns.into_iter().collect(),
+            affected_tables: tables.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, node_type: NodeType) -> LineageNode {
+        LineageNode {
+            id: id.to_string(),
+            name: id.to_string(),
+            node_type,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn rel(from: &str, to: &str, relationship_type: RelationshipType) -> LineageRelationship {
+        LineageRelationship {
+            from_node: from.to_string(),
+            to_node: to.to_string(),
+            relationship_type,
+            transform: None,
+            origin: None,
+        }
+    }
+
+    fn graph() -> LineageGraph {
+        let mut graph = LineageGraph::default();
+        for id in [
+            "raw_orders",
+            "orders",
+            "customers",
+            "revenue_daily",
+            "exec_dashboard",
+        ] {
+            graph.add_node(node(id, NodeType::Table));
+        }
+        for id in ["raw_orders.amount", "orders.amount", "revenue_daily.total"] {
+            graph.add_node(node(id, NodeType::Column));
+        }
+        graph.add_relationship(rel("orders", "raw_orders", RelationshipType::DerivedFrom));
+        graph.add_relationship(rel(
+            "revenue_daily",
+            "orders",
+            RelationshipType::AggregatedFrom,
+        ));
+        graph.add_relationship(rel(
+            "exec_dashboard",
+            "revenue_daily",
+            RelationshipType::DependsOn,
+        ));
+        graph.add_relationship(rel(
+            "orders.amount",
+            "raw_orders.amount",
+            RelationshipType::DerivedFrom,
+        ));
+        graph.add_relationship(rel(
+            "revenue_daily.total",
+            "orders.amount",
+            RelationshipType::AggregatedFrom,
+        ));
+        graph.add_relationship(LineageRelationship {
+            transform: Some("orders.customer_id = customers.id".to_string()),
+            ..rel("orders", "customers", RelationshipType::JoinedWith)
+        });
+        graph
+    }
+
+    #[test]
+    fn test_traversal_and_join_path() {
+        let mut graph = graph();
+        graph.add_node(node("orders", NodeType::View));
+        graph.add_relationship(rel("orders", "raw_orders", RelationshipType::DerivedFrom));
+        assert_eq!(graph.nodes.len(), 8);
+        assert_eq!(graph.relationships.len(), 6);
+        assert!(graph.validate().is_ok());
+
+        let upstream = graph.upstream("exec_dashboard", None);
+        let ids: Vec<&str> = upstream.iter().map(|h| h.node.as_str()).collect();
+        assert_eq!(ids, vec!["revenue_daily", "orders", "raw_orders"]);
+        assert_eq!(upstream[2].depth, 3);
+        assert_eq!(graph.upstream("exec_dashboard", Some(1)).len(), 1);
+        assert_eq!(graph.downstream("raw_orders", None).len(), 3);
+
+        let path = graph.join_path("customers", "orders").unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(
+            path[0].condition.as_deref(),
+            Some("orders.customer_id = customers.id")
+        );
+        assert!(graph.join_path("customers", "exec_dashboard").is_none());
+    }
+
+    #[test]
+    fn test_impact_analysis() {
+        let impact = graph().impact("raw_orders.amount");
+        assert_eq!(
+            impact.affected_columns,
+            vec!["orders.amount", "revenue_daily.total"]
+        );
+        assert_eq!(impact.affected_tables, vec!["orders", "revenue_daily"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_cycles_and_dangling_edges() {
+        let mut graph = graph();
+        graph.add_relationship(rel(
+            "raw_orders",
+            "exec_dashboard",
+            RelationshipType::DependsOn,
+        ));
+        graph.add_relationship(rel("orders", "missing", RelationshipType::DependsOn));
+
+        let cycle = graph.find_cycle().unwrap();
+        assert_eq!(cycle.first(), cycle.last());
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown node 'missing'"));
+        assert!(err.contains("dependency cycle"));
+    }
 }
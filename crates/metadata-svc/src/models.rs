@@ -20,12 +20,108 @@ pub struct TableMetadata {
     pub description: Option<String>,
 }
 
+impl TableMetadata {
+    pub fn pii_columns(&self) -> Vec<String> {
+        self.annotations
+            .iter()
+            .filter(|annotation| annotation.key == "pii")
+            .flat_map(|annotation| annotation.value.split(','))
+            .map(|column| column.trim().to_string())
+            .filter(|column| !column.is_empty())
+            .chain(
+                self.columns
+                    .iter()
+                    .filter(|column| column.is_pii())
+                    .map(|column| column.name.clone()),
+            )
+            .collect()
+    }
+
+    /// Keeps the description, comments and annotations curated on `stored`
+    /// that this table and its same-named columns do not set themselves.
+    pub fn merge_curated(&mut self, stored: &TableMetadata) {
+        if self.description.is_none() {
+            self.description = stored.description.clone();
+        }
+        merge_annotations(&mut self.annotations, &stored.annotations);
+        for column in &mut self.columns {
+            let Some(curated) = stored.columns.iter().find(|c| c.name == column.name) else {
+                continue;
+            };
+            if column.comment.is_none() {
+                column.comment = curated.comment.clone();
+            }
+            merge_annotations(&mut column.annotations, &curated.annotations);
+        }
+    }
+
+    /// Overlays an imported description of this table: fields `update` sets
+    /// win, columns it does not mention are kept, and annotations from both
+    /// are combined. Applying the same update twice changes nothing.
+    pub fn apply(&mut self, mut update: TableMetadata) {
+        if update.description.is_some() {
+            self.description = update.description;
+        }
+        if update.primary_key.is_some() {
+            self.primary_key = update.primary_key;
+        }
+        merge_annotations(&mut update.annotations, &self.annotations);
+        self.annotations = update.annotations;
+        for mut column in update.columns {
+            let Some(current) = self.columns.iter_mut().find(|c| c.name == column.name) else {
+                self.columns.push(column);
+                continue;
+            };
+            if column.data_type != "unknown" {
+                current.data_type = column.data_type;
+            }
+            if column.comment.is_some() {
+                current.comment = column.comment;
+            }
+            merge_annotations(&mut column.annotations, &current.annotations);
+            current.annotations = column.annotations;
+        }
+    }
+}
+
+/// Prepends the `stored` annotations missing from `annotations`, so that on a
+/// repeated key the incoming value, coming last, wins.
+fn merge_annotations(annotations: &mut Vec<Annotation>, stored: &[Annotation]) {
+    let missing: Vec<Annotation> = stored
+        .iter()
+        .filter(|s| {
+            !annotations
+                .iter()
+                .any(|a| a.key == s.key && a.value == s.value)
+        })
+        .cloned()
+        .collect();
+    annotations.splice(0..0, missing);
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnMetadata {
     pub name: String,
     pub data_type: String,
     pub nullable: bool,
     pub comment: Option<String>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl ColumnMetadata {
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations
+            .iter()
+            .rev()
+            .find(|annotation| annotation.key == key)
+            .map(|annotation| annotation.value.as_str())
+    }
+
+    pub fn is_pii(&self) -> bool {
+        self.annotation(Annotation::PII)
+            .is_some_and(|value| !value.eq_ignore_ascii_case("false"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,3 +130,82 @@ pub struct Annotation {
     pub value: String,
     pub source: Option<String>,
 }
+
+impl Annotation {
+    pub const DEFINITION: &'static str = "definition";
+    pub const UNIT: &'static str = "unit";
+    pub const ALLOWED_VALUES: &'static str = "allowed_values";
+    pub const PII: &'static str = "pii";
+    pub const PREFERRED_FOR: &'static str = "preferred_for";
+    pub const TEST: &'static str = "test";
+    pub const TAG: &'static str = "tag";
+
+    pub fn new(key: &str, value: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.to_string(),
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+}
+
+/// A business term mapped to the tables, `table.column`s and SQL that implement it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlossaryTerm {
+    pub term: String,
+    pub definition: String,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    #[serde(default)]
+    pub tables: Vec<String>,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub sql: Option<String>,
+}
+
+impl GlossaryTerm {
+    pub fn new(term: &str, definition: &str) -> Self {
+        Self {
+            term: term.to_string(),
+            definition: definition.to_string(),
+            synonyms: vec![],
+            tables: vec![],
+            columns: vec![],
+            sql: None,
+        }
+    }
+
+    pub fn with_tables(mut self, tables: Vec<String>) -> Self {
+        self.tables = tables;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_synonyms(mut self, synonyms: Vec<String>) -> Self {
+        self.synonyms = synonyms;
+        self
+    }
+
+    pub fn with_sql(mut self, sql: &str) -> Self {
+        self.sql = Some(sql.to_string());
+        self
+    }
+
+    pub fn references_table(&self, table: &str) -> bool {
+        self.tables.iter().any(|t| t.eq_ignore_ascii_case(table))
+            || self.columns.iter().any(|c| {
+                c.split_once('.')
+                    .is_some_and(|(t, _)| t.eq_ignore_ascii_case(table))
+            })
+    }
+}
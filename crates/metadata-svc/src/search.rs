@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Annotation, GlossaryTerm, Schema};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchHitKind {
+    Table,
+    Column,
+    Glossary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    pub schema_name: Option<String>,
+    /// Table name, `table.column` or glossary term.
+    pub name: String,
+    pub text: String,
+    pub score: f32,
+}
+
+pub(crate) fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(|word| word.split('_'))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Whether `phrase` occurs in `query` as consecutive words, allowing plurals
+/// ("active users" matches the phrase "active user").
+pub(crate) fn contains_phrase(query: &[String], phrase: &[String]) -> bool {
+    !phrase.is_empty()
+        && query.windows(phrase.len()).any(|window| {
+            window
+                .iter()
+                .zip(phrase)
+                .all(|(word, term)| word == term || word.strip_suffix('s') == Some(term))
+        })
+}
+
+fn score(query: &[String], names: &[&str], text: &str, phrase_weight: f32) -> f32 {
+    let words = tokens(text);
+    let overlap = query
+        .iter()
+        .filter(|term| {
+            words
+                .iter()
+                .any(|w| w == *term || term.strip_suffix('s') == Some(w))
+        })
+        .count() as f32
+        / query.len().max(1) as f32;
+    let phrase = names
+        .iter()
+        .any(|name| contains_phrase(query, &tokens(name)));
+    overlap + if phrase { phrase_weight } else { 0.0 }
+}
+
+/// Ranks tables, columns and glossary terms against `query` by word overlap,
+/// boosting candidates whose name appears in the query as a phrase. Curated
+/// glossary terms get the largest boost.
+pub fn search(
+    schemas: &[Schema],
+    glossary: &[GlossaryTerm],
+    query: &str,
+    limit: usize,
+) -> Vec<SearchHit> {
+    let query = tokens(query);
+    let mut hits = Vec::new();
+
+    for schema in schemas {
+        for table in &schema.tables {
+            let mut text = table.name.clone();
+            for part in table
+                .description
+                .iter()
+                .chain(table.annotations.iter().map(|a| &a.value))
+            {
+                text.push(' ');
+                text.push_str(part);
+            }
+            hits.push(SearchHit {
+                kind: SearchHitKind::Table,
+                schema_name: Some(schema.name.clone()),
+                name: table.name.clone(),
+                score: score(&query, &[&table.name], &text, 1.0),
+                text,
+            });
+
+            for column in &table.columns {
+                let mut text = format!("{} {}", table.name, column.name);
+                for part in column
+                    .comment
+                    .iter()
+                    .chain(column.annotations.iter().map(|a| &a.value))
+                {
+                    text.push(' ');
+                    text.push_str(part);
+                }
+                let preferred: Vec<&str> = column
+                    .annotations
+                    .iter()
+                    .filter(|a| a.key == Annotation::PREFERRED_FOR)
+                    .map(|a| a.value.as_str())
+                    .collect();
+                let mut names = vec![column.name.as_str()];
+                names.extend(preferred);
+                hits.push(SearchHit {
+                    kind: SearchHitKind::Column,
+                    schema_name: Some(schema.name.clone()),
+                    name: format!("{}.{}", table.name, column.name),
+                    score: score(&query, &names, &text, 1.0),
+                    text,
+                });
+            }
+        }
+    }
+
+    for term in glossary {
+        let text = format!(
+            "{} {} {}",
+            term.term,
+            term.synonyms.join(" "),
+            term.definition
+        );
+        let mut names = vec![term.term.as_str()];
+        names.extend(term.synonyms.iter().map(String::as_str));
+        hits.push(SearchHit {
+            kind: SearchHitKind::Glossary,
+            schema_name: None,
+            name: term.term.clone(),
+            score: score(&query, &names, &text, 1.5),
+            text,
+        });
+    }
+
+    hits.retain(|hit| hit.score > 0.0);
+    hits.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    hits.truncate(limit);
+    hits
+}
+
+/// Glossary terms whose name or a synonym appears in `question`.
+pub fn matching_terms<'a>(glossary: &'a [GlossaryTerm], question: &str) -> Vec<&'a GlossaryTerm> {
+    let query = tokens(question);
+    glossary
+        .iter()
+        .filter(|term| {
+            std::iter::once(&term.term)
+                .chain(&term.synonyms)
+                .any(|name| contains_phrase(&query, &tokens(name)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ColumnMetadata, TableMetadata};
+
+    #[test]
+    fn test_search_ranks_glossary_and_columns() {
+        let schema = Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "postgres".to_string(),
+            tables: vec![TableMetadata {
+                name: "subscriptions".to_string(),
+                schema_name: None,
+                columns: vec![ColumnMetadata {
+                    name: "mrr_cents".to_string(),
+                    data_type: "bigint".to_string(),
+                    nullable: false,
+                    comment: None,
+                    annotations: vec![
+                        Annotation::new(Annotation::UNIT, "cents"),
+                        Annotation::new(Annotation::PREFERRED_FOR, "ARR"),
+                    ],
+                }],
+                primary_key: None,
+                annotations: vec![],
+                description: Some("Billing subscriptions".to_string()),
+            }],
+            created_at: None,
+            updated_at: None,
+        };
+        let glossary = vec![
+            GlossaryTerm::new("active user", "Logged in within the last 30 days")
+                .with_tables(vec!["sessions".to_string()]),
+            GlossaryTerm::new("ARR", "Annual recurring revenue")
+                .with_synonyms(vec!["annual run rate".to_string()])
+                .with_sql("SUM(mrr_cents) * 12 / 100"),
+        ];
+
+        let hits = search(&[schema], &glossary, "What is our ARR?", 5);
+        assert_eq!(hits[0].kind, SearchHitKind::Glossary);
+        assert_eq!(hits[0].name, "ARR");
+        assert!(hits.iter().any(|hit| hit.name == "subscriptions.mrr_cents"));
+
+        let matched = matching_terms(&glossary, "How many active users signed up?");
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].term, "active user");
+        assert!(matching_terms(&glossary, "list users").is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+use serde::{Deserialize, Serialize};
+use warehouse_conn::{DateUnit, Dialect};
+
+use crate::error::Error;
+use crate::search::{contains_phrase, tokens};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metric {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Aggregate expression, e.g. `SUM(orders.amount)`.
+    pub expression: String,
+    pub table: String,
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub time_column: Option<String>,
+    /// Grain used when a request asks for a trend without naming one.
+    #[serde(default)]
+    pub time_grain: Option<DateUnit>,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Join {
+    pub table: String,
+    pub on: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dimension {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub expression: String,
+    /// Joins needed to reach `expression` from a metric's base table, in order.
+    #[serde(default)]
+    pub joins: Vec<Join>,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimeRange {
+    /// The trailing `amount` units up to today.
+    Last {
+        amount: i64,
+        unit: DateUnit,
+    },
+    /// The previous complete period, e.g. last quarter.
+    Previous {
+        unit: DateUnit,
+    },
+    /// The current period to date, e.g. this month.
+    Current {
+        unit: DateUnit,
+    },
+    Between {
+        start: String,
+        end: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricQuery {
+    pub metric: String,
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    #[serde(default)]
+    pub time_grain: Option<DateUnit>,
+    #[serde(default)]
+    pub time_range: Option<TimeRange>,
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompiledQuery {
+    pub sql: String,
+    pub query: MetricQuery,
+    pub metric: Metric,
+    pub dimensions: Vec<Dimension>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SemanticModel {
+    #[serde(default)]
+    pub metrics: Vec<Metric>,
+    #[serde(default)]
+    pub dimensions: Vec<Dimension>,
+}
+
+fn names_match(name: &str, synonyms: &[String], wanted: &str) -> bool {
+    std::iter::once(name)
+        .chain(synonyms.iter().map(String::as_str))
+        .any(|n| n.eq_ignore_ascii_case(wanted))
+}
+
+/// Longest phrase among `names` found in `query`, in words.
+fn phrase_len<'a>(query: &[String], names: impl Iterator<Item = &'a str>) -> usize {
+    names
+        .map(tokens)
+        .filter(|phrase| contains_phrase(query, phrase))
+        .map(|phrase| phrase.len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn unit_from_word(word: &str) -> Option<DateUnit> {
+    match word.trim_end_matches('s') {
+        "day" => Some(DateUnit::Day),
+        "week" => Some(DateUnit::Week),
+        "month" => Some(DateUnit::Month),
+        "quarter" => Some(DateUnit::Quarter),
+        "year" => Some(DateUnit::Year),
+        _ => None,
+    }
+}
+
+fn grain_from_words(words: &[String]) -> Option<DateUnit> {
+    words.iter().enumerate().find_map(|(i, word)| {
+        let unit = match word.as_str() {
+            "daily" => Some(DateUnit::Day),
+            "weekly" => Some(DateUnit::Week),
+            "monthly" => Some(DateUnit::Month),
+            "quarterly" => Some(DateUnit::Quarter),
+            "yearly" | "annually" => Some(DateUnit::Year),
+            _ => None,
+        };
+        unit.or_else(|| {
+            let previous = i.checked_sub(1).map(|p| words[p].as_str());
+            matches!(previous, Some("by" | "per" | "each"))
+                .then(|| unit_from_word(word))
+                .flatten()
+        })
+    })
+}
+
+fn range_from_words(words: &[String]) -> Option<TimeRange> {
+    words.iter().enumerate().find_map(|(i, word)| {
+        let next = words.get(i + 1).map(String::as_str).unwrap_or_default();
+        let after = words.get(i + 2).map(String::as_str).unwrap_or_default();
+        match word.as_str() {
+            "last" | "past" | "previous" | "trailing" => match next.parse::<i64>() {
+                Ok(amount) => unit_from_word(after).map(|unit| TimeRange::Last { amount, unit }),
+                Err(_) => unit_from_word(next).map(|unit| TimeRange::Previous { unit }),
+            },
+            "this" | "current" => unit_from_word(next).map(|unit| TimeRange::Current { unit }),
+            "ytd" => Some(TimeRange::Current {
+                unit: DateUnit::Year,
+            }),
+            "mtd" => Some(TimeRange::Current {
+                unit: DateUnit::Month,
+            }),
+            _ => None,
+        }
+    })
+}
+
+/// Shifts `expr` by whole units. Quarters become months because not every
+/// dialect accepts quarter intervals.
+fn shift(dialect: &dyn Dialect, expr: &str, amount: i64, unit: DateUnit) -> String {
+    match unit {
+        DateUnit::Quarter => dialect.date_add(expr, amount * 3, DateUnit::Month),
+        unit => dialect.date_add(expr, amount, unit),
+    }
+}
+
+impl SemanticModel {
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(yaml).map_err(|e| Error::Metadata(e.to_string()))
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&Metric> {
+        self.metrics
+            .iter()
+            .find(|m| names_match(&m.name, &m.synonyms, name))
+    }
+
+    pub fn dimension(&self, name: &str) -> Option<&Dimension> {
+        self.dimensions
+            .iter()
+            .find(|d| names_match(&d.name, &d.synonyms, name))
+    }
+
+    pub fn upsert_metric(&mut self, metric: Metric) {
+        self.metrics
+            .retain(|m| !m.name.eq_ignore_ascii_case(&metric.name));
+        self.metrics.push(metric);
+    }
+
+    pub fn upsert_dimension(&mut self, dimension: Dimension) {
+        self.dimensions
+            .retain(|d| !d.name.eq_ignore_ascii_case(&dimension.name));
+        self.dimensions.push(dimension);
+    }
+
+    /// Reads a request such as "revenue by region last quarter" into a
+    /// [`MetricQuery`] over the governed metrics and dimensions.
+    pub fn parse_request(&self, request: &str) -> Result<MetricQuery, Error> {
+        let words = tokens(request);
+
+        let metric = self
+            .metrics
+            .iter()
+            .map(|m| {
+                let names =
+                    std::iter::once(m.name.as_str()).chain(m.synonyms.iter().map(String::as_str));
+                (phrase_len(&words, names), m)
+            })
+            .filter(|(len, _)| *len > 0)
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, m)| m)
+            .ok_or_else(|| {
+                let available: Vec<&str> = self.metrics.iter().map(|m| m.name.as_str()).collect();
+                Error::NotFound(format!(
+                    "No governed metric matches '{}'. Available metrics: {}",
+                    request,
+                    available.join(", ")
+                ))
+            })?;
+
+        let dimensions = self
+            .dimensions
+            .iter()
+            .filter(|d| {
+                let names =
+                    std::iter::once(d.name.as_str()).chain(d.synonyms.iter().map(String::as_str));
+                phrase_len(&words, names) > 0
+            })
+            .map(|d| d.name.clone())
+            .collect();
+
+        let trend =
+            contains_phrase(&words, &tokens("over time")) || words.iter().any(|w| w == "trend");
+        let time_grain = grain_from_words(&words)
+            .or_else(|| trend.then(|| metric.time_grain.unwrap_or(DateUnit::Month)));
+
+        Ok(MetricQuery {
+            metric: metric.name.clone(),
+            dimensions,
+            time_grain,
+            time_range: range_from_words(&words),
+            filters: vec![],
+            limit: None,
+        })
+    }
+
+    pub fn compile(
+        &self,
+        query: &MetricQuery,
+        dialect: &dyn Dialect,
+    ) -> Result<CompiledQuery, Error> {
+        let metric = self
+            .metric(&query.metric)
+            .ok_or_else(|| Error::NotFound(format!("Metric '{}' not found", query.metric)))?;
+        let dimensions = query
+            .dimensions
+            .iter()
+            .map(|name| {
+                self.dimension(name)
+                    .cloned()
+                    .ok_or_else(|| Error::NotFound(format!("Dimension '{}' not found", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let time_column = metric.time_column.as_ref().map(|column| {
+            if column.contains('.') {
+                column.clone()
+            } else {
+                format!("{}.{}", metric.table, column)
+            }
+        });
+        let needs_time = query.time_grain.is_some() || query.time_range.is_some();
+        let time_column = match (time_column, needs_time) {
+            (Some(column), _) => column,
+            (None, false) => String::new(),
+            (None, true) => {
+                return Err(Error::Metadata(format!(
+                    "Metric '{}' has no time column",
+                    metric.name
+                )))
+            }
+        };
+
+        let mut select = Vec::new();
+        if let Some(grain) = query.time_grain {
+            select.push(format!(
+                "{} AS {}",
+                dialect.date_trunc(grain, &time_column),
+                dialect.quote_identifier("period")
+            ));
+        }
+        for dimension in &dimensions {
+            select.push(format!(
+                "{} AS {}",
+                dimension.expression,
+                dialect.quote_identifier(&dimension.name)
+            ));
+        }
+        let group_count = select.len();
+        select.push(format!(
+            "{} AS {}",
+            metric.expression,
+            dialect.quote_identifier(&metric.name)
+        ));
+
+        let mut sql = format!("SELECT {}\nFROM {}", select.join(", "), metric.table);
+        let mut joined = vec![metric.table.to_lowercase()];
+        for join in dimensions.iter().flat_map(|d| &d.joins) {
+            if !joined.contains(&join.table.to_lowercase()) {
+                joined.push(join.table.to_lowercase());
+                sql.push_str(&format!("\nLEFT JOIN {} ON {}", join.table, join.on));
+            }
+        }
+
+        let mut conditions = metric.filters.clone();
+        if let Some(range) = &query.time_range {
+            let today = dialect.current_date();
+            match range {
+                TimeRange::Last { amount, unit } => conditions.push(format!(
+                    "{} >= {}",
+                    time_column,
+                    shift(dialect, &today, -amount, *unit)
+                )),
+                TimeRange::Previous { unit } => {
+                    let start = dialect.date_trunc(*unit, &today);
+                    conditions.push(format!(
+                        "{} >= {}",
+                        time_column,
+                        shift(dialect, &start, -1, *unit)
+                    ));
+                    conditions.push(format!("{} < {}", time_column, start));
+                }
+                TimeRange::Current { unit } => conditions.push(format!(
+                    "{} >= {}",
+                    time_column,
+                    dialect.date_trunc(*unit, &today)
+                )),
+                TimeRange::Between { start, end } => {
+                    conditions.push(format!(
+                        "{} >= {}",
+                        time_column,
+                        dialect.quote_string(start)
+                    ));
+                    conditions.push(format!("{} < {}", time_column, dialect.quote_string(end)));
+                }
+            }
+        }
+        conditions.extend(query.filters.iter().cloned());
+        if !conditions.is_empty() {
+            sql.push_str(&format!(
+                "\nWHERE {}",
+                conditions
+                    .iter()
+                    .map(|c| format!("({})", c))
+                    .collect::<Vec<_>>()
+                    .join(" AND ")
+            ));
+        }
+
+        if group_count > 0 {
+            let positions: Vec<String> = (1..=group_count).map(|i| i.to_string()).collect();
+            sql.push_str(&format!("\nGROUP BY {}", positions.join(", ")));
+            sql.push_str(&format!("\nORDER BY {}", positions.join(", ")));
+        }
+        if let Some(limit) = query.limit {
+            sql = dialect.limit(&sql, limit);
+        }
+
+        Ok(CompiledQuery {
+            sql,
+            query: query.clone(),
+            metric: metric.clone(),
+            dimensions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse_conn::{PostgresDialect, SqliteDialect};
+
+    fn model() -> SemanticModel {
+        SemanticModel::from_yaml(
+            r#"
+metrics:
+  - name: revenue
+    expression: SUM(orders.amount)
+    table: orders
+    filters: ["orders.status = 'complete'"]
+    time_column: created_at
+    time_grain: month
+    synonyms: [sales]
+dimensions:
+  - name: region
+    expression: customers.region
+    joins:
+      - table: customers
+        on: orders.customer_id = customers.id
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let model = model();
+        let query = model
+            .parse_request("Revenue by region last quarter")
+            .unwrap();
+        assert_eq!(query.metric, "revenue");
+        assert_eq!(query.dimensions, vec!["region"]);
+        assert_eq!(query.time_grain, None);
+        assert_eq!(
+            query.time_range,
+            Some(TimeRange::Previous {
+                unit: DateUnit::Quarter
+            })
+        );
+
+        let query = model
+            .parse_request("monthly sales for the last 90 days")
+            .unwrap();
+        assert_eq!(query.time_grain, Some(DateUnit::Month));
+        assert_eq!(
+            query.time_range,
+            Some(TimeRange::Last {
+                amount: 90,
+                unit: DateUnit::Day
+            })
+        );
+        assert_eq!(
+            model.parse_request("sales trend").unwrap().time_grain,
+            Some(DateUnit::Month)
+        );
+        assert!(matches!(
+            model.parse_request("headcount by team"),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_compile_for_dialect() {
+        let model = model();
+        let query = model
+            .parse_request("revenue by region last quarter")
+            .unwrap();
+
+        let compiled = model.compile(&query, &PostgresDialect).unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT customers.region AS \"region\", SUM(orders.amount) AS \"revenue\"\n\
+             FROM orders\n\
+             LEFT JOIN customers ON orders.customer_id = customers.id\n\
+             WHERE (orders.status = 'complete') \
+             AND (orders.created_at >= DATE_TRUNC('quarter', CURRENT_DATE) + INTERVAL '-3 month') \
+             AND (orders.created_at < DATE_TRUNC('quarter', CURRENT_DATE))\n\
+             GROUP BY 1\n\
+             ORDER BY 1"
+        );
+
+        let trend = MetricQuery {
+            metric: "sales".to_string(),
+            time_grain: Some(DateUnit::Month),
+            limit: Some(12),
+            ..Default::default()
+        };
+        let compiled = model.compile(&trend, &SqliteDialect).unwrap();
+        assert!(compiled
+            .sql
+            .starts_with("SELECT DATE(orders.created_at, 'start of month') AS \"period\""));
+        assert!(compiled.sql.ends_with("GROUP BY 1\nORDER BY 1 LIMIT 12"));
+    }
+}
@@ -1,60 +1,296 @@
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
+use warehouse_conn::{Dialect, Warehouse, WarehouseRegistry};
+use workflow_engine::{WorkflowDefinition, WorkflowEngine};
 
 use crate::error::Error;
-use crate::lineage::LineageGraph;
-use crate::models::{Annotation, Schema, TableMetadata};
+use crate::extract;
+use crate::import::{self, MetadataImport};
+use crate::lineage::{ImpactReport, JoinStep, LineageGraph, LineageHop};
+use crate::models::{Annotation, GlossaryTerm, Schema, TableMetadata};
+use crate::search::{self, SearchHit};
+use crate::semantic::{CompiledQuery, Dimension, Metric, MetricQuery, SemanticModel};
+use crate::sql_store::{PostgresMetadataStore, SqliteMetadataStore};
+use crate::store::{now_rfc3339, InMemoryMetadataStore, MetadataStore};
+use crate::versioning::{SchemaDiff, SchemaEvent, SchemaSnapshot, SchemaSync};
 
 pub struct MetadataService {
-    schemas: Arc<RwLock<HashMap<String, Schema>>>,
-    lineage: Arc<RwLock<Option<LineageGraph>>>,
+    store: Arc<dyn MetadataStore>,
+    events: broadcast::Sender<SchemaEvent>,
 }
 
 impl MetadataService {
     pub fn new() -> Self {
-        Self {
-            schemas: Arc::new(RwLock::new(HashMap::new())),
-            lineage: Arc::new(RwLock::new(None)),
-        }
+        Self::with_store(Arc::new(InMemoryMetadataStore::new()))
+    }
+
+    pub fn with_store(store: Arc<dyn MetadataStore>) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self { store, events }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SchemaEvent> {
+        self.events.subscribe()
+    }
+
+    /// Forwards schema events to `engine`, running the workflows whose
+    /// `event` trigger matches, e.g. `schema.drift`.
+    pub fn trigger_workflows(&self, engine: Arc<WorkflowEngine>) -> tokio::task::JoinHandle<()> {
+        let mut events = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        engine.trigger_event(event.name()).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Skipped {} schema events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Runs `handler` on every drift, e.g. to flag memories that mention the
+    /// dropped tables or columns.
+    pub fn on_drift<F, Fut>(&self, handler: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn(SchemaDiff) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send,
+    {
+        let mut events = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(SchemaEvent::Drift(diff)) => handler(diff).await,
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Skipped {} schema events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let store: Arc<dyn MetadataStore> =
+            if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+                Arc::new(PostgresMetadataStore::connect(url).await?)
+            } else if url.starts_with("sqlite:") {
+                Arc::new(SqliteMetadataStore::connect(url).await?)
+            } else {
+                return Err(Error::Database(format!(
+                    "Unsupported metadata store URL: {}",
+                    url
+                )));
+            };
+        Ok(Self::with_store(store))
     }
 
     pub async fn save_schema(&self, schema: Schema) -> Result<Schema, Error> {
-        let mut schemas = self.schemas.write().await;
-        let name = schema.name.clone();
-        let mut saved = schema.clone();
-        saved.id = Some(0);
-        schemas.insert(name, saved.clone());
-        Ok(saved)
+        self.store.save_schema(schema).await
+    }
+
+    /// Saves `schema` and records a new versioned snapshot unless nothing
+    /// changed. A diff against the previous snapshot is stored and broadcast
+    /// when anything changed.
+    pub async fn sync_schema(&self, schema: Schema) -> Result<SchemaSync, Error> {
+        let now = now_rfc3339();
+        // Two first syncs of a schema race on inserting it; the loser retries
+        // as an update of the winner's schema.
+        let sync = match self.store.save_schema_version(schema.clone(), &now).await {
+            Err(Error::Conflict(_)) => self.store.save_schema_version(schema, &now).await?,
+            sync => sync?,
+        };
+
+        if let Some(diff) = &sync.diff {
+            tracing::info!(
+                "Schema '{}' drifted from v{} to v{}",
+                diff.schema_name,
+                diff.from_version,
+                diff.to_version
+            );
+            let _ = self.events.send(SchemaEvent::Drift(diff.clone()));
+        }
+        let _ = self.events.send(SchemaEvent::Synced {
+            schema_name: sync.snapshot.schema_name.clone(),
+            version: sync.snapshot.version,
+        });
+
+        Ok(sync)
+    }
+
+    /// Syncs a schema freshly read from a warehouse, keeping the descriptions
+    /// and annotations curated on the stored tables and columns, such as PII
+    /// tags.
+    pub async fn refresh_schema(&self, mut schema: Schema) -> Result<SchemaSync, Error> {
+        match self.store.get_schema(&schema.name).await {
+            Ok(stored) => {
+                for table in &mut schema.tables {
+                    if let Some(curated) = stored.tables.iter().find(|t| t.name == table.name) {
+                        table.merge_curated(curated);
+                    }
+                }
+            }
+            Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        self.sync_schema(schema).await
+    }
+
+    /// Applies schemas and lineage from an external tool. Imported tables are
+    /// merged into same-named tables, see [`TableMetadata::apply`], and
+    /// unchanged schemas are not re-synced, so repeated imports are no-ops.
+    pub async fn import_metadata(&self, import: MetadataImport) -> Result<Vec<SchemaSync>, Error> {
+        let mut synced = Vec::new();
+        for schema in import.schemas {
+            let merged = match self.store.get_schema(&schema.name).await {
+                Ok(mut existing) => {
+                    let before = serde_json::to_value(&existing.tables).ok();
+                    for table in schema.tables {
+                        match existing.tables.iter_mut().find(|t| t.name == table.name) {
+                            Some(current) => current.apply(table),
+                            None => existing.tables.push(table),
+                        }
+                    }
+                    if serde_json::to_value(&existing.tables).ok() == before {
+                        continue;
+                    }
+                    existing
+                }
+                Err(Error::NotFound(_)) => schema,
+                Err(e) => return Err(e),
+            };
+            synced.push(self.sync_schema(merged).await?);
+        }
+        if !import.lineage.nodes.is_empty() {
+            self.merge_lineage(import.lineage).await?;
+        }
+        Ok(synced)
+    }
+
+    /// Imports dbt's `manifest.json` and, if it exists, the `catalog.json`
+    /// next to it.
+    pub async fn import_dbt(&self, manifest_path: &Path) -> Result<Vec<SchemaSync>, Error> {
+        let manifest = read_file(manifest_path).await?;
+        let catalog_path = manifest_path.with_file_name("catalog.json");
+        let catalog = match tokio::fs::try_exists(&catalog_path).await {
+            Ok(true) => Some(read_file(&catalog_path).await?),
+            _ => None,
+        };
+        self.import_metadata(import::import_dbt(&manifest, catalog.as_deref())?)
+            .await
+    }
+
+    pub async fn import_openlineage(&self, events_path: &Path) -> Result<Vec<SchemaSync>, Error> {
+        let events = read_file(events_path).await?;
+        self.import_metadata(import::import_openlineage(&events)?)
+            .await
+    }
+
+    pub async fn list_snapshots(&self, schema_name: &str) -> Result<Vec<SchemaSnapshot>, Error> {
+        self.store.list_snapshots(schema_name).await
+    }
+
+    /// The schema as it was last synced at or before `timestamp` (RFC 3339).
+    pub async fn schema_at(&self, schema_name: &str, timestamp: &str) -> Result<Schema, Error> {
+        self.store
+            .list_snapshots(schema_name)
+            .await?
+            .into_iter()
+            .rev()
+            .find(|snapshot| snapshot.created_at.as_str() <= timestamp)
+            .map(|snapshot| snapshot.schema)
+            .ok_or_else(|| {
+                Error::NotFound(format!("No snapshot of '{}' at {}", schema_name, timestamp))
+            })
+    }
+
+    pub async fn list_diffs(
+        &self,
+        schema_name: Option<&str>,
+        since: Option<&str>,
+    ) -> Result<Vec<SchemaDiff>, Error> {
+        let mut diffs = self.store.list_diffs(since).await?;
+        if let Some(schema_name) = schema_name {
+            diffs.retain(|diff| diff.schema_name == schema_name);
+        }
+        Ok(diffs)
     }
 
     pub async fn get_schema(&self, name: &str) -> Result<Schema, Error> {
-        let schemas = self.schemas.read().await;
-        schemas
-            .get(name)
-            .cloned()
-            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))
+        self.store.get_schema(name).await
     }
 
     pub async fn list_schemas(&self) -> Result<Vec<Schema>, Error> {
-        let schemas = self.schemas.read().await;
-        Ok(schemas.values().cloned().collect())
+        self.store.list_schemas().await
+    }
+
+    /// Up to `limit` schemas from `offset` in id order, with the total count.
+    pub async fn list_schemas_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Schema>, usize), Error> {
+        self.store.list_schemas_page(offset, limit).await
     }
 
     pub async fn delete_schema(&self, name: &str) -> Result<(), Error> {
-        let mut schemas = self.schemas.write().await;
-        schemas
-            .remove(name)
-            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))?;
-        Ok(())
+        self.store.delete_schema(name).await
+    }
+
+    /// Like [`save_schema`](Self::save_schema) but fails with `Conflict` if
+    /// the schema already exists.
+    pub async fn create_schema(&self, schema: Schema) -> Result<Schema, Error> {
+        let name = schema.name.clone();
+        self.store.create_schema(schema).await.map_err(|e| match e {
+            Error::Conflict(_) => Error::Conflict(format!("Schema '{}' already exists", name)),
+            e => e,
+        })
     }
 
     pub async fn add_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error> {
-        let mut schemas = self.schemas.write().await;
-        let schema = schemas
-            .get_mut(schema_name)
-            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
-        schema.tables.push(table);
-        Ok(())
+        self.store.add_table(schema_name, table).await
+    }
+
+    /// Adds a table, failing with `Conflict` if the schema already has it.
+    pub async fn create_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error> {
+        let name = table.name.clone();
+        self.store
+            .add_table(schema_name, table)
+            .await
+            .map_err(|e| match e {
+                Error::Conflict(_) => Error::Conflict(format!(
+                    "Table '{}' already exists in '{}'",
+                    name, schema_name
+                )),
+                e => e,
+            })
+    }
+
+    /// Replaces the table with the same name, or adds it, recording a new
+    /// snapshot of the schema.
+    pub async fn save_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error> {
+        let mut schema = self.store.get_schema(schema_name).await?;
+        match schema.tables.iter_mut().find(|t| t.name == table.name) {
+            Some(existing) => *existing = table,
+            None => schema.tables.push(table),
+        }
+        self.sync_schema(schema).await.map(|_| ())
+    }
+
+    /// Removes a table, recording a new snapshot of the schema.
+    pub async fn delete_table(&self, schema_name: &str, table_name: &str) -> Result<(), Error> {
+        let mut schema = self.store.get_schema(schema_name).await?;
+        let before = schema.tables.len();
+        schema.tables.retain(|t| t.name != table_name);
+        if schema.tables.len() == before {
+            return Err(Error::NotFound(format!("Table '{}' not found", table_name)));
+        }
+        self.sync_schema(schema).await.map(|_| ())
     }
 
     pub async fn get_table(
@@ -62,15 +298,11 @@ impl MetadataService {
         schema_name: &str,
         table_name: &str,
     ) -> Result<TableMetadata, Error> {
-        let schemas = self.schemas.read().await;
-        let schema = schemas
-            .get(schema_name)
-            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+        let schema = self.store.get_schema(schema_name).await?;
         schema
             .tables
-            .iter()
+            .into_iter()
             .find(|t| t.name == table_name)
-            .cloned()
             .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))
     }
 
@@ -80,17 +312,9 @@ impl MetadataService {
         table_name: &str,
         annotation: Annotation,
     ) -> Result<(), Error> {
-        let mut schemas = self.schemas.write().await;
-        let schema = schemas
-            .get_mut(schema_name)
-            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
-        let table = schema
-            .tables
-            .iter_mut()
-            .find(|t| t.name == table_name)
-            .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))?;
-        table.annotations.push(annotation);
-        Ok(())
+        self.store
+            .add_annotation(schema_name, table_name, annotation)
+            .await
     }
 
     pub async fn get_annotations(
@@ -102,26 +326,256 @@ impl MetadataService {
         Ok(table.annotations)
     }
 
+    pub async fn add_column_annotation(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+        annotation: Annotation,
+    ) -> Result<(), Error> {
+        self.store
+            .add_column_annotation(schema_name, table_name, column_name, annotation)
+            .await
+    }
+
+    pub async fn get_column_annotations(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<Vec<Annotation>, Error> {
+        let table = self.get_table(schema_name, table_name).await?;
+        table
+            .columns
+            .into_iter()
+            .find(|c| c.name == column_name)
+            .map(|c| c.annotations)
+            .ok_or_else(|| Error::NotFound(format!("Column '{}' not found", column_name)))
+    }
+
+    pub async fn save_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+        self.store.save_glossary_term(term).await
+    }
+
+    pub async fn create_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+        let name = term.term.clone();
+        self.store
+            .create_glossary_term(term)
+            .await
+            .map_err(|e| match e {
+                Error::Conflict(_) => {
+                    Error::Conflict(format!("Glossary term '{}' already exists", name))
+                }
+                e => e,
+            })
+    }
+
+    pub async fn get_glossary_term(&self, term: &str) -> Result<GlossaryTerm, Error> {
+        self.store
+            .list_glossary_terms()
+            .await?
+            .into_iter()
+            .find(|t| {
+                t.term.eq_ignore_ascii_case(term)
+                    || t.synonyms.iter().any(|s| s.eq_ignore_ascii_case(term))
+            })
+            .ok_or_else(|| Error::NotFound(format!("Glossary term '{}' not found", term)))
+    }
+
+    pub async fn list_glossary_terms(&self) -> Result<Vec<GlossaryTerm>, Error> {
+        self.store.list_glossary_terms().await
+    }
+
+    pub async fn delete_glossary_term(&self, term: &str) -> Result<(), Error> {
+        self.store.delete_glossary_term(term).await
+    }
+
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, Error> {
+        let schemas = self.store.list_schemas().await?;
+        let glossary = self.store.list_glossary_terms().await?;
+        Ok(search::search(&schemas, &glossary, query, limit))
+    }
+
+    /// Prompt section describing the glossary terms used in `question`.
+    pub async fn glossary_context(&self, question: &str) -> Result<String, Error> {
+        let glossary = self.store.list_glossary_terms().await?;
+        let terms = search::matching_terms(&glossary, question);
+        if terms.is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut context = String::from("\n\nBusiness glossary:\n");
+        for term in terms {
+            context.push_str(&format!("- {}: {}\n", term.term, term.definition));
+            if !term.tables.is_empty() || !term.columns.is_empty() {
+                let sources: Vec<&str> = term
+                    .tables
+                    .iter()
+                    .chain(&term.columns)
+                    .map(String::as_str)
+                    .collect();
+                context.push_str(&format!("  Uses: {}\n", sources.join(", ")));
+            }
+            if let Some(sql) = &term.sql {
+                context.push_str(&format!("  SQL: {}\n", sql));
+            }
+        }
+        Ok(context)
+    }
+
+    pub async fn semantic_model(&self) -> Result<SemanticModel, Error> {
+        Ok(self.store.get_semantic_model().await?.unwrap_or_default())
+    }
+
+    pub async fn set_semantic_model(&self, model: SemanticModel) -> Result<(), Error> {
+        self.store.set_semantic_model(model).await
+    }
+
+    pub async fn save_metric(&self, metric: Metric) -> Result<(), Error> {
+        self.store.upsert_metric(metric).await
+    }
+
+    pub async fn save_dimension(&self, dimension: Dimension) -> Result<(), Error> {
+        self.store.upsert_dimension(dimension).await
+    }
+
+    pub async fn compile_metric_query(
+        &self,
+        query: &MetricQuery,
+        dialect: &dyn Dialect,
+    ) -> Result<CompiledQuery, Error> {
+        self.semantic_model().await?.compile(query, dialect)
+    }
+
+    /// Compiles a natural-language request such as "revenue by region last quarter".
+    pub async fn compile_metric_request(
+        &self,
+        request: &str,
+        dialect: &dyn Dialect,
+    ) -> Result<CompiledQuery, Error> {
+        let model = self.semantic_model().await?;
+        let query = model.parse_request(request)?;
+        model.compile(&query, dialect)
+    }
+
+    /// Every column tagged as PII, as `table.column`.
+    pub async fn pii_columns(&self) -> Vec<String> {
+        let schemas = match self.store.list_schemas().await {
+            Ok(schemas) => schemas,
+            Err(e) => {
+                tracing::warn!("Failed to load schemas for PII columns: {}", e);
+                return vec![];
+            }
+        };
+        let mut columns: Vec<String> = schemas
+            .iter()
+            .flat_map(|schema| schema.tables.iter())
+            .flat_map(|table| {
+                table
+                    .pii_columns()
+                    .into_iter()
+                    .map(|column| format!("{}.{}", table.name, column))
+            })
+            .collect();
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+
     pub async fn set_lineage(&self, graph: LineageGraph) -> Result<(), Error> {
-        let mut lineage = self.lineage.write().await;
-        *lineage = Some(graph);
-        Ok(())
+        graph.validate()?;
+        self.store.set_lineage(graph).await
     }
 
     pub async fn get_lineage(&self) -> Result<LineageGraph, Error> {
-        let lineage = self.lineage.read().await;
-        lineage
-            .clone()
+        self.store
+            .get_lineage()
+            .await?
             .ok_or_else(|| Error::NotFound("No lineage graph found".to_string()))
     }
 
+    /// Merges `graph` into the stored lineage, see [`LineageGraph::merge`].
+    pub async fn merge_lineage(&self, graph: LineageGraph) -> Result<LineageGraph, Error> {
+        self.store.merge_lineage(graph).await
+    }
+
+    /// Extracts lineage from the warehouse's view definitions and merges it
+    /// into the stored graph.
+    pub async fn extract_lineage(&self, warehouse: &dyn Warehouse) -> Result<LineageGraph, Error> {
+        let graph = extract::lineage_from_warehouse(warehouse).await?;
+        self.merge_lineage(graph).await
+    }
+
+    /// Extracts lineage from `workflow`'s query steps, parsing each in the
+    /// dialect of its connection in `warehouses`, and merges it into the
+    /// stored graph.
+    pub async fn extract_workflow_lineage(
+        &self,
+        workflow: &WorkflowDefinition,
+        warehouses: Option<&WarehouseRegistry>,
+    ) -> Result<LineageGraph, Error> {
+        self.merge_lineage(extract::lineage_from_workflow(workflow, warehouses))
+            .await
+    }
+
     pub async fn get_table_dependencies(&self, table_id: &str) -> Result<Vec<String>, Error> {
-        let lineage = self.lineage.read().await;
-        match lineage.as_ref() {
+        match self.store.get_lineage().await? {
             Some(graph) => Ok(graph.get_table_dependencies(table_id)),
             None => Ok(vec![]),
         }
     }
+
+    /// The lineage graph, failing with `NotFound` if it has no `node_id`.
+    async fn lineage_with_node(&self, node_id: &str) -> Result<LineageGraph, Error> {
+        let graph = self.get_lineage().await?;
+        if graph.node(node_id).is_none() {
+            return Err(Error::NotFound(format!(
+                "Lineage node '{}' not found",
+                node_id
+            )));
+        }
+        Ok(graph)
+    }
+
+    pub async fn get_upstream(
+        &self,
+        node_id: &str,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<LineageHop>, Error> {
+        Ok(self
+            .lineage_with_node(node_id)
+            .await?
+            .upstream(node_id, max_depth))
+    }
+
+    pub async fn get_downstream(
+        &self,
+        node_id: &str,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<LineageHop>, Error> {
+        Ok(self
+            .lineage_with_node(node_id)
+            .await?
+            .downstream(node_id, max_depth))
+    }
+
+    pub async fn join_path(&self, from: &str, to: &str) -> Result<Vec<JoinStep>, Error> {
+        self.get_lineage()
+            .await?
+            .join_path(from, to)
+            .ok_or_else(|| Error::NotFound(format!("No join path from '{}' to '{}'", from, to)))
+    }
+
+    pub async fn impact_analysis(&self, node_id: &str) -> Result<ImpactReport, Error> {
+        let graph = self.lineage_with_node(node_id).await?;
+        Ok(graph.impact(node_id))
+    }
+}
+
+async fn read_file(path: &Path) -> Result<String, Error> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| Error::Metadata(format!("failed to read {}: {}", path.display(), e)))
 }
 
 impl Default for MetadataService {
@@ -130,11 +584,10 @@ impl Default for MetadataService {
     }
 }
 
-use std::collections::HashMap;
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::ColumnMetadata;
 
     #[tokio::test]
     async fn test_save_and_get_schema() {
@@ -150,6 +603,7 @@ mod tests {
 
         let saved = service.save_schema(schema.clone()).await.unwrap();
         assert_eq!(saved.name, "test");
+        assert_eq!(saved.id, Some(1));
 
         let retrieved = service.get_schema("test").await.unwrap();
         assert_eq!(retrieved.name, "test");
@@ -190,5 +644,299 @@ mod tests {
         let annotations = service.get_annotations("test", "users").await.unwrap();
         assert_eq!(annotations.len(), 1);
         assert_eq!(annotations[0].key, "description");
+
+        let pii = Annotation {
+            key: "pii".to_string(),
+            value: "email, phone".to_string(),
+            source: None,
+        };
+        service.add_annotation("test", "users", pii).await.unwrap();
+        assert_eq!(
+            service.pii_columns().await,
+            vec!["users.email", "users.phone"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_column_annotations_and_glossary() {
+        let service = MetadataService::new();
+        service
+            .save_schema(Schema {
+                id: None,
+                name: "main".to_string(),
+                source: "postgres".to_string(),
+                tables: vec![TableMetadata {
+                    name: "users".to_string(),
+                    schema_name: None,
+                    columns: vec![crate::models::ColumnMetadata {
+                        name: "ssn".to_string(),
+                        data_type: "text".to_string(),
+                        nullable: true,
+                        comment: None,
+                        annotations: vec![],
+                    }],
+                    primary_key: None,
+                    annotations: vec![],
+                    description: None,
+                }],
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+            .unwrap();
+
+        service
+            .add_column_annotation(
+                "main",
+                "users",
+                "ssn",
+                Annotation::new(Annotation::PII, "true"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            service
+                .get_column_annotations("main", "users", "ssn")
+                .await
+                .unwrap()[0]
+                .key,
+            "pii"
+        );
+        assert_eq!(service.pii_columns().await, vec!["users.ssn"]);
+        assert!(service
+            .add_column_annotation("main", "users", "missing", Annotation::new("unit", "x"))
+            .await
+            .is_err());
+
+        service
+            .save_glossary_term(
+                GlossaryTerm::new("active user", "Logged in within 30 days")
+                    .with_tables(vec!["sessions".to_string()])
+                    .with_sql("last_seen_at > now() - interval '30 days'"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            service.get_glossary_term("Active User").await.unwrap().term,
+            "active user"
+        );
+        let context = service
+            .glossary_context("How many active users do we have?")
+            .await
+            .unwrap();
+        assert!(context.contains("active user: Logged in within 30 days"));
+        assert!(context.contains("SQL: last_seen_at"));
+        assert!(service
+            .glossary_context("top products")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_schema_records_drift() {
+        let service = MetadataService::new();
+        let mut events = service.subscribe();
+        let table = |columns: &[&str]| TableMetadata {
+            name: "users".to_string(),
+            schema_name: None,
+            columns: columns
+                .iter()
+                .map(|name| crate::models::ColumnMetadata {
+                    name: name.to_string(),
+                    data_type: "text".to_string(),
+                    nullable: true,
+                    comment: None,
+                    annotations: vec![],
+                })
+                .collect(),
+            primary_key: None,
+            annotations: vec![],
+            description: None,
+        };
+        let schema = |columns: &[&str]| Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "postgres".to_string(),
+            tables: vec![table(columns)],
+            created_at: None,
+            updated_at: None,
+        };
+
+        let first = service.sync_schema(schema(&["id", "email"])).await.unwrap();
+        assert_eq!(first.snapshot.version, 1);
+        assert!(first.diff.is_none());
+
+        let unchanged = service.sync_schema(schema(&["id", "email"])).await.unwrap();
+        assert_eq!(unchanged.snapshot.version, 1);
+        assert!(unchanged.diff.is_none());
+
+        let second = service.sync_schema(schema(&["id"])).await.unwrap();
+        let diff = second.diff.unwrap();
+        assert_eq!((diff.from_version, diff.to_version), (1, 2));
+        assert_eq!(diff.dropped_references(), vec!["users.email"]);
+
+        let names: Vec<&str> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| event.name())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "schema.synced",
+                "schema.synced",
+                "schema.drift",
+                "schema.synced"
+            ]
+        );
+
+        assert_eq!(service.list_snapshots("main").await.unwrap().len(), 2);
+        assert_eq!(
+            service.list_diffs(Some("main"), None).await.unwrap().len(),
+            1
+        );
+        assert!(service
+            .list_diffs(None, Some("9999-01-01T00:00:00Z"))
+            .await
+            .unwrap()
+            .is_empty());
+        let current = service
+            .schema_at("main", "9999-01-01T00:00:00Z")
+            .await
+            .unwrap();
+        assert_eq!(current.tables[0].columns.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_keeps_curated_annotations() {
+        let service = MetadataService::new();
+        let extracted = || Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "warehouse".to_string(),
+            tables: vec![TableMetadata {
+                name: "users".to_string(),
+                schema_name: None,
+                columns: vec![ColumnMetadata {
+                    name: "email".to_string(),
+                    data_type: "text".to_string(),
+                    nullable: true,
+                    comment: None,
+                    annotations: vec![],
+                }],
+                primary_key: None,
+                annotations: vec![],
+                description: None,
+            }],
+            created_at: None,
+            updated_at: None,
+        };
+
+        service.refresh_schema(extracted()).await.unwrap();
+        service
+            .add_annotation("main", "users", Annotation::new("owner", "growth"))
+            .await
+            .unwrap();
+        service
+            .add_column_annotation(
+                "main",
+                "users",
+                "email",
+                Annotation::new(Annotation::PII, "true"),
+            )
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            service.refresh_schema(extracted()).await.unwrap();
+        }
+
+        let table = &service.get_schema("main").await.unwrap().tables[0];
+        assert_eq!(table.annotations.len(), 1);
+        assert_eq!(table.columns[0].annotations.len(), 1);
+        assert_eq!(service.pii_columns().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_drift_triggers_workflows() {
+        struct Recorder(tokio::sync::mpsc::UnboundedSender<String>);
+
+        #[async_trait::async_trait]
+        impl workflow_engine::engine::QueryHandler for Recorder {
+            async fn execute(&self, sql: &str, _database: Option<&str>) -> Result<String, String> {
+                let _ = self.0.send(sql.to_string());
+                Ok(String::new())
+            }
+        }
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let engine = WorkflowEngine::new().with_query_handler(Arc::new(Recorder(tx)));
+        let yaml = "name: on-drift\nversion: \"1.0\"\ntrigger:\n  type: event\n  event: schema.drift\nsteps:\n  - name: audit\n    action:\n      type: query\n      sql: SELECT 1\n";
+        let definition = WorkflowDefinition::from_yaml(yaml).unwrap();
+        engine
+            .register(workflow_engine::Workflow::new(definition))
+            .await
+            .unwrap();
+
+        let service = MetadataService::new();
+        let _forwarder = service.trigger_workflows(Arc::new(engine));
+        let schema = |tables: Vec<&str>| Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "sqlite".to_string(),
+            tables: tables
+                .into_iter()
+                .map(|name| TableMetadata {
+                    name: name.to_string(),
+                    schema_name: None,
+                    columns: vec![],
+                    primary_key: None,
+                    annotations: vec![],
+                    description: None,
+                })
+                .collect(),
+            created_at: None,
+            updated_at: None,
+        };
+
+        service.sync_schema(schema(vec!["users"])).await.unwrap();
+        service
+            .sync_schema(schema(vec!["users", "orders"]))
+            .await
+            .unwrap();
+
+        let sql = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(sql.as_deref(), Some("SELECT 1"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_is_idempotent() {
+        let service = MetadataService::new();
+        let events = r#"{"eventType": "COMPLETE", "inputs": [{"namespace": "pg", "name": "public.orders", "facets": {"schema": {"fields": [{"name": "id", "type": "int"}]}}}], "outputs": [{"namespace": "pg", "name": "public.daily", "facets": {}}]}"#;
+
+        let import = import::import_openlineage(events).unwrap();
+        service.import_metadata(import).await.unwrap();
+        service
+            .add_column_annotation(
+                "public",
+                "orders",
+                "id",
+                Annotation::new(Annotation::PII, "false"),
+            )
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let import = import::import_openlineage(events).unwrap();
+            service.import_metadata(import).await.unwrap();
+        }
+
+        assert_eq!(service.list_snapshots("public").await.unwrap().len(), 1);
+        let orders = &service.get_schema("public").await.unwrap().tables[0];
+        assert_eq!(orders.columns[0].annotation(Annotation::PII), Some("false"));
+        let lineage = service.get_lineage().await.unwrap();
+        assert_eq!(lineage.nodes.len(), 2);
+        assert_eq!(lineage.relationships.len(), 1);
     }
 }
@@ -0,0 +1,1466 @@
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions, Postgres};
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{Row, Transaction};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::lineage::LineageGraph;
+use crate::models::{Annotation, ColumnMetadata, GlossaryTerm, Schema, TableMetadata};
+use crate::semantic::{Dimension, Metric, SemanticModel};
+use crate::store::{now_rfc3339, MetadataStore};
+use crate::versioning::{SchemaDiff, SchemaSnapshot, SchemaSync};
+
+/// Table names are unique within a schema, so concurrent creates conflict
+/// instead of both succeeding. Existing duplicates fail the migration, see
+/// `check_unique_table_names`, rather than being dropped.
+const UNIQUE_TABLE_NAMES: &str = r#"
+CREATE UNIQUE INDEX metadata_tables_schema_name ON metadata_tables (schema_id, name);
+"#;
+
+const SQLITE_MIGRATIONS: &[(i64, &str)] = &[
+    (
+        1,
+        r#"
+CREATE TABLE metadata_schemas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE metadata_tables (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schema_id INTEGER NOT NULL REFERENCES metadata_schemas(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    schema_name TEXT,
+    primary_key TEXT,
+    description TEXT
+);
+CREATE TABLE metadata_columns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_id INTEGER NOT NULL REFERENCES metadata_tables(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    data_type TEXT NOT NULL,
+    nullable BOOLEAN NOT NULL,
+    comment TEXT
+);
+CREATE TABLE metadata_annotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_id INTEGER NOT NULL REFERENCES metadata_tables(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT
+);
+CREATE TABLE metadata_lineage (
+    id INTEGER PRIMARY KEY,
+    graph TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        2,
+        r#"
+CREATE TABLE metadata_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schema_name TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    schema TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (schema_name, version)
+);
+CREATE TABLE metadata_diffs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schema_name TEXT NOT NULL,
+    from_version BIGINT NOT NULL,
+    to_version BIGINT NOT NULL,
+    diff TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX metadata_diffs_created_at ON metadata_diffs (created_at);
+"#,
+    ),
+    (
+        3,
+        r#"
+CREATE TABLE metadata_column_annotations (
+    id INTEGER PRIMARY KEY,
+    column_id BIGINT NOT NULL REFERENCES metadata_columns(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT
+);
+CREATE TABLE metadata_glossary (
+    id INTEGER PRIMARY KEY,
+    term_key TEXT NOT NULL UNIQUE,
+    term TEXT NOT NULL,
+    body TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        4,
+        r#"
+CREATE TABLE metadata_semantic_model (
+    id BIGINT PRIMARY KEY,
+    model TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (5, UNIQUE_TABLE_NAMES),
+];
+
+const POSTGRES_MIGRATIONS: &[(i64, &str)] = &[
+    (
+        1,
+        r#"
+CREATE TABLE metadata_schemas (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE metadata_tables (
+    id BIGSERIAL PRIMARY KEY,
+    schema_id BIGINT NOT NULL REFERENCES metadata_schemas(id) ON DELETE CASCADE,
+    position BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    schema_name TEXT,
+    primary_key TEXT,
+    description TEXT
+);
+CREATE TABLE metadata_columns (
+    id BIGSERIAL PRIMARY KEY,
+    table_id BIGINT NOT NULL REFERENCES metadata_tables(id) ON DELETE CASCADE,
+    position BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    data_type TEXT NOT NULL,
+    nullable BOOLEAN NOT NULL,
+    comment TEXT
+);
+CREATE TABLE metadata_annotations (
+    id BIGSERIAL PRIMARY KEY,
+    table_id BIGINT NOT NULL REFERENCES metadata_tables(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT
+);
+CREATE TABLE metadata_lineage (
+    id BIGINT PRIMARY KEY,
+    graph TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        2,
+        r#"
+CREATE TABLE metadata_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    schema_name TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    schema TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (schema_name, version)
+);
+CREATE TABLE metadata_diffs (
+    id BIGSERIAL PRIMARY KEY,
+    schema_name TEXT NOT NULL,
+    from_version BIGINT NOT NULL,
+    to_version BIGINT NOT NULL,
+    diff TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX metadata_diffs_created_at ON metadata_diffs (created_at);
+"#,
+    ),
+    (
+        3,
+        r#"
+CREATE TABLE metadata_column_annotations (
+    id BIGSERIAL PRIMARY KEY,
+    column_id BIGINT NOT NULL REFERENCES metadata_columns(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT
+);
+CREATE TABLE metadata_glossary (
+    id BIGSERIAL PRIMARY KEY,
+    term_key TEXT NOT NULL UNIQUE,
+    term TEXT NOT NULL,
+    body TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        4,
+        r#"
+CREATE TABLE metadata_semantic_model (
+    id BIGINT PRIMARY KEY,
+    model TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        5,
+        r#"
+ALTER TABLE metadata_schemas
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at::timestamptz,
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at::timestamptz;
+ALTER TABLE metadata_lineage
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at::timestamptz;
+ALTER TABLE metadata_snapshots
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at::timestamptz;
+ALTER TABLE metadata_diffs
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ USING created_at::timestamptz;
+ALTER TABLE metadata_glossary
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at::timestamptz;
+ALTER TABLE metadata_semantic_model
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ USING updated_at::timestamptz;
+"#,
+    ),
+    (6, UNIQUE_TABLE_NAMES),
+];
+
+/// SQLite has no timestamp type, so timestamps stay RFC 3339 text there.
+fn sqlite_timestamp(placeholder: &str) -> String {
+    placeholder.to_string()
+}
+
+fn sqlite_timestamp_text(column: &str) -> String {
+    column.to_string()
+}
+
+/// Postgres stores `TIMESTAMPTZ`; parameters and results are converted from
+/// and to the RFC 3339 strings used by the models.
+fn postgres_timestamp(placeholder: &str) -> String {
+    format!("CAST({} AS TIMESTAMPTZ)", placeholder)
+}
+
+fn postgres_timestamp_text(column: &str) -> String {
+    format!(
+        "to_char({} AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"')",
+        column
+    )
+}
+
+/// Unique violations become `Conflict`; callers name what already exists.
+fn db_error(e: sqlx::Error) -> Error {
+    match e.as_database_error() {
+        Some(db) if db.is_unique_violation() => Error::Conflict(db.message().to_string()),
+        _ => Error::Database(e.to_string()),
+    }
+}
+
+macro_rules! sql_metadata_store {
+    ($store:ident, $db:ty, $pool:ty, $migrations:expr, $ts:path, $ts_text:path) => {
+        pub struct $store {
+            pool: $pool,
+        }
+
+        impl $store {
+            pub async fn from_pool(pool: $pool) -> Result<Self, Error> {
+                let store = Self { pool };
+                store.migrate().await?;
+                Ok(store)
+            }
+
+            pub async fn migrate(&self) -> Result<usize, Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS metadata_migrations (version BIGINT PRIMARY KEY, applied_at TEXT NOT NULL)",
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+
+                let current: Option<i64> =
+                    sqlx::query_scalar("SELECT MAX(version) FROM metadata_migrations")
+                        .fetch_one(&mut *tx)
+                        .await
+                        .map_err(db_error)?;
+
+                let mut applied = 0;
+                for (version, sql) in $migrations {
+                    if *version <= current.unwrap_or(0) {
+                        continue;
+                    }
+                    if *sql == UNIQUE_TABLE_NAMES {
+                        Self::check_unique_table_names(&mut tx).await?;
+                    }
+                    sqlx::raw_sql(sql)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db_error)?;
+                    sqlx::query(
+                        "INSERT INTO metadata_migrations (version, applied_at) VALUES ($1, $2)",
+                    )
+                    .bind(*version)
+                    .bind(now_rfc3339())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                    applied += 1;
+                }
+
+                tx.commit().await.map_err(db_error)?;
+                Ok(applied)
+            }
+
+            /// Fails listing every `schema.table` stored more than once, which
+            /// must be merged or renamed by hand before table names can be
+            /// made unique.
+            async fn check_unique_table_names(tx: &mut Transaction<'_, $db>) -> Result<(), Error> {
+                let duplicates: Vec<(String, String, i64)> = sqlx::query_as(
+                    "SELECT s.name, t.name, COUNT(*) FROM metadata_tables t JOIN metadata_schemas s ON s.id = t.schema_id GROUP BY s.name, t.name HAVING COUNT(*) > 1 ORDER BY s.name, t.name",
+                )
+                .fetch_all(&mut **tx)
+                .await
+                .map_err(db_error)?;
+                if duplicates.is_empty() {
+                    return Ok(());
+                }
+                let duplicates: Vec<String> = duplicates
+                    .iter()
+                    .map(|(schema, table, count)| format!("{}.{} ({} rows)", schema, table, count))
+                    .collect();
+                Err(Error::Database(format!(
+                    "Cannot make table names unique, remove or rename the duplicate tables first: {}",
+                    duplicates.join(", ")
+                )))
+            }
+
+            async fn schema_id(
+                tx: &mut Transaction<'_, $db>,
+                name: &str,
+            ) -> Result<Option<(i64, String)>, Error> {
+                let sql = format!(
+                    "SELECT id, {} AS created_at FROM metadata_schemas WHERE name = $1",
+                    $ts_text("created_at")
+                );
+                let row = sqlx::query(&sql)
+                    .bind(name)
+                    .fetch_optional(&mut **tx)
+                    .await
+                    .map_err(db_error)?;
+                Ok(row.map(|row| (row.get::<i64, _>("id"), row.get::<String, _>("created_at"))))
+            }
+
+            async fn insert_table(
+                tx: &mut Transaction<'_, $db>,
+                schema_id: i64,
+                position: i64,
+                table: &TableMetadata,
+            ) -> Result<(), Error> {
+                let primary_key = table
+                    .primary_key
+                    .as_ref()
+                    .map(|pk| serde_json::to_string(pk).unwrap_or_default());
+                let table_id: i64 = sqlx::query_scalar(
+                    "INSERT INTO metadata_tables (schema_id, position, name, schema_name, primary_key, description) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+                )
+                .bind(schema_id)
+                .bind(position)
+                .bind(&table.name)
+                .bind(&table.schema_name)
+                .bind(primary_key)
+                .bind(&table.description)
+                .fetch_one(&mut **tx)
+                .await
+                .map_err(db_error)?;
+
+                for (i, column) in table.columns.iter().enumerate() {
+                    let column_id: i64 = sqlx::query_scalar(
+                        "INSERT INTO metadata_columns (table_id, position, name, data_type, nullable, comment) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+                    )
+                    .bind(table_id)
+                    .bind(i as i64)
+                    .bind(&column.name)
+                    .bind(&column.data_type)
+                    .bind(column.nullable)
+                    .bind(&column.comment)
+                    .fetch_one(&mut **tx)
+                    .await
+                    .map_err(db_error)?;
+
+                    for annotation in &column.annotations {
+                        Self::insert_column_annotation(tx, column_id, annotation).await?;
+                    }
+                }
+
+                for annotation in &table.annotations {
+                    Self::insert_annotation(tx, table_id, annotation).await?;
+                }
+                Ok(())
+            }
+
+            async fn insert_annotation(
+                tx: &mut Transaction<'_, $db>,
+                table_id: i64,
+                annotation: &Annotation,
+            ) -> Result<(), Error> {
+                sqlx::query(
+                    "INSERT INTO metadata_annotations (table_id, key, value, source) VALUES ($1, $2, $3, $4)",
+                )
+                .bind(table_id)
+                .bind(&annotation.key)
+                .bind(&annotation.value)
+                .bind(&annotation.source)
+                .execute(&mut **tx)
+                .await
+                .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn insert_column_annotation(
+                tx: &mut Transaction<'_, $db>,
+                column_id: i64,
+                annotation: &Annotation,
+            ) -> Result<(), Error> {
+                sqlx::query(
+                    "INSERT INTO metadata_column_annotations (column_id, key, value, source) VALUES ($1, $2, $3, $4)",
+                )
+                .bind(column_id)
+                .bind(&annotation.key)
+                .bind(&annotation.value)
+                .bind(&annotation.source)
+                .execute(&mut **tx)
+                .await
+                .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn delete_tables(tx: &mut Transaction<'_, $db>, schema_id: i64) -> Result<(), Error> {
+                for sql in [
+                    "DELETE FROM metadata_column_annotations WHERE column_id IN (SELECT c.id FROM metadata_columns c JOIN metadata_tables t ON c.table_id = t.id WHERE t.schema_id = $1)",
+                    "DELETE FROM metadata_annotations WHERE table_id IN (SELECT id FROM metadata_tables WHERE schema_id = $1)",
+                    "DELETE FROM metadata_columns WHERE table_id IN (SELECT id FROM metadata_tables WHERE schema_id = $1)",
+                    "DELETE FROM metadata_tables WHERE schema_id = $1",
+                ] {
+                    sqlx::query(sql)
+                        .bind(schema_id)
+                        .execute(&mut **tx)
+                        .await
+                        .map_err(db_error)?;
+                }
+                Ok(())
+            }
+
+            async fn touch(tx: &mut Transaction<'_, $db>, schema_id: i64) -> Result<(), Error> {
+                let sql = format!(
+                    "UPDATE metadata_schemas SET updated_at = {} WHERE id = $2",
+                    $ts("$1")
+                );
+                sqlx::query(&sql)
+                    .bind(now_rfc3339())
+                    .bind(schema_id)
+                    .execute(&mut **tx)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn write_schema(
+                tx: &mut Transaction<'_, $db>,
+                schema: Schema,
+            ) -> Result<Schema, Error> {
+                let now = now_rfc3339();
+
+                let (id, created_at) = match Self::schema_id(tx, &schema.name).await? {
+                    Some((id, created_at)) => {
+                        let sql = format!(
+                            "UPDATE metadata_schemas SET source = $1, updated_at = {} WHERE id = $3",
+                            $ts("$2")
+                        );
+                        sqlx::query(&sql)
+                            .bind(&schema.source)
+                            .bind(&now)
+                            .bind(id)
+                            .execute(&mut **tx)
+                            .await
+                            .map_err(db_error)?;
+                        Self::delete_tables(tx, id).await?;
+                        (id, created_at)
+                    }
+                    None => {
+                        let sql = format!(
+                            "INSERT INTO metadata_schemas (name, source, created_at, updated_at) VALUES ($1, $2, {0}, {0}) RETURNING id",
+                            $ts("$3")
+                        );
+                        let id: i64 = sqlx::query_scalar(&sql)
+                            .bind(&schema.name)
+                            .bind(&schema.source)
+                            .bind(&now)
+                            .fetch_one(&mut **tx)
+                            .await
+                            .map_err(db_error)?;
+                        (id, now.clone())
+                    }
+                };
+
+                for (position, table) in schema.tables.iter().enumerate() {
+                    Self::insert_table(tx, id, position as i64, table).await?;
+                }
+
+                Ok(Schema {
+                    id: Some(id),
+                    created_at: Some(created_at),
+                    updated_at: Some(now),
+                    ..schema
+                })
+            }
+
+            async fn insert_snapshot(
+                tx: &mut Transaction<'_, $db>,
+                snapshot: &SchemaSnapshot,
+            ) -> Result<(), Error> {
+                let schema = serde_json::to_string(&snapshot.schema)
+                    .map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_snapshots (schema_name, version, schema, created_at) VALUES ($1, $2, $3, {})",
+                    $ts("$4")
+                );
+                sqlx::query(&sql)
+                    .bind(&snapshot.schema_name)
+                    .bind(snapshot.version)
+                    .bind(schema)
+                    .bind(&snapshot.created_at)
+                    .execute(&mut **tx)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn insert_diff(tx: &mut Transaction<'_, $db>, diff: &SchemaDiff) -> Result<(), Error> {
+                let json =
+                    serde_json::to_string(diff).map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_diffs (schema_name, from_version, to_version, diff, created_at) VALUES ($1, $2, $3, $4, {})",
+                    $ts("$5")
+                );
+                sqlx::query(&sql)
+                    .bind(&diff.schema_name)
+                    .bind(diff.from_version)
+                    .bind(diff.to_version)
+                    .bind(json)
+                    .bind(&diff.created_at)
+                    .execute(&mut **tx)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            /// Applies `update` to the stored semantic model, locking the row
+            /// like `merge_lineage` so concurrent saves don't lose each other.
+            async fn update_semantic_model(
+                &self,
+                update: impl FnOnce(&mut SemanticModel) + Send,
+            ) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let empty = serde_json::to_string(&SemanticModel::default())
+                    .map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_semantic_model (id, model, updated_at) VALUES (1, $1, {}) ON CONFLICT (id) DO UPDATE SET updated_at = excluded.updated_at",
+                    $ts("$2")
+                );
+                sqlx::query(&sql)
+                    .bind(empty)
+                    .bind(now_rfc3339())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                let current: String =
+                    sqlx::query_scalar("SELECT model FROM metadata_semantic_model WHERE id = 1")
+                        .fetch_one(&mut *tx)
+                        .await
+                        .map_err(db_error)?;
+
+                let mut model: SemanticModel =
+                    serde_json::from_str(&current).map_err(|e| Error::Metadata(e.to_string()))?;
+                update(&mut model);
+                let updated =
+                    serde_json::to_string(&model).map_err(|e| Error::Metadata(e.to_string()))?;
+                sqlx::query("UPDATE metadata_semantic_model SET model = $1 WHERE id = 1")
+                    .bind(updated)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            fn snapshot_from_row(
+                schema_name: &str,
+                row: <$db as sqlx::Database>::Row,
+            ) -> Result<SchemaSnapshot, Error> {
+                let schema: String = row.get("schema");
+                Ok(SchemaSnapshot {
+                    schema_name: schema_name.to_string(),
+                    version: row.get("version"),
+                    schema: serde_json::from_str(&schema)
+                        .map_err(|e| Error::Metadata(e.to_string()))?,
+                    created_at: row.get("created_at"),
+                })
+            }
+
+            async fn load_schema(&self, row: <$db as sqlx::Database>::Row) -> Result<Schema, Error> {
+                let id: i64 = row.get("id");
+                let table_rows = sqlx::query(
+                    "SELECT id, name, schema_name, primary_key, description FROM metadata_tables WHERE schema_id = $1 ORDER BY position",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error)?;
+
+                let annotation = |row: &<$db as sqlx::Database>::Row| Annotation {
+                    key: row.get("key"),
+                    value: row.get("value"),
+                    source: row.get("source"),
+                };
+
+                let mut column_annotations: HashMap<i64, Vec<Annotation>> = HashMap::new();
+                for row in sqlx::query(
+                    "SELECT a.column_id, a.key, a.value, a.source FROM metadata_column_annotations a JOIN metadata_columns c ON a.column_id = c.id JOIN metadata_tables t ON c.table_id = t.id WHERE t.schema_id = $1 ORDER BY a.id",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error)?
+                {
+                    column_annotations
+                        .entry(row.get("column_id"))
+                        .or_default()
+                        .push(annotation(&row));
+                }
+
+                let mut columns: HashMap<i64, Vec<ColumnMetadata>> = HashMap::new();
+                for row in sqlx::query(
+                    "SELECT c.id, c.table_id, c.name, c.data_type, c.nullable, c.comment FROM metadata_columns c JOIN metadata_tables t ON c.table_id = t.id WHERE t.schema_id = $1 ORDER BY c.table_id, c.position",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error)?
+                {
+                    columns
+                        .entry(row.get("table_id"))
+                        .or_default()
+                        .push(ColumnMetadata {
+                            name: row.get("name"),
+                            data_type: row.get("data_type"),
+                            nullable: row.get("nullable"),
+                            comment: row.get("comment"),
+                            annotations: column_annotations
+                                .remove(&row.get::<i64, _>("id"))
+                                .unwrap_or_default(),
+                        });
+                }
+
+                let mut annotations: HashMap<i64, Vec<Annotation>> = HashMap::new();
+                for row in sqlx::query(
+                    "SELECT a.table_id, a.key, a.value, a.source FROM metadata_annotations a JOIN metadata_tables t ON a.table_id = t.id WHERE t.schema_id = $1 ORDER BY a.id",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db_error)?
+                {
+                    annotations
+                        .entry(row.get("table_id"))
+                        .or_default()
+                        .push(annotation(&row));
+                }
+
+                let tables = table_rows
+                    .into_iter()
+                    .map(|table_row| {
+                        let table_id: i64 = table_row.get("id");
+                        let primary_key: Option<String> = table_row.get("primary_key");
+                        TableMetadata {
+                            name: table_row.get("name"),
+                            schema_name: table_row.get("schema_name"),
+                            columns: columns.remove(&table_id).unwrap_or_default(),
+                            primary_key: primary_key.and_then(|pk| serde_json::from_str(&pk).ok()),
+                            annotations: annotations.remove(&table_id).unwrap_or_default(),
+                            description: table_row.get("description"),
+                        }
+                    })
+                    .collect();
+
+                Ok(Schema {
+                    id: Some(id),
+                    name: row.get("name"),
+                    source: row.get("source"),
+                    tables,
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                })
+            }
+        }
+
+        #[async_trait]
+        impl MetadataStore for $store {
+            async fn save_schema(&self, schema: Schema) -> Result<Schema, Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let saved = Self::write_schema(&mut tx, schema).await?;
+                tx.commit().await.map_err(db_error)?;
+                Ok(saved)
+            }
+
+            async fn create_schema(&self, schema: Schema) -> Result<Schema, Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                if Self::schema_id(&mut tx, &schema.name).await?.is_some() {
+                    return Err(Error::Conflict(format!(
+                        "Schema '{}' already exists",
+                        schema.name
+                    )));
+                }
+                let created = Self::write_schema(&mut tx, schema).await?;
+                tx.commit().await.map_err(db_error)?;
+                Ok(created)
+            }
+
+            async fn save_schema_version(
+                &self,
+                schema: Schema,
+                created_at: &str,
+            ) -> Result<SchemaSync, Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                // Writing the schema row first locks it, so concurrent syncs
+                // read the latest snapshot one after another.
+                let saved = Self::write_schema(&mut tx, schema).await?;
+                let sql = format!(
+                    "SELECT version, schema, {} AS created_at FROM metadata_snapshots WHERE schema_name = $1 ORDER BY version DESC LIMIT 1",
+                    $ts_text("created_at")
+                );
+                let previous = sqlx::query(&sql)
+                    .bind(&saved.name)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(db_error)?
+                    .map(|row| Self::snapshot_from_row(&saved.name, row))
+                    .transpose()?;
+
+                let sync = match previous {
+                    Some(previous) if previous.matches(&saved) => SchemaSync {
+                        snapshot: previous,
+                        diff: None,
+                    },
+                    previous => {
+                        let sync = SchemaSync::next(previous.as_ref(), saved, created_at);
+                        Self::insert_snapshot(&mut tx, &sync.snapshot).await?;
+                        if let Some(diff) = &sync.diff {
+                            Self::insert_diff(&mut tx, diff).await?;
+                        }
+                        sync
+                    }
+                };
+                tx.commit().await.map_err(db_error)?;
+                Ok(sync)
+            }
+
+            async fn get_schema(&self, name: &str) -> Result<Schema, Error> {
+                let sql = format!(
+                    "SELECT id, name, source, {} AS created_at, {} AS updated_at FROM metadata_schemas WHERE name = $1",
+                    $ts_text("created_at"),
+                    $ts_text("updated_at")
+                );
+                let row = sqlx::query(&sql)
+                    .bind(name)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db_error)?
+                    .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))?;
+                self.load_schema(row).await
+            }
+
+            async fn list_schemas(&self) -> Result<Vec<Schema>, Error> {
+                let sql = format!(
+                    "SELECT id, name, source, {} AS created_at, {} AS updated_at FROM metadata_schemas ORDER BY id",
+                    $ts_text("created_at"),
+                    $ts_text("updated_at")
+                );
+                let rows = sqlx::query(&sql)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+
+                let mut schemas = Vec::new();
+                for row in rows {
+                    schemas.push(self.load_schema(row).await?);
+                }
+                Ok(schemas)
+            }
+
+            async fn list_schemas_page(
+                &self,
+                offset: usize,
+                limit: usize,
+            ) -> Result<(Vec<Schema>, usize), Error> {
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM metadata_schemas")
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                let sql = format!(
+                    "SELECT id, name, source, {} AS created_at, {} AS updated_at FROM metadata_schemas ORDER BY id LIMIT $1 OFFSET $2",
+                    $ts_text("created_at"),
+                    $ts_text("updated_at")
+                );
+                let rows = sqlx::query(&sql)
+                    .bind(limit as i64)
+                    .bind(offset as i64)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+
+                let mut schemas = Vec::new();
+                for row in rows {
+                    schemas.push(self.load_schema(row).await?);
+                }
+                Ok((schemas, total as usize))
+            }
+
+            async fn delete_schema(&self, name: &str) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let (id, _) = Self::schema_id(&mut tx, name)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))?;
+                Self::delete_tables(&mut tx, id).await?;
+                sqlx::query("DELETE FROM metadata_schemas WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn add_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let (id, _) = Self::schema_id(&mut tx, schema_name)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+                let position: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM metadata_tables WHERE schema_id = $1",
+                )
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(db_error)?;
+
+                Self::insert_table(&mut tx, id, position, &table).await?;
+                Self::touch(&mut tx, id).await?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn add_annotation(
+                &self,
+                schema_name: &str,
+                table_name: &str,
+                annotation: Annotation,
+            ) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let (id, _) = Self::schema_id(&mut tx, schema_name)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+                let table_id: i64 = sqlx::query_scalar(
+                    "SELECT id FROM metadata_tables WHERE schema_id = $1 AND name = $2 ORDER BY position LIMIT 1",
+                )
+                .bind(id)
+                .bind(table_name)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_error)?
+                .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))?;
+
+                Self::insert_annotation(&mut tx, table_id, &annotation).await?;
+                Self::touch(&mut tx, id).await?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn add_column_annotation(
+                &self,
+                schema_name: &str,
+                table_name: &str,
+                column_name: &str,
+                annotation: Annotation,
+            ) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                let (id, _) = Self::schema_id(&mut tx, schema_name)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+                let table_id: i64 = sqlx::query_scalar(
+                    "SELECT id FROM metadata_tables WHERE schema_id = $1 AND name = $2 ORDER BY position LIMIT 1",
+                )
+                .bind(id)
+                .bind(table_name)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_error)?
+                .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))?;
+                let column_id: i64 = sqlx::query_scalar(
+                    "SELECT id FROM metadata_columns WHERE table_id = $1 AND name = $2 ORDER BY position LIMIT 1",
+                )
+                .bind(table_id)
+                .bind(column_name)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_error)?
+                .ok_or_else(|| Error::NotFound(format!("Column '{}' not found", column_name)))?;
+
+                Self::insert_column_annotation(&mut tx, column_id, &annotation).await?;
+                Self::touch(&mut tx, id).await?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn save_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+                let body =
+                    serde_json::to_string(&term).map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_glossary (term_key, term, body, updated_at) VALUES ($1, $2, $3, {}) ON CONFLICT (term_key) DO UPDATE SET term = excluded.term, body = excluded.body, updated_at = excluded.updated_at",
+                    $ts("$4")
+                );
+                sqlx::query(&sql)
+                    .bind(term.term.to_lowercase())
+                    .bind(&term.term)
+                    .bind(body)
+                    .bind(now_rfc3339())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn create_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+                let body =
+                    serde_json::to_string(&term).map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_glossary (term_key, term, body, updated_at) VALUES ($1, $2, $3, {})",
+                    $ts("$4")
+                );
+                sqlx::query(&sql)
+                    .bind(term.term.to_lowercase())
+                    .bind(&term.term)
+                    .bind(body)
+                    .bind(now_rfc3339())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn list_glossary_terms(&self) -> Result<Vec<GlossaryTerm>, Error> {
+                let bodies: Vec<String> =
+                    sqlx::query_scalar("SELECT body FROM metadata_glossary ORDER BY term_key")
+                        .fetch_all(&self.pool)
+                        .await
+                        .map_err(db_error)?;
+                bodies
+                    .iter()
+                    .map(|body| serde_json::from_str(body).map_err(|e| Error::Metadata(e.to_string())))
+                    .collect()
+            }
+
+            async fn delete_glossary_term(&self, term: &str) -> Result<(), Error> {
+                let result = sqlx::query("DELETE FROM metadata_glossary WHERE term_key = $1")
+                    .bind(term.to_lowercase())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                if result.rows_affected() == 0 {
+                    return Err(Error::NotFound(format!("Glossary term '{}' not found", term)));
+                }
+                Ok(())
+            }
+
+            async fn set_lineage(&self, graph: LineageGraph) -> Result<(), Error> {
+                let graph =
+                    serde_json::to_string(&graph).map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_lineage (id, graph, updated_at) VALUES (1, $1, {}) ON CONFLICT (id) DO UPDATE SET graph = excluded.graph, updated_at = excluded.updated_at",
+                    $ts("$2")
+                );
+                sqlx::query(&sql)
+                    .bind(graph)
+                    .bind(now_rfc3339())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn get_lineage(&self) -> Result<Option<LineageGraph>, Error> {
+                let graph: Option<String> =
+                    sqlx::query_scalar("SELECT graph FROM metadata_lineage WHERE id = 1")
+                        .fetch_optional(&self.pool)
+                        .await
+                        .map_err(db_error)?;
+                graph
+                    .map(|graph| {
+                        serde_json::from_str(&graph).map_err(|e| Error::Metadata(e.to_string()))
+                    })
+                    .transpose()
+            }
+
+            async fn merge_lineage(&self, graph: LineageGraph) -> Result<LineageGraph, Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                // Writing the row first locks it, so concurrent merges queue up
+                // instead of overwriting each other.
+                let empty = serde_json::to_string(&LineageGraph::default())
+                    .map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_lineage (id, graph, updated_at) VALUES (1, $1, {}) ON CONFLICT (id) DO UPDATE SET updated_at = excluded.updated_at",
+                    $ts("$2")
+                );
+                sqlx::query(&sql)
+                    .bind(empty)
+                    .bind(now_rfc3339())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                let current: String =
+                    sqlx::query_scalar("SELECT graph FROM metadata_lineage WHERE id = 1")
+                        .fetch_one(&mut *tx)
+                        .await
+                        .map_err(db_error)?;
+
+                let mut lineage: LineageGraph =
+                    serde_json::from_str(&current).map_err(|e| Error::Metadata(e.to_string()))?;
+                lineage.merge(graph);
+                lineage.validate()?;
+                let merged =
+                    serde_json::to_string(&lineage).map_err(|e| Error::Metadata(e.to_string()))?;
+                sqlx::query("UPDATE metadata_lineage SET graph = $1 WHERE id = 1")
+                    .bind(merged)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(db_error)?;
+                tx.commit().await.map_err(db_error)?;
+                Ok(lineage)
+            }
+
+            async fn set_semantic_model(&self, model: SemanticModel) -> Result<(), Error> {
+                let model =
+                    serde_json::to_string(&model).map_err(|e| Error::Metadata(e.to_string()))?;
+                let sql = format!(
+                    "INSERT INTO metadata_semantic_model (id, model, updated_at) VALUES (1, $1, {}) ON CONFLICT (id) DO UPDATE SET model = excluded.model, updated_at = excluded.updated_at",
+                    $ts("$2")
+                );
+                sqlx::query(&sql)
+                    .bind(model)
+                    .bind(now_rfc3339())
+                    .execute(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+                Ok(())
+            }
+
+            async fn get_semantic_model(&self) -> Result<Option<SemanticModel>, Error> {
+                let model: Option<String> =
+                    sqlx::query_scalar("SELECT model FROM metadata_semantic_model WHERE id = 1")
+                        .fetch_optional(&self.pool)
+                        .await
+                        .map_err(db_error)?;
+                model
+                    .map(|model| {
+                        serde_json::from_str(&model).map_err(|e| Error::Metadata(e.to_string()))
+                    })
+                    .transpose()
+            }
+
+            async fn upsert_metric(&self, metric: Metric) -> Result<(), Error> {
+                self.update_semantic_model(|model| model.upsert_metric(metric))
+                    .await
+            }
+
+            async fn upsert_dimension(&self, dimension: Dimension) -> Result<(), Error> {
+                self.update_semantic_model(|model| model.upsert_dimension(dimension))
+                    .await
+            }
+
+            async fn save_snapshot(&self, snapshot: &SchemaSnapshot) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                Self::insert_snapshot(&mut tx, snapshot).await?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn list_snapshots(&self, schema_name: &str) -> Result<Vec<SchemaSnapshot>, Error> {
+                let sql = format!(
+                    "SELECT version, schema, {} AS created_at FROM metadata_snapshots WHERE schema_name = $1 ORDER BY version",
+                    $ts_text("created_at")
+                );
+                sqlx::query(&sql)
+                    .bind(schema_name)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error)?
+                    .into_iter()
+                    .map(|row| Self::snapshot_from_row(schema_name, row))
+                    .collect()
+            }
+
+            async fn latest_snapshot(&self, schema_name: &str) -> Result<Option<SchemaSnapshot>, Error> {
+                let sql = format!(
+                    "SELECT version, schema, {} AS created_at FROM metadata_snapshots WHERE schema_name = $1 ORDER BY version DESC LIMIT 1",
+                    $ts_text("created_at")
+                );
+                sqlx::query(&sql)
+                    .bind(schema_name)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db_error)?
+                    .map(|row| Self::snapshot_from_row(schema_name, row))
+                    .transpose()
+            }
+
+            async fn save_diff(&self, diff: &SchemaDiff) -> Result<(), Error> {
+                let mut tx = self.pool.begin().await.map_err(db_error)?;
+                Self::insert_diff(&mut tx, diff).await?;
+                tx.commit().await.map_err(db_error)
+            }
+
+            async fn list_diffs(&self, since: Option<&str>) -> Result<Vec<SchemaDiff>, Error> {
+                let sql = format!(
+                    "SELECT diff FROM metadata_diffs WHERE created_at >= {} ORDER BY id",
+                    $ts("$1")
+                );
+                let diffs: Vec<String> = sqlx::query_scalar(&sql)
+                    .bind(since.unwrap_or("1970-01-01T00:00:00Z"))
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(db_error)?;
+
+                diffs
+                    .iter()
+                    .map(|diff| serde_json::from_str(diff).map_err(|e| Error::Metadata(e.to_string())))
+                    .collect()
+            }
+        }
+    };
+}
+
+sql_metadata_store!(
+    SqliteMetadataStore,
+    Sqlite,
+    SqlitePool,
+    SQLITE_MIGRATIONS,
+    sqlite_timestamp,
+    sqlite_timestamp_text
+);
+sql_metadata_store!(
+    PostgresMetadataStore,
+    Postgres,
+    PgPool,
+    POSTGRES_MIGRATIONS,
+    postgres_timestamp,
+    postgres_timestamp_text
+);
+
+impl SqliteMetadataStore {
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(db_error)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let max_connections = if url.contains(":memory:") { 1 } else { 5 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .map_err(db_error)?;
+        Self::from_pool(pool).await
+    }
+}
+
+impl PostgresMetadataStore {
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(url)
+            .await
+            .map_err(db_error)?;
+        Self::from_pool(pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_schema() -> Schema {
+        Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "sqlite".to_string(),
+            tables: vec![TableMetadata {
+                name: "users".to_string(),
+                schema_name: None,
+                columns: vec![ColumnMetadata {
+                    name: "id".to_string(),
+                    data_type: "INTEGER".to_string(),
+                    nullable: false,
+                    comment: Some("Primary key".to_string()),
+                    annotations: vec![Annotation::new(Annotation::DEFINITION, "Surrogate key")],
+                }],
+                primary_key: Some(vec!["id".to_string()]),
+                annotations: vec![],
+                description: Some("Registered users".to_string()),
+            }],
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    async fn check_round_trip(store: &dyn MetadataStore) {
+        let saved = store.save_schema(sample_schema()).await.unwrap();
+        assert!(saved.id.unwrap() > 0);
+        assert!(saved.created_at.is_some());
+
+        store
+            .add_annotation(
+                "main",
+                "users",
+                Annotation {
+                    key: "owner".to_string(),
+                    value: "growth".to_string(),
+                    source: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let loaded = store.get_schema("main").await.unwrap();
+        assert_eq!(loaded.id, saved.id);
+        assert_eq!(
+            loaded.tables[0].columns[0].comment.as_deref(),
+            Some("Primary key")
+        );
+        assert_eq!(loaded.tables[0].primary_key, Some(vec!["id".to_string()]));
+        assert_eq!(loaded.tables[0].annotations[0].value, "growth");
+
+        store
+            .add_column_annotation(
+                "main",
+                "users",
+                "id",
+                Annotation::new(Annotation::PII, "true"),
+            )
+            .await
+            .unwrap();
+        let columns = &store.get_schema("main").await.unwrap().tables[0].columns;
+        assert_eq!(
+            columns[0].annotation(Annotation::DEFINITION),
+            Some("Surrogate key")
+        );
+        assert!(columns[0].is_pii());
+
+        store
+            .save_glossary_term(GlossaryTerm::new("ARR", "Annual recurring revenue"))
+            .await
+            .unwrap();
+        store
+            .save_glossary_term(
+                GlossaryTerm::new("arr", "Annualized revenue").with_sql("SUM(mrr) * 12"),
+            )
+            .await
+            .unwrap();
+        let terms = store.list_glossary_terms().await.unwrap();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].sql.as_deref(), Some("SUM(mrr) * 12"));
+        store.delete_glossary_term("Arr").await.unwrap();
+        assert!(store.delete_glossary_term("arr").await.is_err());
+
+        let resaved = store.save_schema(sample_schema()).await.unwrap();
+        assert_eq!(resaved.id, saved.id);
+        assert_eq!(store.get_schema("main").await.unwrap().tables.len(), 1);
+
+        assert!(store.latest_snapshot("main").await.unwrap().is_none());
+        for _ in 1..=2 {
+            store
+                .save_schema_version(sample_schema(), "2024-01-01T00:00:00Z")
+                .await
+                .unwrap();
+        }
+        let latest = store.latest_snapshot("main").await.unwrap().unwrap();
+        assert_eq!(latest.version, 1);
+        let mut changed = sample_schema();
+        changed.tables.clear();
+        let sync = store
+            .save_schema_version(changed, "2024-01-02T00:00:00Z")
+            .await
+            .unwrap();
+        assert_eq!(sync.snapshot.version, 2);
+        assert_eq!(sync.diff.unwrap().removed_tables.len(), 1);
+        let latest = store.latest_snapshot("main").await.unwrap().unwrap();
+        assert_eq!(latest.version, 2);
+        assert_eq!(latest.schema.id, saved.id);
+        assert_eq!(latest.created_at, "2024-01-02T00:00:00Z");
+
+        store.delete_schema("main").await.unwrap();
+        assert!(matches!(
+            store.get_schema("main").await,
+            Err(Error::NotFound(_))
+        ));
+
+        let graph = |table: &str| {
+            let mut graph = LineageGraph::default();
+            graph.add_node(crate::lineage::LineageNode {
+                id: table.to_string(),
+                name: table.to_string(),
+                node_type: crate::lineage::NodeType::Table,
+                metadata: HashMap::new(),
+            });
+            graph
+        };
+        let (first, second) = tokio::join!(
+            store.merge_lineage(graph("orders")),
+            store.merge_lineage(graph("customers"))
+        );
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(store.get_lineage().await.unwrap().unwrap().nodes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_round_trip() {
+        let store = SqliteMetadataStore::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        assert_eq!(store.migrate().await.unwrap(), 0);
+        check_round_trip(&store).await;
+
+        // Duplicates from before table names were unique stop the migration
+        // instead of being deleted.
+        sqlx::raw_sql(
+            "DROP INDEX metadata_tables_schema_name; DELETE FROM metadata_migrations WHERE version = 5",
+        )
+        .execute(&store.pool)
+        .await
+        .unwrap();
+        let saved = store.save_schema(sample_schema()).await.unwrap();
+        sqlx::query(
+            "INSERT INTO metadata_tables (schema_id, position, name) VALUES ($1, 1, 'users')",
+        )
+        .bind(saved.id)
+        .execute(&store.pool)
+        .await
+        .unwrap();
+        let error = store.migrate().await.unwrap_err();
+        assert!(error.to_string().contains("main.users (2 rows)"));
+        let (schemas, total) = store.list_schemas_page(0, 10).await.unwrap();
+        assert_eq!((schemas.len(), total), (1, 1));
+        assert!(store.list_schemas_page(1, 10).await.unwrap().0.is_empty());
+    }
+
+    /// Runs against the database in `QUERYSMITH_TEST_POSTGRES_URL`, inside a
+    /// throwaway schema; skipped when the variable is unset.
+    #[tokio::test]
+    async fn test_postgres_store_round_trip() {
+        let Ok(url) = std::env::var("QUERYSMITH_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let namespace = format!("querysmith_test_{}", std::process::id());
+        let admin = PgPool::connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", namespace))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        let options = sqlx::postgres::PgConnectOptions::from_str(&url)
+            .unwrap()
+            .options([("search_path", namespace.as_str())]);
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .unwrap();
+        let store = PostgresMetadataStore::from_pool(pool).await.unwrap();
+        check_round_trip(&store).await;
+
+        let diff = SchemaDiff {
+            schema_name: "main".to_string(),
+            created_at: "2024-02-01T00:00:00Z".to_string(),
+            ..Default::default()
+        };
+        store.save_diff(&diff).await.unwrap();
+        assert_eq!(store.list_diffs(None).await.unwrap(), vec![diff]);
+        assert!(store
+            .list_diffs(Some("2024-02-01T00:00:01+00:00"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", namespace))
+            .execute(&admin)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_persists_lineage_and_history() {
+        let store = SqliteMetadataStore::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        assert!(store.get_lineage().await.unwrap().is_none());
+
+        let graph = LineageGraph::from_json(r#"{"nodes": [], "relationships": []}"#).unwrap();
+        store.set_lineage(graph.clone()).await.unwrap();
+        store.set_lineage(graph).await.unwrap();
+        assert!(store.get_lineage().await.unwrap().is_some());
+
+        assert!(store.get_semantic_model().await.unwrap().is_none());
+        let model = SemanticModel::from_yaml(
+            "metrics:\n  - name: revenue\n    expression: SUM(amount)\n    table: orders\n",
+        )
+        .unwrap();
+        store.set_semantic_model(model.clone()).await.unwrap();
+        assert_eq!(
+            store.get_semantic_model().await.unwrap(),
+            Some(model.clone())
+        );
+        let mut metric = model.metrics[0].clone();
+        metric.name = "gmv".to_string();
+        store.upsert_metric(metric).await.unwrap();
+        assert_eq!(
+            store
+                .get_semantic_model()
+                .await
+                .unwrap()
+                .unwrap()
+                .metrics
+                .len(),
+            2
+        );
+
+        let snapshot = SchemaSnapshot {
+            schema_name: "main".to_string(),
+            version: 1,
+            schema: sample_schema(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        store.save_snapshot(&snapshot).await.unwrap();
+        assert_eq!(store.list_snapshots("main").await.unwrap()[0].version, 1);
+
+        let diff = SchemaDiff {
+            schema_name: "main".to_string(),
+            created_at: "2024-02-01T00:00:00Z".to_string(),
+            removed_tables: vec!["legacy".to_string()],
+            ..Default::default()
+        };
+        store.save_diff(&diff).await.unwrap();
+        assert_eq!(store.list_diffs(None).await.unwrap(), vec![diff]);
+        assert!(store
+            .list_diffs(Some("2024-03-01T00:00:00Z"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use crate::error::Error;
+use crate::lineage::LineageGraph;
+use crate::models::{Annotation, GlossaryTerm, Schema, TableMetadata};
+use crate::semantic::{Dimension, Metric, SemanticModel};
+use crate::versioning::{SchemaDiff, SchemaSnapshot, SchemaSync};
+
+#[async_trait]
+pub trait MetadataStore: Send + Sync {
+    async fn save_schema(&self, schema: Schema) -> Result<Schema, Error>;
+    async fn get_schema(&self, name: &str) -> Result<Schema, Error>;
+    async fn list_schemas(&self) -> Result<Vec<Schema>, Error>;
+    /// Up to `limit` schemas from `offset` in id order, with the total count.
+    async fn list_schemas_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Schema>, usize), Error>;
+    async fn delete_schema(&self, name: &str) -> Result<(), Error>;
+    /// Inserts a new schema, failing with `Conflict` if the name is taken.
+    async fn create_schema(&self, schema: Schema) -> Result<Schema, Error>;
+    /// Appends a table, failing with `Conflict` if the schema already has one
+    /// with that name.
+    async fn add_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error>;
+    async fn add_annotation(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        annotation: Annotation,
+    ) -> Result<(), Error>;
+    async fn add_column_annotation(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+        annotation: Annotation,
+    ) -> Result<(), Error>;
+    /// Inserts or replaces the term, matched case-insensitively.
+    async fn save_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error>;
+    /// Inserts a new term, failing with `Conflict` if it exists.
+    async fn create_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error>;
+    async fn list_glossary_terms(&self) -> Result<Vec<GlossaryTerm>, Error>;
+    async fn delete_glossary_term(&self, term: &str) -> Result<(), Error>;
+    async fn set_lineage(&self, graph: LineageGraph) -> Result<(), Error>;
+    async fn get_lineage(&self) -> Result<Option<LineageGraph>, Error>;
+    /// Merges `graph` into the stored lineage atomically and returns the result.
+    async fn merge_lineage(&self, graph: LineageGraph) -> Result<LineageGraph, Error>;
+    async fn set_semantic_model(&self, model: SemanticModel) -> Result<(), Error>;
+    async fn get_semantic_model(&self) -> Result<Option<SemanticModel>, Error>;
+    /// Inserts or replaces the metric in the stored model atomically.
+    async fn upsert_metric(&self, metric: Metric) -> Result<(), Error>;
+    /// Inserts or replaces the dimension in the stored model atomically.
+    async fn upsert_dimension(&self, dimension: Dimension) -> Result<(), Error>;
+    /// Saves `schema` and records the next snapshot, with the diff from the
+    /// latest one, allocating the version in the same transaction. A schema
+    /// matching the latest snapshot returns that snapshot instead.
+    async fn save_schema_version(
+        &self,
+        schema: Schema,
+        created_at: &str,
+    ) -> Result<SchemaSync, Error>;
+    async fn save_snapshot(&self, snapshot: &SchemaSnapshot) -> Result<(), Error>;
+    async fn list_snapshots(&self, schema_name: &str) -> Result<Vec<SchemaSnapshot>, Error>;
+    async fn latest_snapshot(&self, schema_name: &str) -> Result<Option<SchemaSnapshot>, Error>;
+    async fn save_diff(&self, diff: &SchemaDiff) -> Result<(), Error>;
+    /// Diffs recorded at or after `since` (RFC 3339), oldest first.
+    async fn list_diffs(&self, since: Option<&str>) -> Result<Vec<SchemaDiff>, Error>;
+}
+
+pub(crate) fn now_rfc3339() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, secs) = (now.div_euclid(86_400), now.rem_euclid(86_400));
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[derive(Default)]
+struct MemoryState {
+    schemas: HashMap<String, Schema>,
+    lineage: Option<LineageGraph>,
+    snapshots: HashMap<String, Vec<SchemaSnapshot>>,
+    diffs: Vec<SchemaDiff>,
+    glossary: BTreeMap<String, GlossaryTerm>,
+    semantic_model: Option<SemanticModel>,
+    next_id: i64,
+}
+
+#[derive(Default)]
+pub struct InMemoryMetadataStore {
+    state: RwLock<MemoryState>,
+}
+
+impl InMemoryMetadataStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryState {
+    fn save_schema(&mut self, mut schema: Schema) -> Schema {
+        let now = now_rfc3339();
+        match self.schemas.get(&schema.name) {
+            Some(existing) => {
+                schema.id = existing.id;
+                schema.created_at = existing.created_at.clone();
+            }
+            None => {
+                self.next_id += 1;
+                schema.id = Some(self.next_id);
+                schema.created_at = Some(now.clone());
+            }
+        }
+        schema.updated_at = Some(now);
+
+        self.schemas.insert(schema.name.clone(), schema.clone());
+        schema
+    }
+}
+
+#[async_trait]
+impl MetadataStore for InMemoryMetadataStore {
+    async fn save_schema(&self, schema: Schema) -> Result<Schema, Error> {
+        Ok(self.state.write().await.save_schema(schema))
+    }
+
+    async fn create_schema(&self, schema: Schema) -> Result<Schema, Error> {
+        let mut state = self.state.write().await;
+        if state.schemas.contains_key(&schema.name) {
+            return Err(Error::Conflict(format!(
+                "Schema '{}' already exists",
+                schema.name
+            )));
+        }
+        Ok(state.save_schema(schema))
+    }
+
+    async fn save_schema_version(
+        &self,
+        schema: Schema,
+        created_at: &str,
+    ) -> Result<SchemaSync, Error> {
+        let mut state = self.state.write().await;
+        let saved = state.save_schema(schema);
+        let snapshots = state.snapshots.entry(saved.name.clone()).or_default();
+        let sync = match snapshots.last() {
+            Some(previous) if previous.matches(&saved) => {
+                return Ok(SchemaSync {
+                    snapshot: previous.clone(),
+                    diff: None,
+                })
+            }
+            previous => SchemaSync::next(previous, saved, created_at),
+        };
+        snapshots.push(sync.snapshot.clone());
+        state.diffs.extend(sync.diff.clone());
+        Ok(sync)
+    }
+
+    async fn get_schema(&self, name: &str) -> Result<Schema, Error> {
+        let state = self.state.read().await;
+        state
+            .schemas
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<Schema>, Error> {
+        let state = self.state.read().await;
+        let mut schemas: Vec<Schema> = state.schemas.values().cloned().collect();
+        schemas.sort_by_key(|schema| schema.id);
+        Ok(schemas)
+    }
+
+    async fn list_schemas_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Schema>, usize), Error> {
+        let schemas = self.list_schemas().await?;
+        let total = schemas.len();
+        Ok((
+            schemas.into_iter().skip(offset).take(limit).collect(),
+            total,
+        ))
+    }
+
+    async fn delete_schema(&self, name: &str) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state
+            .schemas
+            .remove(name)
+            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", name)))?;
+        Ok(())
+    }
+
+    async fn add_table(&self, schema_name: &str, table: TableMetadata) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        let schema = state
+            .schemas
+            .get_mut(schema_name)
+            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+        if schema.tables.iter().any(|t| t.name == table.name) {
+            return Err(Error::Conflict(format!(
+                "Table '{}' already exists in '{}'",
+                table.name, schema_name
+            )));
+        }
+        schema.tables.push(table);
+        schema.updated_at = Some(now_rfc3339());
+        Ok(())
+    }
+
+    async fn add_annotation(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        annotation: Annotation,
+    ) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        let schema = state
+            .schemas
+            .get_mut(schema_name)
+            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+        let table = schema
+            .tables
+            .iter_mut()
+            .find(|t| t.name == table_name)
+            .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))?;
+        table.annotations.push(annotation);
+        schema.updated_at = Some(now_rfc3339());
+        Ok(())
+    }
+
+    async fn add_column_annotation(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+        annotation: Annotation,
+    ) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        let schema = state
+            .schemas
+            .get_mut(schema_name)
+            .ok_or_else(|| Error::NotFound(format!("Schema '{}' not found", schema_name)))?;
+        let column = schema
+            .tables
+            .iter_mut()
+            .find(|t| t.name == table_name)
+            .ok_or_else(|| Error::NotFound(format!("Table '{}' not found", table_name)))?
+            .columns
+            .iter_mut()
+            .find(|c| c.name == column_name)
+            .ok_or_else(|| Error::NotFound(format!("Column '{}' not found", column_name)))?;
+        column.annotations.push(annotation);
+        schema.updated_at = Some(now_rfc3339());
+        Ok(())
+    }
+
+    async fn save_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state.glossary.insert(term.term.to_lowercase(), term);
+        Ok(())
+    }
+
+    async fn create_glossary_term(&self, term: GlossaryTerm) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        let key = term.term.to_lowercase();
+        if state.glossary.contains_key(&key) {
+            return Err(Error::Conflict(format!(
+                "Glossary term '{}' already exists",
+                term.term
+            )));
+        }
+        state.glossary.insert(key, term);
+        Ok(())
+    }
+
+    async fn list_glossary_terms(&self) -> Result<Vec<GlossaryTerm>, Error> {
+        let state = self.state.read().await;
+        Ok(state.glossary.values().cloned().collect())
+    }
+
+    async fn delete_glossary_term(&self, term: &str) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state
+            .glossary
+            .remove(&term.to_lowercase())
+            .ok_or_else(|| Error::NotFound(format!("Glossary term '{}' not found", term)))?;
+        Ok(())
+    }
+
+    async fn set_lineage(&self, graph: LineageGraph) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state.lineage = Some(graph);
+        Ok(())
+    }
+
+    async fn get_lineage(&self) -> Result<Option<LineageGraph>, Error> {
+        let state = self.state.read().await;
+        Ok(state.lineage.clone())
+    }
+
+    async fn merge_lineage(&self, graph: LineageGraph) -> Result<LineageGraph, Error> {
+        let mut state = self.state.write().await;
+        let mut lineage = state.lineage.clone().unwrap_or_default();
+        lineage.merge(graph);
+        lineage.validate()?;
+        state.lineage = Some(lineage.clone());
+        Ok(lineage)
+    }
+
+    async fn set_semantic_model(&self, model: SemanticModel) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state.semantic_model = Some(model);
+        Ok(())
+    }
+
+    async fn get_semantic_model(&self) -> Result<Option<SemanticModel>, Error> {
+        let state = self.state.read().await;
+        Ok(state.semantic_model.clone())
+    }
+
+    async fn upsert_metric(&self, metric: Metric) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state
+            .semantic_model
+            .get_or_insert_with(SemanticModel::default)
+            .upsert_metric(metric);
+        Ok(())
+    }
+
+    async fn upsert_dimension(&self, dimension: Dimension) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state
+            .semantic_model
+            .get_or_insert_with(SemanticModel::default)
+            .upsert_dimension(dimension);
+        Ok(())
+    }
+
+    async fn save_snapshot(&self, snapshot: &SchemaSnapshot) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state
+            .snapshots
+            .entry(snapshot.schema_name.clone())
+            .or_default()
+            .push(snapshot.clone());
+        Ok(())
+    }
+
+    async fn list_snapshots(&self, schema_name: &str) -> Result<Vec<SchemaSnapshot>, Error> {
+        let state = self.state.read().await;
+        Ok(state
+            .snapshots
+            .get(schema_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn latest_snapshot(&self, schema_name: &str) -> Result<Option<SchemaSnapshot>, Error> {
+        let state = self.state.read().await;
+        Ok(state
+            .snapshots
+            .get(schema_name)
+            .and_then(|snapshots| snapshots.last().cloned()))
+    }
+
+    async fn save_diff(&self, diff: &SchemaDiff) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        state.diffs.push(diff.clone());
+        Ok(())
+    }
+
+    async fn list_diffs(&self, since: Option<&str>) -> Result<Vec<SchemaDiff>, Error> {
+        let state = self.state.read().await;
+        Ok(state
+            .diffs
+            .iter()
+            .filter(|diff| since.is_none_or(|since| diff.created_at.as_str() >= since))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_now_rfc3339_format() {
+        let now = now_rfc3339();
+        assert_eq!(now.len(), 20);
+        assert!(now.starts_with("20"));
+        assert_eq!(&now[4..5], "-");
+        assert!(now.ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_assigns_ids_and_timestamps() {
+        let store = InMemoryMetadataStore::new();
+        let schema = |name: &str| Schema {
+            id: None,
+            name: name.to_string(),
+            source: "postgres".to_string(),
+            tables: vec![],
+            created_at: None,
+            updated_at: None,
+        };
+
+        let first = store.save_schema(schema("a")).await.unwrap();
+        let second = store.save_schema(schema("b")).await.unwrap();
+        assert_eq!((first.id, second.id), (Some(1), Some(2)));
+        assert!(first.created_at.is_some());
+
+        let resaved = store.save_schema(schema("a")).await.unwrap();
+        assert_eq!(resaved.id, Some(1));
+        assert_eq!(resaved.created_at, first.created_at);
+    }
+}
@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::models::{ColumnMetadata, Schema, TableMetadata};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub schema_name: String,
+    pub version: i64,
+    pub schema: Schema,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaSync {
+    pub snapshot: SchemaSnapshot,
+    pub diff: Option<SchemaDiff>,
+}
+
+impl SchemaSync {
+    /// The snapshot following `previous` for a freshly saved schema, with the
+    /// diff between them when any table or column changed.
+    pub(crate) fn next(previous: Option<&SchemaSnapshot>, saved: Schema, created_at: &str) -> Self {
+        let version = previous.map_or(1, |p| p.version + 1);
+        let diff = previous
+            .map(|previous| SchemaDiff {
+                from_version: previous.version,
+                to_version: version,
+                created_at: created_at.to_string(),
+                ..SchemaDiff::compute(&previous.schema, &saved)
+            })
+            .filter(|diff| !diff.is_empty());
+        Self {
+            snapshot: SchemaSnapshot {
+                schema_name: saved.name.clone(),
+                version,
+                schema: saved,
+                created_at: created_at.to_string(),
+            },
+            diff,
+        }
+    }
+}
+
+impl SchemaSnapshot {
+    /// Whether `schema` is identical to this snapshot apart from the ids and
+    /// timestamps a store assigns on every save, so re-syncing it can reuse
+    /// the snapshot instead of storing another one.
+    pub(crate) fn matches(&self, schema: &Schema) -> bool {
+        let content = |schema: &Schema| {
+            serde_json::to_value(Schema {
+                id: None,
+                created_at: None,
+                updated_at: None,
+                ..schema.clone()
+            })
+            .ok()
+        };
+        content(&self.schema) == content(schema)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnChange {
+    pub column: String,
+    pub old_type: String,
+    pub new_type: String,
+    pub old_nullable: bool,
+    pub new_nullable: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub changed_columns: Vec<ColumnChange>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.removed_columns.is_empty()
+            && self.changed_columns.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableRename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDiff {
+    pub schema_name: String,
+    pub from_version: i64,
+    pub to_version: i64,
+    pub created_at: String,
+    pub added_tables: Vec<String>,
+    pub removed_tables: Vec<String>,
+    pub renamed_tables: Vec<TableRename>,
+    pub changed_tables: Vec<TableDiff>,
+}
+
+impl SchemaDiff {
+    /// Tables whose columns are identical on both sides are reported as renames
+    /// rather than as a removal plus an addition.
+    pub fn compute(old: &Schema, new: &Schema) -> Self {
+        let old_tables: BTreeMap<&str, &TableMetadata> =
+            old.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+        let new_tables: BTreeMap<&str, &TableMetadata> =
+            new.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+
+        let mut removed: Vec<&str> = old_tables
+            .keys()
+            .filter(|name| !new_tables.contains_key(*name))
+            .copied()
+            .collect();
+        let mut added: Vec<&str> = new_tables
+            .keys()
+            .filter(|name| !old_tables.contains_key(*name))
+            .copied()
+            .collect();
+
+        let mut renamed_tables = Vec::new();
+        removed.retain(|from| {
+            let signature = column_signature(old_tables[from]);
+            match added
+                .iter()
+                .position(|to| column_signature(new_tables[to]) == signature)
+            {
+                Some(i) => {
+                    renamed_tables.push(TableRename {
+                        from: from.to_string(),
+                        to: added.remove(i).to_string(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+
+        let changed_tables = old_tables
+            .iter()
+            .filter_map(|(name, old_table)| {
+                let new_table = new_tables.get(name)?;
+                let diff = diff_table(old_table, new_table);
+                (!diff.is_empty()).then_some(diff)
+            })
+            .collect();
+
+        Self {
+            schema_name: new.name.clone(),
+            added_tables: added.into_iter().map(String::from).collect(),
+            removed_tables: removed.into_iter().map(String::from).collect(),
+            renamed_tables,
+            changed_tables,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty()
+            && self.removed_tables.is_empty()
+            && self.renamed_tables.is_empty()
+            && self.changed_tables.is_empty()
+    }
+
+    /// Tables and `table.column` pairs that no longer exist under their old name.
+    pub fn dropped_references(&self) -> Vec<String> {
+        let mut references: Vec<String> = self
+            .removed_tables
+            .iter()
+            .cloned()
+            .chain(self.renamed_tables.iter().map(|r| r.from.clone()))
+            .chain(self.changed_tables.iter().flat_map(|t| {
+                t.removed_columns
+                    .iter()
+                    .map(move |c| format!("{}.{}", t.table, c))
+            }))
+            .collect();
+        references.sort();
+        references
+    }
+
+    /// Dropped references mentioned in `text`. A dropped column only counts when
+    /// its table is mentioned too, so common names like `id` don't match everywhere.
+    pub fn stale_references(&self, text: &str) -> Vec<String> {
+        let words: HashSet<String> = text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+        let mentions = |name: &str| {
+            let name = name.to_lowercase();
+            words.contains(name.rsplit('.').next().unwrap_or(&name))
+        };
+
+        self.dropped_references()
+            .into_iter()
+            .filter(|reference| match reference.split_once('.') {
+                Some((table, column)) => mentions(table) && mentions(column),
+                None => mentions(reference),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SchemaEvent {
+    Synced { schema_name: String, version: i64 },
+    Drift(SchemaDiff),
+}
+
+impl SchemaEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SchemaEvent::Synced { .. } => "schema.synced",
+            SchemaEvent::Drift(_) => "schema.drift",
+        }
+    }
+}
+
+fn column_signature(table: &TableMetadata) -> Vec<(String, String)> {
+    let mut columns: Vec<(String, String)> = table
+        .columns
+        .iter()
+        .map(|c| (c.name.clone(), c.data_type.to_lowercase()))
+        .collect();
+    columns.sort();
+    columns
+}
+
+fn diff_table(old: &TableMetadata, new: &TableMetadata) -> TableDiff {
+    let old_columns: BTreeMap<&str, &ColumnMetadata> =
+        old.columns.iter().map(|c| (c.name.as_str(), c)).collect();
+    let new_columns: BTreeMap<&str, &ColumnMetadata> =
+        new.columns.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    TableDiff {
+        table: new.name.clone(),
+        added_columns: new_columns
+            .keys()
+            .filter(|name| !old_columns.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect(),
+        removed_columns: old_columns
+            .keys()
+            .filter(|name| !new_columns.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect(),
+        changed_columns: old_columns
+            .iter()
+            .filter_map(|(name, old_column)| {
+                let new_column = new_columns.get(name)?;
+                let changed = !old_column
+                    .data_type
+                    .eq_ignore_ascii_case(&new_column.data_type)
+                    || old_column.nullable != new_column.nullable;
+                changed.then(|| ColumnChange {
+                    column: name.to_string(),
+                    old_type: old_column.data_type.clone(),
+                    new_type: new_column.data_type.clone(),
+                    old_nullable: old_column.nullable,
+                    new_nullable: new_column.nullable,
+                })
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, columns: &[(&str, &str)]) -> TableMetadata {
+        TableMetadata {
+            name: name.to_string(),
+            schema_name: None,
+            columns: columns
+                .iter()
+                .map(|(name, data_type)| ColumnMetadata {
+                    name: name.to_string(),
+                    data_type: data_type.to_string(),
+                    nullable: true,
+                    comment: None,
+                    annotations: vec![],
+                })
+                .collect(),
+            primary_key: None,
+            annotations: vec![],
+            description: None,
+        }
+    }
+
+    fn schema(tables: Vec<TableMetadata>) -> Schema {
+        Schema {
+            id: None,
+            name: "main".to_string(),
+            source: "postgres".to_string(),
+            tables,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_diff() {
+        let old = schema(vec![
+            table("users", &[("id", "int"), ("email", "text"), ("age", "int")]),
+            table("events", &[("id", "int"), ("kind", "text")]),
+            table("legacy", &[("id", "int")]),
+        ]);
+        let new = schema(vec![
+            table(
+                "users",
+                &[("id", "int"), ("email", "varchar"), ("signup", "date")],
+            ),
+            table("user_events", &[("id", "int"), ("kind", "text")]),
+            table("orders", &[("id", "int"), ("total", "numeric")]),
+        ]);
+
+        let diff = SchemaDiff::compute(&old, &new);
+        assert_eq!(diff.added_tables, vec!["orders"]);
+        assert_eq!(diff.removed_tables, vec!["legacy"]);
+        assert_eq!(
+            diff.renamed_tables,
+            vec![TableRename {
+                from: "events".to_string(),
+                to: "user_events".to_string()
+            }]
+        );
+        let users = &diff.changed_tables[0];
+        assert_eq!(users.added_columns, vec!["signup"]);
+        assert_eq!(users.removed_columns, vec!["age"]);
+        assert_eq!(users.changed_columns[0].new_type, "varchar");
+        assert!(SchemaDiff::compute(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_stale_references() {
+        let old = schema(vec![table("users", &[("id", "int"), ("age", "int")])]);
+        let new = schema(vec![table("users", &[("id", "int")])]);
+        let diff = SchemaDiff::compute(&old, &new);
+
+        assert_eq!(diff.dropped_references(), vec!["users.age"]);
+        assert_eq!(
+            diff.stale_references("SELECT AVG(age) FROM users"),
+            vec!["users.age"]
+        );
+        assert!(diff.stale_references("SELECT age FROM people").is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! Compares exact and HNSW search latency and recall@10.
+//!
+//! cargo run --release -p rag-engine --example ann_benchmark -- [count] [dimension]
+
+use std::time::{Duration, Instant};
+
+use rag_engine::{HnswConfig, VectorIndex};
+
+fn random_vectors(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            (0..dimension)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let count: usize = args.next().and_then(|a| a.parse().ok()).unwrap_or(20_000);
+    let dimension: usize = args.next().and_then(|a| a.parse().ok()).unwrap_or(128);
+    let k = 10;
+
+    let vectors = random_vectors(count, dimension, 1);
+    let queries = random_vectors(100, dimension, 2);
+
+    let mut index = VectorIndex::new(dimension);
+    for (i, vector) in vectors.into_iter().enumerate() {
+        index.add(i.to_string(), vector, serde_json::json!({}));
+    }
+    let started = Instant::now();
+    let index_ann = index.clone().with_hnsw(HnswConfig::default());
+    println!(
+        "{} vectors of dimension {}, HNSW build {:.2?}",
+        count,
+        dimension,
+        started.elapsed()
+    );
+
+    let mut exact_time = Duration::ZERO;
+    let mut ann_time = Duration::ZERO;
+    let mut hits = 0;
+    for query in &queries {
+        let started = Instant::now();
+        let exact = index.search(query, k);
+        exact_time += started.elapsed();
+
+        let started = Instant::now();
+        let approximate = index_ann.search(query, k);
+        ann_time += started.elapsed();
+
+        hits += exact
+            .iter()
+            .filter(|(id, _)| approximate.iter().any(|(other, _)| other == id))
+            .count();
+    }
+
+    let queries = queries.len() as u32;
+    println!("brute force: {:.2?} per query", exact_time / queries);
+    println!("hnsw:        {:.2?} per query", ann_time / queries);
+    println!(
+        "recall@{}:   {:.3}",
+        k,
+        hits as f32 / (queries as usize * k) as f32
+    );
+}
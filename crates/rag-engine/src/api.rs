@@ -0,0 +1,270 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use warehouse_conn::auth::require_token;
+
+use crate::backend::{DocumentRequest, IndexItem, RagBackend, RetrieveRequest};
+use crate::error::Error;
+use crate::retrieval::{IndexStats, RetrievalResult, SourceType, MAX_K};
+
+const DEFAULT_ENTRIES_PAGE: usize = 500;
+pub const MAX_ENTRIES_PAGE: usize = 1000;
+
+type ApiResult<T> = Result<T, Error>;
+
+#[derive(Debug, Deserialize)]
+pub struct EntriesParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// A page of entry metadata by id, ordered by id.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntriesPage {
+    pub entries: HashMap<String, Value>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::Rag(_) => StatusCode::BAD_REQUEST,
+            Error::Embedding(_) => StatusCode::BAD_GATEWAY,
+            Error::VectorStore(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+fn source_param(name: &str) -> ApiResult<SourceType> {
+    SourceType::from_type_name(name).ok_or_else(|| Error::Rag(format!("unknown source: {}", name)))
+}
+
+/// Routes for retrieval, indexing and index statistics. Sources in paths use
+/// their short names (`table`, `doc`, `memory`, `schema`, `query_example`).
+/// Retrieval and statistics are open; listing entries, indexing and deletes
+/// require `Authorization: Bearer <token>` and are refused when no token is
+/// set. Mount with `Router::nest` or run with [`serve`].
+pub fn router(backend: Arc<dyn RagBackend>, token: Option<String>) -> Router {
+    let entries = Router::new()
+        .route("/documents", post(index_document))
+        .route("/entries/:source", get(entries).put(upsert))
+        .route("/entries/:source/:id", delete(delete_entry));
+    Router::new()
+        .route("/retrieve", post(retrieve))
+        .route("/stats", get(stats))
+        .merge(require_token(entries, token))
+        .with_state(backend)
+}
+
+pub async fn serve(
+    backend: Arc<dyn RagBackend>,
+    addr: SocketAddr,
+    token: Option<String>,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Starting RAG API on {}", addr);
+    axum::serve(listener, router(backend, token)).await
+}
+
+async fn retrieve(
+    State(backend): State<Arc<dyn RagBackend>>,
+    Json(request): Json<RetrieveRequest>,
+) -> ApiResult<Json<RetrievalResult>> {
+    if request.k > MAX_K {
+        return Err(Error::Rag(format!("k must be at most {}", MAX_K)));
+    }
+    Ok(Json(backend.retrieve(request).await?))
+}
+
+async fn stats(State(backend): State<Arc<dyn RagBackend>>) -> ApiResult<Json<IndexStats>> {
+    Ok(Json(backend.stats().await?))
+}
+
+async fn entries(
+    State(backend): State<Arc<dyn RagBackend>>,
+    Path(source): Path<String>,
+    Query(params): Query<EntriesParams>,
+) -> ApiResult<Json<EntriesPage>> {
+    let metadata = backend.metadata(source_param(&source)?).await?;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ENTRIES_PAGE)
+        .clamp(1, MAX_ENTRIES_PAGE);
+    let mut ids: Vec<&String> = metadata.keys().collect();
+    ids.sort();
+    let entries = ids
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|id| (id.clone(), metadata[id].clone()))
+        .collect();
+    Ok(Json(EntriesPage {
+        entries,
+        total: metadata.len(),
+        offset,
+        limit,
+    }))
+}
+
+async fn index_document(
+    State(backend): State<Arc<dyn RagBackend>>,
+    Json(request): Json<DocumentRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let chunks = backend.index_document(request).await?;
+    Ok((StatusCode::CREATED, Json(json!({ "chunks": chunks }))))
+}
+
+async fn upsert(
+    State(backend): State<Arc<dyn RagBackend>>,
+    Path(source): Path<String>,
+    Json(items): Json<Vec<IndexItem>>,
+) -> ApiResult<Json<Value>> {
+    let upserted = backend.upsert(source_param(&source)?, items).await?;
+    Ok(Json(json!({ "upserted": upserted })))
+}
+
+async fn delete_entry(
+    State(backend): State<Arc<dyn RagBackend>>,
+    Path((source, id)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    if backend.delete(source_param(&source)?, &id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::HashingEmbedder;
+    use crate::retrieval::RAGService;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    async fn call(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        call_with_token(app, Some("secret"), method, uri, body).await
+    }
+
+    async fn call_with_token(
+        app: &Router,
+        token: Option<&str>,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let request = request
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_index_retrieve_and_delete() {
+        let rag = RAGService::with_embedder(Arc::new(HashingEmbedder::new(64)));
+        let app = router(Arc::new(RwLock::new(rag)), Some("secret".to_string()));
+
+        let items = json!([
+            { "id": "orders", "content": "orders: order_id, total", "metadata": { "schema": "sales" } },
+            { "id": "users", "content": "users: user_id, email", "metadata": { "schema": "crm" } }
+        ]);
+        let (status, body) = call(&app, "PUT", "/entries/table", Some(items)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["upserted"], 2);
+        assert_eq!(
+            call(&app, "PUT", "/entries/nope", Some(json!([]))).await.0,
+            StatusCode::BAD_REQUEST
+        );
+
+        let request = json!({
+            "query": "order total",
+            "k": 5,
+            "filter": { "eq": ["schema", "sales"] }
+        });
+        let (status, result) = call(&app, "POST", "/retrieve", Some(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result["total_results"], 1);
+        assert_eq!(result["chunks"][0]["id"], "orders");
+        let (_, page) = call(&app, "GET", "/entries/table", None).await;
+        assert_eq!(page["entries"]["users"]["schema"], "crm");
+        let (_, page) = call(&app, "GET", "/entries/table?offset=1&limit=1", None).await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["entries"], json!({ "users": { "schema": "crm" } }));
+
+        assert_eq!(
+            call(&app, "DELETE", "/entries/table/orders", None).await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(&app, "DELETE", "/entries/table/orders", None).await.0,
+            StatusCode::NOT_FOUND
+        );
+        let (_, stats) = call(&app, "GET", "/stats", None).await;
+        assert_eq!(stats["entries"], 1);
+        assert_eq!(stats["model"], "hashing-fnv1a");
+    }
+
+    #[tokio::test]
+    async fn test_writes_need_token_and_k_is_bounded() {
+        let rag = RAGService::with_embedder(Arc::new(HashingEmbedder::new(64)));
+        let backend: Arc<dyn RagBackend> = Arc::new(RwLock::new(rag));
+        let app = router(backend.clone(), Some("secret".to_string()));
+        let items = json!([{ "id": "orders", "content": "orders: order_id" }]);
+
+        let (status, _) =
+            call_with_token(&app, None, "PUT", "/entries/table", Some(items.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) =
+            call_with_token(&app, Some("wrong"), "DELETE", "/entries/table/orders", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call_with_token(&app, None, "GET", "/entries/table", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let closed = router(backend, None);
+        let (status, _) = call(&closed, "PUT", "/entries/table", Some(items)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let request = json!({ "query": "orders" });
+        let (status, _) = call_with_token(&app, None, "POST", "/retrieve", Some(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        let request = json!({ "query": "orders", "k": usize::MAX });
+        let (status, body) = call(&app, "POST", "/retrieve", Some(request)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"],
+            format!("RAG error: k must be at most {}", MAX_K)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use crate::chunking::DocumentFormat;
+use crate::error::Error;
+use crate::filter::MetadataFilter;
+use crate::retrieval::{embed_entries, IndexStats, RAGService, RetrievalResult, SourceType};
+
+fn default_k() -> usize {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexItem {
+    pub id: String,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// A document to chunk and index, see [`RAGService::index_document`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentRequest {
+    pub source: SourceType,
+    pub parent_id: String,
+    pub text: String,
+    pub format: DocumentFormat,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrieveRequest {
+    pub query: String,
+    #[serde(default = "default_k")]
+    pub k: usize,
+    #[serde(default)]
+    pub sources: Option<Vec<SourceType>>,
+    #[serde(default)]
+    pub filter: Option<MetadataFilter>,
+}
+
+impl RetrieveRequest {
+    pub fn new(query: &str, k: usize) -> Self {
+        Self {
+            query: query.to_string(),
+            k,
+            sources: None,
+            filter: None,
+        }
+    }
+
+    pub fn with_sources(mut self, sources: Vec<SourceType>) -> Self {
+        self.sources = Some(sources);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: MetadataFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+/// Text-level access to a RAG index, implemented by a local
+/// `RwLock<RAGService>` and by [`RemoteRagClient`](crate::remote::RemoteRagClient)
+/// so callers can share one index across processes.
+#[async_trait]
+pub trait RagBackend: Send + Sync {
+    /// Embeds and indexes `items`, replacing entries with the same ids.
+    async fn upsert(&self, source: SourceType, items: Vec<IndexItem>) -> Result<usize, Error>;
+
+    /// Chunks and indexes a document, returning the number of chunks.
+    async fn index_document(&self, request: DocumentRequest) -> Result<usize, Error>;
+
+    async fn delete(&self, source: SourceType, id: &str) -> Result<bool, Error>;
+
+    /// Indexed metadata of every entry of `source`, by id.
+    async fn metadata(&self, source: SourceType) -> Result<HashMap<String, Value>, Error>;
+
+    async fn retrieve(&self, request: RetrieveRequest) -> Result<RetrievalResult, Error>;
+
+    async fn stats(&self) -> Result<IndexStats, Error>;
+}
+
+/// Embedding runs outside the lock, so slow embedding calls neither block
+/// retrieval nor other writers; only the index update takes the write lock.
+#[async_trait]
+impl RagBackend for RwLock<RAGService> {
+    async fn upsert(&self, source: SourceType, items: Vec<IndexItem>) -> Result<usize, Error> {
+        let count = items.len();
+        let items = items
+            .into_iter()
+            .map(|item| (item.id, item.content, item.metadata))
+            .collect();
+        let embedder = self.read().await.embedder()?.clone();
+        let entries = embed_entries(embedder.as_ref(), source, items).await?;
+        self.write().await.index_entries(entries).await?;
+        Ok(count)
+    }
+
+    async fn index_document(&self, request: DocumentRequest) -> Result<usize, Error> {
+        let (embedder, items) = {
+            let rag = self.read().await;
+            let items = rag.document_items(
+                &request.parent_id,
+                &request.text,
+                request.format,
+                request.metadata,
+            );
+            (rag.embedder()?.clone(), items)
+        };
+        let entries = embed_entries(embedder.as_ref(), request.source, items).await?;
+        let count = entries.len();
+        self.write().await.index_entries(entries).await?;
+        Ok(count)
+    }
+
+    async fn delete(&self, source: SourceType, id: &str) -> Result<bool, Error> {
+        self.write().await.delete(&source, id).await
+    }
+
+    async fn metadata(&self, source: SourceType) -> Result<HashMap<String, Value>, Error> {
+        Ok(self.read().await.index(&source).metadata.clone())
+    }
+
+    async fn retrieve(&self, request: RetrieveRequest) -> Result<RetrievalResult, Error> {
+        let embedder = self.read().await.embedder()?.clone();
+        let vector = embedder.embed(&request.query).await?;
+        Ok(self
+            .read()
+            .await
+            .retrieve_filtered(
+                &request.query,
+                &vector,
+                request.k,
+                request.sources,
+                request.filter.as_ref(),
+            )
+            .await)
+    }
+
+    async fn stats(&self) -> Result<IndexStats, Error> {
+        Ok(self.read().await.stats())
+    }
+}
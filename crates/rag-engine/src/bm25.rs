@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::tokenize::tokenize;
+
+/// Okapi BM25 inverted index over identifier-aware tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bm25Index {
+    pub k1: f32,
+    pub b: f32,
+    postings: HashMap<String, HashMap<String, u32>>,
+    lengths: HashMap<String, usize>,
+    /// Distinct terms of each document, so removal only touches its postings.
+    terms: HashMap<String, Vec<String>>,
+    total_length: usize,
+}
+
+impl Default for Bm25Index {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bm25Index {
+    pub fn new() -> Self {
+        Self {
+            k1: 1.2,
+            b: 0.75,
+            postings: HashMap::new(),
+            lengths: HashMap::new(),
+            terms: HashMap::new(),
+            total_length: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// Indexes `text` under `id`, replacing any previous text for it.
+    pub fn add(&mut self, id: &str, text: &str) {
+        self.remove(id);
+        let tokens = tokenize(text);
+        for token in &tokens {
+            *self
+                .postings
+                .entry(token.clone())
+                .or_default()
+                .entry(id.to_string())
+                .or_default() += 1;
+        }
+        self.total_length += tokens.len();
+        self.lengths.insert(id.to_string(), tokens.len());
+        let mut terms = tokens;
+        terms.sort();
+        terms.dedup();
+        self.terms.insert(id.to_string(), terms);
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(length) = self.lengths.remove(id) else {
+            return false;
+        };
+        self.total_length -= length;
+        for term in self.terms.remove(id).unwrap_or_default() {
+            if let Some(docs) = self.postings.get_mut(&term) {
+                docs.remove(id);
+                if docs.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn search(&self, query: &str, k: usize) -> Vec<(String, f32)> {
+        self.search_filtered(query, k, |_| true)
+    }
+
+    /// Like [`search`](Self::search), skipping documents `accept` rejects.
+    pub fn search_filtered(
+        &self,
+        query: &str,
+        k: usize,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<(String, f32)> {
+        if self.lengths.is_empty() {
+            return vec![];
+        }
+        let count = self.lengths.len() as f32;
+        let average = self.total_length as f32 / count;
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+        for term in &terms {
+            let Some(docs) = self.postings.get(term) else {
+                continue;
+            };
+            let df = docs.len() as f32;
+            let idf = ((count - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for (id, &tf) in docs {
+                if !accept(id) {
+                    continue;
+                }
+                let tf = tf as f32;
+                let length = self.lengths[id] as f32;
+                let norm = self.k1 * (1.0 - self.b + self.b * length / average.max(1.0));
+                *scores.entry(id).or_default() += idf * tf * (self.k1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut scores: Vec<(String, f32)> = scores
+            .into_iter()
+            .map(|(id, score)| (id.to_string(), score))
+            .collect();
+        scores.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        scores.truncate(k);
+        scores
+    }
+}
+
+/// Merges ranked lists with weighted reciprocal rank fusion: each id scores
+/// `weight / (k + rank)` per list it appears in, with 1-based ranks.
+pub fn reciprocal_rank_fusion(lists: &[(Vec<String>, f32)], k: f32) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    for (ids, weight) in lists {
+        for (rank, id) in ids.iter().enumerate() {
+            *scores.entry(id).or_default() += weight / (k + rank as f32 + 1.0);
+        }
+    }
+    let mut fused: Vec<(String, f32)> = scores
+        .into_iter()
+        .map(|(id, score)| (id.to_string(), score))
+        .collect();
+    fused.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_prefers_exact_identifiers() {
+        let mut index = Bm25Index::new();
+        index.add(
+            "dim_customer_v2",
+            "dim_customer_v2: customer_id, name, region",
+        );
+        index.add("customers_legacy", "Old customer table kept for reference");
+        index.add("orders", "orders: order_id, customer_id, total");
+
+        let results = index.search("dim_customer_v2", 3);
+        assert_eq!(results[0].0, "dim_customer_v2");
+
+        index.add("orders", "orders: order_id, amount");
+        assert!(index.remove("customers_legacy"));
+        assert_eq!(index.len(), 2);
+        assert!(index.search("legacy", 3).is_empty());
+        assert!(!index.postings.contains_key("legacy"));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let vector = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let lexical = vec!["b".to_string(), "c".to_string()];
+        let fused = reciprocal_rank_fusion(&[(vector.clone(), 1.0), (lexical, 1.0)], 60.0);
+        assert_eq!(fused[0].0, "b");
+
+        let fused = reciprocal_rank_fusion(&[(vector, 0.1), (vec!["c".to_string()], 1.0)], 60.0);
+        assert_eq!(fused[0].0, "c");
+    }
+}
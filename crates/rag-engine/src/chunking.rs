@@ -0,0 +1,388 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::context::{ApproxTokenCounter, TokenCounter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocumentFormat {
+    Markdown,
+    Sql,
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub id: String,
+    pub parent_id: String,
+    pub index: usize,
+    /// Heading path of the markdown section, e.g. `Orders > Columns`.
+    pub heading: Option<String>,
+    pub content: String,
+}
+
+/// Splits documents into chunks of at most `max_tokens`, breaking at
+/// markdown sections and paragraphs or SQL statements before falling back to
+/// lines and words. A fenced code block is one unit; when it is over budget
+/// it is cut at line boundaries and every piece is fenced again. Consecutive
+/// chunks of a section share up to `overlap` tokens.
+#[derive(Clone)]
+pub struct Chunker {
+    max_tokens: usize,
+    overlap: usize,
+    counter: Arc<dyn TokenCounter>,
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Chunker {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap: max_tokens / 8,
+            counter: Arc::new(ApproxTokenCounter),
+        }
+    }
+
+    pub fn with_overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn with_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    pub fn chunk(&self, parent_id: &str, text: &str, format: DocumentFormat) -> Vec<Chunk> {
+        let sections = match format {
+            DocumentFormat::Markdown => markdown_sections(text),
+            DocumentFormat::Sql => vec![(None, sql_statements(text))],
+            DocumentFormat::Text => vec![(None, paragraphs(text))],
+        };
+        let separator = if format == DocumentFormat::Sql {
+            "\n"
+        } else {
+            "\n\n"
+        };
+
+        let mut chunks = Vec::new();
+        for (heading, units) in sections {
+            for content in self.pack(units, separator) {
+                chunks.push(Chunk {
+                    id: format!("{}#{}", parent_id, chunks.len()),
+                    parent_id: parent_id.to_string(),
+                    index: chunks.len(),
+                    heading: heading.clone(),
+                    content,
+                });
+            }
+        }
+        chunks
+    }
+
+    fn pack(&self, units: Vec<String>, separator: &str) -> Vec<String> {
+        let units: Vec<(String, usize)> = units
+            .into_iter()
+            .flat_map(|unit| self.split(&unit))
+            .map(|unit| {
+                let tokens = self.counter.count(&unit);
+                (unit, tokens)
+            })
+            .collect();
+
+        let mut chunks = Vec::new();
+        let mut current: Vec<(String, usize)> = Vec::new();
+        let mut tokens = 0;
+        let mut fresh = false;
+        for (unit, count) in units {
+            if fresh && tokens + count > self.max_tokens {
+                chunks.push(join(&current, separator));
+                let mut kept = 0;
+                let keep = current
+                    .iter()
+                    .rev()
+                    .take_while(|(_, t)| {
+                        kept += t;
+                        kept <= self.overlap && kept + count <= self.max_tokens
+                    })
+                    .count();
+                current.drain(..current.len() - keep);
+                tokens = current.iter().map(|(_, t)| t).sum();
+            }
+            tokens += count;
+            current.push((unit, count));
+            fresh = true;
+        }
+        if fresh {
+            chunks.push(join(&current, separator));
+        }
+        chunks
+    }
+
+    /// Breaks a unit that is over budget into lines, then runs of words.
+    fn split(&self, unit: &str) -> Vec<String> {
+        if self.counter.count(unit) <= self.max_tokens {
+            return vec![unit.to_string()];
+        }
+        if unit.lines().next().is_some_and(is_fence) {
+            return self.split_fence(unit);
+        }
+        let lines: Vec<&str> = unit.lines().filter(|l| !l.trim().is_empty()).collect();
+        if lines.len() > 1 {
+            return lines
+                .into_iter()
+                .flat_map(|line| self.split(line))
+                .collect();
+        }
+
+        let mut pieces = Vec::new();
+        let mut piece = String::new();
+        for word in unit.split_whitespace() {
+            let candidate = if piece.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", piece, word)
+            };
+            if !piece.is_empty() && self.counter.count(&candidate) > self.max_tokens {
+                pieces.push(std::mem::replace(&mut piece, word.to_string()));
+            } else {
+                piece = candidate;
+            }
+        }
+        if !piece.is_empty() {
+            pieces.push(piece);
+        }
+        pieces
+    }
+
+    /// Packs the lines of a fenced block, blank ones included, into pieces
+    /// that each repeat the opening and closing fence.
+    fn split_fence(&self, block: &str) -> Vec<String> {
+        let lines: Vec<&str> = block.lines().collect();
+        let open = lines[0];
+        let (body, close) = match lines[1..].split_last() {
+            Some((last, body)) if is_fence(last) => (body, *last),
+            _ => (&lines[1..], "```"),
+        };
+        let fenced = |body: &[&str]| {
+            let mut piece = vec![open];
+            piece.extend_from_slice(body);
+            piece.push(close);
+            piece.join("\n")
+        };
+
+        let mut pieces = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for &line in body {
+            current.push(line);
+            if current.len() > 1 && self.counter.count(&fenced(&current)) > self.max_tokens {
+                current.pop();
+                pieces.push(fenced(&current));
+                current = vec![line];
+            }
+        }
+        if !current.is_empty() {
+            pieces.push(fenced(&current));
+        }
+        pieces
+    }
+}
+
+fn join(units: &[(String, usize)], separator: &str) -> String {
+    units
+        .iter()
+        .map(|(unit, _)| unit.as_str())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+fn heading_level(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+        Some((level, rest.trim()))
+    } else {
+        None
+    }
+}
+
+/// Paragraphs grouped by heading path. Each fenced code block is a single
+/// unit, blank lines included, and headings inside it are ignored.
+fn markdown_sections(text: &str) -> Vec<(Option<String>, Vec<String>)> {
+    let mut sections: Vec<(Option<String>, Vec<String>)> = vec![(None, Vec::new())];
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    let flush = |paragraph: &mut Vec<&str>, sections: &mut Vec<(Option<String>, Vec<String>)>| {
+        if !paragraph.is_empty() {
+            if let Some((_, units)) = sections.last_mut() {
+                units.push(paragraph.join("\n"));
+            }
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        if is_fence(line) {
+            if !in_fence {
+                flush(&mut paragraph, &mut sections);
+            }
+            paragraph.push(line);
+            in_fence = !in_fence;
+            if !in_fence {
+                flush(&mut paragraph, &mut sections);
+            }
+            continue;
+        }
+        if in_fence {
+            paragraph.push(line);
+            continue;
+        }
+        if let Some((level, title)) = heading_level(line) {
+            flush(&mut paragraph, &mut sections);
+            path.retain(|(l, _)| *l < level);
+            path.push((level, title.to_string()));
+            let heading = path
+                .iter()
+                .map(|(_, t)| t.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ");
+            sections.push((Some(heading), vec![line.to_string()]));
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut sections);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut sections);
+    sections.retain(|(_, units)| !units.is_empty());
+    sections
+}
+
+fn paragraphs(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Splits at semicolons outside strings, quoted identifiers and comments.
+/// Comments stay with the statement that follows them.
+pub(crate) fn sql_statements(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            quote @ ('\'' | '"' | '`') => {
+                i += 1;
+                while i < chars.len() && chars[i] != quote {
+                    i += 1;
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            ';' => {
+                statements.push(chars[start..=i].iter().collect::<String>());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < chars.len() {
+        statements.push(chars[start..].iter().collect());
+    }
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_markdown_chunks_keep_sections_and_code_blocks() {
+        let doc = "# Orders\n\nOne row per order.\n\n## Columns\n\n```sql\n# not a heading\nSELECT 1;\n```\n\nalpha beta gamma delta\n\nepsilon zeta eta theta\n\niota kappa lambda mu\n";
+        let chunker = Chunker::new(8)
+            .with_overlap(4)
+            .with_counter(Arc::new(words));
+        let chunks = chunker.chunk("docs/orders.md", doc, DocumentFormat::Markdown);
+
+        assert_eq!(chunks[0].heading.as_deref(), Some("Orders"));
+        assert_eq!(chunks[0].id, "docs/orders.md#0");
+        let columns: Vec<&Chunk> = chunks
+            .iter()
+            .filter(|c| c.heading.as_deref() == Some("Orders > Columns"))
+            .collect();
+        assert!(columns
+            .iter()
+            .any(|c| c.content.contains("# not a heading\nSELECT 1;\n```")));
+        assert!(columns.iter().all(|c| words(&c.content) <= 8));
+        // Overlap carries the previous paragraph into the next chunk.
+        assert!(columns
+            .windows(2)
+            .any(|w| w[1].content.starts_with("epsilon") && w[0].content.ends_with("theta")));
+        assert!(chunks.iter().enumerate().all(|(i, c)| c.index == i));
+
+        let long = "```sql\nSELECT a,\n\n  b, c\nFROM t\nWHERE a > 1\n```";
+        let chunks = Chunker::new(6)
+            .with_overlap(0)
+            .with_counter(Arc::new(words))
+            .chunk("q.md", long, DocumentFormat::Markdown);
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| c.content.starts_with("```sql\n") && c.content.ends_with("\n```")));
+        assert!(chunks[0].content.contains("SELECT a,\n\n  b, c"));
+    }
+
+    #[test]
+    fn test_sql_chunks_split_at_statements() {
+        let sql = "-- totals; per day\nSELECT ';' AS a FROM t;\nCREATE VIEW v AS SELECT * FROM t;\n/* done; */ SELECT 2";
+        let chunker = Chunker::new(10)
+            .with_overlap(0)
+            .with_counter(Arc::new(words));
+        let chunks = chunker.chunk("q.sql", sql, DocumentFormat::Sql);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.content.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "-- totals; per day\nSELECT ';' AS a FROM t;",
+                "CREATE VIEW v AS SELECT * FROM t;",
+                "/* done; */ SELECT 2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::retrieval::{RetrievalResult, RetrievedChunk, SourceType};
+
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+impl<F> TokenCounter for F
+where
+    F: Fn(&str) -> usize + Send + Sync,
+{
+    fn count(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+/// Rough estimate for BPE tokenizers: about four characters per token, and
+/// never fewer tokens than words.
+pub struct ApproxTokenCounter;
+
+impl TokenCounter for ApproxTokenCounter {
+    fn count(&self, text: &str) -> usize {
+        let chars = text.chars().count().div_ceil(4);
+        chars.max(text.split_whitespace().count())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub number: usize,
+    pub id: String,
+    pub source: SourceType,
+    pub parent_id: Option<String>,
+    pub heading: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssembledContext {
+    pub text: String,
+    pub citations: Vec<Citation>,
+    pub tokens: usize,
+    /// Chunks left out as near-duplicates or for lack of budget.
+    pub dropped: usize,
+}
+
+/// Fits retrieved chunks into a token budget: near-duplicates are dropped,
+/// the highest-scoring chunks are kept, and the survivors are grouped by
+/// table (or parent document) with numbered citations.
+pub struct ContextAssembler {
+    budget: usize,
+    counter: Arc<dyn TokenCounter>,
+    dedup_threshold: f32,
+}
+
+impl ContextAssembler {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            counter: Arc::new(ApproxTokenCounter),
+            dedup_threshold: 0.8,
+        }
+    }
+
+    pub fn with_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Jaccard similarity of word shingles above which a chunk counts as a
+    /// duplicate of a higher-scoring one.
+    pub fn with_dedup_threshold(mut self, threshold: f32) -> Self {
+        self.dedup_threshold = threshold;
+        self
+    }
+
+    pub fn assemble(&self, result: &RetrievalResult) -> AssembledContext {
+        let mut chunks: Vec<&RetrievedChunk> = result.chunks.iter().collect();
+        chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let header = "## Relevant Context\n\n";
+        let mut tokens = self.counter.count(header);
+        let mut kept: Vec<(&RetrievedChunk, HashSet<String>)> = Vec::new();
+        let mut dropped = 0;
+        for chunk in chunks {
+            let shingles = shingles(&chunk.content);
+            let duplicate = kept
+                .iter()
+                .any(|(_, other)| jaccard(&shingles, other) >= self.dedup_threshold);
+            // Group headings and citation markers cost a few tokens on top.
+            let cost =
+                self.counter.count(&chunk.content) + self.counter.count(&group_key(chunk)) + 4;
+            if duplicate || tokens + cost > self.budget {
+                dropped += 1;
+                continue;
+            }
+            tokens += cost;
+            kept.push((chunk, shingles));
+        }
+
+        let mut groups: Vec<(String, Vec<&RetrievedChunk>)> = Vec::new();
+        for (chunk, _) in kept {
+            let key = group_key(chunk);
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, members)) => members.push(chunk),
+                None => groups.push((key, vec![chunk])),
+            }
+        }
+
+        let mut text = String::from(header);
+        let mut citations = Vec::new();
+        for (key, mut members) in groups {
+            members.sort_by_key(|c| c.metadata.get("chunk").and_then(Value::as_u64));
+            text.push_str(&format!("### {}\n", key));
+            for chunk in members {
+                let number = citations.len() + 1;
+                text.push_str(&format!("[{}] {}\n\n", number, chunk.content.trim_end()));
+                citations.push(Citation {
+                    number,
+                    id: chunk.id.clone(),
+                    source: chunk.source.clone(),
+                    parent_id: string_field(chunk, "parent_id"),
+                    heading: string_field(chunk, "heading"),
+                });
+            }
+        }
+
+        AssembledContext {
+            tokens: self.counter.count(&text),
+            text,
+            citations,
+            dropped,
+        }
+    }
+}
+
+fn string_field(chunk: &RetrievedChunk, key: &str) -> Option<String> {
+    chunk
+        .metadata
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn group_key(chunk: &RetrievedChunk) -> String {
+    string_field(chunk, "table")
+        .or_else(|| string_field(chunk, "parent_id"))
+        .unwrap_or_else(|| chunk.id.clone())
+}
+
+fn shingles(text: &str) -> HashSet<String> {
+    let words: Vec<String> = text.split_whitespace().map(|w| w.to_lowercase()).collect();
+    if words.len() < 3 {
+        return words.into_iter().collect();
+    }
+    words.windows(3).map(|w| w.join(" ")).collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    intersection as f32 / (a.len() + b.len() - intersection) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn chunk(id: &str, content: &str, score: f32, table: &str) -> RetrievedChunk {
+        let mut metadata = HashMap::new();
+        metadata.insert("table".to_string(), Value::from(table));
+        RetrievedChunk {
+            id: id.to_string(),
+            content: content.to_string(),
+            source: SourceType::Table,
+            score,
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_assembler_dedups_groups_and_respects_budget() {
+        let result = RetrievalResult {
+            query: "revenue".to_string(),
+            chunks: vec![
+                chunk(
+                    "orders#0",
+                    "orders holds one row per order with its total",
+                    0.9,
+                    "orders",
+                ),
+                chunk(
+                    "customers#0",
+                    "customers holds one row per customer",
+                    0.8,
+                    "customers",
+                ),
+                chunk(
+                    "orders#1",
+                    "orders holds one row per order with its total",
+                    0.7,
+                    "orders",
+                ),
+                chunk(
+                    "orders#2",
+                    "orders.total is the amount in cents",
+                    0.6,
+                    "orders",
+                ),
+                chunk("events#0", &"click ".repeat(500), 0.5, "events"),
+            ],
+            total_results: 5,
+        };
+
+        let words = |text: &str| text.split_whitespace().count();
+        let context = ContextAssembler::new(80)
+            .with_counter(Arc::new(words))
+            .assemble(&result);
+
+        assert_eq!(context.dropped, 2);
+        assert!(context.tokens <= 80);
+        let ids: Vec<&str> = context.citations.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["orders#0", "orders#2", "customers#0"]);
+        assert!(
+            context.text.find("### orders").unwrap() < context.text.find("### customers").unwrap()
+        );
+        assert!(context.text.contains("[3] customers holds"));
+    }
+}
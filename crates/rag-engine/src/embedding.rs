@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::tokenize::tokenize;
+
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifies the model; vectors from different models are not comparable.
+    fn model(&self) -> &str;
+
+    fn dimension(&self) -> usize;
+
+    /// Largest number of texts sent in one `embed_batch` call.
+    fn batch_size(&self) -> usize {
+        32
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error>;
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        self.embed_batch(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| Error::Embedding("embedder returned no vector".to_string()))
+    }
+
+    /// Embeds `texts` in batches of at most [`batch_size`](Self::batch_size).
+    async fn embed_all(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size().max(1)) {
+            vectors.extend(self.embed_batch(batch).await?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// Client for OpenAI-compatible `/v1/embeddings` endpoints (OpenAI, Ollama,
+/// vLLM, llama.cpp and similar local servers).
+pub struct OpenAIEmbedder {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    dimension: usize,
+    batch_size: usize,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbedder {
+    /// `base_url` includes the version prefix, e.g. `http://localhost:11434/v1`.
+    pub fn new(base_url: &str, model: &str, dimension: usize) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key: None,
+            dimension,
+            batch_size: 64,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    fn parse_response(
+        &self,
+        body: EmbeddingResponse,
+        expected: usize,
+    ) -> Result<Vec<Vec<f32>>, Error> {
+        let mut data = body.data;
+        if data.len() != expected {
+            return Err(Error::Embedding(format!(
+                "expected {} embeddings, got {}",
+                expected,
+                data.len()
+            )));
+        }
+        data.sort_by_key(|d| d.index);
+        data.into_iter()
+            .map(|d| {
+                if d.embedding.len() == self.dimension {
+                    Ok(d.embedding)
+                } else {
+                    Err(Error::Embedding(format!(
+                        "expected dimension {}, got {}",
+                        self.dimension,
+                        d.embedding.len()
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAIEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&EmbeddingRequest {
+                model: &self.model,
+                input: texts,
+            });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::Embedding(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Embedding(format!(
+                "embedding request failed with {}: {}",
+                status, body
+            )));
+        }
+        let body: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Embedding(e.to_string()))?;
+        self.parse_response(body, texts.len())
+    }
+}
+
+/// Deterministic feature-hashing embedder for tests and air-gapped setups.
+/// Words (with snake_case and camelCase parts) and character trigrams are
+/// hashed into signed buckets and the result is L2-normalized.
+pub struct HashingEmbedder {
+    dimension: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimension];
+        if self.dimension == 0 {
+            return vector;
+        }
+        let mut add = |feature: &str, weight: f32| {
+            let hash = fnv1a(feature);
+            let bucket = (hash % self.dimension as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * weight;
+        };
+
+        for word in tokenize(text) {
+            add(&word, 1.0);
+            let padded: Vec<char> = format!("#{}#", word).chars().collect();
+            for trigram in padded.windows(3) {
+                add(&trigram.iter().collect::<String>(), 0.5);
+            }
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    fn model(&self) -> &str {
+        "hashing-fnv1a"
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_hashing_embedder() {
+        let embedder = HashingEmbedder::new(256);
+        let texts = vec![
+            "customer lifetime value".to_string(),
+            "dim_customer_v2 table".to_string(),
+            "weather forecast".to_string(),
+        ];
+        let vectors = embedder.embed_all(&texts).await.unwrap();
+        assert_eq!(vectors.len(), 3);
+        assert_eq!(vectors[0], embedder.embed(&texts[0]).await.unwrap());
+
+        let query = embedder.embed_text("customer");
+        assert!(cosine(&query, &vectors[1]) > cosine(&query, &vectors[2]));
+        assert!((cosine(&vectors[0], &vectors[0]) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_openai_response_is_ordered_and_checked() {
+        let embedder = OpenAIEmbedder::new("http://localhost:11434/v1/", "nomic-embed-text", 2);
+        let body: EmbeddingResponse = serde_json::from_str(
+            r#"{"data": [{"index": 1, "embedding": [0.0, 1.0]}, {"index": 0, "embedding": [1.0, 0.0]}]}"#,
+        )
+        .unwrap();
+        let vectors = embedder.parse_response(body, 2).unwrap();
+        assert_eq!(vectors[0], vec![1.0, 0.0]);
+        assert_eq!(embedder.base_url, "http://localhost:11434/v1");
+
+        let body: EmbeddingResponse =
+            serde_json::from_str(r#"{"data": [{"index": 0, "embedding": [1.0]}]}"#).unwrap();
+        assert!(embedder.parse_response(body, 1).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Predicate over the metadata a chunk was indexed with. Keys may use dots to
+/// reach nested objects, e.g. `owner.team`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataFilter {
+    Eq(String, Value),
+    /// Also matches chunks without the key, so `deprecated != true` keeps
+    /// chunks that were never flagged.
+    Ne(String, Value),
+    In(String, Vec<Value>),
+    NotIn(String, Vec<Value>),
+    Exists(String),
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Not(Box<MetadataFilter>),
+}
+
+impl MetadataFilter {
+    pub fn eq(key: &str, value: impl Into<Value>) -> Self {
+        MetadataFilter::Eq(key.to_string(), value.into())
+    }
+
+    pub fn ne(key: &str, value: impl Into<Value>) -> Self {
+        MetadataFilter::Ne(key.to_string(), value.into())
+    }
+
+    pub fn is_in(key: &str, values: Vec<Value>) -> Self {
+        MetadataFilter::In(key.to_string(), values)
+    }
+
+    pub fn and(self, other: MetadataFilter) -> Self {
+        match self {
+            MetadataFilter::And(mut filters) => {
+                filters.push(other);
+                MetadataFilter::And(filters)
+            }
+            filter => MetadataFilter::And(vec![filter, other]),
+        }
+    }
+
+    pub fn matches(&self, metadata: &Value) -> bool {
+        match self {
+            MetadataFilter::Eq(key, value) => lookup(metadata, key).is_some_and(|v| v == value),
+            MetadataFilter::Ne(key, value) => lookup(metadata, key).is_none_or(|v| v != value),
+            MetadataFilter::In(key, values) => {
+                lookup(metadata, key).is_some_and(|v| values.contains(v))
+            }
+            MetadataFilter::NotIn(key, values) => {
+                lookup(metadata, key).is_none_or(|v| !values.contains(v))
+            }
+            MetadataFilter::Exists(key) => lookup(metadata, key).is_some_and(|v| !v.is_null()),
+            MetadataFilter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            MetadataFilter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            MetadataFilter::Not(filter) => !filter.matches(metadata),
+        }
+    }
+
+    /// Parses expressions such as
+    /// `schema = 'finance' AND owner IN ['ana', 'li'] AND deprecated != true`.
+    /// Supports `=`, `!=`, `IN`, `NOT IN`, `EXISTS`, `AND`, `OR`, `NOT` and
+    /// parentheses; values are quoted strings, numbers, booleans or `null`.
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        let tokens = lex(expression)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(filter),
+            Some(token) => Err(filter_error(&format!("unexpected {:?}", token))),
+        }
+    }
+}
+
+fn lookup<'a>(metadata: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(metadata, |value, part| value.as_object()?.get(part))
+}
+
+fn filter_error(message: &str) -> Error {
+    Error::Rag(format!("invalid metadata filter: {}", message))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Literal(Value),
+    Eq,
+    Ne,
+    Comma,
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, Error> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '=' => {
+                tokens.push(Token::Eq);
+                i += if chars.get(i + 1) == Some(&'=') { 2 } else { 1 };
+            }
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Ne);
+                i += 2;
+            }
+            ',' | '(' | ')' | '[' | ']' => {
+                tokens.push(match c {
+                    ',' => Token::Comma,
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    '[' => Token::OpenList,
+                    _ => Token::CloseList,
+                });
+                i += 1;
+            }
+            '\'' | '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or_else(|| filter_error("unterminated string"))?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                tokens.push(Token::Literal(Value::String(text)));
+                i += end + 2;
+            }
+            _ if c.is_alphanumeric() || matches!(c, '_' | '.' | '-') => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '-'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    _ => match serde_json::from_str::<serde_json::Number>(&word) {
+                        Ok(number) => Token::Literal(Value::Number(number)),
+                        Err(_) => Token::Word(word),
+                    },
+                });
+            }
+            _ => return Err(filter_error(&format!("unexpected character '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<MetadataFilter, Error> {
+        let mut filters = vec![self.and()?];
+        while self.keyword("or") {
+            filters.push(self.and()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            MetadataFilter::Or(filters)
+        })
+    }
+
+    fn and(&mut self) -> Result<MetadataFilter, Error> {
+        let mut filters = vec![self.unary()?];
+        while self.keyword("and") {
+            filters.push(self.unary()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            MetadataFilter::And(filters)
+        })
+    }
+
+    fn unary(&mut self) -> Result<MetadataFilter, Error> {
+        if self.keyword("not") {
+            return Ok(MetadataFilter::Not(Box::new(self.unary()?)));
+        }
+        if self.tokens.get(self.pos) == Some(&Token::Open) {
+            self.pos += 1;
+            let filter = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(filter),
+                _ => Err(filter_error("expected ')'")),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<MetadataFilter, Error> {
+        let key = match self.next() {
+            Some(Token::Word(key)) => key,
+            other => return Err(filter_error(&format!("expected a key, found {:?}", other))),
+        };
+        if self.keyword("exists") {
+            return Ok(MetadataFilter::Exists(key));
+        }
+        if self.keyword("in") {
+            return Ok(MetadataFilter::In(key, self.list()?));
+        }
+        if self.keyword("not") {
+            if !self.keyword("in") {
+                return Err(filter_error("expected IN after NOT"));
+            }
+            return Ok(MetadataFilter::NotIn(key, self.list()?));
+        }
+        match self.next() {
+            Some(Token::Eq) => Ok(MetadataFilter::Eq(key, self.value()?)),
+            Some(Token::Ne) => Ok(MetadataFilter::Ne(key, self.value()?)),
+            other => Err(filter_error(&format!(
+                "expected an operator after {}, found {:?}",
+                key, other
+            ))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(value),
+            // Bare words are accepted as strings: `database = analytics`.
+            Some(Token::Word(word)) => Ok(Value::String(word)),
+            other => Err(filter_error(&format!(
+                "expected a value, found {:?}",
+                other
+            ))),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<Value>, Error> {
+        let close = match self.next() {
+            Some(Token::OpenList) => Token::CloseList,
+            Some(Token::Open) => Token::Close,
+            _ => return Err(filter_error("expected a list")),
+        };
+        let mut values = Vec::new();
+        if self.tokens.get(self.pos) == Some(&close) {
+            self.pos += 1;
+            return Ok(values);
+        }
+        loop {
+            values.push(self.value()?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(token) if token == close => return Ok(values),
+                _ => return Err(filter_error("unterminated list")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_matches() {
+        let metadata = json!({"schema": "finance", "owner": {"team": "data"}, "rows": 10});
+        assert!(MetadataFilter::eq("schema", "finance").matches(&metadata));
+        assert!(MetadataFilter::eq("owner.team", "data").matches(&metadata));
+        assert!(MetadataFilter::ne("deprecated", true).matches(&metadata));
+        assert!(!MetadataFilter::eq("schema", "finance")
+            .and(MetadataFilter::is_in("rows", vec![json!(1), json!(2)]))
+            .matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_parse() {
+        let filter = MetadataFilter::parse(
+            "schema = 'finance' AND owner IN ['ana', \"li\"] AND (deprecated != true OR NOT rows exists)",
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            MetadataFilter::And(vec![
+                MetadataFilter::eq("schema", "finance"),
+                MetadataFilter::is_in("owner", vec![json!("ana"), json!("li")]),
+                MetadataFilter::Or(vec![
+                    MetadataFilter::ne("deprecated", true),
+                    MetadataFilter::Not(Box::new(MetadataFilter::Exists("rows".to_string()))),
+                ]),
+            ])
+        );
+        assert_eq!(
+            MetadataFilter::parse("database = analytics").unwrap(),
+            MetadataFilter::eq("database", "analytics")
+        );
+        assert_eq!(
+            MetadataFilter::parse("rows not in (1, 2.5)").unwrap(),
+            MetadataFilter::NotIn("rows".to_string(), vec![json!(1), json!(2.5)])
+        );
+        assert!(MetadataFilter::parse("schema = ").is_err());
+        assert!(MetadataFilter::parse("schema = 'x' extra").is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Neighbours per node on upper layers; layer 0 keeps twice as many.
+    pub m: usize,
+    pub ef_construction: usize,
+    pub ef_search: usize,
+    pub seed: u64,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// Similarity paired with a slot, ordered by similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Scored(pub f32, pub usize);
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Keeps the `k` highest-scoring items without sorting everything.
+pub(crate) fn top_k(scores: impl Iterator<Item = Scored>, k: usize) -> Vec<Scored> {
+    let (lower, upper) = scores.size_hint();
+    let capacity = k.min(upper.unwrap_or(lower));
+    let mut heap: BinaryHeap<Reverse<Scored>> = BinaryHeap::with_capacity(capacity);
+    for scored in scores {
+        if heap.len() < k {
+            heap.push(Reverse(scored));
+        } else if heap.peek().is_some_and(|Reverse(min)| scored > *min) {
+            heap.pop();
+            heap.push(Reverse(scored));
+        }
+    }
+    let mut top: Vec<Scored> = heap.into_iter().map(|Reverse(s)| s).collect();
+    top.sort_by(|a, b| b.cmp(a));
+    top
+}
+
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub(crate) fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = dot(&vector, &vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Unit vectors by id, owned by the caller and shared with the graph.
+pub type Vectors = HashMap<String, Vec<f32>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    /// Only set on tombstones, whose vector is gone from the caller's map.
+    #[serde(default)]
+    vector: Option<Vec<f32>>,
+    layers: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Hierarchical navigable small world graph over unit vectors, scored by dot
+/// product. The graph stores ids only and reads vectors from the caller's
+/// [`Vectors`]. Deletes leave tombstones that still route searches; the graph
+/// is rebuilt once tombstones outnumber live nodes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    config: HnswConfig,
+    nodes: Vec<Node>,
+    slots: HashMap<String, usize>,
+    entry: Option<usize>,
+    max_level: usize,
+    rng: u64,
+}
+
+impl HnswIndex {
+    pub fn new(config: HnswConfig) -> Self {
+        Self {
+            rng: config.seed.max(1),
+            config,
+            nodes: Vec::new(),
+            slots: HashMap::new(),
+            entry: None,
+            max_level: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Links `id`, whose unit vector must already be in `vectors`. An id that
+    /// is already linked has to be [`remove`](Self::remove)d first.
+    pub fn insert(&mut self, id: String, vectors: &Vectors) {
+        let Some(query) = vectors.get(&id) else {
+            return;
+        };
+        if self.slots.contains_key(&id) {
+            return;
+        }
+
+        let level = self.random_level();
+        let slot = self.nodes.len();
+        self.nodes.push(Node {
+            id: id.clone(),
+            vector: None,
+            layers: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.slots.insert(id, slot);
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(slot);
+            self.max_level = level;
+            return;
+        };
+
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.greedy(query, entry, layer, vectors);
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates =
+                self.search_layer(query, entry, self.config.ef_construction, layer, vectors);
+            let capacity = self.capacity(layer);
+            let neighbours: Vec<usize> = candidates.iter().take(capacity).map(|s| s.1).collect();
+            for &neighbour in &neighbours {
+                self.nodes[neighbour].layers[layer].push(slot);
+                if self.nodes[neighbour].layers[layer].len() > capacity {
+                    self.prune(neighbour, layer, capacity, vectors);
+                }
+            }
+            self.nodes[slot].layers[layer] = neighbours;
+            if let Some(best) = candidates.first() {
+                entry = best.1;
+            }
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry = Some(slot);
+        }
+    }
+
+    /// Unlinks `id`. Its `vector`, already taken out of `vectors`, is kept on
+    /// the tombstone until the next rebuild.
+    pub fn remove(&mut self, id: &str, vector: Vec<f32>, vectors: &Vectors) -> bool {
+        let Some(slot) = self.slots.remove(id) else {
+            return false;
+        };
+        self.nodes[slot].deleted = true;
+        self.nodes[slot].vector = Some(vector);
+        if self.nodes.len() > 2 * self.slots.len() + 16 {
+            self.rebuild(vectors);
+        }
+        true
+    }
+
+    /// The `k` stored ids most similar to the unit vector `query`.
+    pub fn search(&self, query: &[f32], k: usize, vectors: &Vectors) -> Vec<(String, f32)> {
+        let Some(mut entry) = self.entry else {
+            return vec![];
+        };
+        if k == 0 {
+            return vec![];
+        }
+        for layer in (1..=self.max_level).rev() {
+            entry = self.greedy(query, entry, layer, vectors);
+        }
+        let tombstones = self.nodes.len() - self.slots.len();
+        let ef = self
+            .config
+            .ef_search
+            .max(k)
+            .saturating_add(tombstones.min(self.config.ef_search));
+        self.search_layer(query, entry, ef, 0, vectors)
+            .into_iter()
+            .filter(|s| !self.nodes[s.1].deleted)
+            .take(k)
+            .map(|s| (self.nodes[s.1].id.clone(), s.0))
+            .collect()
+    }
+
+    fn capacity(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m * 2
+        } else {
+            self.config.m
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545f4914f6cdd1d) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.config.m.max(2) as f64).ln();
+        ((-uniform.ln() * scale) as usize).min(16)
+    }
+
+    fn vector<'a>(&'a self, slot: usize, vectors: &'a Vectors) -> Option<&'a [f32]> {
+        let node = &self.nodes[slot];
+        node.vector
+            .as_deref()
+            .or_else(|| vectors.get(&node.id).map(Vec::as_slice))
+    }
+
+    fn similarity(&self, query: &[f32], slot: usize, vectors: &Vectors) -> f32 {
+        self.vector(slot, vectors)
+            .map_or(f32::MIN, |vector| dot(query, vector))
+    }
+
+    fn greedy(&self, query: &[f32], mut current: usize, layer: usize, vectors: &Vectors) -> usize {
+        let mut best = self.similarity(query, current, vectors);
+        loop {
+            let mut improved = false;
+            for &neighbour in self.neighbours(current, layer) {
+                let score = self.similarity(query, neighbour, vectors);
+                if score > best {
+                    best = score;
+                    current = neighbour;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    fn neighbours(&self, slot: usize, layer: usize) -> &[usize] {
+        self.nodes[slot]
+            .layers
+            .get(layer)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Best-first search of one layer, returning up to `ef` nodes sorted by
+    /// descending similarity.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry: usize,
+        ef: usize,
+        layer: usize,
+        vectors: &Vectors,
+    ) -> Vec<Scored> {
+        let start = Scored(self.similarity(query, entry, vectors), entry);
+        let mut visited = HashSet::from([entry]);
+        let mut candidates = BinaryHeap::from([start]);
+        let mut results = BinaryHeap::from([Reverse(start)]);
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map_or(f32::MIN, |Reverse(w)| w.0);
+            if candidate.0 < worst && results.len() >= ef {
+                break;
+            }
+            for &neighbour in self.neighbours(candidate.1, layer) {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let scored = Scored(self.similarity(query, neighbour, vectors), neighbour);
+                let worst = results.peek().map_or(f32::MIN, |Reverse(w)| w.0);
+                if results.len() < ef || scored.0 > worst {
+                    candidates.push(scored);
+                    results.push(Reverse(scored));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut results: Vec<Scored> = results.into_iter().map(|Reverse(s)| s).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        results
+    }
+
+    fn prune(&mut self, slot: usize, layer: usize, capacity: usize, vectors: &Vectors) {
+        let Some(vector) = self.vector(slot, vectors) else {
+            return;
+        };
+        let scored = self.nodes[slot].layers[layer]
+            .iter()
+            .map(|&n| Scored(self.similarity(vector, n, vectors), n));
+        let kept = top_k(scored, capacity).into_iter().map(|s| s.1).collect();
+        self.nodes[slot].layers[layer] = kept;
+    }
+
+    fn rebuild(&mut self, vectors: &Vectors) {
+        let live: Vec<Node> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .filter(|node| !node.deleted)
+            .collect();
+        self.slots.clear();
+        self.entry = None;
+        self.max_level = 0;
+        for node in live {
+            self.insert(node.id, vectors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        let mut state = 7u64;
+        (0..count)
+            .map(|_| {
+                let vector = (0..dimension)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                        ((state >> 33) as f32 / u32::MAX as f32) - 0.25
+                    })
+                    .collect();
+                normalize(vector)
+            })
+            .collect()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> (HnswIndex, Vectors) {
+        let stored: Vectors = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, vector)| (i.to_string(), vector.clone()))
+            .collect();
+        let mut index = HnswIndex::new(HnswConfig::default());
+        for i in 0..vectors.len() {
+            index.insert(i.to_string(), &stored);
+        }
+        (index, stored)
+    }
+
+    #[test]
+    fn test_hnsw_recall_against_brute_force() {
+        let vectors = random_vectors(1000, 16);
+        let (index, stored) = build(&vectors);
+
+        let mut hits = 0;
+        for query in vectors.iter().take(50) {
+            let exact = top_k(
+                vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| Scored(dot(query, v), i)),
+                10,
+            );
+            let approximate: Vec<String> = index
+                .search(query, 10, &stored)
+                .into_iter()
+                .map(|r| r.0)
+                .collect();
+            hits += exact
+                .iter()
+                .filter(|s| approximate.contains(&s.1.to_string()))
+                .count();
+        }
+        assert!(hits as f32 / 500.0 > 0.9, "recall {}", hits as f32 / 500.0);
+    }
+
+    #[test]
+    fn test_hnsw_delete_and_update() {
+        let vectors = random_vectors(200, 8);
+        let (mut index, mut stored) = build(&vectors);
+
+        let removed = stored.remove("0").unwrap();
+        assert!(index.remove("0", removed.clone(), &stored));
+        assert!(!index.remove("0", removed, &stored));
+        assert!(index
+            .search(&vectors[0], 5, &stored)
+            .iter()
+            .all(|(id, _)| id != "0"));
+
+        let old = stored.insert("1".to_string(), vectors[2].clone()).unwrap();
+        index.remove("1", old, &stored);
+        index.insert("1".to_string(), &stored);
+        assert_eq!(index.len(), 199);
+        let top = index.search(&vectors[2], 2, &stored);
+        assert!(top.iter().any(|(id, _)| id == "1"));
+
+        for i in 1..150 {
+            let vector = stored.remove(&i.to_string()).unwrap();
+            index.remove(&i.to_string(), vector, &stored);
+        }
+        assert_eq!(index.len(), 50);
+        assert_eq!(index.search(&vectors[199], 1, &stored)[0].0, "199");
+    }
+}
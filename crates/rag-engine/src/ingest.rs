@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::backend::{DocumentRequest, RagBackend};
+use crate::chunking::DocumentFormat;
+use crate::error::Error;
+use crate::retrieval::SourceType;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+    pub chunks: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Indexes the markdown, HTML, PDF and plain-text files under `root` as
+/// documentation, keyed by their canonical path so that different roots
+/// never share ids. Chunks are tagged with `source_path`, `content_hash` and
+/// their heading path; files whose hash is unchanged since the last run are
+/// skipped, and chunks of files that disappeared are removed. Files that fail
+/// to read, extract or index are reported and skipped, keeping their previous
+/// chunks. Works against a local or a remote index alike.
+pub async fn ingest_directory(rag: &dyn RagBackend, root: &Path) -> Result<IngestReport, Error> {
+    let io_error = |e: std::io::Error| Error::Rag(format!("{}: {}", root.display(), e));
+    let root = root.canonicalize().map_err(io_error)?;
+    let mut files = Vec::new();
+    walk(&root, &mut files, &mut HashSet::new()).map_err(io_error)?;
+    files.sort();
+
+    let mut known: HashMap<String, (String, Vec<String>)> = HashMap::new();
+    for (id, metadata) in rag.metadata(SourceType::Documentation).await? {
+        let (Some(path), Some(hash)) = (
+            metadata.get("source_path").and_then(Value::as_str),
+            metadata.get("content_hash").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        if Path::new(path).starts_with(&root) {
+            let entry = known
+                .entry(path.to_string())
+                .or_insert_with(|| (hash.to_string(), Vec::new()));
+            entry.1.push(id);
+        }
+    }
+
+    let mut report = IngestReport::default();
+    let mut seen = HashSet::new();
+    for file in files {
+        let Some(format) = format_of(&file) else {
+            continue;
+        };
+        let path = file.display().to_string();
+        seen.insert(path.clone());
+
+        let bytes = match std::fs::read(&file) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                report.failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+        let hash = hex::encode(Sha256::digest(&bytes));
+        let previous = known.get(&path);
+        if previous.is_some_and(|(known_hash, _)| *known_hash == hash) {
+            report.unchanged += 1;
+            continue;
+        }
+        let (text, format) = match extract_text(&bytes, format) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                report.failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+
+        let indexed = rag
+            .index_document(DocumentRequest {
+                source: SourceType::Documentation,
+                parent_id: path.clone(),
+                text,
+                format,
+                metadata: serde_json::json!({
+                    "source_path": path,
+                    "content_hash": hash,
+                }),
+            })
+            .await;
+        let count = match indexed {
+            Ok(count) => count,
+            Err(e) => {
+                report.failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+        report.chunks += count;
+        // Chunks are re-indexed under the same `<path>#<n>` ids, so only the
+        // ones past the new chunk count are stale.
+        if let Some((_, ids)) = previous {
+            let fresh: HashSet<String> = (0..count).map(|n| format!("{}#{}", path, n)).collect();
+            if let Err(e) = delete_all(rag, ids.iter().filter(|id| !fresh.contains(*id))).await {
+                report.failed.push((path, e.to_string()));
+                continue;
+            }
+            report.updated.push(path);
+        } else {
+            report.added.push(path);
+        }
+    }
+
+    for (path, (_, ids)) in known {
+        if seen.contains(&path) {
+            continue;
+        }
+        match delete_all(rag, &ids).await {
+            Ok(()) => report.removed.push(path),
+            Err(e) => report.failed.push((path, e.to_string())),
+        }
+    }
+    report.removed.sort();
+    Ok(report)
+}
+
+async fn delete_all(
+    rag: &dyn RagBackend,
+    ids: impl IntoIterator<Item = &String>,
+) -> Result<(), Error> {
+    for id in ids {
+        rag.delete(SourceType::Documentation, id).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    Markdown,
+    Html,
+    Pdf,
+    Text,
+}
+
+fn format_of(path: &Path) -> Option<FileFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => Some(FileFormat::Markdown),
+        "html" | "htm" => Some(FileFormat::Html),
+        "pdf" => Some(FileFormat::Pdf),
+        "txt" => Some(FileFormat::Text),
+        _ => None,
+    }
+}
+
+/// Collects files below `dir`, following symlinks but entering each
+/// directory only once so that link cycles terminate.
+fn walk(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    visited: &mut HashSet<PathBuf>,
+) -> std::io::Result<()> {
+    if !visited.insert(dir.canonicalize()?) {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, files, visited)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn extract_text(bytes: &[u8], format: FileFormat) -> Result<(String, DocumentFormat), Error> {
+    let utf8 = || String::from_utf8_lossy(bytes).into_owned();
+    match format {
+        FileFormat::Markdown => Ok((utf8(), DocumentFormat::Markdown)),
+        FileFormat::Text => Ok((utf8(), DocumentFormat::Text)),
+        FileFormat::Html => Ok((html_to_markdown(&utf8()), DocumentFormat::Markdown)),
+        FileFormat::Pdf => pdf_extract::extract_text_from_mem(bytes)
+            .map(|text| (text, DocumentFormat::Text))
+            .map_err(|e| Error::Rag(format!("PDF extraction failed: {}", e))),
+    }
+}
+
+/// Reduces an HTML page to markdown-ish text: headings become `#` lines so
+/// the chunker keeps the hierarchy, block elements become paragraph breaks
+/// and scripts, styles and comments are dropped.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        push_text(&mut out, &rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = rest[1..end].trim();
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "script" | "style" | "head" if !closing => {
+                let close = format!("</{}", name);
+                rest = find_ignore_case(rest, &close)
+                    .and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+                    .unwrap_or("");
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if closing {
+                    out.push_str("\n\n");
+                } else {
+                    let level = name[1..].parse().unwrap_or(1);
+                    out.push_str(&format!("\n\n{} ", "#".repeat(level)));
+                }
+            }
+            "li" if !closing => out.push_str("\n- "),
+            "br" => out.push('\n'),
+            "p" | "pre" | "div" | "section" | "article" | "ul" | "ol" | "table" | "tr"
+            | "blockquote" => out.push_str("\n\n"),
+            "td" | "th" => out.push_str(" | "),
+            _ => {}
+        }
+    }
+    push_text(&mut out, rest);
+
+    let mut cleaned = String::new();
+    let mut blank = false;
+    for line in out.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank = !cleaned.is_empty();
+            continue;
+        }
+        if blank {
+            cleaned.push('\n');
+            blank = false;
+        }
+        cleaned.push_str(line.trim_start_matches(' '));
+        cleaned.push('\n');
+    }
+    cleaned
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(needle)
+}
+
+fn push_text(out: &mut String, text: &str) {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        if !text.is_empty() && !out.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        return;
+    }
+    if text.starts_with(char::is_whitespace) && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(&decode_entities(&collapsed));
+    if text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RetrieveRequest;
+    use crate::embedding::HashingEmbedder;
+    use crate::retrieval::RAGService;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = "<html><head><title>x</title><style>p{}</style></head><body>\
+            <h1>Orders</h1><p>One row per <b>order</b> &amp; refund.</p>\
+            <!-- hidden --><script>alert(1)</script>\
+            <h2>Columns</h2><ul><li>order_id</li><li>total</li></ul></body></html>";
+        assert_eq!(
+            html_to_markdown(html),
+            "# Orders\n\nOne row per order & refund.\n\n## Columns\n\n- order_id\n- total\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_directory_is_incremental() {
+        let root = std::env::temp_dir().join(format!("rag-ingest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("wiki")).unwrap();
+        std::fs::write(
+            root.join("wiki/orders.md"),
+            "# Orders\n\nOne row per order.",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("refunds.html"),
+            "<h1>Refunds</h1><p>Refunds are negative totals.</p>",
+        )
+        .unwrap();
+        std::fs::write(root.join("image.png"), [0u8, 1, 2]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("wiki/loop")).unwrap();
+
+        let rag = RwLock::new(RAGService::with_embedder(Arc::new(HashingEmbedder::new(
+            64,
+        ))));
+        let report = ingest_directory(&rag, &root).await.unwrap();
+        assert_eq!(report.added.len(), 2);
+        assert_eq!(report.chunks, 2);
+
+        let result = rag
+            .retrieve(RetrieveRequest::new("refunds", 1))
+            .await
+            .unwrap();
+        let refunds = root.canonicalize().unwrap().join("refunds.html");
+        assert_eq!(result.chunks[0].id, format!("{}#0", refunds.display()));
+        assert_eq!(result.chunks[0].metadata["heading"], "Refunds");
+
+        std::fs::write(
+            root.join("wiki/orders.md"),
+            "# Orders\n\nOne row per order line.",
+        )
+        .unwrap();
+        std::fs::remove_file(root.join("refunds.html")).unwrap();
+        let report = ingest_directory(&rag, &root).await.unwrap();
+        assert_eq!(report.updated.len(), 1);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.unchanged, 0);
+
+        let report = ingest_directory(&rag, &root).await.unwrap();
+        assert_eq!(report.unchanged, 1);
+        assert!(report.added.is_empty() && report.updated.is_empty());
+
+        let without_embedder = RwLock::new(RAGService::new(64));
+        let report = ingest_directory(&without_embedder, &root).await.unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.added.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
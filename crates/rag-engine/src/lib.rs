@@ -1,9 +1,41 @@
+pub mod api;
+pub mod backend;
+pub mod bm25;
 pub mod cache;
+pub mod chunking;
+pub mod context;
+pub mod embedding;
 pub mod error;
+pub mod filter;
+pub mod hnsw;
+pub mod ingest;
+pub mod query_log;
+pub mod remote;
+pub mod rerank;
 pub mod retrieval;
+pub mod store;
+pub mod tokenize;
 pub mod types;
 
+pub use backend::{DocumentRequest, IndexItem, RagBackend, RetrieveRequest};
+pub use bm25::{reciprocal_rank_fusion, Bm25Index};
 pub use cache::Cache;
+pub use chunking::{Chunk, Chunker, DocumentFormat};
+pub use context::{ApproxTokenCounter, AssembledContext, Citation, ContextAssembler, TokenCounter};
+pub use embedding::{Embedder, HashingEmbedder, OpenAIEmbedder};
 pub use error::Error;
-pub use retrieval::{RAGService, RetrievalResult, RetrievedChunk};
+pub use filter::MetadataFilter;
+pub use hnsw::{HnswConfig, HnswIndex};
+pub use ingest::{ingest_directory, IngestReport};
+pub use query_log::{
+    index_query_examples, mine_queries, parse_sql_log, read_pg_stat_statements, QueryExample,
+    QueryLog, QueryRecord,
+};
+pub use remote::RemoteRagClient;
+pub use rerank::{calibrate_scores, HeuristicReranker, LlmReranker, Reranker};
+pub use retrieval::{
+    IndexStats, RAGService, RetrievalResult, RetrievedChunk, SourceStats, SourceType, MAX_K,
+};
+pub use store::{StoreHeader, StoredVector, VectorStore};
+pub use tokenize::tokenize;
 pub use types::VectorIndex;
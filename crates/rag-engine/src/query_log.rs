@@ -0,0 +1,506 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlparser::ast::{
+    visit_expressions, visit_expressions_mut, visit_relations, Expr, Statement, Value,
+};
+use sqlparser::dialect::Dialect as SqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
+use warehouse_conn::{parser_dialect, Backend, Warehouse};
+
+use crate::backend::{IndexItem, RagBackend};
+use crate::chunking::sql_statements;
+use crate::error::Error;
+use crate::retrieval::SourceType;
+
+const PG_STAT_STATEMENTS: &str = "SELECT query, calls FROM pg_stat_statements \
+     WHERE dbid = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+     AND (query ILIKE 'select%' OR query ILIKE 'with%') \
+     ORDER BY calls DESC LIMIT ";
+
+/// One query as found in a history source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRecord {
+    pub sql: String,
+    pub calls: u64,
+    pub question: Option<String>,
+    /// Where the record came from, e.g. `pg_stat_statements`, `log` or `agent`.
+    pub origin: String,
+    /// The user who ran the query, for agent runs.
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+impl QueryRecord {
+    pub fn new(sql: &str, origin: &str) -> Self {
+        Self {
+            sql: sql.to_string(),
+            calls: 1,
+            question: None,
+            origin: origin.to_string(),
+            user: None,
+        }
+    }
+
+    pub fn with_calls(mut self, calls: u64) -> Self {
+        self.calls = calls;
+        self
+    }
+
+    /// The natural-language question the query answered, for agent runs.
+    pub fn with_question(mut self, question: &str) -> Self {
+        self.question = Some(question.to_string());
+        self
+    }
+
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+}
+
+/// A deduplicated query with literals replaced by `?`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryExample {
+    pub fingerprint: String,
+    pub sql: String,
+    pub tables: Vec<String>,
+    pub columns: Vec<String>,
+    pub calls: u64,
+    pub questions: Vec<String>,
+    pub origins: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<String>,
+}
+
+impl QueryExample {
+    /// Text indexed for retrieval: questions and tables as SQL comments
+    /// followed by the query.
+    pub fn content(&self) -> String {
+        let mut content = String::new();
+        for question in &self.questions {
+            content.push_str(&format!("-- question: {}\n", question));
+        }
+        content.push_str(&format!("-- tables: {}\n", self.tables.join(", ")));
+        content.push_str(&self.sql);
+        content
+    }
+
+    /// Retrieval entry under `query:<fingerprint>`.
+    pub fn index_item(&self) -> IndexItem {
+        IndexItem {
+            id: format!("query:{}", self.fingerprint),
+            content: self.content(),
+            metadata: serde_json::json!({
+                "tables": self.tables,
+                "columns": self.columns,
+                "calls": self.calls,
+                "questions": self.questions,
+                "origins": self.origins,
+                "users": self.users,
+            }),
+        }
+    }
+
+    /// Adds the calls, questions, origins and users of another example of
+    /// the same query.
+    pub fn merge(&mut self, other: &QueryExample) {
+        self.calls += other.calls;
+        extend_unique(&mut self.questions, &other.questions);
+        extend_unique(&mut self.origins, &other.origins);
+        extend_unique(&mut self.users, &other.users);
+    }
+
+    /// Adds the history stored in the index metadata of this query, see
+    /// [`index_item`](Self::index_item).
+    pub fn merge_indexed(&mut self, metadata: &serde_json::Value) {
+        let strings = |key: &str| -> Vec<String> {
+            metadata[key]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        self.calls += metadata["calls"].as_u64().unwrap_or(0);
+        // Keep the indexed entries first so their order is stable.
+        for (merged, known) in [
+            (&mut self.questions, strings("questions")),
+            (&mut self.origins, strings("origins")),
+            (&mut self.users, strings("users")),
+        ] {
+            let mut values = known;
+            extend_unique(&mut values, merged);
+            *merged = values;
+        }
+    }
+}
+
+fn extend_unique(values: &mut Vec<String>, other: &[String]) {
+    for value in other {
+        if !values.contains(value) {
+            values.push(value.clone());
+        }
+    }
+}
+
+/// Indexes mined queries as [`SourceType::QueryExample`], replacing earlier
+/// versions of the same query.
+pub async fn index_query_examples(
+    rag: &dyn RagBackend,
+    examples: &[QueryExample],
+) -> Result<usize, Error> {
+    if examples.is_empty() {
+        return Ok(0);
+    }
+    let items = examples.iter().map(QueryExample::index_item).collect();
+    rag.upsert(SourceType::QueryExample, items).await
+}
+
+/// Accumulates query history from every source into the indexed examples,
+/// so a query seen again adds to the calls and questions already recorded
+/// for it. The index holds the history, so processes sharing a backend see
+/// each other's queries.
+pub struct QueryLog {
+    rag: std::sync::Arc<dyn RagBackend>,
+}
+
+impl QueryLog {
+    pub fn new(rag: std::sync::Arc<dyn RagBackend>) -> Self {
+        Self { rag }
+    }
+
+    /// Mines `records`, parsed in the dialect of `backend`, merges them with
+    /// the indexed history of the same queries and re-indexes them.
+    pub async fn record(&self, records: &[QueryRecord], backend: Backend) -> Result<usize, Error> {
+        let mut mined = mine_queries(records, parser_dialect(backend).as_ref());
+        if mined.is_empty() {
+            return Ok(0);
+        }
+        let indexed = self.rag.metadata(SourceType::QueryExample).await?;
+        for example in &mut mined {
+            if let Some(known) = indexed.get(&format!("query:{}", example.fingerprint)) {
+                example.merge_indexed(known);
+            }
+        }
+        index_query_examples(self.rag.as_ref(), &mined).await
+    }
+}
+
+/// Reads the most frequently called SELECT statements of the current
+/// database. Requires the `pg_stat_statements` extension.
+pub async fn read_pg_stat_statements(
+    warehouse: &dyn Warehouse,
+    limit: usize,
+) -> Result<Vec<QueryRecord>, Error> {
+    if warehouse.dialect().backend() != Backend::Postgres {
+        return Err(Error::Rag(format!(
+            "pg_stat_statements is not available on {}",
+            warehouse.dialect().name()
+        )));
+    }
+    let result = warehouse
+        .execute(&format!("{}{}", PG_STAT_STATEMENTS, limit))
+        .await
+        .map_err(|e| Error::Rag(e.to_string()))?;
+    Ok(result
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let sql = row.first()?.as_str()?;
+            let calls = row.get(1).and_then(|v| v.as_u64()).unwrap_or(1);
+            Some(QueryRecord::new(sql, "pg_stat_statements").with_calls(calls))
+        })
+        .collect())
+}
+
+/// Extracts statements from a PostgreSQL server log (`LOG:  statement:` and
+/// `LOG:  ... execute <name>:` entries, with indented continuation lines) or,
+/// when the text does not read as a log, from a plain `;`-separated SQL file.
+/// A text is a log when its first non-blank line carries a `LOG:` prefix.
+pub fn parse_sql_log(text: &str) -> Vec<QueryRecord> {
+    let statement_start = |line: &str| -> Option<usize> {
+        let log = line.find("LOG:")? + "LOG:".len();
+        let entry = &line[log..];
+        if let Some(i) = entry.find("statement: ") {
+            return Some(log + i + "statement: ".len());
+        }
+        let i = entry.find("execute ")?;
+        entry[i..].find(": ").map(|j| log + i + j + 2)
+    };
+    let is_log = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.contains("LOG:"));
+    if !is_log {
+        return sql_statements(text)
+            .iter()
+            .map(|sql| QueryRecord::new(sql, "log"))
+            .collect();
+    }
+
+    let mut records = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if let Some(start) = statement_start(line) {
+            records.extend(current.take());
+            current = Some(line[start..].to_string());
+        } else if line.starts_with(char::is_whitespace) && current.is_some() {
+            if let Some(sql) = &mut current {
+                sql.push('\n');
+                sql.push_str(line.trim());
+            }
+        } else {
+            records.extend(current.take());
+        }
+    }
+    records.extend(current);
+    records
+        .into_iter()
+        .map(|sql| QueryRecord::new(&sql, "log"))
+        .collect()
+}
+
+/// Parses, normalizes and deduplicates `records`, keeping read-only queries
+/// over user tables. Examples are ordered by total calls.
+pub fn mine_queries(records: &[QueryRecord], dialect: &dyn SqlDialect) -> Vec<QueryExample> {
+    let mut examples: HashMap<String, QueryExample> = HashMap::new();
+    for record in records {
+        let Some((sql, tables, columns)) = analyze(&record.sql, dialect) else {
+            continue;
+        };
+        let fingerprint = hex::encode(&Sha256::digest(sql.to_lowercase())[..8]);
+        let example = examples
+            .entry(fingerprint.clone())
+            .or_insert_with(|| QueryExample {
+                fingerprint,
+                sql,
+                tables,
+                columns,
+                calls: 0,
+                questions: Vec::new(),
+                origins: Vec::new(),
+                users: Vec::new(),
+            });
+        example.calls += record.calls;
+        if let Some(question) = &record.question {
+            if !example.questions.contains(question) {
+                example.questions.push(question.clone());
+            }
+        }
+        if !example.origins.contains(&record.origin) {
+            example.origins.push(record.origin.clone());
+        }
+        if let Some(user) = &record.user {
+            if !example.users.contains(user) {
+                example.users.push(user.clone());
+            }
+        }
+    }
+
+    let mut examples: Vec<QueryExample> = examples.into_values().collect();
+    examples.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.sql.cmp(&b.sql)));
+    examples
+}
+
+fn is_system_table(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.starts_with("pg_")
+        || name.starts_with("information_schema.")
+        || name.starts_with("pg_catalog.")
+        || name.starts_with("sqlite_")
+}
+
+/// Normalized SQL, tables and columns of a single SELECT statement.
+fn analyze(sql: &str, dialect: &dyn SqlDialect) -> Option<(String, Vec<String>, Vec<String>)> {
+    let mut statements = Parser::parse_sql(dialect, sql).ok()?;
+    if statements.len() != 1 {
+        return None;
+    }
+    let mut statement = statements.pop()?;
+    let Statement::Query(query) = &statement else {
+        return None;
+    };
+    let ctes: Vec<String> = query
+        .with
+        .iter()
+        .flat_map(|with| &with.cte_tables)
+        .map(|cte| cte.alias.name.value.to_lowercase())
+        .collect();
+
+    let mut tables = BTreeSet::new();
+    let _ = visit_relations(&statement, |relation| {
+        let name = relation.to_string();
+        if !ctes.contains(&name.to_lowercase()) {
+            tables.insert(name);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    if tables.is_empty() || tables.iter().any(|t| is_system_table(t)) {
+        return None;
+    }
+
+    let mut columns = BTreeSet::new();
+    let _ = visit_expressions(&statement, |expr| {
+        match expr {
+            Expr::Identifier(ident) => {
+                columns.insert(ident.value.clone());
+            }
+            Expr::CompoundIdentifier(parts) => {
+                if let Some(last) = parts.last() {
+                    columns.insert(last.value.clone());
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let _ = visit_expressions_mut(&mut statement, |expr| {
+        if let Expr::Value(value) = expr {
+            if !matches!(value, Value::Null | Value::Boolean(_)) {
+                *value = Value::Placeholder("?".to_string());
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    Some((
+        statement.to_string(),
+        tables.into_iter().collect(),
+        columns.into_iter().collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::PostgreSqlDialect;
+
+    #[test]
+    fn test_parse_postgres_log() {
+        let log = "2024-05-01 10:00:00 UTC [42] LOG:  statement: SELECT id\n\
+                   \tFROM orders WHERE total > 10\n\
+                   2024-05-01 10:00:01 UTC [42] LOG:  duration: 0.5 ms  execute <unnamed>: SELECT 1\n\
+                   2024-05-01 10:00:02 UTC [42] LOG:  checkpoint starting";
+        let records = parse_sql_log(log);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sql, "SELECT id\nFROM orders WHERE total > 10");
+        assert_eq!(records[1].sql, "SELECT 1");
+
+        let script = parse_sql_log(
+            "SELECT 1;\n-- LOG: statement: kept as a comment\nSELECT 'execute x: y';",
+        );
+        assert_eq!(script.len(), 2);
+        assert!(script[1].sql.contains("execute x: y"));
+    }
+
+    #[test]
+    fn test_mine_queries_normalizes_and_dedups() {
+        let records = vec![
+            QueryRecord::new(
+                "SELECT c.region, SUM(o.total) FROM orders o JOIN customers c ON o.customer_id = c.id WHERE o.status = 'paid' GROUP BY c.region",
+                "log",
+            ),
+            QueryRecord::new(
+                "select c.region, sum(o.total) from orders o join customers c on o.customer_id = c.id where o.status = 'refunded' group by c.region",
+                "agent",
+            )
+            .with_question("Revenue by region"),
+            QueryRecord::new("SELECT * FROM pg_stat_activity", "pg_stat_statements"),
+            QueryRecord::new("WITH t AS (SELECT id FROM users) SELECT count(*) FROM t", "log")
+                .with_calls(5),
+            QueryRecord::new("DELETE FROM users", "log"),
+            QueryRecord::new("not sql", "log"),
+        ];
+        let examples = mine_queries(&records, &PostgreSqlDialect {});
+        assert_eq!(examples.len(), 2);
+
+        assert_eq!(examples[0].tables, vec!["users"]);
+        assert_eq!(examples[0].calls, 5);
+
+        let revenue = &examples[1];
+        assert_eq!(revenue.calls, 2);
+        assert_eq!(revenue.tables, vec!["customers", "orders"]);
+        assert!(revenue.columns.contains(&"customer_id".to_string()));
+        assert!(revenue.sql.contains("o.status = ?"));
+        assert_eq!(revenue.questions, vec!["Revenue by region"]);
+        assert_eq!(revenue.origins, vec!["log", "agent"]);
+        assert!(revenue
+            .content()
+            .starts_with("-- question: Revenue by region\n"));
+    }
+
+    #[tokio::test]
+    async fn test_query_examples_are_retrievable() {
+        let embedder = std::sync::Arc::new(crate::embedding::HashingEmbedder::new(128));
+        let mut rag = crate::retrieval::RAGService::with_embedder(embedder);
+        rag.index_table(
+            "orders".to_string(),
+            vec![0.0; 128],
+            "orders: order_id, total".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        let records = vec![
+            QueryRecord::new(
+                "SELECT region, SUM(total) FROM orders GROUP BY region",
+                "agent",
+            )
+            .with_question("revenue per region"),
+            QueryRecord::new("SELECT name FROM users WHERE id = 1", "log"),
+        ];
+        let examples = mine_queries(&records, &PostgreSqlDialect {});
+        assert_eq!(rag.index_query_examples(&examples).await.unwrap(), 2);
+
+        let result = rag.retrieve_examples("revenue by region", 1).await.unwrap();
+        assert_eq!(result.chunks.len(), 1);
+        assert!(result.chunks[0].content.contains("SUM(total)"));
+        assert_eq!(
+            result.chunks[0].metadata["tables"],
+            serde_json::json!(["orders"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_log_merges_history() {
+        let embedder = std::sync::Arc::new(crate::embedding::HashingEmbedder::new(64));
+        let rag = std::sync::Arc::new(tokio::sync::RwLock::new(
+            crate::retrieval::RAGService::with_embedder(embedder),
+        ));
+        let log = QueryLog::new(rag.clone());
+        let sql = "SELECT region, SUM(total) FROM orders WHERE status = 'paid' GROUP BY region";
+        log.record(
+            &[QueryRecord::new(sql, "log").with_calls(3)],
+            Backend::Postgres,
+        )
+        .await
+        .unwrap();
+        log.record(
+            &[QueryRecord::new(&sql.replace("paid", "open"), "agent")
+                .with_question("revenue by region")],
+            Backend::Postgres,
+        )
+        .await
+        .unwrap();
+
+        let result = rag
+            .retrieve(
+                crate::backend::RetrieveRequest::new("revenue by region", 5)
+                    .with_sources(vec![SourceType::QueryExample]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.chunks.len(), 1);
+        assert_eq!(result.chunks[0].metadata["calls"], 4);
+        assert_eq!(
+            result.chunks[0].metadata["origins"],
+            serde_json::json!(["log", "agent"])
+        );
+    }
+}
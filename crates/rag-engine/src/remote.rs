@@ -0,0 +1,197 @@
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::api::{EntriesPage, MAX_ENTRIES_PAGE};
+use crate::backend::{DocumentRequest, IndexItem, RagBackend, RetrieveRequest};
+use crate::error::Error;
+use crate::retrieval::{IndexStats, RetrievalResult, SourceType};
+
+/// Client for a RAG index served by [`api::router`](crate::api::router).
+pub struct RemoteRagClient {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl RemoteRagClient {
+    /// `base_url` is where the router is mounted, e.g. `http://rag:3000/rag`.
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Bearer token the server requires for listing entries, indexing and
+    /// deletes.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// `/entries/<source>[/<id>]`, with the id escaped as one path segment.
+    fn entry_url(&self, source: &SourceType, id: Option<&str>) -> Result<reqwest::Url, Error> {
+        let invalid = || Error::Rag(format!("invalid RAG service URL: {}", self.base_url));
+        let mut url = reqwest::Url::parse(&self.base_url).map_err(|_| invalid())?;
+        {
+            let mut segments = url.path_segments_mut().map_err(|_| invalid())?;
+            segments
+                .pop_if_empty()
+                .push("entries")
+                .push(source.type_name());
+            segments.extend(id);
+        }
+        Ok(url)
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        let response = self
+            .authorized(request)
+            .send()
+            .await
+            .map_err(|e| Error::Rag(format!("RAG service unreachable: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: Value = response.json().await.unwrap_or(Value::Null);
+            let message = body["error"].as_str().unwrap_or("no details").to_string();
+            return Err(match status {
+                StatusCode::BAD_GATEWAY => Error::Embedding(message),
+                s if s.is_server_error() => Error::VectorStore(message),
+                _ => Error::Rag(format!("RAG service returned {}: {}", status, message)),
+            });
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| Error::Rag(format!("invalid RAG service response: {}", e)))
+    }
+}
+
+fn count(body: &Value, key: &str) -> usize {
+    body[key].as_u64().unwrap_or(0) as usize
+}
+
+#[async_trait]
+impl RagBackend for RemoteRagClient {
+    async fn upsert(&self, source: SourceType, items: Vec<IndexItem>) -> Result<usize, Error> {
+        let url = self.entry_url(&source, None)?;
+        let body: Value = self.send(self.client.put(url).json(&items)).await?;
+        Ok(count(&body, "upserted"))
+    }
+
+    async fn index_document(&self, request: DocumentRequest) -> Result<usize, Error> {
+        let url = self.url("/documents");
+        let body: Value = self.send(self.client.post(url).json(&request)).await?;
+        Ok(count(&body, "chunks"))
+    }
+
+    async fn delete(&self, source: SourceType, id: &str) -> Result<bool, Error> {
+        let url = self.entry_url(&source, Some(id))?;
+        let response = self
+            .authorized(self.client.delete(url))
+            .send()
+            .await
+            .map_err(|e| Error::Rag(format!("RAG service unreachable: {}", e)))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(Error::Rag(format!("RAG service returned {}", status))),
+        }
+    }
+
+    async fn metadata(&self, source: SourceType) -> Result<HashMap<String, Value>, Error> {
+        let url = self.entry_url(&source, None)?;
+        let mut metadata = HashMap::new();
+        loop {
+            let query = [("offset", metadata.len()), ("limit", MAX_ENTRIES_PAGE)];
+            let page: EntriesPage = self
+                .send(self.client.get(url.clone()).query(&query))
+                .await?;
+            let done = page.entries.is_empty() || page.offset + page.entries.len() >= page.total;
+            metadata.extend(page.entries);
+            if done {
+                return Ok(metadata);
+            }
+        }
+    }
+
+    async fn retrieve(&self, request: RetrieveRequest) -> Result<RetrievalResult, Error> {
+        let url = self.url("/retrieve");
+        self.send(self.client.post(url).json(&request)).await
+    }
+
+    async fn stats(&self) -> Result<IndexStats, Error> {
+        self.send(self.client.get(self.url("/stats"))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::DocumentFormat;
+    use crate::embedding::HashingEmbedder;
+    use crate::retrieval::RAGService;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_remote_client_round_trip() {
+        let rag = RAGService::with_embedder(Arc::new(HashingEmbedder::new(64)));
+        let app = crate::api::router(Arc::new(RwLock::new(rag)), Some("secret".to_string()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = RemoteRagClient::new(&format!("http://{}/", addr)).with_token("secret");
+        let document = DocumentRequest {
+            source: SourceType::Documentation,
+            parent_id: "wiki/refunds.md".to_string(),
+            text: "# Refunds\n\nRefunds are stored as negative totals.".to_string(),
+            format: DocumentFormat::Markdown,
+            metadata: serde_json::json!({ "team": "finance" }),
+        };
+        assert_eq!(client.index_document(document).await.unwrap(), 1);
+
+        let result = client
+            .retrieve(
+                RetrieveRequest::new("refunds", 3)
+                    .with_sources(vec![SourceType::Documentation])
+                    .with_filter(crate::filter::MetadataFilter::eq("team", "finance")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.chunks[0].id, "wiki/refunds.md#0");
+        assert_eq!(result.chunks[0].metadata["heading"], "Refunds");
+
+        let metadata = client.metadata(SourceType::Documentation).await.unwrap();
+        assert_eq!(
+            metadata["wiki/refunds.md#0"]["parent_id"],
+            "wiki/refunds.md"
+        );
+        let stats = client.stats().await.unwrap();
+        assert_eq!(stats.entries, 1);
+        assert!(client
+            .delete(SourceType::Documentation, "wiki/refunds.md#0")
+            .await
+            .unwrap());
+        assert!(!client
+            .delete(SourceType::Documentation, "missing")
+            .await
+            .unwrap());
+    }
+}
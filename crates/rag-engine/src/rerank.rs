@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::retrieval::RetrievedChunk;
+use crate::tokenize::tokenize;
+
+/// Reorders merged retrieval candidates. Implementations may rescore chunks
+/// but should keep the originals' ids and content.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    async fn rerank(
+        &self,
+        query: &str,
+        chunks: Vec<RetrievedChunk>,
+    ) -> Result<Vec<RetrievedChunk>, Error>;
+}
+
+/// Rescales one source's scores to `[0, 1]` by `max`, the best score a chunk
+/// can reach (for reciprocal rank fusion, first place in every ranking), and
+/// weights them by `strength`, how well the source matched at all, e.g. its
+/// best vector similarity. Calibrated per source, a weakly matching source's
+/// first hit no longer ties with a strongly matching source's. The raw score
+/// is kept as `raw_score`.
+pub fn calibrate_scores(chunks: &mut [RetrievedChunk], max: f32, strength: f32) {
+    let strength = strength.clamp(0.0, 1.0);
+    for chunk in chunks.iter_mut() {
+        chunk
+            .metadata
+            .insert("raw_score".to_string(), Value::from(chunk.score));
+        chunk.score = if max > 0.0 {
+            (chunk.score / max).clamp(0.0, 1.0) * strength
+        } else {
+            0.0
+        };
+    }
+}
+
+fn sort_by_score(chunks: &mut [RetrievedChunk]) {
+    chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+/// Cheap reranker: blends the calibrated retrieval score with the share of
+/// query identifiers found in the chunk and, when the chunk metadata has an
+/// `updated_at` (epoch seconds or `YYYY-MM-DD...`), an exponential recency
+/// decay.
+pub struct HeuristicReranker {
+    identifier_weight: f32,
+    recency_weight: f32,
+    half_life_days: f32,
+}
+
+impl Default for HeuristicReranker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeuristicReranker {
+    pub fn new() -> Self {
+        Self {
+            identifier_weight: 1.0,
+            recency_weight: 0.2,
+            half_life_days: 90.0,
+        }
+    }
+
+    pub fn with_weights(mut self, identifier: f32, recency: f32) -> Self {
+        self.identifier_weight = identifier;
+        self.recency_weight = recency;
+        self
+    }
+
+    pub fn with_half_life_days(mut self, days: f32) -> Self {
+        self.half_life_days = days;
+        self
+    }
+
+    fn score(&self, query: &HashSet<String>, chunk: &RetrievedChunk, now: f64) -> f32 {
+        let mut score = chunk.score;
+        if !query.is_empty() {
+            let text: HashSet<String> = tokenize(&format!("{} {}", chunk.id, chunk.content))
+                .into_iter()
+                .collect();
+            let overlap = query.intersection(&text).count() as f32 / query.len() as f32;
+            score += self.identifier_weight * overlap;
+        }
+        if let Some(updated) = chunk.metadata.get("updated_at").and_then(epoch_seconds) {
+            let age_days = ((now - updated) / 86_400.0).max(0.0) as f32;
+            score += self.recency_weight * 0.5f32.powf(age_days / self.half_life_days.max(1.0));
+        }
+        score
+    }
+}
+
+#[async_trait]
+impl Reranker for HeuristicReranker {
+    async fn rerank(
+        &self,
+        query: &str,
+        mut chunks: Vec<RetrievedChunk>,
+    ) -> Result<Vec<RetrievedChunk>, Error> {
+        let query: HashSet<String> = tokenize(query).into_iter().collect();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        for chunk in &mut chunks {
+            chunk.score = self.score(&query, chunk, now);
+        }
+        sort_by_score(&mut chunks);
+        Ok(chunks)
+    }
+}
+
+fn epoch_seconds(value: &Value) -> Option<f64> {
+    if let Some(seconds) = value.as_f64() {
+        return Some(seconds);
+    }
+    let date = value.as_str()?.get(..10)?;
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days from civil date, Howard Hinnant's algorithm.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some((era * 146_097 + doe - 719_468) as f64 * 86_400.0)
+}
+
+/// Asks a chat model to order the candidates. `complete` sends one prompt
+/// and returns the reply, so any client can be plugged in, e.g. the agent's
+/// LLM call. Candidates the model leaves out keep their relative order after
+/// the ranked ones.
+pub struct LlmReranker<F> {
+    complete: F,
+    max_chunks: usize,
+    max_chars: usize,
+}
+
+impl<F, Fut> LlmReranker<F>
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, String>> + Send,
+{
+    pub fn new(complete: F) -> Self {
+        Self {
+            complete,
+            max_chunks: 20,
+            max_chars: 600,
+        }
+    }
+
+    /// Candidates beyond the first `max_chunks` are not shown to the model.
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+        self.max_chunks = max_chunks;
+        self
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    fn prompt(&self, query: &str, chunks: &[RetrievedChunk]) -> String {
+        let mut prompt = format!(
+            "Rank the passages by how useful they are for answering the question.\n\
+             Question: {}\n\n",
+            query
+        );
+        for (i, chunk) in chunks.iter().enumerate() {
+            let content: String = chunk.content.chars().take(self.max_chars).collect();
+            prompt.push_str(&format!("[{}] {}\n{}\n\n", i, chunk.id, content));
+        }
+        prompt.push_str(
+            "Reply with only a JSON array of passage numbers, most useful first, \
+             omitting irrelevant passages. Example: [2, 0]",
+        );
+        prompt
+    }
+}
+
+fn parse_ranking(reply: &str) -> Option<Vec<usize>> {
+    let start = reply.find('[')?;
+    let end = start + reply[start..].find(']')?;
+    serde_json::from_str(&reply[start..=end]).ok()
+}
+
+#[async_trait]
+impl<F, Fut> Reranker for LlmReranker<F>
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, String>> + Send,
+{
+    async fn rerank(
+        &self,
+        query: &str,
+        mut chunks: Vec<RetrievedChunk>,
+    ) -> Result<Vec<RetrievedChunk>, Error> {
+        let shown = chunks.len().min(self.max_chunks);
+        if shown < 2 {
+            return Ok(chunks);
+        }
+        let reply = (self.complete)(self.prompt(query, &chunks[..shown]))
+            .await
+            .map_err(Error::Rag)?;
+        let ranking = parse_ranking(&reply)
+            .ok_or_else(|| Error::Rag(format!("unexpected reranker reply: {}", reply)))?;
+
+        let mut order: Vec<usize> = Vec::with_capacity(chunks.len());
+        for i in ranking {
+            if i < shown && !order.contains(&i) {
+                order.push(i);
+            }
+        }
+        let unranked: Vec<usize> = (0..chunks.len()).filter(|i| !order.contains(i)).collect();
+        order.extend(unranked);
+
+        let total = order.len() as f32;
+        let mut slots: Vec<Option<RetrievedChunk>> = chunks.drain(..).map(Some).collect();
+        Ok(order
+            .into_iter()
+            .enumerate()
+            .filter_map(|(rank, i)| {
+                let mut chunk = slots[i].take()?;
+                chunk.score = 1.0 - rank as f32 / total;
+                Some(chunk)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retrieval::SourceType;
+    use std::collections::HashMap;
+
+    fn chunk(id: &str, source: SourceType, score: f32, content: &str) -> RetrievedChunk {
+        RetrievedChunk {
+            id: id.to_string(),
+            content: content.to_string(),
+            source,
+            score,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calibration_and_heuristic_reranker() {
+        let mut chunks = vec![
+            chunk(
+                "orders",
+                SourceType::Table,
+                0.030,
+                "orders: order_id, total",
+            ),
+            chunk(
+                "customers",
+                SourceType::Table,
+                0.020,
+                "customers: customer_id",
+            ),
+            chunk(
+                "note",
+                SourceType::Memory,
+                0.010,
+                "Revenue excludes refunds",
+            ),
+            chunk(
+                "old_note",
+                SourceType::Memory,
+                0.005,
+                "Use fct_orders for totals",
+            ),
+        ];
+        calibrate_scores(&mut chunks, 0.040, 1.0);
+        assert_eq!(chunks[0].score, 0.75);
+        assert_eq!(chunks[2].score, 0.25);
+        assert!(chunks[3].score < chunks[2].score);
+        let mut weak = vec![chunks[0].clone()];
+        weak[0].score = 0.030;
+        calibrate_scores(&mut weak, 0.040, 0.5);
+        assert_eq!(weak[0].score, 0.375);
+        assert_eq!(chunks[0].metadata["raw_score"], Value::from(0.030f32));
+
+        chunks[3]
+            .metadata
+            .insert("updated_at".to_string(), Value::from("2001-01-01"));
+        let reranked = HeuristicReranker::new()
+            .with_weights(3.0, 0.2)
+            .rerank("customer_id by customer", chunks)
+            .await
+            .unwrap();
+        assert_eq!(reranked[0].id, "customers");
+        assert_eq!(
+            epoch_seconds(&Value::from("1970-01-02T00:00:00Z")),
+            Some(86_400.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_llm_reranker_follows_model_order() {
+        let chunks = vec![
+            chunk("a", SourceType::Table, 0.9, "a"),
+            chunk("b", SourceType::Table, 0.8, "b"),
+            chunk("c", SourceType::Table, 0.7, "c"),
+        ];
+        let reranker = LlmReranker::new(|prompt: String| async move {
+            assert!(prompt.contains("[2] c"));
+            Ok("Ranking: [2, 2, 7, 0]".to_string())
+        });
+        let reranked = reranker.rerank("q", chunks.clone()).await.unwrap();
+        let ids: Vec<&str> = reranked.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+        assert!(reranked[0].score > reranked[1].score);
+
+        let failing = LlmReranker::new(|_: String| async { Ok("no idea".to_string()) });
+        assert!(failing.rerank("q", chunks).await.is_err());
+    }
+}
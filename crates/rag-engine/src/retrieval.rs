@@ -1,9 +1,25 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::bm25::{reciprocal_rank_fusion, Bm25Index};
+use crate::chunking::{Chunker, DocumentFormat};
+use crate::context::ContextAssembler;
+use crate::embedding::Embedder;
+use crate::error::Error;
+use crate::filter::MetadataFilter;
+use crate::hnsw::HnswConfig;
+use crate::query_log::QueryExample;
+use crate::rerank::{calibrate_scores, Reranker};
+use crate::store::{StoredVector, VectorStore};
 use crate::types::VectorIndex;
 
+const RRF_K: f32 = 60.0;
+const CONTEXT_BUDGET: usize = 2048;
+/// Largest `k` a retrieval accepts; larger requests are clamped.
+pub const MAX_K: usize = 1000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetrievedChunk {
     pub id: String,
@@ -13,12 +29,49 @@ pub struct RetrievedChunk {
     pub metadata: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum SourceType {
     Table,
     Documentation,
     Memory,
     Schema,
+    QueryExample,
+}
+
+impl SourceType {
+    /// Sources searched when a request names none. Query examples are only
+    /// searched on request, see [`RAGService::retrieve_examples`].
+    pub fn all() -> Vec<SourceType> {
+        vec![
+            SourceType::Table,
+            SourceType::Documentation,
+            SourceType::Memory,
+            SourceType::Schema,
+        ]
+    }
+
+    /// Every source, query examples included.
+    pub(crate) fn every() -> Vec<SourceType> {
+        let mut sources = Self::all();
+        sources.push(SourceType::QueryExample);
+        sources
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            SourceType::Table => "table",
+            SourceType::Documentation => "doc",
+            SourceType::Memory => "memory",
+            SourceType::Schema => "schema",
+            SourceType::QueryExample => "query_example",
+        }
+    }
+
+    pub(crate) fn from_type_name(name: &str) -> Option<SourceType> {
+        SourceType::every()
+            .into_iter()
+            .find(|source| source.type_name() == name)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,11 +81,36 @@ pub struct RetrievalResult {
     pub total_results: usize,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStats {
+    pub source: SourceType,
+    pub entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub model: Option<String>,
+    pub dimension: usize,
+    pub entries: usize,
+    pub sources: Vec<SourceStats>,
+    pub hnsw: bool,
+    pub persistent: bool,
+}
+
 pub struct RAGService {
     table_index: VectorIndex,
     doc_index: VectorIndex,
     memory_index: VectorIndex,
     schema_index: VectorIndex,
+    query_index: VectorIndex,
+    lexical: HashMap<SourceType, Bm25Index>,
+    vector_weight: f32,
+    lexical_weight: f32,
+    embedder: Option<Arc<dyn Embedder>>,
+    store: Option<Arc<VectorStore>>,
+    chunker: Chunker,
+    reranker: Option<Arc<dyn Reranker>>,
+    assembler: ContextAssembler,
 }
 
 impl RAGService {
@@ -42,51 +120,386 @@ impl RAGService {
             doc_index: VectorIndex::new(dimension),
             memory_index: VectorIndex::new(dimension),
             schema_index: VectorIndex::new(dimension),
+            query_index: VectorIndex::new(dimension),
+            lexical: HashMap::new(),
+            vector_weight: 1.0,
+            lexical_weight: 1.0,
+            embedder: None,
+            store: None,
+            chunker: Chunker::default(),
+            reranker: None,
+            assembler: ContextAssembler::new(CONTEXT_BUDGET),
+        }
+    }
+
+    /// Weights of the vector and BM25 rankings in reciprocal rank fusion.
+    /// A weight of zero disables that retriever.
+    pub fn with_fusion_weights(mut self, vector: f32, lexical: f32) -> Self {
+        self.vector_weight = vector;
+        self.lexical_weight = lexical;
+        self
+    }
+
+    /// Chunker used by [`index_document`](Self::index_document).
+    pub fn with_chunker(mut self, chunker: Chunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    /// Reorders the merged candidates after retrieval.
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    /// Assembler used by [`format_context`](Self::format_context).
+    pub fn with_context_assembler(mut self, assembler: ContextAssembler) -> Self {
+        self.assembler = assembler;
+        self
+    }
+
+    /// Lets the service embed text itself, see [`index_text`](Self::index_text)
+    /// and [`retrieve_text`](Self::retrieve_text). Indexes are sized to the
+    /// embedder's dimension.
+    pub fn with_embedder(embedder: Arc<dyn Embedder>) -> Self {
+        Self {
+            embedder: Some(embedder.clone()),
+            ..Self::new(embedder.dimension())
+        }
+    }
+
+    /// Serves vector search from HNSW graphs instead of exact scans.
+    pub fn with_hnsw(mut self, config: HnswConfig) -> Self {
+        for source in SourceType::every() {
+            let index = std::mem::replace(self.index_mut(&source), VectorIndex::new(0));
+            *self.index_mut(&source) = index.with_hnsw(config.clone());
+        }
+        self
+    }
+
+    /// Loads the vectors saved in `store` and keeps it up to date with every
+    /// `index_*` call and [`delete`](Self::delete). Fails if
+    /// the store was built with a different embedding model or dimension.
+    pub async fn open(embedder: Arc<dyn Embedder>, store: Arc<VectorStore>) -> Result<Self, Error> {
+        store
+            .header()
+            .check(embedder.model(), embedder.dimension())?;
+        let mut rag = Self::with_embedder(embedder);
+        for entry in store.load().await? {
+            rag.insert(
+                entry.source,
+                entry.id,
+                entry.vector,
+                entry.content,
+                entry.metadata,
+            )?;
+        }
+        rag.store = Some(store);
+        Ok(rag)
+    }
+
+    pub(crate) fn embedder(&self) -> Result<&Arc<dyn Embedder>, Error> {
+        self.embedder
+            .as_ref()
+            .ok_or_else(|| Error::Embedding("no embedder configured".to_string()))
+    }
+
+    pub(crate) fn index(&self, source: &SourceType) -> &VectorIndex {
+        match source {
+            SourceType::Table => &self.table_index,
+            SourceType::Documentation => &self.doc_index,
+            SourceType::Memory => &self.memory_index,
+            SourceType::Schema => &self.schema_index,
+            SourceType::QueryExample => &self.query_index,
+        }
+    }
+
+    fn index_mut(&mut self, source: &SourceType) -> &mut VectorIndex {
+        match source {
+            SourceType::Table => &mut self.table_index,
+            SourceType::Documentation => &mut self.doc_index,
+            SourceType::Memory => &mut self.memory_index,
+            SourceType::Schema => &mut self.schema_index,
+            SourceType::QueryExample => &mut self.query_index,
+        }
+    }
+
+    fn check_dimension(&self, source: &SourceType, id: &str, vector: &[f32]) -> Result<(), Error> {
+        let dimension = self.index(source).dimension;
+        if vector.len() != dimension {
+            return Err(Error::Rag(format!(
+                "vector for {} has dimension {}, index expects {}",
+                id,
+                vector.len(),
+                dimension
+            )));
         }
+        Ok(())
     }
 
-    pub fn index_table(
+    fn insert(
         &mut self,
+        source: SourceType,
         id: String,
         vector: Vec<f32>,
         content: String,
         metadata: serde_json::Value,
-    ) {
-        self.table_index
+    ) -> Result<(), Error> {
+        self.check_dimension(&source, &id, &vector)?;
+        self.lexical
+            .entry(source.clone())
+            .or_default()
+            .add(&id, &content);
+        self.index_mut(&source)
             .add_with_content(id, vector, content, metadata);
+        Ok(())
+    }
+
+    fn remove(&mut self, source: &SourceType, id: &str) -> bool {
+        if let Some(lexical) = self.lexical.get_mut(source) {
+            lexical.remove(id);
+        }
+        self.index_mut(source).remove(id)
+    }
+
+    /// Removes `id` from memory and from the attached store, if any.
+    pub async fn delete(&mut self, source: &SourceType, id: &str) -> Result<bool, Error> {
+        let removed = self.remove(source, id);
+        match &self.store {
+            Some(store) => Ok(store.delete(source, id).await? || removed),
+            None => Ok(removed),
+        }
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        let sources: Vec<SourceStats> = SourceType::every()
+            .into_iter()
+            .map(|source| SourceStats {
+                entries: self.index(&source).len(),
+                source,
+            })
+            .collect();
+        IndexStats {
+            model: self.embedder.as_ref().map(|e| e.model().to_string()),
+            dimension: self.table_index.dimension,
+            entries: sources.iter().map(|s| s.entries).sum(),
+            sources,
+            hnsw: self.table_index.ann.is_some(),
+            persistent: self.store.is_some(),
+        }
+    }
+
+    /// Every indexed vector, e.g. for [`VectorStore::snapshot`].
+    pub fn entries(&self) -> Vec<StoredVector> {
+        let mut entries = Vec::new();
+        for source in SourceType::every() {
+            let index = self.index(&source);
+            for (id, vector) in &index.vectors {
+                entries.push(StoredVector {
+                    source: source.clone(),
+                    id: id.clone(),
+                    vector: vector.clone(),
+                    content: index.contents.get(id).cloned().unwrap_or_default(),
+                    metadata: index.metadata.get(id).cloned().unwrap_or_default(),
+                });
+            }
+        }
+        entries
+    }
+
+    pub async fn index_text(
+        &mut self,
+        source: SourceType,
+        id: String,
+        content: String,
+        metadata: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.index_texts(source, vec![(id, content, metadata)])
+            .await
+    }
+
+    /// Embeds `(id, content, metadata)` items in batches and indexes them.
+    pub async fn index_texts(
+        &mut self,
+        source: SourceType,
+        items: Vec<(String, String, serde_json::Value)>,
+    ) -> Result<(), Error> {
+        let entries = embed_entries(self.embedder()?.as_ref(), source, items).await?;
+        self.index_entries(entries).await
+    }
+
+    /// Indexes precomputed vectors, writing them to the attached store first.
+    pub(crate) async fn index_entries(&mut self, entries: Vec<StoredVector>) -> Result<(), Error> {
+        for entry in &entries {
+            self.check_dimension(&entry.source, &entry.id, &entry.vector)?;
+        }
+        if let Some(store) = &self.store {
+            store.upsert(&entries).await?;
+        }
+        for entry in entries {
+            self.insert(
+                entry.source,
+                entry.id,
+                entry.vector,
+                entry.content,
+                entry.metadata,
+            )?;
+        }
+        Ok(())
     }
 
-    pub fn index_documentation(
+    async fn index_vector(
         &mut self,
+        source: SourceType,
         id: String,
         vector: Vec<f32>,
         content: String,
         metadata: serde_json::Value,
-    ) {
-        self.doc_index
-            .add_with_content(id, vector, content, metadata);
+    ) -> Result<(), Error> {
+        self.index_entries(vec![StoredVector {
+            source,
+            id,
+            vector,
+            content,
+            metadata,
+        }])
+        .await
     }
 
-    pub fn index_memory(
+    /// Chunks a document and indexes each chunk as `<parent_id>#<n>`. Chunk
+    /// metadata extends `metadata` with `parent_id`, `chunk` and `heading`.
+    pub async fn index_document(
+        &mut self,
+        source: SourceType,
+        parent_id: &str,
+        text: &str,
+        format: DocumentFormat,
+        metadata: serde_json::Value,
+    ) -> Result<usize, Error> {
+        let items = self.document_items(parent_id, text, format, metadata);
+        let count = items.len();
+        self.index_texts(source, items).await?;
+        Ok(count)
+    }
+
+    /// The `(id, content, metadata)` items [`index_document`](Self::index_document) embeds.
+    pub(crate) fn document_items(
+        &self,
+        parent_id: &str,
+        text: &str,
+        format: DocumentFormat,
+        metadata: serde_json::Value,
+    ) -> Vec<(String, String, serde_json::Value)> {
+        self.chunker
+            .chunk(parent_id, text, format)
+            .into_iter()
+            .map(|chunk| {
+                let mut chunk_metadata = match &metadata {
+                    Value::Object(fields) => fields.clone(),
+                    _ => serde_json::Map::new(),
+                };
+                chunk_metadata.insert("parent_id".to_string(), Value::from(chunk.parent_id));
+                chunk_metadata.insert("chunk".to_string(), Value::from(chunk.index));
+                if let Some(heading) = chunk.heading {
+                    chunk_metadata.insert("heading".to_string(), Value::from(heading));
+                }
+                (chunk.id, chunk.content, Value::Object(chunk_metadata))
+            })
+            .collect()
+    }
+
+    /// Indexes mined queries as [`SourceType::QueryExample`] under
+    /// `query:<fingerprint>`, replacing earlier versions of the same query.
+    pub async fn index_query_examples(
+        &mut self,
+        examples: &[QueryExample],
+    ) -> Result<usize, Error> {
+        let items: Vec<(String, String, serde_json::Value)> = examples
+            .iter()
+            .map(|example| {
+                let item = example.index_item();
+                (item.id, item.content, item.metadata)
+            })
+            .collect();
+        let count = items.len();
+        self.index_texts(SourceType::QueryExample, items).await?;
+        Ok(count)
+    }
+
+    /// Past queries most similar to `question`, for few-shot prompting.
+    pub async fn retrieve_examples(
+        &self,
+        question: &str,
+        k: usize,
+    ) -> Result<RetrievalResult, Error> {
+        self.retrieve_text(question, k, Some(vec![SourceType::QueryExample]))
+            .await
+    }
+
+    pub async fn retrieve_text(
+        &self,
+        query: &str,
+        k: usize,
+        sources: Option<Vec<SourceType>>,
+    ) -> Result<RetrievalResult, Error> {
+        self.retrieve_text_filtered(query, k, sources, None).await
+    }
+
+    pub async fn retrieve_text_filtered(
+        &self,
+        query: &str,
+        k: usize,
+        sources: Option<Vec<SourceType>>,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<RetrievalResult, Error> {
+        let vector = self.embedder()?.embed(query).await?;
+        Ok(self
+            .retrieve_filtered(query, &vector, k, sources, filter)
+            .await)
+    }
+
+    pub async fn index_table(
         &mut self,
         id: String,
         vector: Vec<f32>,
         content: String,
         metadata: serde_json::Value,
-    ) {
-        self.memory_index
-            .add_with_content(id, vector, content, metadata);
+    ) -> Result<(), Error> {
+        self.index_vector(SourceType::Table, id, vector, content, metadata)
+            .await
     }
 
-    pub fn index_schema(
+    pub async fn index_documentation(
         &mut self,
         id: String,
         vector: Vec<f32>,
         content: String,
         metadata: serde_json::Value,
-    ) {
-        self.schema_index
-            .add_with_content(id, vector, content, metadata);
+    ) -> Result<(), Error> {
+        self.index_vector(SourceType::Documentation, id, vector, content, metadata)
+            .await
+    }
+
+    pub async fn index_memory(
+        &mut self,
+        id: String,
+        vector: Vec<f32>,
+        content: String,
+        metadata: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.index_vector(SourceType::Memory, id, vector, content, metadata)
+            .await
+    }
+
+    pub async fn index_schema(
+        &mut self,
+        id: String,
+        vector: Vec<f32>,
+        content: String,
+        metadata: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.index_vector(SourceType::Schema, id, vector, content, metadata)
+            .await
     }
 
     pub async fn retrieve(
@@ -96,90 +509,101 @@ impl RAGService {
         k: usize,
         sources: Option<Vec<SourceType>>,
     ) -> RetrievalResult {
-        let sources = sources.unwrap_or_else(|| {
-            vec![
-                SourceType::Table,
-                SourceType::Documentation,
-                SourceType::Memory,
-                SourceType::Schema,
-            ]
-        });
+        self.retrieve_filtered(query, query_vector, k, sources, None)
+            .await
+    }
 
-        let mut all_chunks = Vec::new();
+    /// Ranks each source by vector similarity and BM25 over the same content
+    /// and fuses the two rankings with weighted reciprocal rank fusion. Each
+    /// source's fused scores are calibrated by the best attainable fused score
+    /// and the source's best vector similarity before the sources are merged,
+    /// and the reranker, if any, reorders the merged candidates.
+    /// Chunks whose indexed metadata does not match `filter` are skipped by
+    /// both retrievers. `k` is clamped to [`MAX_K`].
+    pub async fn retrieve_filtered(
+        &self,
+        query: &str,
+        query_vector: &[f32],
+        k: usize,
+        sources: Option<Vec<SourceType>>,
+        filter: Option<&MetadataFilter>,
+    ) -> RetrievalResult {
+        let sources = sources.unwrap_or_else(SourceType::all);
+        let k = k.min(MAX_K);
+        let candidates = (k * 4).max(20);
 
-        if sources.contains(&SourceType::Table) {
-            let results = self.table_index.search_with_content(query_vector, k);
-            for (id, content, score) in results {
-                all_chunks.push(RetrievedChunk {
-                    id: id.clone(),
-                    content,
-                    source: SourceType::Table,
-                    score,
-                    metadata: {
-                        let mut m = HashMap::new();
-                        m.insert("type".to_string(), Value::String("table".to_string()));
-                        m
-                    },
-                });
-            }
-        }
+        let best = (self.vector_weight.max(0.0) + self.lexical_weight.max(0.0)) / (RRF_K + 1.0);
+        let mut all_chunks = Vec::new();
+        for source in &sources {
+            let index = self.index(source);
+            let accept = |id: &str| match filter {
+                Some(filter) => index.metadata.get(id).is_some_and(|m| filter.matches(m)),
+                None => true,
+            };
+            let vector_hits = if self.vector_weight > 0.0 {
+                index.search_filtered(query_vector, candidates, filter)
+            } else {
+                vec![]
+            };
+            let lexical_hits = match self.lexical.get(source) {
+                Some(lexical) if self.lexical_weight > 0.0 => {
+                    lexical.search_filtered(query, candidates, accept)
+                }
+                _ => vec![],
+            };
 
-        if sources.contains(&SourceType::Documentation) {
-            let results = self.doc_index.search_with_content(query_vector, k);
-            for (id, content, score) in results {
-                all_chunks.push(RetrievedChunk {
-                    id: id.clone(),
-                    content,
-                    source: SourceType::Documentation,
-                    score,
-                    metadata: {
-                        let mut m = HashMap::new();
-                        m.insert("type".to_string(), Value::String("doc".to_string()));
-                        m
-                    },
-                });
-            }
-        }
+            let ids = |hits: &[(String, f32)]| hits.iter().map(|(id, _)| id.clone()).collect();
+            let fused = reciprocal_rank_fusion(
+                &[
+                    (ids(&vector_hits), self.vector_weight),
+                    (ids(&lexical_hits), self.lexical_weight),
+                ],
+                RRF_K,
+            );
 
-        if sources.contains(&SourceType::Memory) {
-            let results = self.memory_index.search_with_content(query_vector, k);
-            for (id, content, score) in results {
-                all_chunks.push(RetrievedChunk {
-                    id: id.clone(),
-                    content,
-                    source: SourceType::Memory,
+            // Without vector hits there is no comparable evidence, so lexical
+            // matches keep their rank-based score.
+            let strength = vector_hits
+                .iter()
+                .map(|(_, similarity)| *similarity)
+                .reduce(f32::max)
+                .unwrap_or(1.0);
+            let mut source_chunks = Vec::new();
+            for (id, score) in fused.into_iter().take(k) {
+                let mut metadata: HashMap<String, Value> = match index.metadata.get(&id) {
+                    Some(Value::Object(indexed)) => indexed.clone().into_iter().collect(),
+                    _ => HashMap::new(),
+                };
+                metadata
+                    .entry("type".to_string())
+                    .or_insert_with(|| Value::String(source.type_name().to_string()));
+                for (key, hits) in [
+                    ("vector_score", &vector_hits),
+                    ("lexical_score", &lexical_hits),
+                ] {
+                    if let Some((_, raw)) = hits.iter().find(|(hit, _)| *hit == id) {
+                        metadata.insert(key.to_string(), Value::from(*raw));
+                    }
+                }
+                source_chunks.push(RetrievedChunk {
+                    content: index.contents.get(&id).cloned().unwrap_or_default(),
+                    id,
+                    source: source.clone(),
                     score,
-                    metadata: {
-                        let mut m = HashMap::new();
-                        m.insert("type".to_string(), Value::String("memory".to_string()));
-                        m
-                    },
+                    metadata,
                 });
             }
+            calibrate_scores(&mut source_chunks, best, strength);
+            all_chunks.extend(source_chunks);
         }
 
-        if sources.contains(&SourceType::Schema) {
-            let results = self.schema_index.search_with_content(query_vector, k);
-            for (id, content, score) in results {
-                all_chunks.push(RetrievedChunk {
-                    id: id.clone(),
-                    content,
-                    source: SourceType::Schema,
-                    score,
-                    metadata: {
-                        let mut m = HashMap::new();
-                        m.insert("type".to_string(), Value::String("schema".to_string()));
-                        m
-                    },
-                });
+        all_chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        if let Some(reranker) = &self.reranker {
+            match reranker.rerank(query, all_chunks.clone()).await {
+                Ok(reranked) => all_chunks = reranked,
+                Err(e) => tracing::warn!("reranking failed, keeping retrieval order: {}", e),
             }
         }
-
-        all_chunks.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
         all_chunks.truncate(k);
 
         let total_results = all_chunks.len();
@@ -191,35 +615,42 @@ impl RAGService {
         }
     }
 
+    /// Prompt text for `result`, deduplicated and fitted to the token budget
+    /// of the configured [`ContextAssembler`].
     pub fn format_context(&self, result: &RetrievalResult) -> String {
-        let mut context = String::from("## Relevant Context\n\n");
-
-        for chunk in &result.chunks {
-            context.push_str(&format!(
-                "### {} (score: {:.2})\n",
-                self.source_to_string(&chunk.source),
-                chunk.score
-            ));
-            context.push_str(&chunk.content);
-            context.push_str("\n\n");
-        }
-
-        context
+        self.assembler.assemble(result).text
     }
+}
 
-    fn source_to_string(&self, source: &SourceType) -> String {
-        match source {
-            SourceType::Table => "Table".to_string(),
-            SourceType::Documentation => "Documentation".to_string(),
-            SourceType::Memory => "Memory".to_string(),
-            SourceType::Schema => "Schema".to_string(),
-        }
-    }
+/// Embeds `(id, content, metadata)` items in one batch, without touching an
+/// index, so callers can embed before taking a write lock.
+pub(crate) async fn embed_entries(
+    embedder: &dyn Embedder,
+    source: SourceType,
+    items: Vec<(String, String, serde_json::Value)>,
+) -> Result<Vec<StoredVector>, Error> {
+    let texts: Vec<String> = items
+        .iter()
+        .map(|(_, content, _)| content.clone())
+        .collect();
+    let vectors = embedder.embed_all(&texts).await?;
+    Ok(items
+        .into_iter()
+        .zip(vectors)
+        .map(|((id, content, metadata), vector)| StoredVector {
+            source: source.clone(),
+            id,
+            vector,
+            content,
+            metadata,
+        })
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::embedding::HashingEmbedder;
 
     #[test]
     fn test_rag_service_creation() {
@@ -227,8 +658,8 @@ mod tests {
         assert!(!rag.table_index.vectors.is_empty() || rag.table_index.vectors.is_empty());
     }
 
-    #[test]
-    fn test_index_and_retrieve() {
+    #[tokio::test]
+    async fn test_index_and_retrieve() {
         let mut rag = RAGService::new(3);
 
         rag.index_table(
@@ -236,12 +667,158 @@ mod tests {
             vec![1.0, 0.0, 0.0],
             "Users table with id, name, email".to_string(),
             serde_json::json!({"table": "users"}),
-        );
+        )
+        .await
+        .unwrap();
+        assert!(rag
+            .index_table(
+                "orders".to_string(),
+                vec![1.0, 0.0],
+                "orders".to_string(),
+                serde_json::json!({}),
+            )
+            .await
+            .is_err());
+        assert!(rag.lexical[&SourceType::Table]
+            .search("orders", 1)
+            .is_empty());
 
-        let result = tokio::runtime::Runtime::new()
-            .unwrap()
-            .block_on(rag.retrieve("user data", &vec![1.0, 0.0, 0.0], 5, None));
+        let result = rag.retrieve("user data", &[1.0, 0.0, 0.0], 5, None).await;
 
         assert!(!result.chunks.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_index_and_retrieve_text() {
+        let mut rag = RAGService::with_embedder(Arc::new(HashingEmbedder::new(128)));
+        rag.index_texts(
+            SourceType::Table,
+            vec![
+                (
+                    "orders".to_string(),
+                    "orders: order_id, customer_id, total_amount".to_string(),
+                    serde_json::json!({}),
+                ),
+                (
+                    "weather".to_string(),
+                    "weather: city, temperature, humidity".to_string(),
+                    serde_json::json!({}),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let result = rag
+            .retrieve_text("total order amount", 1, None)
+            .await
+            .unwrap();
+        assert_eq!(result.chunks[0].id, "orders");
+
+        let doc = "# Refunds\n\nRefunds are negative order totals.\n\n# Shipping\n\nCarriers and delivery dates.";
+        let count = rag
+            .index_document(
+                SourceType::Documentation,
+                "docs/orders.md",
+                doc,
+                DocumentFormat::Markdown,
+                serde_json::json!({"owner": "finance"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        let result = rag
+            .retrieve_text("refunds", 1, Some(vec![SourceType::Documentation]))
+            .await
+            .unwrap();
+        assert_eq!(result.chunks[0].id, "docs/orders.md#0");
+        assert_eq!(result.chunks[0].metadata["parent_id"], "docs/orders.md");
+        assert_eq!(result.chunks[0].metadata["heading"], "Refunds");
+        assert_eq!(result.chunks[0].metadata["owner"], "finance");
+        assert!(RAGService::new(3)
+            .retrieve_text("x", 1, None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_retrieval_favours_exact_identifiers() {
+        let mut rag = RAGService::new(2);
+        rag.index_table(
+            "dim_customer_v2".to_string(),
+            vec![0.6, 0.8],
+            "dim_customer_v2: customer_id, region".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        rag.index_table(
+            "customer_notes".to_string(),
+            vec![1.0, 0.0],
+            "Free text notes about customers".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+
+        let result = rag.retrieve("dim_customer_v2", &[1.0, 0.0], 1, None).await;
+        assert_eq!(result.chunks[0].id, "dim_customer_v2");
+        assert!(result.chunks[0].metadata.contains_key("lexical_score"));
+
+        let rag = rag.with_fusion_weights(1.0, 0.0);
+        let result = rag.retrieve("dim_customer_v2", &[1.0, 0.0], 1, None).await;
+        assert_eq!(result.chunks[0].id, "customer_notes");
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_filtered_returns_indexed_metadata() {
+        let mut rag = RAGService::new(2).with_hnsw(HnswConfig::default());
+        rag.index_table(
+            "revenue".to_string(),
+            vec![1.0, 0.0],
+            "revenue by month".to_string(),
+            serde_json::json!({"schema": "finance", "owner": "ana"}),
+        )
+        .await
+        .unwrap();
+        rag.index_table(
+            "revenue_old".to_string(),
+            vec![1.0, 0.1],
+            "revenue by month, legacy".to_string(),
+            serde_json::json!({"schema": "finance", "deprecated": true}),
+        )
+        .await
+        .unwrap();
+        rag.index_table(
+            "sessions".to_string(),
+            vec![0.9, 0.1],
+            "web sessions".to_string(),
+            serde_json::json!({"schema": "marketing"}),
+        )
+        .await
+        .unwrap();
+
+        let filter = MetadataFilter::parse("schema = 'finance' AND deprecated != true").unwrap();
+        let result = rag
+            .retrieve_filtered("revenue", &[1.0, 0.0], 5, None, Some(&filter))
+            .await;
+        assert_eq!(result.chunks.len(), 1);
+        assert_eq!(result.chunks[0].id, "revenue");
+        assert_eq!(result.chunks[0].metadata["owner"], "ana");
+        assert_eq!(result.chunks[0].metadata["type"], "table");
+
+        rag.index_memory(
+            "note".to_string(),
+            vec![0.1, 1.0],
+            "sessions are deduplicated daily".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        // The memory source's best hit barely matches, so it ranks below every
+        // table instead of tying with the best one.
+        let result = rag.retrieve("anything", &[1.0, 0.0], 5, None).await;
+        assert_eq!(result.chunks[0].id, "revenue");
+        assert_eq!(result.chunks.last().unwrap().id, "note");
+    }
 }
@@ -0,0 +1,297 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{Row, Sqlite, Transaction};
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::retrieval::SourceType;
+
+/// Bumped whenever the on-disk layout changes.
+pub const FORMAT_VERSION: i64 = 1;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS rag_header (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    format_version INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    dimension INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS rag_vectors (
+    source TEXT NOT NULL,
+    id TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    content TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    PRIMARY KEY (source, id)
+);
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreHeader {
+    pub format_version: i64,
+    pub model: String,
+    pub dimension: usize,
+}
+
+impl StoreHeader {
+    /// Fails unless the store holds vectors of `model` and `dimension` in the
+    /// current format.
+    pub fn check(&self, model: &str, dimension: usize) -> Result<(), Error> {
+        if self.format_version == FORMAT_VERSION
+            && self.model == model
+            && self.dimension == dimension
+        {
+            return Ok(());
+        }
+        Err(Error::VectorStore(format!(
+            "store was built with {} (dimension {}, format {}), refusing to load it with {} (dimension {}, format {})",
+            self.model, self.dimension, self.format_version, model, dimension, FORMAT_VERSION
+        )))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredVector {
+    pub source: SourceType,
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub content: String,
+    pub metadata: serde_json::Value,
+}
+
+/// SQLite-backed vector store. The header records the embedding model and
+/// dimension the vectors were built with; opening the store with another
+/// model fails instead of mixing incomparable vectors.
+pub struct VectorStore {
+    pool: SqlitePool,
+    header: StoreHeader,
+}
+
+fn store_error(e: impl std::fmt::Display) -> Error {
+    Error::VectorStore(e.to_string())
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+impl VectorStore {
+    /// Opens or creates the store at `url` (e.g. `sqlite://rag.db`).
+    pub async fn open(url: &str, model: &str, dimension: usize) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(store_error)?
+            .create_if_missing(true);
+        let max_connections = if url.contains(":memory:") { 1 } else { 5 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .map_err(store_error)?;
+        sqlx::raw_sql(SCHEMA)
+            .execute(&pool)
+            .await
+            .map_err(store_error)?;
+
+        let row = sqlx::query("SELECT format_version, model, dimension FROM rag_header")
+            .fetch_optional(&pool)
+            .await
+            .map_err(store_error)?;
+        let header = match row {
+            Some(row) => StoreHeader {
+                format_version: row.get("format_version"),
+                model: row.get("model"),
+                dimension: row.get::<i64, _>("dimension") as usize,
+            },
+            None => {
+                let header = StoreHeader {
+                    format_version: FORMAT_VERSION,
+                    model: model.to_string(),
+                    dimension,
+                };
+                sqlx::query(
+                    "INSERT INTO rag_header (id, format_version, model, dimension) VALUES (1, ?, ?, ?)",
+                )
+                .bind(header.format_version)
+                .bind(&header.model)
+                .bind(header.dimension as i64)
+                .execute(&pool)
+                .await
+                .map_err(store_error)?;
+                header
+            }
+        };
+
+        header.check(model, dimension)?;
+        Ok(Self { pool, header })
+    }
+
+    pub fn header(&self) -> &StoreHeader {
+        &self.header
+    }
+
+    pub async fn upsert(&self, entries: &[StoredVector]) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        self.write(&mut tx, entries).await?;
+        tx.commit().await.map_err(store_error)
+    }
+
+    async fn write(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        entries: &[StoredVector],
+    ) -> Result<(), Error> {
+        for entry in entries {
+            if entry.vector.len() != self.header.dimension {
+                return Err(Error::VectorStore(format!(
+                    "vector {} has dimension {}, expected {}",
+                    entry.id,
+                    entry.vector.len(),
+                    self.header.dimension
+                )));
+            }
+            sqlx::query(
+                "INSERT INTO rag_vectors (source, id, vector, content, metadata) VALUES (?, ?, ?, ?, ?)
+                 ON CONFLICT (source, id) DO UPDATE SET vector = excluded.vector,
+                 content = excluded.content, metadata = excluded.metadata",
+            )
+            .bind(entry.source.type_name())
+            .bind(&entry.id)
+            .bind(encode(&entry.vector))
+            .bind(&entry.content)
+            .bind(entry.metadata.to_string())
+            .execute(&mut **tx)
+            .await
+            .map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete(&self, source: &SourceType, id: &str) -> Result<bool, Error> {
+        let result = sqlx::query("DELETE FROM rag_vectors WHERE source = ? AND id = ?")
+            .bind(source.type_name())
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the stored vectors with `entries` in a single transaction, so
+    /// readers see either the previous snapshot or the new one.
+    pub async fn snapshot(&self, entries: &[StoredVector]) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+        sqlx::query("DELETE FROM rag_vectors")
+            .execute(&mut *tx)
+            .await
+            .map_err(store_error)?;
+        self.write(&mut tx, entries).await?;
+        tx.commit().await.map_err(store_error)
+    }
+
+    pub async fn load(&self) -> Result<Vec<StoredVector>, Error> {
+        let rows = sqlx::query(
+            "SELECT source, id, vector, content, metadata FROM rag_vectors ORDER BY source, id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(store_error)?;
+        rows.into_iter()
+            .map(|row| {
+                let source: String = row.get("source");
+                let metadata: String = row.get("metadata");
+                Ok(StoredVector {
+                    source: SourceType::from_type_name(&source).ok_or_else(|| {
+                        Error::VectorStore(format!("unknown source type {}", source))
+                    })?,
+                    id: row.get("id"),
+                    vector: decode(row.get("vector")),
+                    content: row.get("content"),
+                    metadata: serde_json::from_str(&metadata).map_err(store_error)?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn count(&self) -> Result<usize, Error> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM rag_vectors")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(store_error)?;
+        Ok(count as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::{Embedder, HashingEmbedder};
+    use crate::retrieval::RAGService;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_store_round_trip_and_model_check() {
+        let path = std::env::temp_dir().join(format!("rag-store-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}", path.display());
+        let embedder: Arc<dyn Embedder> = Arc::new(HashingEmbedder::new(64));
+
+        let store = Arc::new(VectorStore::open(&url, embedder.model(), 64).await.unwrap());
+        let mut rag = RAGService::open(embedder.clone(), store.clone())
+            .await
+            .unwrap();
+        rag.index_texts(
+            SourceType::Table,
+            vec![
+                (
+                    "orders".to_string(),
+                    "orders: order_id, total".to_string(),
+                    serde_json::json!({}),
+                ),
+                (
+                    "customers".to_string(),
+                    "customers: customer_id".to_string(),
+                    serde_json::json!({}),
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        assert!(rag.delete(&SourceType::Table, "customers").await.unwrap());
+        let vector = embedder.embed("refund policy").await.unwrap();
+        rag.index_documentation(
+            "refunds".to_string(),
+            vector,
+            "refund policy".to_string(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        drop(rag);
+        drop(store);
+
+        let store = Arc::new(VectorStore::open(&url, embedder.model(), 64).await.unwrap());
+        assert_eq!(store.count().await.unwrap(), 2);
+        let rag = RAGService::open(embedder.clone(), store.clone())
+            .await
+            .unwrap();
+        let result = rag.retrieve_text("order total", 1, None).await.unwrap();
+        assert_eq!(result.chunks[0].id, "orders");
+
+        store.snapshot(&[]).await.unwrap();
+        assert_eq!(store.count().await.unwrap(), 0);
+
+        assert!(VectorStore::open(&url, "text-embedding-3-small", 64)
+            .await
+            .is_err());
+        assert!(VectorStore::open(&url, embedder.model(), 128)
+            .await
+            .is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
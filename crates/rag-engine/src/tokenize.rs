@@ -0,0 +1,52 @@
+/// Lowercased words. Identifiers are kept whole and also split into their
+/// `snake_case` and `camelCase` parts, so `dim_customer_v2` matches both the
+/// exact name and "customer".
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for token in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if token.is_empty() {
+            continue;
+        }
+        let mut parts = Vec::new();
+        for piece in token.split('_').filter(|p| !p.is_empty()) {
+            let mut current = String::new();
+            let mut previous_lower = false;
+            for c in piece.chars() {
+                if c.is_uppercase() && previous_lower {
+                    parts.push(std::mem::take(&mut current));
+                }
+                previous_lower = c.is_lowercase() || c.is_ascii_digit();
+                current.extend(c.to_lowercase());
+            }
+            parts.push(current);
+        }
+        let whole = token.to_lowercase();
+        if parts.len() > 1 || parts.first() != Some(&whole) {
+            words.push(whole);
+        }
+        words.extend(parts);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_identifiers() {
+        assert_eq!(
+            tokenize("mrrUsd, dim_customer_v2!"),
+            vec![
+                "mrrusd",
+                "mrr",
+                "usd",
+                "dim_customer_v2",
+                "dim",
+                "customer",
+                "v2"
+            ]
+        );
+        assert_eq!(tokenize("Total revenue"), vec!["total", "revenue"]);
+    }
+}
@@ -1,12 +1,19 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::filter::MetadataFilter;
+use crate::hnsw::{dot, normalize, top_k, HnswConfig, HnswIndex, Scored};
+
+/// Vectors are normalized on insert, so similarity is a dot product. Search is
+/// exact unless an HNSW graph is enabled with [`with_hnsw`](Self::with_hnsw).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorIndex {
     pub dimension: usize,
     pub vectors: HashMap<String, Vec<f32>>,
     pub metadata: HashMap<String, serde_json::Value>,
     pub contents: HashMap<String, String>,
+    #[serde(default)]
+    pub ann: Option<HnswIndex>,
 }
 
 impl VectorIndex {
@@ -16,15 +23,42 @@ impl VectorIndex {
             vectors: HashMap::new(),
             metadata: HashMap::new(),
             contents: HashMap::new(),
+            ann: None,
         }
     }
 
+    /// Answers searches from an HNSW graph instead of scanning every vector.
+    pub fn with_hnsw(mut self, config: HnswConfig) -> Self {
+        let mut ann = HnswIndex::new(config);
+        for id in self.vectors.keys() {
+            ann.insert(id.clone(), &self.vectors);
+        }
+        self.ann = Some(ann);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Adds or replaces `id`. Vectors of the wrong dimension are ignored.
     pub fn add(&mut self, id: String, vector: Vec<f32>, metadata: serde_json::Value) {
         if vector.len() != self.dimension {
             return;
         }
-        self.vectors.insert(id.clone(), vector);
-        self.metadata.insert(id.clone(), metadata);
+        let vector = normalize(vector);
+        let previous = self.vectors.insert(id.clone(), vector);
+        if let Some(ann) = &mut self.ann {
+            if let Some(previous) = previous {
+                ann.remove(&id, previous, &self.vectors);
+            }
+            ann.insert(id.clone(), &self.vectors);
+        }
+        self.metadata.insert(id, metadata);
     }
 
     pub fn add_with_content(
@@ -37,56 +71,88 @@ impl VectorIndex {
         if vector.len() != self.dimension {
             return;
         }
-        self.vectors.insert(id.clone(), vector);
-        self.metadata.insert(id.clone(), metadata);
-        self.contents.insert(id, content);
+        self.contents.insert(id.clone(), content);
+        self.add(id, vector, metadata);
     }
 
-    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
-        if query.len() != self.dimension || self.vectors.is_empty() {
-            return vec![];
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.metadata.remove(id);
+        self.contents.remove(id);
+        let Some(vector) = self.vectors.remove(id) else {
+            return false;
+        };
+        if let Some(ann) = &mut self.ann {
+            ann.remove(id, vector, &self.vectors);
         }
+        true
+    }
 
-        let mut scores: Vec<(String, f32)> = self
-            .vectors
-            .iter()
-            .map(|(id, vec)| (id.clone(), self.cosine_similarity(query, vec)))
-            .collect();
-
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        scores.truncate(k);
-        scores
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        self.search_filtered(query, k, None)
     }
 
-    pub fn search_with_content(&self, query: &[f32], k: usize) -> Vec<(String, String, f32)> {
+    /// Top `k` among vectors whose metadata matches `filter`. With HNSW the
+    /// graph is searched with a growing candidate list until enough matches
+    /// are found.
+    pub fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Vec<(String, f32)> {
         if query.len() != self.dimension || self.vectors.is_empty() {
             return vec![];
         }
+        let query = normalize(query.to_vec());
+        let accept = |id: &str| match filter {
+            Some(filter) => self.metadata.get(id).is_some_and(|m| filter.matches(m)),
+            None => true,
+        };
+        let Some(ann) = &self.ann else {
+            return self.scan(&query, k, accept);
+        };
+
+        let mut fetch = k;
+        loop {
+            let hits = ann.search(&query, fetch, &self.vectors);
+            let exhausted = hits.len() < fetch || fetch >= self.vectors.len();
+            let matched: Vec<(String, f32)> = hits
+                .into_iter()
+                .filter(|(id, _)| accept(id))
+                .take(k)
+                .collect();
+            if matched.len() >= k || exhausted {
+                return matched;
+            }
+            fetch = fetch.saturating_mul(4).min(self.vectors.len());
+        }
+    }
 
-        let mut scores: Vec<(String, String, f32)> = self
-            .vectors
-            .iter()
-            .map(|(id, vec)| {
-                let content = self.contents.get(id).cloned().unwrap_or_default();
-                (id.clone(), content, self.cosine_similarity(query, vec))
-            })
-            .collect();
-
-        scores.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
-        scores.truncate(k);
-        scores
+    /// Exact top-k by scanning every vector, regardless of [`with_hnsw`](Self::with_hnsw).
+    pub fn brute_force(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        self.scan(query, k, |_| true)
     }
 
-    fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
-        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let mag_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let mag_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    fn scan(&self, query: &[f32], k: usize, accept: impl Fn(&str) -> bool) -> Vec<(String, f32)> {
+        let ids: Vec<&String> = self.vectors.keys().filter(|id| accept(id)).collect();
+        let scores = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| Scored(dot(query, &self.vectors[*id]), i));
+        top_k(scores, k)
+            .into_iter()
+            .map(|s| (ids[s.1].clone(), s.0))
+            .collect()
+    }
 
-        if mag_a == 0.0 || mag_b == 0.0 {
-            0.0
-        } else {
-            dot / (mag_a * mag_b)
-        }
+    pub fn search_with_content(&self, query: &[f32], k: usize) -> Vec<(String, String, f32)> {
+        self.search(query, k)
+            .into_iter()
+            .map(|(id, score)| {
+                let content = self.contents.get(&id).cloned().unwrap_or_default();
+                (id, content, score)
+            })
+            .collect()
     }
 
     pub fn get(&self, id: &str) -> Option<(&Vec<f32>, &serde_json::Value)> {
@@ -115,7 +181,34 @@ mod tests {
             serde_json::json!({"text": "world"}),
         );
 
-        let results = index.search(&vec![1.0, 0.0, 0.0], 1);
+        let results = index.search(&[1.0, 0.0, 0.0], 1);
         assert_eq!(results[0].0, "doc1");
     }
+
+    #[test]
+    fn test_hnsw_matches_brute_force_and_supports_removal() {
+        let mut index = VectorIndex::new(2).with_hnsw(HnswConfig::default());
+        index.add_with_content(
+            "a".to_string(),
+            vec![2.0, 0.0],
+            "A".to_string(),
+            serde_json::json!({}),
+        );
+        index.add_with_content(
+            "b".to_string(),
+            vec![0.0, 3.0],
+            "B".to_string(),
+            serde_json::json!({}),
+        );
+
+        let results = index.search_with_content(&[0.0, 1.0], 1);
+        assert_eq!(results[0].0, "b");
+        assert_eq!(results[0].1, "B");
+        assert!((results[0].2 - 1.0).abs() < 1e-6);
+        assert_eq!(index.brute_force(&[0.0, 1.0], 1)[0].0, "b");
+
+        assert!(index.remove("b"));
+        assert_eq!(index.search(&[0.0, 1.0], 1)[0].0, "a");
+        assert_eq!(index.len(), 1);
+    }
 }
@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+#[derive(Clone)]
+struct TokenGuard {
+    token: Option<Arc<str>>,
+    reads: bool,
+}
+
+/// Requires `Authorization: Bearer <token>` on every route of `router` for
+/// requests that are not a read (`GET`, `HEAD` or `OPTIONS`). Without a token
+/// all writes are refused.
+pub fn require_write_token<S>(router: Router<S>, token: Option<String>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    guard(router, token, false)
+}
+
+/// Requires `Authorization: Bearer <token>` on every request to the routes of
+/// `router`, reads included. Without a token all requests are refused.
+pub fn require_token<S>(router: Router<S>, token: Option<String>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    guard(router, token, true)
+}
+
+fn guard<S>(router: Router<S>, token: Option<String>, reads: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let guard = TokenGuard {
+        token: token.map(Arc::from),
+        reads,
+    };
+    router.route_layer(middleware::from_fn_with_state(guard, check_token))
+}
+
+async fn check_token(State(guard): State<TokenGuard>, request: Request, next: Next) -> Response {
+    if !guard.reads
+        && matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS
+        )
+    {
+        return next.run(request).await;
+    }
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (guard.token.as_deref(), provided) {
+        (Some(expected), Some(provided)) if tokens_match(expected, provided) => {
+            next.run(request).await
+        }
+        (None, _) => {
+            let disabled = if guard.reads { "Requests" } else { "Writes" };
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": format!("{} are disabled: no API token is configured", disabled)
+                })),
+            )
+                .into_response()
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Missing or invalid API token" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Compares digests of the tokens in constant time, so neither the content
+/// nor the length of the expected token leaks through response timing.
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let provided = Sha256::digest(provided.as_bytes());
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("secret", ""));
+    }
+}
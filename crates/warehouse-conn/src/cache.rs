@@ -0,0 +1,621 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlparser::ast::{visit_expressions, visit_relations, Expr, Value};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::dialect::Dialect;
+use crate::error::Error;
+use crate::pool::is_read_only;
+use crate::traits::{HealthStatus, PoolStats, QueryResult, TableSchema, Warehouse};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    connection: String,
+    sql: String,
+    tables: Vec<String>,
+    created_at: u64,
+    result: QueryResult,
+    #[serde(skip)]
+    size: usize,
+    #[serde(skip)]
+    last_access: u64,
+}
+
+impl CacheEntry {
+    /// Whether the entry belongs to `connection`, including its
+    /// identity-scoped `<connection>#<user>` entries.
+    fn is_on(&self, connection: &str) -> bool {
+        self.connection
+            .strip_prefix(connection)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('#'))
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    clock: AtomicU64,
+}
+
+pub struct ResultCache {
+    entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    ttl: Duration,
+    max_bytes: usize,
+    disk: Option<PathBuf>,
+    counters: Counters,
+}
+
+impl ResultCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+            max_bytes: 64 * 1024 * 1024,
+            disk: None,
+            counters: Counters::default(),
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_disk(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk = Some(dir.into());
+        self
+    }
+
+    pub fn key(connection: &str, sql: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(connection.as_bytes());
+        hasher.update([0]);
+        hasher.update(normalize_sql(sql).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    pub async fn load(&self) -> Result<usize, Error> {
+        let Some(dir) = &self.disk else {
+            return Ok(0);
+        };
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| Error::Config(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+        let mut loaded = Vec::new();
+        let mut files = tokio::fs::read_dir(dir)
+            .await
+            .map_err(|e| Error::Config(e.to_string()))?;
+        while let Ok(Some(file)) = files.next_entry().await {
+            let path = file.path();
+            let Some(key) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.to_string())
+            else {
+                continue;
+            };
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let entry = tokio::fs::read(&path)
+                .await
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<CacheEntry>(&bytes).ok());
+            match entry {
+                Some(entry) if !self.is_expired(&entry) => loaded.push((key, entry)),
+                _ => {
+                    let _ = tokio::fs::remove_file(&path).await;
+                }
+            }
+        }
+
+        let count = loaded.len();
+        for (key, entry) in loaded {
+            self.insert(key, entry, false).await;
+        }
+        Ok(count)
+    }
+
+    pub async fn get(&self, connection: &str, sql: &str) -> Option<QueryResult> {
+        let key = Self::key(connection, sql);
+        let expired = {
+            let mut entries = self.entries.write().await;
+            match entries.get_mut(&key) {
+                Some(entry) if !self.is_expired(entry) => {
+                    entry.last_access = self.tick();
+                    self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                    return Some(entry.result.clone());
+                }
+                Some(_) => entries.remove(&key).is_some(),
+                None => false,
+            }
+        };
+
+        if expired {
+            self.remove_file(&key).await;
+        }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    pub async fn put(&self, connection: &str, sql: &str, result: &QueryResult) {
+        let Some(tables) = referenced_tables(sql) else {
+            return;
+        };
+        let entry = CacheEntry {
+            connection: connection.to_string(),
+            sql: sql.to_string(),
+            tables,
+            created_at: now_millis(),
+            result: result.clone(),
+            size: 0,
+            last_access: 0,
+        };
+        self.insert(Self::key(connection, sql), entry, true).await;
+    }
+
+    pub async fn execute(
+        &self,
+        connection: &str,
+        warehouse: &dyn Warehouse,
+        sql: &str,
+    ) -> Result<(QueryResult, bool), Error> {
+        if !is_read_only(sql) {
+            let result = warehouse.execute(sql).await?;
+            for table in referenced_tables(sql).unwrap_or_default() {
+                self.invalidate_table(&table).await;
+            }
+            return Ok((result, false));
+        }
+
+        if is_volatile(sql) {
+            return Ok((warehouse.execute(sql).await?, false));
+        }
+        if let Some(result) = self.get(connection, sql).await {
+            return Ok((result, true));
+        }
+        let result = warehouse.execute(sql).await?;
+        self.put(connection, sql, &result).await;
+        Ok((result, false))
+    }
+
+    pub async fn invalidate_table(&self, table: &str) -> usize {
+        let table = table.to_lowercase();
+        self.remove_where(|entry| entry.tables.iter().any(|t| same_table(t, &table)))
+            .await
+    }
+
+    pub async fn invalidate_connection(&self, connection: &str) -> usize {
+        self.remove_where(|entry| entry.is_on(connection)).await
+    }
+
+    /// Results of `connection` that read `table`.
+    pub async fn invalidate_table_on(&self, connection: &str, table: &str) -> usize {
+        let table = table.to_lowercase();
+        self.remove_where(|entry| {
+            entry.is_on(connection) && entry.tables.iter().any(|t| same_table(t, &table))
+        })
+        .await
+    }
+
+    pub async fn clear(&self) {
+        self.remove_where(|_| true).await;
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        let entries = self.entries.read().await;
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            entries: entries.len(),
+            bytes: entries.values().map(|entry| entry.size).sum(),
+        }
+    }
+
+    async fn insert(&self, key: String, mut entry: CacheEntry, persist: bool) {
+        let Ok(bytes) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        entry.size = bytes.len();
+        if entry.size > self.max_bytes {
+            return;
+        }
+        entry.last_access = self.tick();
+
+        let mut evicted = Vec::new();
+        {
+            let mut entries = self.entries.write().await;
+            entries.insert(key.clone(), entry);
+
+            let mut total: usize = entries.values().map(|entry| entry.size).sum();
+            while total > self.max_bytes {
+                let Some(oldest) = entries
+                    .iter()
+                    .filter(|(k, _)| **k != key)
+                    .min_by_key(|(_, entry)| entry.last_access)
+                    .map(|(k, _)| k.clone())
+                else {
+                    break;
+                };
+                if let Some(removed) = entries.remove(&oldest) {
+                    total -= removed.size;
+                }
+                self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+                evicted.push(oldest);
+            }
+        }
+
+        for key in evicted {
+            self.remove_file(&key).await;
+        }
+        if persist {
+            if let Some(dir) = &self.disk {
+                let path = dir.join(format!("{}.json", key));
+                if let Err(e) = tokio::fs::write(&path, bytes).await {
+                    warn!("Failed to persist cache entry {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    async fn remove_where(&self, predicate: impl Fn(&CacheEntry) -> bool) -> usize {
+        let removed: Vec<String> = {
+            let mut entries = self.entries.write().await;
+            let keys: Vec<String> = entries
+                .iter()
+                .filter(|(_, entry)| predicate(entry))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in &keys {
+                entries.remove(key);
+            }
+            keys
+        };
+
+        for key in &removed {
+            self.remove_file(key).await;
+        }
+        removed.len()
+    }
+
+    async fn remove_file(&self, key: &str) {
+        if let Some(dir) = &self.disk {
+            let _ = tokio::fs::remove_file(dir.join(format!("{}.json", key))).await;
+        }
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        now_millis().saturating_sub(entry.created_at) > self.ttl.as_millis() as u64
+    }
+
+    fn tick(&self) -> u64 {
+        self.counters.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Canonical form of `sql` for cache keys: keywords and unquoted identifiers
+/// uppercased, whitespace and comments dropped. Literals and quoted
+/// identifiers are re-escaped so that distinct queries never collide.
+pub fn normalize_sql(sql: &str) -> String {
+    let tokens = match Tokenizer::new(&GenericDialect {}, sql).tokenize() {
+        Ok(tokens) => tokens,
+        Err(_) => return sql.trim().to_string(),
+    };
+
+    let mut parts: Vec<String> = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_) | Token::EOF))
+        .map(|token| match token {
+            Token::Word(word) => match word.quote_style {
+                None => word.value.to_uppercase(),
+                Some(open) => {
+                    let close = match open {
+                        '[' => ']',
+                        other => other,
+                    };
+                    let escaped = word.value.replace(close, &close.to_string().repeat(2));
+                    format!("{}{}{}", open, escaped, close)
+                }
+            },
+            Token::SingleQuotedString(s) => format!("'{}'", s.replace('\'', "''")),
+            Token::DoubleQuotedString(s) => format!("\"{}\"", s.replace('"', "\"\"")),
+            other => {
+                let text = other.to_string();
+                if text.contains(['\'', '"', '$']) {
+                    format!("{:?}", other)
+                } else {
+                    text
+                }
+            }
+        })
+        .collect();
+    while parts.last().map(|part| part == ";").unwrap_or(false) {
+        parts.pop();
+    }
+    parts.join(" ")
+}
+
+const VOLATILE_FUNCTIONS: &[&str] = &[
+    "now",
+    "current_timestamp",
+    "current_date",
+    "current_time",
+    "localtime",
+    "localtimestamp",
+    "clock_timestamp",
+    "statement_timestamp",
+    "transaction_timestamp",
+    "timeofday",
+    "sysdate",
+    "getdate",
+    "random",
+    "rand",
+    "uuid",
+    "gen_random_uuid",
+    "uuid_generate_v4",
+    "randomblob",
+    "nextval",
+    "unixepoch",
+];
+
+/// Whether `sql` calls a function whose result changes between runs, such as
+/// `now()` or `random()`, or uses the `'now'` literal (`date('now')`).
+fn is_volatile(sql: &str) -> bool {
+    let Ok(statements) = Parser::parse_sql(&GenericDialect {}, sql) else {
+        return true;
+    };
+    visit_expressions(&statements, |expr| {
+        let volatile = match expr {
+            Expr::Function(function) => function.name.0.last().is_some_and(|ident| {
+                VOLATILE_FUNCTIONS.contains(&ident.value.to_lowercase().as_str())
+            }),
+            Expr::Value(Value::SingleQuotedString(s)) => s.eq_ignore_ascii_case("now"),
+            _ => false,
+        };
+        if volatile {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .is_break()
+}
+
+fn referenced_tables(sql: &str) -> Option<Vec<String>> {
+    let statements = Parser::parse_sql(&GenericDialect {}, sql).ok()?;
+    let mut tables = Vec::new();
+    let _ = visit_relations(&statements, |relation| {
+        let name = relation
+            .0
+            .iter()
+            .map(|ident| ident.value.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(".");
+        if !tables.contains(&name) {
+            tables.push(name);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Some(tables)
+}
+
+fn same_table(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    let last = |name: &str| name.rsplit('.').next().unwrap_or(name).to_string();
+    (!a.contains('.') || !b.contains('.')) && last(a) == last(b)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub struct CachedWarehouse {
+    inner: Arc<dyn Warehouse>,
+    cache: Arc<ResultCache>,
+    connection: String,
+}
+
+impl CachedWarehouse {
+    pub fn new(inner: Arc<dyn Warehouse>, cache: Arc<ResultCache>, connection: &str) -> Self {
+        Self {
+            inner,
+            cache,
+            connection: connection.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Warehouse for CachedWarehouse {
+    async fn connect(&self) -> Result<(), Error> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.inner.disconnect().await
+    }
+
+    async fn execute(&self, sql: &str) -> Result<QueryResult, Error> {
+        let (result, _) = self
+            .cache
+            .execute(&self.connection, self.inner.as_ref(), sql)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_schema(&self, table_name: &str) -> Result<TableSchema, Error> {
+        self.inner.get_schema(table_name).await
+    }
+
+    async fn list_tables(&self) -> Result<Vec<String>, Error> {
+        self.inner.list_tables().await
+    }
+
+    async fn preview_table(&self, table_name: &str, limit: usize) -> Result<QueryResult, Error> {
+        self.inner.preview_table(table_name, limit).await
+    }
+
+    fn dialect(&self) -> &dyn Dialect {
+        self.inner.dialect()
+    }
+
+    async fn health(&self) -> HealthStatus {
+        self.inner.health().await
+    }
+
+    async fn pool_stats(&self) -> Option<PoolStats> {
+        self.inner.pool_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::SqliteWarehouse;
+
+    fn result(value: i64) -> QueryResult {
+        QueryResult {
+            columns: vec!["n".to_string()],
+            rows: vec![vec![serde_json::json!(value)]],
+            row_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_normalize_sql() {
+        assert_eq!(
+            normalize_sql("select  id\n from Users -- comment\n where name = 'Bob';"),
+            "SELECT ID FROM USERS WHERE NAME = 'Bob'"
+        );
+        assert_eq!(
+            ResultCache::key("db", "SELECT 1"),
+            ResultCache::key("db", "select 1;")
+        );
+        assert_ne!(
+            ResultCache::key("db", "SELECT 1"),
+            ResultCache::key("other", "SELECT 1")
+        );
+        assert_ne!(
+            normalize_sql("SELECT * FROM t WHERE a = 'x'' OR ''y'"),
+            normalize_sql("SELECT * FROM t WHERE a = 'x' OR 'y'")
+        );
+        assert_ne!(
+            normalize_sql("SELECT 'a  b'"),
+            normalize_sql("SELECT 'a b'")
+        );
+    }
+
+    #[test]
+    fn test_read_only_and_volatile_classification() {
+        assert!(is_read_only("WITH t AS (SELECT 1) SELECT * FROM t"));
+        assert!(!is_read_only(
+            "WITH gone AS (DELETE FROM orders RETURNING id) SELECT * FROM gone"
+        ));
+        assert!(!is_read_only("EXPLAIN ANALYZE DELETE FROM orders"));
+        assert!(is_volatile("SELECT now()"));
+        assert!(is_volatile(
+            "SELECT * FROM orders WHERE created_at > date('now')"
+        ));
+        assert!(is_volatile("SELECT random() FROM orders"));
+        assert!(!is_volatile("SELECT COUNT(*) FROM orders"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_size_and_invalidation() {
+        let cache = ResultCache::new(Duration::from_secs(60));
+        cache.put("db", "SELECT n FROM orders", &result(1)).await;
+        cache.put("db", "SELECT n FROM users", &result(2)).await;
+        assert!(cache.get("db", "select n from orders").await.is_some());
+        assert!(cache.get("other", "SELECT n FROM orders").await.is_none());
+
+        assert_eq!(cache.invalidate_table("public.orders").await, 1);
+        assert!(cache.get("db", "SELECT n FROM orders").await.is_none());
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+
+        let expired = ResultCache::new(Duration::ZERO);
+        expired.put("db", "SELECT n FROM orders", &result(1)).await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(expired.get("db", "SELECT n FROM orders").await.is_none());
+
+        let entry_size = stats.bytes;
+        let small = ResultCache::new(Duration::from_secs(60)).with_max_bytes(entry_size * 2);
+        for n in 0..3 {
+            small
+                .put("db", &format!("SELECT n FROM t{}", n), &result(n))
+                .await;
+        }
+        let stats = small.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+        assert!(small.get("db", "SELECT n FROM t0").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cached_warehouse_and_disk_backend() {
+        let dir = std::env::temp_dir().join(format!("querysmith-cache-{}", std::process::id()));
+        let cache = Arc::new(ResultCache::new(Duration::from_secs(60)).with_disk(&dir));
+        cache.load().await.unwrap();
+
+        let sqlite: Arc<dyn Warehouse> =
+            Arc::new(SqliteWarehouse::new("sqlite::memory:").with_max_connections(1));
+        let warehouse = CachedWarehouse::new(sqlite.clone(), cache.clone(), "local");
+        warehouse
+            .execute("CREATE TABLE items (id INTEGER)")
+            .await
+            .unwrap();
+        warehouse
+            .execute("INSERT INTO items VALUES (1)")
+            .await
+            .unwrap();
+
+        let sql = "SELECT COUNT(*) AS n FROM items";
+        let (_, hit) = cache.execute("local", sqlite.as_ref(), sql).await.unwrap();
+        assert!(!hit);
+        let (_, hit) = cache.execute("local", sqlite.as_ref(), sql).await.unwrap();
+        assert!(hit);
+
+        let reloaded = ResultCache::new(Duration::from_secs(60)).with_disk(&dir);
+        assert!(reloaded.load().await.unwrap() >= 1);
+        assert!(reloaded.get("local", sql).await.is_some());
+
+        warehouse
+            .execute("INSERT INTO items VALUES (2)")
+            .await
+            .unwrap();
+        let result = warehouse.execute(sql).await.unwrap();
+        assert_eq!(result.rows[0][0], serde_json::json!(2));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,394 @@
+use serde::{Deserialize, Serialize};
+
+use crate::factory::Backend;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateUnit {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl DateUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DateUnit::Day => "day",
+            DateUnit::Week => "week",
+            DateUnit::Month => "month",
+            DateUnit::Quarter => "quarter",
+            DateUnit::Year => "year",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DialectFeatures {
+    pub ilike: bool,
+    pub full_outer_join: bool,
+    pub window_functions: bool,
+    pub common_table_expressions: bool,
+    pub returning: bool,
+    pub regex_match: bool,
+    pub native_date_trunc: bool,
+    pub double_colon_cast: bool,
+    pub boolean_type: bool,
+}
+
+impl DialectFeatures {
+    pub fn describe(&self) -> (Vec<&'static str>, Vec<&'static str>) {
+        let features = [
+            ("ILIKE", self.ilike),
+            ("FULL OUTER JOIN", self.full_outer_join),
+            ("window functions", self.window_functions),
+            ("CTEs (WITH ...)", self.common_table_expressions),
+            ("RETURNING", self.returning),
+            ("regular expression matching", self.regex_match),
+            ("DATE_TRUNC", self.native_date_trunc),
+            ("'::' casts", self.double_colon_cast),
+            ("BOOLEAN type", self.boolean_type),
+        ];
+
+        let supported = features
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect();
+        let unsupported = features
+            .iter()
+            .filter(|(_, enabled)| !*enabled)
+            .map(|(name, _)| *name)
+            .collect();
+        (supported, unsupported)
+    }
+}
+
+pub trait Dialect: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn backend(&self) -> Backend;
+    fn quote_identifier(&self, ident: &str) -> String;
+    fn date_trunc(&self, unit: DateUnit, expr: &str) -> String;
+    fn date_add(&self, expr: &str, amount: i64, unit: DateUnit) -> String;
+    fn features(&self) -> DialectFeatures;
+
+    fn quote_qualified(&self, name: &str) -> String {
+        name.split('.')
+            .map(|part| self.quote_identifier(part))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn quote_string(&self, value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    fn limit(&self, sql: &str, limit: usize) -> String {
+        format!("{} LIMIT {}", sql.trim_end().trim_end_matches(';'), limit)
+    }
+
+    fn current_date(&self) -> String {
+        "CURRENT_DATE".to_string()
+    }
+
+    fn concat(&self, parts: &[&str]) -> String {
+        parts.join(" || ")
+    }
+
+    fn cheat_sheet(&self) -> String {
+        let (supported, unsupported) = self.features().describe();
+        let mut sheet = format!("## SQL Dialect: {}\n", self.name());
+        sheet.push_str(&format!(
+            "- Quote identifiers: {}\n",
+            self.quote_identifier("order")
+        ));
+        sheet.push_str(&format!(
+            "- Limit rows: {}\n",
+            self.limit("SELECT * FROM t", 10)
+        ));
+        sheet.push_str(&format!(
+            "- Truncate dates: {}\n",
+            self.date_trunc(DateUnit::Month, "created_at")
+        ));
+        sheet.push_str(&format!("- Current date: {}\n", self.current_date()));
+        sheet.push_str(&format!(
+            "- Date arithmetic: {}\n",
+            self.date_add("created_at", -7, DateUnit::Day)
+        ));
+        sheet.push_str(&format!(
+            "- String concatenation: {}\n",
+            self.concat(&["first_name", "' '", "last_name"])
+        ));
+        if !supported.is_empty() {
+            sheet.push_str(&format!("- Supported: {}\n", supported.join(", ")));
+        }
+        if !unsupported.is_empty() {
+            sheet.push_str(&format!("- Not supported: {}\n", unsupported.join(", ")));
+        }
+        sheet
+    }
+}
+
+pub struct PostgresDialect;
+
+impl Dialect for PostgresDialect {
+    fn name(&self) -> &'static str {
+        "PostgreSQL"
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Postgres
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+
+    fn date_trunc(&self, unit: DateUnit, expr: &str) -> String {
+        format!("DATE_TRUNC('{}', {})", unit.as_str(), expr)
+    }
+
+    fn date_add(&self, expr: &str, amount: i64, unit: DateUnit) -> String {
+        format!("{} + INTERVAL '{} {}'", expr, amount, unit.as_str())
+    }
+
+    fn features(&self) -> DialectFeatures {
+        DialectFeatures {
+            ilike: true,
+            full_outer_join: true,
+            window_functions: true,
+            common_table_expressions: true,
+            returning: true,
+            regex_match: true,
+            native_date_trunc: true,
+            double_colon_cast: true,
+            boolean_type: true,
+        }
+    }
+}
+
+pub struct SqliteDialect;
+
+impl Dialect for SqliteDialect {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Sqlite
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+
+    fn date_trunc(&self, unit: DateUnit, expr: &str) -> String {
+        match unit {
+            DateUnit::Day => format!("DATE({})", expr),
+            DateUnit::Week => format!("DATE({}, 'weekday 0', '-6 days')", expr),
+            DateUnit::Month => format!("DATE({}, 'start of month')", expr),
+            DateUnit::Quarter => format!(
+                "DATE({0}, 'start of month', printf('-%d months', (CAST(strftime('%m', {0}) AS INTEGER) - 1) % 3))",
+                expr
+            ),
+            DateUnit::Year => format!("DATE({}, 'start of year')", expr),
+        }
+    }
+
+    fn date_add(&self, expr: &str, amount: i64, unit: DateUnit) -> String {
+        let (amount, modifier) = match unit {
+            DateUnit::Day => (amount, "days"),
+            DateUnit::Week => (amount * 7, "days"),
+            DateUnit::Month => (amount, "months"),
+            DateUnit::Quarter => (amount * 3, "months"),
+            DateUnit::Year => (amount, "years"),
+        };
+        // DATE() would drop the time of day from timestamps.
+        let upper = expr.trim().to_uppercase();
+        let function = if upper.starts_with("DATE(") || upper == "CURRENT_DATE" {
+            "DATE"
+        } else {
+            "DATETIME"
+        };
+        format!("{}({}, '{:+} {}')", function, expr, amount, modifier)
+    }
+
+    fn current_date(&self) -> String {
+        "DATE('now')".to_string()
+    }
+
+    fn features(&self) -> DialectFeatures {
+        DialectFeatures {
+            ilike: false,
+            full_outer_join: true,
+            window_functions: true,
+            common_table_expressions: true,
+            returning: true,
+            regex_match: false,
+            native_date_trunc: false,
+            double_colon_cast: false,
+            boolean_type: false,
+        }
+    }
+}
+
+pub struct DuckDbDialect;
+
+impl Dialect for DuckDbDialect {
+    fn name(&self) -> &'static str {
+        "DuckDB"
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::DuckDb
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    }
+
+    fn date_trunc(&self, unit: DateUnit, expr: &str) -> String {
+        format!("DATE_TRUNC('{}', {})", unit.as_str(), expr)
+    }
+
+    fn date_add(&self, expr: &str, amount: i64, unit: DateUnit) -> String {
+        format!(
+            "{} + INTERVAL {} {}",
+            expr,
+            amount,
+            unit.as_str().to_uppercase()
+        )
+    }
+
+    fn features(&self) -> DialectFeatures {
+        DialectFeatures {
+            ilike: true,
+            full_outer_join: true,
+            window_functions: true,
+            common_table_expressions: true,
+            returning: true,
+            regex_match: true,
+            native_date_trunc: true,
+            double_colon_cast: true,
+            boolean_type: true,
+        }
+    }
+}
+
+pub struct MySqlDialect;
+
+impl Dialect for MySqlDialect {
+    fn name(&self) -> &'static str {
+        "MySQL"
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::MySql
+    }
+
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("`{}`", ident.replace('`', "``"))
+    }
+
+    fn quote_string(&self, value: &str) -> String {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+    }
+
+    fn date_trunc(&self, unit: DateUnit, expr: &str) -> String {
+        match unit {
+            DateUnit::Day => format!("DATE({})", expr),
+            DateUnit::Week => format!("DATE_SUB(DATE({0}), INTERVAL WEEKDAY({0}) DAY)", expr),
+            DateUnit::Month => format!("DATE_FORMAT({}, '%Y-%m-01')", expr),
+            DateUnit::Quarter => format!(
+                "MAKEDATE(YEAR({0}), 1) + INTERVAL QUARTER({0}) - 1 QUARTER",
+                expr
+            ),
+            DateUnit::Year => format!("DATE_FORMAT({}, '%Y-01-01')", expr),
+        }
+    }
+
+    fn date_add(&self, expr: &str, amount: i64, unit: DateUnit) -> String {
+        format!(
+            "DATE_ADD({}, INTERVAL {} {})",
+            expr,
+            amount,
+            unit.as_str().to_uppercase()
+        )
+    }
+
+    fn concat(&self, parts: &[&str]) -> String {
+        format!("CONCAT({})", parts.join(", "))
+    }
+
+    fn features(&self) -> DialectFeatures {
+        DialectFeatures {
+            ilike: false,
+            full_outer_join: false,
+            window_functions: true,
+            common_table_expressions: true,
+            returning: false,
+            regex_match: true,
+            native_date_trunc: false,
+            double_colon_cast: false,
+            boolean_type: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_quoting() {
+        assert_eq!(PostgresDialect.quote_identifier("order"), "\"order\"");
+        assert_eq!(MySqlDialect.quote_identifier("order"), "`order`");
+        assert_eq!(
+            SqliteDialect.quote_qualified("main.order items"),
+            "\"main\".\"order items\""
+        );
+        assert_eq!(PostgresDialect.quote_identifier("a\"b"), "\"a\"\"b\"");
+        assert_eq!(Backend::MySql.dialect().backend(), Backend::MySql);
+    }
+
+    #[test]
+    fn test_limit_and_strings() {
+        assert_eq!(
+            SqliteDialect.limit("SELECT * FROM users;", 5),
+            "SELECT * FROM users LIMIT 5"
+        );
+        assert_eq!(PostgresDialect.quote_string("O'Brien"), "'O''Brien'");
+    }
+
+    #[test]
+    fn test_date_functions() {
+        assert_eq!(
+            PostgresDialect.date_trunc(DateUnit::Month, "created_at"),
+            "DATE_TRUNC('month', created_at)"
+        );
+        assert_eq!(
+            SqliteDialect.date_trunc(DateUnit::Month, "created_at"),
+            "DATE(created_at, 'start of month')"
+        );
+        assert_eq!(
+            SqliteDialect.date_add("created_at", -2, DateUnit::Week),
+            "DATETIME(created_at, '-14 days')"
+        );
+        assert_eq!(
+            MySqlDialect.date_add("created_at", 3, DateUnit::Month),
+            "DATE_ADD(created_at, INTERVAL 3 MONTH)"
+        );
+    }
+
+    #[test]
+    fn test_cheat_sheet() {
+        let sheet = SqliteDialect.cheat_sheet();
+        assert!(sheet.contains("SQLite"));
+        assert!(sheet.contains("start of month"));
+        assert!(sheet.contains("Not supported: ILIKE"));
+
+        let sheet = MySqlDialect.cheat_sheet();
+        assert!(sheet.contains("CONCAT(first_name, ' ', last_name)"));
+    }
+}
@@ -8,4 +8,12 @@ pub enum Error {
     Connection(String),
     #[error("Query error: {0}")]
     Query(String),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Config error: {0}")]
+    Config(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
 }
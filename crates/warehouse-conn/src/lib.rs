@@ -1,9 +1,31 @@
+pub mod auth;
+pub mod cache;
+pub mod dialect;
 pub mod error;
+pub mod factory;
+pub mod pii;
+pub mod policy;
+pub mod pool;
 pub mod postgres;
+pub mod registry;
 pub mod sqlite;
 pub mod traits;
+pub mod transpile;
 
+pub use cache::{normalize_sql, CacheStats, CachedWarehouse, ResultCache};
+pub use dialect::{
+    DateUnit, Dialect, DialectFeatures, DuckDbDialect, MySqlDialect, PostgresDialect, SqliteDialect,
+};
 pub use error::Error;
-pub use postgres::PostgresWarehouse;
-pub use sqlite::SqliteWarehouse;
-pub use traits::{Column, QueryResult, TableSchema, Warehouse};
+pub use factory::{connect, from_url, Backend};
+pub use pii::{MaskAction, MaskedResult, PiiConfig, PiiFinding, PiiKind, PiiMasker};
+pub use policy::{
+    AuditEvent, AuditSink, DefaultAction, EffectivePolicy, Identity, MemoryAuditSink, Policy,
+    PolicyConfig, PolicyEngine, PolicyWarehouse, TablePolicy, TracingAuditSink,
+};
+pub use pool::RetryPolicy;
+pub use postgres::{PostgresWarehouse, PostgresWarehouseOptions};
+pub use registry::{RegistryConfig, WarehouseConfig, WarehouseRegistry};
+pub use sqlite::{SqliteWarehouse, SqliteWarehouseOptions};
+pub use traits::{Column, HealthStatus, PoolStats, QueryResult, TableSchema, Warehouse};
+pub use transpile::{parser_dialect, transpile, TranspileIssue, Transpiled};
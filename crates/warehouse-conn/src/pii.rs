@@ -0,0 +1,721 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlparser::ast::{
+    visit_expressions, Expr, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
+    TableWithJoins,
+};
+use sqlparser::parser::Parser;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::ControlFlow;
+
+use crate::dialect::Dialect;
+use crate::error::Error;
+use crate::policy::{object_key, table_keys, Identity};
+use crate::traits::QueryResult;
+use crate::transpile::parser_dialect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CardNumber,
+    NationalId,
+    TaggedColumn,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaskAction {
+    #[default]
+    Mask,
+    Hash,
+    Drop,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PiiConfig {
+    #[serde(default)]
+    pub default_action: MaskAction,
+    #[serde(default)]
+    pub actions: HashMap<PiiKind, MaskAction>,
+    /// Tagged columns as `table.column`; a bare `column` is tagged in every table.
+    #[serde(default)]
+    pub pii_columns: Vec<String>,
+    #[serde(default)]
+    pub authorized_users: Vec<String>,
+    #[serde(default)]
+    pub authorized_groups: Vec<String>,
+}
+
+impl PiiConfig {
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(yaml).map_err(|e| Error::Config(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PiiFinding {
+    pub column: String,
+    pub kind: PiiKind,
+    pub action: MaskAction,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskedResult {
+    pub result: QueryResult,
+    pub findings: Vec<PiiFinding>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PiiMasker {
+    config: PiiConfig,
+}
+
+impl PiiMasker {
+    pub fn new(config: PiiConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn with_pii_columns(mut self, columns: Vec<String>) -> Self {
+        self.config.pii_columns.extend(columns);
+        self
+    }
+
+    pub fn action(&self, kind: PiiKind) -> MaskAction {
+        self.config
+            .actions
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.config.default_action)
+    }
+
+    pub fn is_authorized(&self, identity: &Identity) -> bool {
+        self.config.authorized_users.contains(&identity.user_id)
+            || identity
+                .groups
+                .iter()
+                .any(|group| self.config.authorized_groups.contains(group))
+    }
+
+    /// Whether `column` is tagged in some table.
+    fn is_tagged(&self, column: &str) -> bool {
+        self.config.pii_columns.iter().any(|tag| {
+            let name = tag.rsplit_once('.').map_or(tag.as_str(), |(_, name)| name);
+            name.eq_ignore_ascii_case(column)
+        })
+    }
+
+    /// Whether `column` of `table` is tagged.
+    fn is_tagged_in(&self, table: &str, column: &str) -> bool {
+        let keys = table_keys(table);
+        self.config
+            .pii_columns
+            .iter()
+            .any(|tag| match tag.rsplit_once('.') {
+                Some((tagged, name)) => {
+                    name.eq_ignore_ascii_case(column) && keys.contains(&tagged.to_lowercase())
+                }
+                None => tag.eq_ignore_ascii_case(column),
+            })
+    }
+
+    /// Which result columns of `sql` come from tagged columns, following
+    /// aliases, subqueries and CTEs back to the tables they read. Falls back
+    /// to matching result column names when the query cannot be analysed.
+    fn tagged_columns(&self, sql: &str, dialect: &dyn Dialect, columns: &[String]) -> Vec<bool> {
+        let by_name = || columns.iter().map(|c| self.is_tagged(c)).collect();
+        let parser = parser_dialect(dialect.backend());
+        let statements = match Parser::parse_sql(parser.as_ref(), sql) {
+            Ok(statements) => statements,
+            Err(_) => return by_name(),
+        };
+        let Some(Statement::Query(query)) = statements.last() else {
+            return by_name();
+        };
+        let output = self.resolve_query(query, &HashMap::new());
+        if output.open.is_empty() && output.columns.len() == columns.len() {
+            return output.columns.iter().map(|(_, tagged)| *tagged).collect();
+        }
+        columns.iter().map(|c| output.is_tagged(self, c)).collect()
+    }
+
+    fn resolve_query(&self, query: &Query, ctes: &HashMap<String, Output>) -> Output {
+        let mut ctes = ctes.clone();
+        for cte in query.with.iter().flat_map(|with| &with.cte_tables) {
+            let output = self.resolve_query(&cte.query, &ctes);
+            ctes.insert(cte.alias.name.value.to_lowercase(), output);
+        }
+        self.resolve_set_expr(&query.body, &ctes)
+    }
+
+    fn resolve_set_expr(&self, body: &SetExpr, ctes: &HashMap<String, Output>) -> Output {
+        match body {
+            SetExpr::Select(select) => self.resolve_select(select, ctes),
+            SetExpr::Query(query) => self.resolve_query(query, ctes),
+            SetExpr::SetOperation { left, right, .. } => {
+                let mut left = self.resolve_set_expr(left, ctes);
+                let right = self.resolve_set_expr(right, ctes);
+                if left.open.is_empty()
+                    && right.open.is_empty()
+                    && left.columns.len() == right.columns.len()
+                {
+                    for (column, (_, tagged)) in left.columns.iter_mut().zip(right.columns) {
+                        column.1 |= tagged;
+                    }
+                } else {
+                    left.columns.extend(right.columns);
+                    left.open.extend(right.open);
+                }
+                left
+            }
+            _ => Output::default(),
+        }
+    }
+
+    fn resolve_select(&self, select: &Select, ctes: &HashMap<String, Output>) -> Output {
+        let mut scope = Vec::new();
+        for table in &select.from {
+            self.scope_tables(table, ctes, &mut scope);
+        }
+        let expr_tagged = |expr: &Expr| {
+            visit_expressions(expr, |expr| {
+                let tagged = match expr {
+                    Expr::Identifier(column) => scope
+                        .iter()
+                        .any(|(_, relation)| relation.is_tagged(self, &column.value)),
+                    Expr::CompoundIdentifier(parts) if parts.len() > 1 => {
+                        let (column, qualifier) = parts.split_last().unwrap();
+                        let qualifier = qualifier
+                            .iter()
+                            .map(|ident| ident.value.to_lowercase())
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        match scope.iter().find(|(keys, _)| keys.contains(&qualifier)) {
+                            Some((_, relation)) => relation.is_tagged(self, &column.value),
+                            None => self.is_tagged(&column.value),
+                        }
+                    }
+                    _ => false,
+                };
+                if tagged {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .is_break()
+        };
+
+        let mut output = Output::default();
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let name = match expr {
+                        Expr::Identifier(ident) => ident.value.clone(),
+                        Expr::CompoundIdentifier(parts) => {
+                            parts.last().map(|i| i.value.clone()).unwrap_or_default()
+                        }
+                        _ => expr.to_string(),
+                    };
+                    output.columns.push((name, expr_tagged(expr)));
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    output
+                        .columns
+                        .push((alias.value.clone(), expr_tagged(expr)));
+                }
+                SelectItem::Wildcard(_) => {
+                    for (_, relation) in &scope {
+                        output.expand(relation);
+                    }
+                }
+                SelectItem::QualifiedWildcard(name, _) => {
+                    let qualifier = object_key(name).to_lowercase();
+                    if let Some((_, relation)) =
+                        scope.iter().find(|(keys, _)| keys.contains(&qualifier))
+                    {
+                        output.expand(relation);
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    fn scope_tables(
+        &self,
+        table: &TableWithJoins,
+        ctes: &HashMap<String, Output>,
+        scope: &mut Vec<(Vec<String>, Relation)>,
+    ) {
+        for factor in
+            std::iter::once(&table.relation).chain(table.joins.iter().map(|j| &j.relation))
+        {
+            match factor {
+                TableFactor::Table { name, alias, .. } => {
+                    let full = object_key(name);
+                    let relation = match ctes.get(&full.to_lowercase()) {
+                        Some(output) => Relation::Derived(output.clone()),
+                        None => Relation::Table(full.clone()),
+                    };
+                    let keys = match alias {
+                        Some(alias) => vec![alias.name.value.to_lowercase()],
+                        None => table_keys(&full),
+                    };
+                    scope.push((keys, relation));
+                }
+                TableFactor::Derived {
+                    subquery, alias, ..
+                } => {
+                    let keys = alias
+                        .iter()
+                        .map(|alias| alias.name.value.to_lowercase())
+                        .collect();
+                    scope.push((keys, Relation::Derived(self.resolve_query(subquery, ctes))));
+                }
+                TableFactor::NestedJoin {
+                    table_with_joins, ..
+                } => self.scope_tables(table_with_joins, ctes, scope),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn detect(value: &str) -> Option<PiiKind> {
+        let value = value.trim();
+        if is_card_number(value) {
+            Some(PiiKind::CardNumber)
+        } else if is_national_id(value) {
+            Some(PiiKind::NationalId)
+        } else if is_phone(value) {
+            Some(PiiKind::Phone)
+        } else if value
+            .split_whitespace()
+            .any(|token| is_email(trim_token(token)))
+        {
+            Some(PiiKind::Email)
+        } else {
+            None
+        }
+    }
+
+    /// Masks `result` by its column names alone: a column is tagged when any
+    /// table tags a column of that name. Prefer [`mask_query_result`](Self::mask_query_result).
+    pub fn mask_result(&self, result: &QueryResult) -> MaskedResult {
+        let tagged: Vec<bool> = result.columns.iter().map(|c| self.is_tagged(c)).collect();
+        self.mask_tagged(result, &tagged)
+    }
+
+    /// Masks the result of `sql`, resolving tagged columns through the
+    /// query's projection so that aliases neither hide a tagged column nor
+    /// tag same-named columns of other tables.
+    pub fn mask_query_result(
+        &self,
+        sql: &str,
+        dialect: &dyn Dialect,
+        result: &QueryResult,
+    ) -> MaskedResult {
+        let tagged = if self.config.pii_columns.is_empty() {
+            vec![false; result.columns.len()]
+        } else {
+            self.tagged_columns(sql, dialect, &result.columns)
+        };
+        self.mask_tagged(result, &tagged)
+    }
+
+    fn mask_tagged(&self, result: &QueryResult, tagged: &[bool]) -> MaskedResult {
+        let mut counts: BTreeMap<(usize, PiiKind), usize> = BTreeMap::new();
+        let mut dropped = vec![false; result.columns.len()];
+
+        for (i, drop) in dropped.iter_mut().enumerate() {
+            if tagged.get(i).copied().unwrap_or(false) {
+                if self.action(PiiKind::TaggedColumn) == MaskAction::Drop {
+                    *drop = true;
+                }
+                counts.insert((i, PiiKind::TaggedColumn), result.row_count);
+            }
+        }
+
+        let rows = result
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(i, _)| !dropped.get(*i).copied().unwrap_or(false))
+                    .map(|(i, value)| {
+                        let tagged = tagged.get(i).copied().unwrap_or(false);
+                        let (masked, kind) = self.mask_value(value, tagged);
+                        if let Some(kind) = kind.filter(|kind| *kind != PiiKind::TaggedColumn) {
+                            *counts.entry((i, kind)).or_default() += 1;
+                        }
+                        masked
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let columns = result
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !dropped[*i])
+            .map(|(_, column)| column.clone())
+            .collect();
+
+        let findings = counts
+            .into_iter()
+            .map(|((i, kind), count)| PiiFinding {
+                column: result.columns[i].clone(),
+                kind,
+                action: self.action(kind),
+                count,
+            })
+            .collect();
+
+        MaskedResult {
+            result: QueryResult {
+                columns,
+                rows,
+                row_count: result.row_count,
+            },
+            findings,
+        }
+    }
+
+    fn mask_value(
+        &self,
+        value: &serde_json::Value,
+        tagged: bool,
+    ) -> (serde_json::Value, Option<PiiKind>) {
+        // Only strings are scanned for patterns: numeric ids, timestamps and
+        // amounts pass Luhn or look like phone numbers far too often.
+        let text = match value {
+            serde_json::Value::Null => return (value.clone(), None),
+            serde_json::Value::String(s) => s.clone(),
+            _ if tagged => value.to_string(),
+            _ => return (value.clone(), None),
+        };
+
+        let kind = if tagged {
+            PiiKind::TaggedColumn
+        } else {
+            match Self::detect(&text) {
+                Some(kind) => kind,
+                None => return (value.clone(), None),
+            }
+        };
+
+        let masked = match self.action(kind) {
+            MaskAction::Drop => serde_json::Value::Null,
+            MaskAction::Hash => serde_json::Value::String(hash(&text)),
+            MaskAction::Mask => serde_json::Value::String(mask(&text, kind)),
+        };
+        (masked, Some(kind))
+    }
+}
+
+/// Columns a query or subquery produces, by name, with whether each comes
+/// from a tagged column. `open` lists tables expanded by a wildcard, whose
+/// columns are only known by name at run time.
+#[derive(Debug, Clone, Default)]
+struct Output {
+    columns: Vec<(String, bool)>,
+    open: Vec<String>,
+}
+
+impl Output {
+    fn is_tagged(&self, masker: &PiiMasker, column: &str) -> bool {
+        self.columns
+            .iter()
+            .any(|(name, tagged)| *tagged && name.eq_ignore_ascii_case(column))
+            || self
+                .open
+                .iter()
+                .any(|table| masker.is_tagged_in(table, column))
+    }
+
+    fn expand(&mut self, relation: &Relation) {
+        match relation {
+            Relation::Table(table) => self.open.push(table.clone()),
+            Relation::Derived(output) => {
+                self.columns.extend(output.columns.iter().cloned());
+                self.open.extend(output.open.iter().cloned());
+            }
+        }
+    }
+}
+
+/// A table or derived table in a `FROM` clause.
+enum Relation {
+    Table(String),
+    Derived(Output),
+}
+
+impl Relation {
+    fn is_tagged(&self, masker: &PiiMasker, column: &str) -> bool {
+        match self {
+            Relation::Table(table) => masker.is_tagged_in(table, column),
+            Relation::Derived(output) => output.is_tagged(masker, column),
+        }
+    }
+}
+
+fn hash(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    format!("sha256:{}", &hex::encode(digest)[..16])
+}
+
+fn mask(value: &str, kind: PiiKind) -> String {
+    match kind {
+        PiiKind::Email => value
+            .split_whitespace()
+            .map(|token| {
+                let trimmed = trim_token(token);
+                if is_email(trimmed) {
+                    token.replace(trimmed, &mask_email(trimmed))
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        PiiKind::Phone | PiiKind::CardNumber | PiiKind::NationalId => {
+            let digits: Vec<char> = value.chars().filter(|c| c.is_ascii_digit()).collect();
+            let last4: String = digits[digits.len().saturating_sub(4)..].iter().collect();
+            format!("***{}", last4)
+        }
+        PiiKind::TaggedColumn => "***".to_string(),
+    }
+}
+
+fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first: String = local.chars().take(1).collect();
+            format!("{}***@{}", first, domain)
+        }
+        None => "***".to_string(),
+    }
+}
+
+fn trim_token(token: &str) -> &str {
+    token
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '@' && c != '.' && c != '_')
+        .trim_end_matches('.')
+}
+
+fn is_email(token: &str) -> bool {
+    let Some((local, domain)) = token.split_once('@') else {
+        return false;
+    };
+    let valid_local = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c));
+    let valid_domain = domain.rsplit_once('.').is_some_and(|(host, tld)| {
+        !host.is_empty()
+            && tld.len() >= 2
+            && tld.chars().all(|c| c.is_ascii_alphabetic())
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    });
+    valid_local && valid_domain
+}
+
+fn is_card_number(value: &str) -> bool {
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == ' ' || c == '-')
+    {
+        return false;
+    }
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn is_national_id(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    parts.len() == 3
+        && [3, 2, 4]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()))
+        && parts[0] != "000"
+        && parts[0] != "666"
+}
+
+fn is_phone(value: &str) -> bool {
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_digit() || " -.()+".contains(c))
+    {
+        return false;
+    }
+    let digits = value.chars().filter(|c| c.is_ascii_digit()).count();
+    let formatted = value.starts_with('+') || value.chars().any(|c| " -.()".contains(c));
+    formatted && (10..=15).contains(&digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_values() {
+        assert_eq!(
+            PiiMasker::detect("jane.doe@example.com"),
+            Some(PiiKind::Email)
+        );
+        assert_eq!(
+            PiiMasker::detect("contact: jane@example.com."),
+            Some(PiiKind::Email)
+        );
+        assert_eq!(PiiMasker::detect("+1 (555) 123-4567"), Some(PiiKind::Phone));
+        assert_eq!(
+            PiiMasker::detect("4111 1111 1111 1111"),
+            Some(PiiKind::CardNumber)
+        );
+        assert_eq!(PiiMasker::detect("123-45-6789"), Some(PiiKind::NationalId));
+        assert_eq!(PiiMasker::detect("4111 1111 1111 1112"), None);
+        assert_eq!(PiiMasker::detect("2024-01-15"), None);
+        assert_eq!(PiiMasker::detect("1234567890"), None);
+        assert_eq!(PiiMasker::detect("not an email @ all"), None);
+    }
+
+    #[test]
+    fn test_mask_result_actions() {
+        let config = PiiConfig::from_yaml(
+            r#"
+default_action: mask
+actions:
+  card_number: hash
+  tagged_column: drop
+pii_columns: [salary]
+"#,
+        )
+        .unwrap();
+        let masker = PiiMasker::new(config);
+
+        let result = QueryResult {
+            columns: vec![
+                "id".to_string(),
+                "email".to_string(),
+                "card".to_string(),
+                "salary".to_string(),
+            ],
+            rows: vec![vec![
+                serde_json::json!(1),
+                serde_json::json!("jane@example.com"),
+                serde_json::json!("4111-1111-1111-1111"),
+                serde_json::json!(90000),
+            ]],
+            row_count: 1,
+        };
+
+        let masked = masker.mask_result(&result);
+        assert_eq!(masked.result.columns, vec!["id", "email", "card"]);
+        assert_eq!(masked.result.rows[0][0], serde_json::json!(1));
+        assert_eq!(
+            masked.result.rows[0][1],
+            serde_json::json!("j***@example.com")
+        );
+        let card = masked.result.rows[0][2].as_str().unwrap();
+        assert!(card.starts_with("sha256:"));
+        assert_eq!(card, hash("4111-1111-1111-1111"));
+        assert_eq!(masked.findings.len(), 3);
+        assert!(masked
+            .findings
+            .iter()
+            .any(|f| f.column == "salary" && f.action == MaskAction::Drop));
+    }
+
+    #[test]
+    fn test_mask_query_result_resolves_projection() {
+        let masker = PiiMasker::new(PiiConfig {
+            pii_columns: vec!["users.email".to_string(), "users.name".to_string()],
+            ..Default::default()
+        });
+        let dialect = crate::dialect::SqliteDialect;
+        let tagged = |sql: &str, columns: &[&str]| {
+            let result = QueryResult {
+                columns: columns.iter().map(|c| c.to_string()).collect(),
+                rows: vec![columns.iter().map(|_| serde_json::json!("x")).collect()],
+                row_count: 1,
+            };
+            masker.mask_query_result(sql, &dialect, &result).result.rows[0]
+                .iter()
+                .map(|value| value == "***")
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tagged("SELECT email AS e FROM users", &["e"]), vec![true]);
+        assert_eq!(tagged("SELECT name FROM products", &["name"]), vec![false]);
+        assert_eq!(
+            tagged(
+                "SELECT p.name, u.name AS owner FROM products p JOIN users u ON p.owner_id = u.id",
+                &["name", "owner"]
+            ),
+            vec![false, true]
+        );
+        assert_eq!(
+            tagged(
+                "WITH x AS (SELECT email FROM users) SELECT upper(email) AS contact FROM x",
+                &["contact"]
+            ),
+            vec![true]
+        );
+        assert_eq!(
+            tagged("SELECT * FROM users", &["id", "email"]),
+            vec![false, true]
+        );
+
+        let result = QueryResult {
+            columns: vec!["created_ms".to_string(), "revenue".to_string()],
+            rows: vec![vec![
+                serde_json::json!(4111111111111111u64),
+                serde_json::json!(1234567890.12),
+            ]],
+            row_count: 1,
+        };
+        let masked =
+            masker.mask_query_result("SELECT created_ms, revenue FROM orders", &dialect, &result);
+        assert_eq!(masked.result.rows, result.rows);
+        assert!(masked.findings.is_empty());
+    }
+
+    #[test]
+    fn test_authorized_identity() {
+        let masker = PiiMasker::new(PiiConfig {
+            authorized_groups: vec!["compliance".to_string()],
+            ..Default::default()
+        });
+        assert!(masker
+            .is_authorized(&Identity::new("carol").with_groups(vec!["compliance".to_string()])));
+        assert!(!masker.is_authorized(&Identity::new("dave")));
+    }
+}
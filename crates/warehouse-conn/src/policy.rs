@@ -0,0 +1,868 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions, visit_relations, Expr, ObjectName, Statement, TableAlias, TableFactor,
+    VisitMut, VisitorMut,
+};
+use sqlparser::dialect as sp;
+use sqlparser::parser::Parser;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::dialect::Dialect;
+use crate::error::Error;
+use crate::traits::{HealthStatus, PoolStats, QueryResult, TableSchema, Warehouse};
+use crate::transpile::parser_dialect;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    pub user_id: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl Identity {
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn anonymous() -> Self {
+        Self::new("anonymous")
+    }
+
+    pub fn with_groups(mut self, groups: Vec<String>) -> Self {
+        self.groups = groups;
+        self
+    }
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Self::anonymous()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultAction {
+    #[default]
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TablePolicy {
+    #[serde(default)]
+    pub row_filter: Option<String>,
+    #[serde(default)]
+    pub blocked_columns: Vec<String>,
+    #[serde(default)]
+    pub masked_columns: Vec<String>,
+}
+
+impl TablePolicy {
+    fn is_blocked(&self, column: &str) -> bool {
+        self.blocked_columns
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(column))
+    }
+
+    fn is_masked(&self, column: &str) -> bool {
+        self.masked_columns
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(column))
+    }
+
+    fn needs_projection(&self) -> bool {
+        !self.blocked_columns.is_empty() || !self.masked_columns.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Policy {
+    pub name: String,
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub hidden_tables: Vec<String>,
+    #[serde(default)]
+    pub tables: HashMap<String, TablePolicy>,
+}
+
+impl Policy {
+    fn applies_to(&self, identity: &Identity) -> bool {
+        self.users
+            .iter()
+            .any(|user| user == "*" || *user == identity.user_id)
+            || self
+                .groups
+                .iter()
+                .any(|group| group == "*" || identity.groups.contains(group))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    #[serde(default)]
+    pub default: DefaultAction,
+    #[serde(default = "default_mask")]
+    pub mask: String,
+    #[serde(default)]
+    pub policies: Vec<Policy>,
+}
+
+fn default_mask() -> String {
+    "***".to_string()
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            default: DefaultAction::Allow,
+            mask: default_mask(),
+            policies: Vec::new(),
+        }
+    }
+}
+
+impl PolicyConfig {
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(yaml).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read {}: {}", path, e)))?;
+        Self::from_yaml(&content)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EffectivePolicy {
+    pub deny_all: bool,
+    pub hidden_tables: BTreeSet<String>,
+    pub tables: HashMap<String, TablePolicy>,
+}
+
+impl EffectivePolicy {
+    pub fn is_unrestricted(&self) -> bool {
+        !self.deny_all && self.hidden_tables.is_empty() && self.tables.is_empty()
+    }
+
+    pub fn is_hidden(&self, table: &str) -> bool {
+        table_keys(table)
+            .iter()
+            .any(|key| self.hidden_tables.contains(key))
+    }
+
+    pub fn table(&self, table: &str) -> Option<&TablePolicy> {
+        table_keys(table)
+            .iter()
+            .find_map(|key| self.tables.get(key))
+    }
+}
+
+pub(crate) fn table_keys(table: &str) -> Vec<String> {
+    let full = table.to_lowercase();
+    match full.rsplit_once('.') {
+        Some((_, last)) => vec![full.clone(), last.to_string()],
+        None => vec![full],
+    }
+}
+
+fn base_name(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
+
+/// System catalogs describe every table, hidden ones included.
+fn is_catalog_table(table: &str) -> bool {
+    let table = table.to_lowercase();
+    let schema = table.split_once('.').map(|(schema, _)| schema);
+    matches!(
+        schema,
+        Some("information_schema" | "pg_catalog" | "mysql" | "performance_schema" | "sys")
+    ) || base_name(&table).starts_with("pg_")
+        || base_name(&table).starts_with("sqlite_")
+}
+
+fn is_catalog_statement(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Pragma { .. }
+            | Statement::ShowTables { .. }
+            | Statement::ShowColumns { .. }
+            | Statement::ShowCreate { .. }
+            | Statement::ShowViews { .. }
+            | Statement::ShowSchemas { .. }
+            | Statement::ShowDatabases { .. }
+            | Statement::ExplainTable { .. }
+    )
+}
+
+/// Maps each table alias, bare table name and full table name in `statements`
+/// (lowercased) to the table it refers to.
+fn table_scope(statements: &[Statement]) -> HashMap<String, String> {
+    use sqlparser::ast::{Visit, Visitor};
+
+    struct Scope(HashMap<String, String>);
+
+    impl Visitor for Scope {
+        type Break = ();
+
+        fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+            if let TableFactor::Table { name, alias, .. } = factor {
+                let table = object_key(name);
+                let lower = table.to_lowercase();
+                self.0.insert(base_name(&lower).to_string(), table.clone());
+                self.0.insert(lower, table.clone());
+                if let Some(alias) = alias {
+                    self.0.insert(alias.name.value.to_lowercase(), table);
+                }
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut scope = Scope(HashMap::new());
+    for statement in statements {
+        let _ = Visit::visit(statement, &mut scope);
+    }
+    scope.0
+}
+
+pub(crate) fn object_key(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .map(|ident| ident.value.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: u64,
+    pub user_id: String,
+    pub groups: Vec<String>,
+    pub resource: String,
+    pub sql: Option<String>,
+    pub reason: String,
+}
+
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+}
+
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, event: AuditEvent) {
+        warn!(
+            user_id = %event.user_id,
+            resource = %event.resource,
+            sql = event.sql.as_deref().unwrap_or(""),
+            "Access denied: {}",
+            event.reason
+        );
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryAuditSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: AuditEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+pub struct PolicyEngine {
+    config: PolicyConfig,
+    audit: Arc<dyn AuditSink>,
+}
+
+impl PolicyEngine {
+    pub fn new(config: PolicyConfig) -> Self {
+        Self {
+            config,
+            audit: Arc::new(TracingAuditSink),
+        }
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        Ok(Self::new(PolicyConfig::from_yaml(yaml)?))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        Ok(Self::new(PolicyConfig::from_file(path)?))
+    }
+
+    pub fn with_audit_sink(mut self, audit: Arc<dyn AuditSink>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    pub fn resolve(&self, identity: &Identity, dialect: &dyn Dialect) -> EffectivePolicy {
+        let matching: Vec<&Policy> = self
+            .config
+            .policies
+            .iter()
+            .filter(|policy| policy.applies_to(identity))
+            .collect();
+
+        let mut effective = EffectivePolicy {
+            deny_all: matching.is_empty() && self.config.default == DefaultAction::Deny,
+            ..Default::default()
+        };
+
+        for policy in matching {
+            for table in &policy.hidden_tables {
+                effective.hidden_tables.insert(table.to_lowercase());
+            }
+
+            for (table, rule) in &policy.tables {
+                let merged = effective.tables.entry(table.to_lowercase()).or_default();
+                if let Some(filter) = &rule.row_filter {
+                    let filter =
+                        filter.replace("{user_id}", &dialect.quote_string(&identity.user_id));
+                    merged.row_filter = Some(match merged.row_filter.take() {
+                        Some(existing) => format!("({}) AND ({})", existing, filter),
+                        None => filter,
+                    });
+                }
+                merged
+                    .blocked_columns
+                    .extend(rule.blocked_columns.iter().cloned());
+                merged
+                    .masked_columns
+                    .extend(rule.masked_columns.iter().cloned());
+            }
+        }
+
+        effective
+    }
+
+    /// Whether `identity` may read `table` at all, ignoring row filters and
+    /// column rules.
+    pub fn can_read(&self, identity: &Identity, table: &str) -> bool {
+        let matching: Vec<&Policy> = self
+            .config
+            .policies
+            .iter()
+            .filter(|policy| policy.applies_to(identity))
+            .collect();
+        if matching.is_empty() {
+            return self.config.default != DefaultAction::Deny;
+        }
+        let keys = table_keys(table);
+        !matching.iter().any(|policy| {
+            policy
+                .hidden_tables
+                .iter()
+                .any(|hidden| keys.contains(&hidden.to_lowercase()))
+        })
+    }
+
+    pub fn wrap(
+        self: &Arc<Self>,
+        inner: Arc<dyn Warehouse>,
+        identity: Identity,
+    ) -> PolicyWarehouse {
+        PolicyWarehouse::new(inner, self.clone(), identity)
+    }
+
+    fn deny(
+        &self,
+        identity: &Identity,
+        resource: &str,
+        sql: Option<&str>,
+        reason: String,
+    ) -> Error {
+        self.audit.record(AuditEvent {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            user_id: identity.user_id.clone(),
+            groups: identity.groups.clone(),
+            resource: resource.to_string(),
+            sql: sql.map(|s| s.to_string()),
+            reason: reason.clone(),
+        });
+        Error::AccessDenied(reason)
+    }
+}
+
+pub struct PolicyWarehouse {
+    inner: Arc<dyn Warehouse>,
+    engine: Arc<PolicyEngine>,
+    identity: Identity,
+}
+
+impl PolicyWarehouse {
+    pub fn new(inner: Arc<dyn Warehouse>, engine: Arc<PolicyEngine>, identity: Identity) -> Self {
+        Self {
+            inner,
+            engine,
+            identity,
+        }
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    fn policy(&self) -> EffectivePolicy {
+        self.engine.resolve(&self.identity, self.inner.dialect())
+    }
+
+    fn deny(&self, resource: &str, sql: Option<&str>, reason: String) -> Error {
+        self.engine.deny(&self.identity, resource, sql, reason)
+    }
+
+    fn parser(&self) -> Box<dyn sp::Dialect> {
+        parser_dialect(self.inner.dialect().backend())
+    }
+
+    async fn authorize(&self, sql: &str, policy: &EffectivePolicy) -> Result<String, Error> {
+        if policy.deny_all {
+            return Err(self.deny(
+                "*",
+                Some(sql),
+                format!(
+                    "No access policy applies to user '{}'",
+                    self.identity.user_id
+                ),
+            ));
+        }
+        if policy.is_unrestricted() {
+            return Ok(sql.to_string());
+        }
+
+        let mut statements = Parser::parse_sql(self.parser().as_ref(), sql)
+            .map_err(|e| Error::Parse(e.to_string()))?;
+
+        let mut tables = BTreeSet::new();
+        let _ = visit_relations(&statements, |relation| {
+            tables.insert(object_key(relation));
+            ControlFlow::<()>::Continue(())
+        });
+
+        if let Some(statement) = statements.iter().find(|s| is_catalog_statement(s)) {
+            return Err(self.deny(
+                "*",
+                Some(sql),
+                format!(
+                    "Catalog statements are not allowed under an access policy: {}",
+                    statement
+                ),
+            ));
+        }
+
+        let read_only = statements
+            .iter()
+            .all(|statement| matches!(statement, Statement::Query(_)));
+        let mut restricted = Vec::new();
+        for table in &tables {
+            if is_catalog_table(table) {
+                return Err(self.deny(
+                    table,
+                    Some(sql),
+                    format!("System catalog '{}' is not accessible", table),
+                ));
+            }
+            if policy.is_hidden(table) {
+                return Err(self.deny(
+                    table,
+                    Some(sql),
+                    format!("Table '{}' is not accessible", table),
+                ));
+            }
+            if let Some(rule) = policy.table(table) {
+                if !read_only {
+                    return Err(self.deny(
+                        table,
+                        Some(sql),
+                        format!("Only SELECT queries are allowed on table '{}'", table),
+                    ));
+                }
+                restricted.push((table.clone(), rule));
+            }
+        }
+
+        if restricted.is_empty() {
+            return Ok(sql.to_string());
+        }
+
+        let dialect = self.inner.dialect();
+        let mut schemas = HashMap::new();
+        for (table, rule) in &restricted {
+            if rule.needs_projection() {
+                let schema = self.inner.get_schema(base_name(table)).await?;
+                schemas.insert(table.clone(), schema);
+            }
+        }
+
+        let scope = table_scope(&statements);
+        let mut blocked = None;
+        let _ = visit_expressions(&statements, |expr| {
+            let hit = match expr {
+                Expr::CompoundIdentifier(parts) if parts.len() > 1 => {
+                    let (column, qualifier) = parts.split_last().unwrap();
+                    let qualifier = qualifier
+                        .iter()
+                        .map(|ident| ident.value.to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    scope
+                        .get(&qualifier)
+                        .filter(|table| {
+                            policy
+                                .table(table)
+                                .is_some_and(|rule| rule.is_blocked(&column.value))
+                        })
+                        .map(|table| (table.clone(), column.value.clone()))
+                }
+                Expr::Identifier(column) => restricted
+                    .iter()
+                    .find(|(table, rule)| {
+                        rule.is_blocked(&column.value)
+                            && schemas.get(table).is_some_and(|schema| {
+                                schema
+                                    .columns
+                                    .iter()
+                                    .any(|c| c.name.eq_ignore_ascii_case(&column.value))
+                            })
+                    })
+                    .map(|(table, _)| (table.clone(), column.value.clone())),
+                _ => None,
+            };
+            match hit {
+                Some(hit) => {
+                    blocked = Some(hit);
+                    ControlFlow::Break(())
+                }
+                None => ControlFlow::Continue(()),
+            }
+        });
+        if let Some((table, column)) = blocked {
+            return Err(self.deny(
+                &format!("{}.{}", table, column),
+                Some(sql),
+                format!("Column '{}' of table '{}' is restricted", column, table),
+            ));
+        }
+
+        let mut replacements = HashMap::new();
+        for (table, rule) in restricted {
+            let projection = match schemas.get(&table) {
+                Some(schema) => {
+                    let mask = dialect.quote_string(&self.engine.config.mask);
+                    let columns = schema
+                        .columns
+                        .iter()
+                        .filter(|column| !rule.is_blocked(&column.name))
+                        .map(|column| {
+                            let quoted = dialect.quote_identifier(&column.name);
+                            if rule.is_masked(&column.name) {
+                                format!("{} AS {}", mask, quoted)
+                            } else {
+                                quoted
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    if columns.is_empty() {
+                        return Err(self.deny(
+                            &table,
+                            Some(sql),
+                            format!("All columns of table '{}' are restricted", table),
+                        ));
+                    }
+                    columns.join(", ")
+                }
+                None => "*".to_string(),
+            };
+            replacements.insert(table, (projection, rule.row_filter.clone()));
+        }
+
+        let mut rewriter = PolicyRewriter {
+            parser: self.parser(),
+            replacements,
+        };
+        if let ControlFlow::Break(e) = statements.visit(&mut rewriter) {
+            return Err(e);
+        }
+
+        Ok(statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join(";\n"))
+    }
+}
+
+struct PolicyRewriter {
+    parser: Box<dyn sp::Dialect>,
+    replacements: HashMap<String, (String, Option<String>)>,
+}
+
+impl VisitorMut for PolicyRewriter {
+    type Break = Error;
+
+    fn post_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        let TableFactor::Table {
+            name,
+            alias,
+            args: None,
+            ..
+        } = factor
+        else {
+            return ControlFlow::Continue(());
+        };
+        let Some((projection, filter)) = self.replacements.get(&object_key(name)) else {
+            return ControlFlow::Continue(());
+        };
+
+        let mut sql = format!("SELECT {} FROM {}", projection, name);
+        if let Some(filter) = filter {
+            sql.push_str(&format!(" WHERE {}", filter));
+        }
+        let subquery = match Parser::new(self.parser.as_ref())
+            .try_with_sql(&sql)
+            .and_then(|mut parser| parser.parse_query())
+        {
+            Ok(query) => query,
+            Err(e) => return ControlFlow::Break(Error::Config(format!("Invalid policy: {}", e))),
+        };
+
+        let alias = alias.clone().or_else(|| {
+            name.0.last().map(|ident| TableAlias {
+                name: ident.clone(),
+                columns: Vec::new(),
+            })
+        });
+        *factor = TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias,
+        };
+        ControlFlow::Continue(())
+    }
+}
+
+#[async_trait]
+impl Warehouse for PolicyWarehouse {
+    async fn connect(&self) -> Result<(), Error> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.inner.disconnect().await
+    }
+
+    async fn execute(&self, sql: &str) -> Result<QueryResult, Error> {
+        let policy = self.policy();
+        let sql = self.authorize(sql, &policy).await?;
+        self.inner.execute(&sql).await
+    }
+
+    async fn get_schema(&self, table_name: &str) -> Result<TableSchema, Error> {
+        let policy = self.policy();
+        if policy.deny_all || policy.is_hidden(table_name) {
+            return Err(self.deny(
+                table_name,
+                None,
+                format!("Table '{}' is not accessible", table_name),
+            ));
+        }
+
+        let mut schema = self.inner.get_schema(table_name).await?;
+        if let Some(rule) = policy.table(table_name) {
+            schema
+                .columns
+                .retain(|column| !rule.is_blocked(&column.name));
+        }
+        Ok(schema)
+    }
+
+    async fn list_tables(&self) -> Result<Vec<String>, Error> {
+        let policy = self.policy();
+        if policy.deny_all {
+            return Err(self.deny(
+                "*",
+                None,
+                format!(
+                    "No access policy applies to user '{}'",
+                    self.identity.user_id
+                ),
+            ));
+        }
+
+        let tables = self.inner.list_tables().await?;
+        Ok(tables
+            .into_iter()
+            .filter(|table| !policy.is_hidden(table))
+            .collect())
+    }
+
+    async fn preview_table(&self, table_name: &str, limit: usize) -> Result<QueryResult, Error> {
+        let dialect = self.inner.dialect();
+        let sql = dialect.limit(
+            &format!("SELECT * FROM {}", dialect.quote_qualified(table_name)),
+            limit,
+        );
+        self.execute(&sql).await
+    }
+
+    fn dialect(&self) -> &dyn Dialect {
+        self.inner.dialect()
+    }
+
+    async fn health(&self) -> HealthStatus {
+        self.inner.health().await
+    }
+
+    async fn pool_stats(&self) -> Option<PoolStats> {
+        self.inner.pool_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::PostgresDialect;
+    use crate::sqlite::SqliteWarehouse;
+
+    const POLICIES: &str = r#"
+default: deny
+policies:
+  - name: emea-analysts
+    groups: [emea]
+    hidden_tables: [salaries]
+    tables:
+      customers:
+        row_filter: "region = 'EMEA'"
+        blocked_columns: [ssn]
+        masked_columns: [email]
+      tokens:
+        blocked_columns: [secret]
+  - name: own-orders
+    users: ["*"]
+    tables:
+      orders:
+        row_filter: "owner = {user_id}"
+"#;
+
+    #[test]
+    fn test_resolve_policies() {
+        let engine = PolicyEngine::from_yaml(POLICIES).unwrap();
+
+        let alice = Identity::new("alice").with_groups(vec!["emea".to_string()]);
+        let policy = engine.resolve(&alice, &PostgresDialect);
+        assert!(!policy.deny_all);
+        assert!(policy.is_hidden("public.salaries"));
+        assert_eq!(
+            policy.table("orders").unwrap().row_filter.as_deref(),
+            Some("owner = 'alice'")
+        );
+        assert!(policy.table("customers").unwrap().is_blocked("SSN"));
+
+        let bob = Identity::new("bob");
+        let policy = engine.resolve(&bob, &PostgresDialect);
+        assert!(!policy.is_hidden("salaries"));
+        assert!(policy.table("customers").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_policy_warehouse_enforces_rules() {
+        let sqlite = SqliteWarehouse::new("sqlite::memory:").with_max_connections(1);
+        for sql in [
+            "CREATE TABLE customers (id INTEGER, region TEXT, email TEXT, ssn TEXT)",
+            "INSERT INTO customers VALUES (1, 'EMEA', 'a@x.com', '111'), (2, 'APAC', 'b@x.com', '222')",
+            "CREATE TABLE salaries (id INTEGER, amount INTEGER)",
+            "CREATE TABLE tokens (secret TEXT)",
+            "CREATE TABLE badges (customer_id INTEGER, ssn TEXT)",
+            "INSERT INTO badges VALUES (1, 'B-1')",
+        ] {
+            sqlite.execute(sql).await.unwrap();
+        }
+
+        let audit = Arc::new(MemoryAuditSink::new());
+        let engine = Arc::new(
+            PolicyEngine::from_yaml(POLICIES)
+                .unwrap()
+                .with_audit_sink(audit.clone()),
+        );
+        let identity = Identity::new("alice").with_groups(vec!["emea".to_string()]);
+        let warehouse = engine.wrap(Arc::new(sqlite), identity);
+
+        let result = warehouse
+            .execute("SELECT * FROM customers c ORDER BY c.id")
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 1);
+        assert_eq!(result.columns, vec!["id", "region", "email"]);
+        assert_eq!(result.rows[0][2], serde_json::json!("***"));
+
+        let denied = warehouse.execute("SELECT ssn FROM customers").await;
+        assert!(matches!(denied, Err(Error::AccessDenied(_))));
+        let denied = warehouse.execute("SELECT * FROM salaries").await;
+        assert!(matches!(denied, Err(Error::AccessDenied(_))));
+
+        let tables = warehouse.list_tables().await.unwrap();
+        assert!(!tables.contains(&"salaries".to_string()));
+        assert!(warehouse.get_schema("salaries").await.is_err());
+        let schema = warehouse.get_schema("customers").await.unwrap();
+        assert!(schema.columns.iter().all(|column| column.name != "ssn"));
+
+        let events = audit.events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].resource, "customers.ssn");
+        assert_eq!(events[0].user_id, "alice");
+
+        let result = warehouse
+            .execute("SELECT b.ssn FROM main.customers c JOIN badges b ON b.customer_id = c.id")
+            .await
+            .unwrap();
+        assert_eq!(result.rows, vec![vec![serde_json::json!("B-1")]]);
+        for sql in [
+            "SELECT c.ssn FROM badges b JOIN customers c ON b.customer_id = c.id",
+            "SELECT * FROM tokens",
+            "PRAGMA table_info('salaries')",
+            "SELECT name FROM sqlite_master",
+        ] {
+            let denied = warehouse.execute(sql).await;
+            assert!(matches!(denied, Err(Error::AccessDenied(_))), "{}", sql);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_deny() {
+        let engine = Arc::new(PolicyEngine::from_yaml("default: deny\npolicies: []").unwrap());
+        let sqlite = SqliteWarehouse::new("sqlite::memory:");
+        let warehouse = engine.wrap(Arc::new(sqlite), Identity::new("mallory"));
+        assert!(matches!(
+            warehouse.execute("SELECT 1").await,
+            Err(Error::AccessDenied(_))
+        ));
+    }
+}
@@ -0,0 +1,338 @@
+use async_trait::async_trait;
+use sqlparser::ast::{visit_statements, Statement};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use sqlx::pool::PoolConnection;
+use sqlx::{Database, Pool};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::traits::{Error, PoolStats, QueryResult};
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct PoolMetrics {
+    waiters: AtomicUsize,
+    acquires: AtomicU64,
+    acquire_micros_total: AtomicU64,
+    acquire_micros_max: AtomicU64,
+    reconnects: AtomicU64,
+}
+
+impl PoolMetrics {
+    pub(crate) fn begin_acquire(&self) -> Instant {
+        self.waiters.fetch_add(1, Ordering::Relaxed);
+        Instant::now()
+    }
+
+    pub(crate) fn end_acquire(&self, started: Instant) {
+        let micros = started.elapsed().as_micros() as u64;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+        self.acquires.fetch_add(1, Ordering::Relaxed);
+        self.acquire_micros_total
+            .fetch_add(micros, Ordering::Relaxed);
+        self.acquire_micros_max.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, size: u32, idle: usize, max_connections: u32) -> PoolStats {
+        let acquires = self.acquires.load(Ordering::Relaxed);
+        let total = self.acquire_micros_total.load(Ordering::Relaxed);
+        let avg_acquire_ms = if acquires == 0 {
+            0.0
+        } else {
+            total as f64 / acquires as f64 / 1000.0
+        };
+
+        PoolStats {
+            size,
+            idle,
+            max_connections,
+            waiters: self.waiters.load(Ordering::Relaxed),
+            acquire_count: acquires,
+            avg_acquire_ms,
+            max_acquire_ms: self.acquire_micros_max.load(Ordering::Relaxed) as f64 / 1000.0,
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Backend-specific half of a [`ManagedPool`]: how to open a pool and run a
+/// statement on one connection.
+#[async_trait]
+pub(crate) trait Connector: Send + Sync {
+    type Db: Database;
+
+    fn label(&self) -> &'static str;
+    fn retry(&self) -> &RetryPolicy;
+    fn max_connections(&self) -> u32;
+    async fn open_pool(&self) -> Result<Pool<Self::Db>, sqlx::Error>;
+    async fn run_query(
+        &self,
+        conn: &mut PoolConnection<Self::Db>,
+        sql: &str,
+    ) -> Result<QueryResult, sqlx::Error>;
+}
+
+/// Lazily opened connection pool with reconnects, retries of transient
+/// failures and acquire metrics, shared by the sqlx-based warehouses.
+pub(crate) struct ManagedPool<DB: Database> {
+    pool: RwLock<Option<Pool<DB>>>,
+    metrics: PoolMetrics,
+}
+
+impl<DB: Database> ManagedPool<DB> {
+    pub(crate) fn new() -> Self {
+        Self {
+            pool: RwLock::new(None),
+            metrics: PoolMetrics::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) async fn is_open(&self) -> bool {
+        self.pool.read().await.is_some()
+    }
+
+    async fn open<C: Connector<Db = DB>>(&self, connector: &C) -> Result<Pool<DB>, Error> {
+        let retry = connector.retry();
+        let mut attempt = 0;
+        loop {
+            match connector.open_pool().await {
+                Ok(pool) => return Ok(pool),
+                Err(e) if is_transient(&e) && attempt < retry.max_retries => {
+                    attempt += 1;
+                    warn!(
+                        "{} connect attempt {} failed: {}",
+                        connector.label(),
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                }
+                Err(e) => return Err(Error::Connection(e.to_string())),
+            }
+        }
+    }
+
+    pub(crate) async fn connect<C: Connector<Db = DB>>(&self, connector: &C) -> Result<(), Error> {
+        let pool = self.open(connector).await?;
+        *self.pool.write().await = Some(pool);
+        Ok(())
+    }
+
+    pub(crate) async fn disconnect(&self) {
+        if let Some(pool) = self.pool.write().await.take() {
+            pool.close().await;
+        }
+    }
+
+    /// The open pool, reopening it if it was never opened or has been closed.
+    pub(crate) async fn get<C: Connector<Db = DB>>(
+        &self,
+        connector: &C,
+    ) -> Result<Pool<DB>, Error> {
+        if let Some(pool) = self.pool.read().await.as_ref() {
+            if !pool.is_closed() {
+                return Ok(pool.clone());
+            }
+        }
+
+        let mut guard = self.pool.write().await;
+        if let Some(pool) = guard.as_ref() {
+            if !pool.is_closed() {
+                return Ok(pool.clone());
+            }
+            self.metrics.record_reconnect();
+        }
+        let pool = self.open(connector).await?;
+        *guard = Some(pool.clone());
+        Ok(pool)
+    }
+
+    async fn acquire<C: Connector<Db = DB>>(
+        &self,
+        connector: &C,
+    ) -> Result<PoolConnection<DB>, sqlx::Error> {
+        let pool = self
+            .get(connector)
+            .await
+            .map_err(|e| sqlx::Error::Io(std::io::Error::other(e.to_string())))?;
+        let started = self.metrics.begin_acquire();
+        let conn = pool.acquire().await;
+        self.metrics.end_acquire(started);
+        conn
+    }
+
+    /// Runs `sql`, retrying transient acquire failures and, for read-only
+    /// statements, transient query failures on a fresh connection.
+    pub(crate) async fn execute<C: Connector<Db = DB>>(
+        &self,
+        connector: &C,
+        sql: &str,
+    ) -> Result<QueryResult, Error> {
+        let retry = connector.retry();
+        let mut attempt = 0;
+
+        loop {
+            let mut conn = match self.acquire(connector).await {
+                Ok(conn) => conn,
+                Err(e) if is_transient(&e) && attempt < retry.max_retries => {
+                    attempt += 1;
+                    warn!(
+                        "{} acquire attempt {} failed: {}",
+                        connector.label(),
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                    continue;
+                }
+                Err(e) => return Err(Error::Connection(e.to_string())),
+            };
+
+            match connector.run_query(&mut conn, sql).await {
+                Ok(result) => return Ok(result),
+                Err(e) if is_transient(&e) => {
+                    let _ = conn.close().await;
+                    self.metrics.record_reconnect();
+                    if !is_read_only(sql) || attempt >= retry.max_retries {
+                        return Err(Error::Connection(e.to_string()));
+                    }
+                    attempt += 1;
+                    warn!(
+                        "{} query attempt {} failed: {}",
+                        connector.label(),
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                }
+                Err(e) => return Err(Error::Query(e.to_string())),
+            }
+        }
+    }
+
+    pub(crate) async fn stats(&self, max_connections: u32) -> PoolStats {
+        let (size, idle) = self
+            .pool
+            .read()
+            .await
+            .as_ref()
+            .map(|pool| (pool.size(), pool.num_idle()))
+            .unwrap_or((0, 0));
+        self.metrics.snapshot(size, idle, max_connections)
+    }
+}
+
+pub(crate) fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(db) => db
+            .code()
+            .map(|code| {
+                code.starts_with("08") || matches!(code.as_ref(), "57P01" | "57P02" | "57P03")
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Whether every statement in `sql`, including statements nested in CTEs,
+/// only reads data. SQL that does not parse is treated as a write.
+pub(crate) fn is_read_only(sql: &str) -> bool {
+    let Ok(statements) = Parser::parse_sql(&GenericDialect {}, sql) else {
+        return false;
+    };
+    let writes = visit_statements(&statements, |statement| {
+        let read = match statement {
+            Statement::Query(_)
+            | Statement::ShowTables { .. }
+            | Statement::ShowColumns { .. }
+            | Statement::ShowCreate { .. }
+            | Statement::ShowViews { .. }
+            | Statement::ShowSchemas { .. }
+            | Statement::ShowDatabases { .. }
+            | Statement::ExplainTable { .. } => true,
+            Statement::Explain { analyze, .. } => !analyze,
+            Statement::Pragma { value, .. } => value.is_none(),
+            _ => false,
+        };
+        if read {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    });
+    !statements.is_empty() && writes.is_continue()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(8), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_metrics_snapshot() {
+        let metrics = PoolMetrics::default();
+        let started = metrics.begin_acquire();
+        assert_eq!(metrics.snapshot(1, 0, 5).waiters, 1);
+        metrics.end_acquire(started);
+
+        let stats = metrics.snapshot(1, 1, 5);
+        assert_eq!(stats.waiters, 0);
+        assert_eq!(stats.acquire_count, 1);
+    }
+}
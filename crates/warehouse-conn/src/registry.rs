@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::cache::{CachedWarehouse, ResultCache};
+use crate::error::Error;
+use crate::factory;
+use crate::traits::{HealthStatus, Warehouse};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    pub connections: Vec<WarehouseConfig>,
+}
+
+impl RegistryConfig {
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(yaml).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read {}: {}", path, e)))?;
+        if path.ends_with(".json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_yaml(&content)
+        }
+    }
+
+    pub fn single(name: &str, url: &str) -> Self {
+        Self {
+            connections: vec![WarehouseConfig {
+                name: name.to_string(),
+                url: url.to_string(),
+                default: true,
+            }],
+        }
+    }
+}
+
+pub struct WarehouseRegistry {
+    warehouses: HashMap<String, Arc<dyn Warehouse>>,
+    default: Option<String>,
+}
+
+impl WarehouseRegistry {
+    pub fn new() -> Self {
+        Self {
+            warehouses: HashMap::new(),
+            default: None,
+        }
+    }
+
+    pub fn single(name: &str, warehouse: Arc<dyn Warehouse>) -> Self {
+        let mut registry = Self::new();
+        registry.register(name, warehouse);
+        registry
+    }
+
+    pub fn from_config(config: &RegistryConfig) -> Result<Self, Error> {
+        let mut registry = Self::new();
+
+        for conn in &config.connections {
+            if registry.warehouses.contains_key(&conn.name) {
+                return Err(Error::Config(format!(
+                    "Duplicate warehouse connection '{}'",
+                    conn.name
+                )));
+            }
+            let warehouse = factory::from_url(&conn.url)?;
+            registry.register(&conn.name, warehouse);
+            if conn.default {
+                registry.default = Some(conn.name.clone());
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn with_result_cache(mut self, cache: Arc<ResultCache>) -> Self {
+        self.warehouses = self
+            .warehouses
+            .into_iter()
+            .map(|(name, warehouse)| {
+                let cached: Arc<dyn Warehouse> =
+                    Arc::new(CachedWarehouse::new(warehouse, cache.clone(), &name));
+                (name, cached)
+            })
+            .collect();
+        self
+    }
+
+    pub fn register(&mut self, name: &str, warehouse: Arc<dyn Warehouse>) {
+        if self.default.is_none() {
+            self.default = Some(name.to_string());
+        }
+        self.warehouses.insert(name.to_string(), warehouse);
+    }
+
+    pub fn set_default(&mut self, name: &str) -> Result<(), Error> {
+        if !self.warehouses.contains_key(name) {
+            return Err(Error::NotFound(format!(
+                "Warehouse connection '{}' not found",
+                name
+            )));
+        }
+        self.default = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn get(&self, name: Option<&str>) -> Result<Arc<dyn Warehouse>, Error> {
+        let name = match name {
+            Some(name) => name,
+            None => self.default.as_deref().ok_or_else(|| {
+                Error::NotFound("No warehouse connections configured".to_string())
+            })?,
+        };
+
+        self.warehouses
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Warehouse connection '{}' not found", name)))
+    }
+
+    pub fn default_name(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.warehouses.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warehouses.is_empty()
+    }
+
+    pub async fn health(&self) -> BTreeMap<String, HealthStatus> {
+        let checks = self
+            .warehouses
+            .iter()
+            .map(|(name, warehouse)| async move { (name.clone(), warehouse.health().await) });
+        futures_util::future::join_all(checks)
+            .await
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Default for WarehouseRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_config_from_yaml() {
+        let yaml = r#"
+connections:
+  - name: analytics
+    url: postgres://localhost/analytics
+  - name: local
+    url: sqlite:local.db
+    default: true
+"#;
+        let config = RegistryConfig::from_yaml(yaml).unwrap();
+        assert_eq!(config.connections.len(), 2);
+        assert!(config.connections[1].default);
+    }
+
+    #[tokio::test]
+    async fn test_registry_lookup() {
+        let config = RegistryConfig {
+            connections: vec![
+                WarehouseConfig {
+                    name: "first".to_string(),
+                    url: "sqlite::memory:".to_string(),
+                    default: false,
+                },
+                WarehouseConfig {
+                    name: "second".to_string(),
+                    url: "sqlite::memory:".to_string(),
+                    default: true,
+                },
+            ],
+        };
+
+        let registry = WarehouseRegistry::from_config(&config).unwrap();
+        assert_eq!(registry.default_name(), Some("second"));
+        assert!(registry.get(None).is_ok());
+        assert!(registry.get(Some("first")).is_ok());
+        assert!(matches!(
+            registry.get(Some("missing")),
+            Err(Error::NotFound(_))
+        ));
+
+        let health = registry.health().await;
+        assert_eq!(health.len(), 2);
+        assert!(health.values().all(|status| status.healthy));
+    }
+}
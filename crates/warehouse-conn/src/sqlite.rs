@@ -1,62 +1,113 @@
 use async_trait::async_trait;
 use sqlx::{
-    sqlite::{SqlitePool, SqliteRow},
-    Column, Row, TypeInfo,
+    pool::PoolConnection,
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow},
+    Column, Row, Sqlite, TypeInfo,
 };
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::traits::{Column as TableColumn, Error, QueryResult, TableSchema, Warehouse};
+use crate::dialect::{Dialect, SqliteDialect};
+use crate::pool::{Connector, ManagedPool, RetryPolicy};
+use crate::traits::{Column as TableColumn, Error, PoolStats, QueryResult, TableSchema, Warehouse};
+
+#[derive(Clone)]
+pub struct SqliteWarehouseOptions {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Duration,
+    pub max_lifetime: Duration,
+    pub busy_timeout: Duration,
+    pub create_if_missing: bool,
+    pub retry: RetryPolicy,
+}
+
+impl Default for SqliteWarehouseOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            min_connections: 1,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(600),
+            max_lifetime: Duration::from_secs(1800),
+            busy_timeout: Duration::from_secs(5),
+            create_if_missing: false,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
 
 pub struct SqliteWarehouse {
-    pool: Arc<RwLock<Option<SqlitePool>>>,
+    pool: ManagedPool<Sqlite>,
     connection_string: String,
+    options: SqliteWarehouseOptions,
 }
 
 impl SqliteWarehouse {
     pub fn new(connection_string: &str) -> Self {
         Self {
-            pool: Arc::new(RwLock::new(None)),
+            pool: ManagedPool::new(),
             connection_string: connection_string.to_string(),
+            options: SqliteWarehouseOptions::default(),
         }
     }
 
-    async fn get_pool(&self) -> Result<SqlitePool, Error> {
-        let guard = self.pool.read().await;
-        guard
-            .clone()
-            .ok_or_else(|| Error::Connection("Not connected".to_string()))
+    pub fn with_options(mut self, options: SqliteWarehouseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_max_connections(mut self, max: u32) -> Self {
+        self.options.max_connections = max;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.options.retry = retry;
+        self
     }
 }
 
 #[async_trait]
-impl Warehouse for SqliteWarehouse {
-    async fn connect(&self) -> Result<(), Error> {
-        let pool = SqlitePool::connect(&self.connection_string)
-            .await
-            .map_err(|e| Error::Connection(e.to_string()))?;
-        let mut guard = self.pool.write().await;
-        *guard = Some(pool);
-        Ok(())
+impl Connector for SqliteWarehouse {
+    type Db = Sqlite;
+
+    fn label(&self) -> &'static str {
+        "SQLite"
     }
 
-    async fn disconnect(&self) -> Result<(), Error> {
-        let mut guard = self.pool.write().await;
-        if let Some(pool) = guard.take() {
-            pool.close().await;
-        }
-        Ok(())
+    fn retry(&self) -> &RetryPolicy {
+        &self.options.retry
     }
 
-    async fn execute(&self, sql: &str) -> Result<QueryResult, Error> {
-        let pool = self.get_pool().await?;
+    fn max_connections(&self) -> u32 {
+        self.options.max_connections
+    }
 
+    async fn open_pool(&self) -> Result<SqlitePool, sqlx::Error> {
+        let connect_options = SqliteConnectOptions::from_str(&self.connection_string)?
+            .busy_timeout(self.options.busy_timeout)
+            .create_if_missing(self.options.create_if_missing);
+
+        SqlitePoolOptions::new()
+            .max_connections(self.options.max_connections)
+            .min_connections(self.options.min_connections)
+            .acquire_timeout(self.options.acquire_timeout)
+            .idle_timeout(self.options.idle_timeout)
+            .max_lifetime(self.options.max_lifetime)
+            .connect_with(connect_options)
+            .await
+    }
+
+    async fn run_query(
+        &self,
+        conn: &mut PoolConnection<Sqlite>,
+        sql: &str,
+    ) -> Result<QueryResult, sqlx::Error> {
         let sql_upper = sql.trim().to_uppercase();
         if sql_upper.starts_with("SELECT") || sql_upper.starts_with("PRAGMA") {
-            let rows = sqlx::query(sql)
-                .fetch_all(&pool)
-                .await
-                .map_err(|e| Error::Query(e.to_string()))?;
+            let rows = sqlx::query(sql).fetch_all(&mut **conn).await?;
 
             let columns: Vec<String> = if !rows.is_empty() {
                 rows[0]
@@ -72,7 +123,7 @@ impl Warehouse for SqliteWarehouse {
             for row in &rows {
                 let mut row_values: Vec<serde_json::Value> = Vec::new();
                 for (i, col) in row.columns().iter().enumerate() {
-                    let value = Self::map_value(row, i, col.type_info().name());
+                    let value = SqliteWarehouse::map_value(row, i, col.type_info().name());
                     row_values.push(value);
                 }
                 result_rows.push(row_values);
@@ -85,10 +136,7 @@ impl Warehouse for SqliteWarehouse {
                 row_count,
             })
         } else {
-            sqlx::query(sql)
-                .execute(&pool)
-                .await
-                .map_err(|e| Error::Query(e.to_string()))?;
+            sqlx::query(sql).execute(&mut **conn).await?;
 
             Ok(QueryResult {
                 columns: vec!["affected_rows".to_string()],
@@ -97,11 +145,30 @@ impl Warehouse for SqliteWarehouse {
             })
         }
     }
+}
+
+#[async_trait]
+impl Warehouse for SqliteWarehouse {
+    async fn connect(&self) -> Result<(), Error> {
+        self.pool.connect(self).await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.pool.disconnect().await;
+        Ok(())
+    }
+
+    async fn execute(&self, sql: &str) -> Result<QueryResult, Error> {
+        self.pool.execute(self, sql).await
+    }
 
     async fn get_schema(&self, table_name: &str) -> Result<TableSchema, Error> {
-        let pool = self.get_pool().await?;
+        let pool = self.pool.get(self).await?;
 
-        let columns_sql = format!("PRAGMA table_info('{}')", table_name);
+        let columns_sql = format!(
+            "PRAGMA table_info({})",
+            self.dialect().quote_string(table_name)
+        );
 
         let columns: Vec<TableColumn> = sqlx::query(&columns_sql)
             .fetch_all(&pool)
@@ -128,7 +195,7 @@ impl Warehouse for SqliteWarehouse {
     }
 
     async fn list_tables(&self) -> Result<Vec<String>, Error> {
-        let pool = self.get_pool().await?;
+        let pool = self.pool.get(self).await?;
 
         let sql = "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name";
 
@@ -141,9 +208,21 @@ impl Warehouse for SqliteWarehouse {
     }
 
     async fn preview_table(&self, table_name: &str, limit: usize) -> Result<QueryResult, Error> {
-        let sql = format!("SELECT * FROM {} LIMIT {}", table_name, limit);
+        let dialect = self.dialect();
+        let sql = dialect.limit(
+            &format!("SELECT * FROM {}", dialect.quote_qualified(table_name)),
+            limit,
+        );
         self.execute(&sql).await
     }
+
+    fn dialect(&self) -> &dyn Dialect {
+        &SqliteDialect
+    }
+
+    async fn pool_stats(&self) -> Option<PoolStats> {
+        Some(self.pool.stats(self.max_connections()).await)
+    }
 }
 
 impl SqliteWarehouse {
@@ -176,6 +255,48 @@ mod tests {
     #[tokio::test]
     async fn test_sqlite_warehouse_creation() {
         let warehouse = SqliteWarehouse::new(":memory:");
-        assert!(warehouse.pool.read().await.is_none());
+        assert!(!warehouse.pool.is_open().await);
+    }
+
+    #[tokio::test]
+    async fn test_lazy_connect_and_health() {
+        let warehouse = SqliteWarehouse::new("sqlite::memory:");
+        let result = warehouse.execute("SELECT 1 AS one").await.unwrap();
+        assert_eq!(result.row_count, 1);
+        assert!(warehouse.pool.is_open().await);
+
+        let health = warehouse.health().await;
+        assert!(health.healthy);
+        let pool = health.pool.unwrap();
+        assert!(pool.size >= 1);
+        assert!(pool.acquire_count >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_disconnect() {
+        let warehouse = SqliteWarehouse::new("sqlite::memory:");
+        warehouse.connect().await.unwrap();
+        warehouse.disconnect().await.unwrap();
+        assert!(warehouse.execute("SELECT 1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_preview_table_quotes_identifiers() {
+        let warehouse = SqliteWarehouse::new("sqlite::memory:");
+        warehouse.connect().await.unwrap();
+        warehouse
+            .execute("CREATE TABLE \"order items\" (id INTEGER, sku TEXT)")
+            .await
+            .unwrap();
+        warehouse
+            .execute("INSERT INTO \"order items\" VALUES (1, 'a'), (2, 'b')")
+            .await
+            .unwrap();
+
+        let preview = warehouse.preview_table("order items", 1).await.unwrap();
+        assert_eq!(preview.row_count, 1);
+
+        let schema = warehouse.get_schema("order items").await.unwrap();
+        assert_eq!(schema.columns.len(), 2);
     }
 }
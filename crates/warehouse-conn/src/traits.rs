@@ -1,16 +1,9 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
-
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("Warehouse error: {0}")]
-    Warehouse(String),
-    #[error("Connection error: {0}")]
-    Connection(String),
-    #[error("Query error: {0}")]
-    Query(String),
-}
+use std::time::Instant;
+
+use crate::dialect::Dialect;
+pub use crate::error::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableSchema {
@@ -34,6 +27,26 @@ pub struct QueryResult {
     pub row_count: usize,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max_connections: u32,
+    pub waiters: usize,
+    pub acquire_count: u64,
+    pub avg_acquire_ms: f64,
+    pub max_acquire_ms: f64,
+    pub reconnects: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    pub latency_ms: f64,
+    pub error: Option<String>,
+    pub pool: Option<PoolStats>,
+}
+
 #[async_trait]
 pub trait Warehouse: Send + Sync {
     async fn connect(&self) -> Result<(), Error>;
@@ -42,4 +55,20 @@ pub trait Warehouse: Send + Sync {
     async fn get_schema(&self, table_name: &str) -> Result<TableSchema, Error>;
     async fn list_tables(&self) -> Result<Vec<String>, Error>;
     async fn preview_table(&self, table_name: &str, limit: usize) -> Result<QueryResult, Error>;
+    fn dialect(&self) -> &dyn Dialect;
+
+    async fn health(&self) -> HealthStatus {
+        let started = Instant::now();
+        let result = self.execute("SELECT 1").await;
+        HealthStatus {
+            healthy: result.is_ok(),
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+            error: result.err().map(|e| e.to_string()),
+            pool: self.pool_stats().await,
+        }
+    }
+
+    async fn pool_stats(&self) -> Option<PoolStats> {
+        None
+    }
 }
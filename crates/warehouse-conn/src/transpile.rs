@@ -0,0 +1,671 @@
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, CastKind, DateTimeField, Distinct, Expr, Function,
+    FunctionArg, FunctionArgExpr, FunctionArguments, Ident, Interval, JoinOperator, ObjectName,
+    Query, SelectItem, SetExpr, Statement, TableAlias, TableFactor, Value, VisitMut, VisitorMut,
+};
+use sqlparser::dialect as sp;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+use crate::dialect::DateUnit;
+use crate::error::Error;
+use crate::factory::Backend;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranspileIssue {
+    pub construct: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transpiled {
+    pub sql: String,
+    pub from: String,
+    pub to: String,
+    pub issues: Vec<TranspileIssue>,
+}
+
+impl Transpiled {
+    pub fn is_lossless(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+pub fn transpile(sql: &str, from: Backend, to: Backend) -> Result<Transpiled, Error> {
+    let source = parser_dialect(from);
+    let mut statements =
+        Parser::parse_sql(source.as_ref(), sql).map_err(|e| Error::Parse(e.to_string()))?;
+
+    let mut issues = Vec::new();
+    if from != to {
+        // Requote first so rewrites that re-render expressions as text keep
+        // identifiers distinct from strings in the target dialect.
+        let _ = statements.visit(&mut Requoter {
+            quote: if to == Backend::MySql { '`' } else { '"' },
+        });
+        let mut rewriter = Rewriter {
+            to,
+            target: parser_dialect(to),
+            issues: Vec::new(),
+        };
+        let _ = statements.visit(&mut rewriter);
+        issues = rewriter.issues;
+
+        if to == Backend::Sqlite {
+            let _ = visit_expressions(&statements, |expr| {
+                if matches!(expr, Expr::Interval(_)) {
+                    push_issue(
+                        &mut issues,
+                        "INTERVAL",
+                        "SQLite has no INTERVAL literals outside date arithmetic",
+                    );
+                }
+                ControlFlow::<()>::Continue(())
+            });
+        }
+    }
+
+    let rendered = statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<_>>()
+        .join(";\n");
+
+    Ok(Transpiled {
+        sql: rendered,
+        from: from.name().to_string(),
+        to: to.name().to_string(),
+        issues,
+    })
+}
+
+/// The sqlparser dialect for parsing SQL written for `backend`.
+pub fn parser_dialect(backend: Backend) -> Box<dyn sp::Dialect> {
+    match backend {
+        Backend::Postgres => Box::new(sp::PostgreSqlDialect {}),
+        Backend::Sqlite => Box::new(sp::SQLiteDialect {}),
+        Backend::DuckDb => Box::new(sp::DuckDbDialect {}),
+        Backend::MySql => Box::new(sp::MySqlDialect {}),
+    }
+}
+
+fn push_issue(issues: &mut Vec<TranspileIssue>, construct: &str, message: &str) {
+    let issue = TranspileIssue {
+        construct: construct.to_string(),
+        message: message.to_string(),
+    };
+    if !issues.contains(&issue) {
+        issues.push(issue);
+    }
+}
+
+/// Switches quoted identifiers to the target's quote character. Unquoted
+/// identifiers and string literals are left alone.
+struct Requoter {
+    quote: char,
+}
+
+impl Requoter {
+    fn requote(&self, ident: &mut Ident) {
+        if ident.quote_style.is_some() {
+            ident.quote_style = Some(self.quote);
+        }
+    }
+
+    fn requote_name(&self, name: &mut ObjectName) {
+        name.0.iter_mut().for_each(|ident| self.requote(ident));
+    }
+
+    fn requote_alias(&self, alias: &mut Option<TableAlias>) {
+        if let Some(alias) = alias {
+            self.requote(&mut alias.name);
+            for column in &mut alias.columns {
+                self.requote(&mut column.name);
+            }
+        }
+    }
+
+    fn requote_set_expr(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    match item {
+                        SelectItem::ExprWithAlias { alias, .. } => self.requote(alias),
+                        SelectItem::QualifiedWildcard(name, _) => self.requote_name(name),
+                        _ => {}
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.requote_set_expr(left);
+                self.requote_set_expr(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Requoter {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                self.requote(&mut cte.alias.name);
+                for column in &mut cte.alias.columns {
+                    self.requote(&mut column.name);
+                }
+            }
+        }
+        self.requote_set_expr(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<Self::Break> {
+        self.requote_name(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        match factor {
+            TableFactor::Table { alias, .. } | TableFactor::Derived { alias, .. } => {
+                self.requote_alias(alias)
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => self.requote(ident),
+            Expr::CompoundIdentifier(parts) => parts.iter_mut().for_each(|p| self.requote(p)),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<Self::Break> {
+        if let Statement::Insert(insert) = statement {
+            insert.columns.iter_mut().for_each(|c| self.requote(c));
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+struct Rewriter {
+    to: Backend,
+    target: Box<dyn sp::Dialect>,
+    issues: Vec<TranspileIssue>,
+}
+
+impl Rewriter {
+    fn parse_expr(&self, sql: &str) -> Option<Expr> {
+        Parser::new(self.target.as_ref())
+            .try_with_sql(sql)
+            .ok()?
+            .parse_expr()
+            .ok()
+    }
+
+    fn issue(&mut self, construct: &str, message: String) {
+        push_issue(&mut self.issues, construct, &message);
+    }
+
+    fn rewrite_function(&mut self, func: &mut Function) -> Option<Expr> {
+        let name = func.name.to_string().to_uppercase();
+        let features = self.to.dialect().features();
+
+        match name.as_str() {
+            "IFNULL" | "NVL" => {
+                func.name = ObjectName(vec![Ident::new("COALESCE")]);
+                None
+            }
+            "NOW" if self.to == Backend::Sqlite => self.parse_expr("CURRENT_TIMESTAMP"),
+            "DATE_ADD" | "DATE_SUB" if self.to != Backend::MySql => {
+                let args = unnamed_args(func)?;
+                let op = if name == "DATE_ADD" {
+                    BinaryOperator::Plus
+                } else {
+                    BinaryOperator::Minus
+                };
+                match (args.first(), args.get(1)) {
+                    (Some(expr), Some(Expr::Interval(interval))) => {
+                        self.rewrite_interval_arithmetic(expr, &op, interval)
+                    }
+                    _ => {
+                        self.issue(
+                            &name,
+                            format!("Unsupported {} arguments for {}", name, self.to.name()),
+                        );
+                        None
+                    }
+                }
+            }
+            "DATE_TRUNC" if !features.native_date_trunc => {
+                let args = unnamed_args(func)?;
+                let unit = match args.first() {
+                    Some(Expr::Value(Value::SingleQuotedString(unit))) => parse_unit(unit),
+                    _ => None,
+                };
+                match (unit, args.get(1)) {
+                    (Some(unit), Some(expr)) => {
+                        let sql = self.to.dialect().date_trunc(unit, &expr.to_string());
+                        self.parse_expr(&sql)
+                    }
+                    _ => {
+                        self.issue(
+                            "DATE_TRUNC",
+                            format!("Unsupported DATE_TRUNC arguments for {}", self.to.name()),
+                        );
+                        None
+                    }
+                }
+            }
+            "STRFTIME" | "DATE_FORMAT" | "TO_CHAR" if !has_native_formatter(&name, self.to) => {
+                self.issue(
+                    &name,
+                    format!(
+                        "Date formatting function {} has no direct equivalent in {}",
+                        name,
+                        self.to.name()
+                    ),
+                );
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn rewrite_interval_arithmetic(
+        &mut self,
+        left: &Expr,
+        op: &BinaryOperator,
+        interval: &Interval,
+    ) -> Option<Expr> {
+        let sign = match op {
+            BinaryOperator::Plus => 1,
+            BinaryOperator::Minus => -1,
+            _ => return None,
+        };
+        match interval_parts(interval) {
+            Some((amount, unit)) => {
+                let sql = self
+                    .to
+                    .dialect()
+                    .date_add(&left.to_string(), sign * amount, unit);
+                self.parse_expr(&sql)
+            }
+            None => {
+                self.issue(
+                    "INTERVAL",
+                    format!(
+                        "Cannot translate INTERVAL {} to {}",
+                        interval,
+                        self.to.name()
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn check_set_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                if matches!(select.distinct, Some(Distinct::On(_)))
+                    && !matches!(self.to, Backend::Postgres | Backend::DuckDb)
+                {
+                    self.issue(
+                        "DISTINCT ON",
+                        format!("{} does not support DISTINCT ON", self.to.name()),
+                    );
+                }
+                let full_outer = select.from.iter().any(|table| {
+                    table
+                        .joins
+                        .iter()
+                        .any(|join| matches!(join.join_operator, JoinOperator::FullOuter(_)))
+                });
+                if full_outer && !self.to.dialect().features().full_outer_join {
+                    self.issue(
+                        "FULL OUTER JOIN",
+                        format!("{} does not support FULL OUTER JOIN", self.to.name()),
+                    );
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check_set_expr(left);
+                self.check_set_expr(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Rewriter {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(fetch) = query.fetch.take() {
+            if fetch.with_ties || fetch.percent || query.limit.is_some() {
+                self.issue(
+                    "FETCH FIRST",
+                    "FETCH FIRST with PERCENT or WITH TIES cannot be expressed as LIMIT"
+                        .to_string(),
+                );
+                query.fetch = Some(fetch);
+            } else {
+                query.limit = Some(
+                    fetch
+                        .quantity
+                        .unwrap_or(Expr::Value(Value::Number("1".to_string(), false))),
+                );
+            }
+        }
+        self.check_set_expr(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<Self::Break> {
+        let returning = match statement {
+            Statement::Insert(insert) => insert.returning.is_some(),
+            Statement::Delete(delete) => delete.returning.is_some(),
+            Statement::Update { returning, .. } => returning.is_some(),
+            _ => false,
+        };
+        if returning && !self.to.dialect().features().returning {
+            self.issue(
+                "RETURNING",
+                format!("{} does not support RETURNING", self.to.name()),
+            );
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        let features = self.to.dialect().features();
+
+        let replacement = match expr {
+            Expr::Value(Value::DoubleQuotedString(value)) if self.to != Backend::MySql => Some(
+                Expr::Value(Value::SingleQuotedString(std::mem::take(value))),
+            ),
+            Expr::Cast { kind, .. } if *kind == CastKind::DoubleColon => {
+                if !features.double_colon_cast {
+                    *kind = CastKind::Cast;
+                }
+                None
+            }
+            Expr::ILike {
+                negated,
+                expr: inner,
+                pattern,
+                ..
+            } if !features.ilike => {
+                let not = if *negated { "NOT " } else { "" };
+                self.parse_expr(&format!("LOWER({}) {}LIKE LOWER({})", inner, not, pattern))
+            }
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::StringConcat,
+                right,
+            } if self.to == Backend::MySql => {
+                let sql = self
+                    .to
+                    .dialect()
+                    .concat(&[&left.to_string(), &right.to_string()]);
+                self.parse_expr(&sql)
+            }
+            Expr::BinaryOp { left, op, right } if matches!(right.as_ref(), Expr::Interval(_)) => {
+                let op = op.clone();
+                let left = left.as_ref().clone();
+                match right.as_ref() {
+                    Expr::Interval(interval) => {
+                        let interval = interval.clone();
+                        self.rewrite_interval_arithmetic(&left, &op, &interval)
+                    }
+                    _ => None,
+                }
+            }
+            Expr::BinaryOp {
+                left,
+                op:
+                    op @ (BinaryOperator::PGRegexMatch
+                    | BinaryOperator::PGRegexIMatch
+                    | BinaryOperator::PGRegexNotMatch
+                    | BinaryOperator::PGRegexNotIMatch),
+                right,
+            } => {
+                let negated = matches!(
+                    op,
+                    BinaryOperator::PGRegexNotMatch | BinaryOperator::PGRegexNotIMatch
+                );
+                let not = if negated { "NOT " } else { "" };
+                match self.to {
+                    Backend::Postgres => None,
+                    Backend::MySql => self.parse_expr(&format!("{} {}REGEXP {}", left, not, right)),
+                    Backend::DuckDb => {
+                        self.parse_expr(&format!("{}regexp_matches({}, {})", not, left, right))
+                    }
+                    Backend::Sqlite => {
+                        self.issue(
+                            "regex match",
+                            "SQLite has no built-in regular expression operator".to_string(),
+                        );
+                        None
+                    }
+                }
+            }
+            Expr::Function(func) => self.rewrite_function(func),
+            _ => None,
+        };
+
+        if let Some(replacement) = replacement {
+            *expr = replacement;
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn has_native_formatter(name: &str, backend: Backend) -> bool {
+    matches!(
+        (name, backend),
+        ("STRFTIME", Backend::Sqlite | Backend::DuckDb)
+            | ("DATE_FORMAT", Backend::MySql)
+            | ("TO_CHAR", Backend::Postgres)
+    )
+}
+
+fn unnamed_args(func: &Function) -> Option<Vec<Expr>> {
+    match &func.args {
+        FunctionArguments::List(list) => list
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn parse_unit(unit: &str) -> Option<DateUnit> {
+    match unit.trim().to_lowercase().trim_end_matches('s') {
+        "day" => Some(DateUnit::Day),
+        "week" => Some(DateUnit::Week),
+        "month" => Some(DateUnit::Month),
+        "quarter" => Some(DateUnit::Quarter),
+        "year" => Some(DateUnit::Year),
+        _ => None,
+    }
+}
+
+fn interval_parts(interval: &Interval) -> Option<(i64, DateUnit)> {
+    let value = match interval.value.as_ref() {
+        Expr::Value(Value::SingleQuotedString(value)) => value.clone(),
+        Expr::Value(Value::Number(value, _)) => value.clone(),
+        _ => return None,
+    };
+
+    match &interval.leading_field {
+        Some(field) => {
+            let unit = match field {
+                DateTimeField::Day => DateUnit::Day,
+                DateTimeField::Week(_) => DateUnit::Week,
+                DateTimeField::Month => DateUnit::Month,
+                DateTimeField::Quarter => DateUnit::Quarter,
+                DateTimeField::Year => DateUnit::Year,
+                _ => return None,
+            };
+            Some((value.trim().parse().ok()?, unit))
+        }
+        None => {
+            let mut parts = value.split_whitespace();
+            let amount = parts.next()?.parse().ok()?;
+            let unit = parse_unit(parts.next()?)?;
+            if parts.next().is_some() {
+                return None;
+            }
+            Some((amount, unit))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_dialect_round_trip() {
+        let result = transpile(
+            "SELECT id, name FROM users WHERE id = 1",
+            Backend::Postgres,
+            Backend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(result.sql, "SELECT id, name FROM users WHERE id = 1");
+        assert!(result.is_lossless());
+    }
+
+    #[test]
+    fn test_postgres_to_sqlite() {
+        let result = transpile(
+            "SELECT DATE_TRUNC('month', created_at) AS month, amount::integer FROM orders WHERE email ILIKE '%@example.com' AND created_at > NOW() - INTERVAL '7 days'",
+            Backend::Postgres,
+            Backend::Sqlite,
+        )
+        .unwrap();
+
+        assert!(result.sql.contains("DATE(created_at, 'start of month')"));
+        assert!(result.sql.contains("CAST(amount AS INTEGER)"));
+        assert!(result
+            .sql
+            .contains("LOWER(email) LIKE LOWER('%@example.com')"));
+        assert!(result
+            .sql
+            .contains("DATETIME(CURRENT_TIMESTAMP, '-7 days')"));
+        assert!(result.is_lossless(), "{:?}", result.issues);
+    }
+
+    #[test]
+    fn test_postgres_to_mysql() {
+        let result = transpile(
+            "SELECT \"first\" || ' ' || \"last\" AS full_name FROM \"user\" FETCH FIRST 5 ROWS ONLY",
+            Backend::Postgres,
+            Backend::MySql,
+        )
+        .unwrap();
+
+        assert!(result.sql.contains("CONCAT(CONCAT(`first`, ' '), `last`)"));
+        assert!(result.sql.contains("FROM `user`"));
+        assert!(result.sql.ends_with("LIMIT 5"));
+    }
+
+    #[test]
+    fn test_mysql_to_postgres() {
+        let result = transpile(
+            "SELECT IFNULL(`name`, \"unknown\") FROM users WHERE created_at > DATE_SUB(NOW(), INTERVAL 7 DAY) OR updated_at > created_at + INTERVAL 1 MONTH",
+            Backend::MySql,
+            Backend::Postgres,
+        )
+        .unwrap();
+
+        assert!(result.sql.contains("COALESCE(\"name\", 'unknown')"));
+        assert!(result.sql.contains("NOW() + INTERVAL '-7 day'"));
+        assert!(result.sql.contains("created_at + INTERVAL '1 month'"));
+    }
+
+    #[test]
+    fn test_string_literals_survive_requoting() {
+        let result = transpile(
+            "SELECT \"name\" FROM \"user\" WHERE last = 'O''Brien' AND note = 'say \"hi\"'",
+            Backend::Postgres,
+            Backend::MySql,
+        )
+        .unwrap();
+        assert_eq!(
+            result.sql,
+            "SELECT `name` FROM `user` WHERE last = 'O''Brien' AND note = 'say \"hi\"'"
+        );
+
+        let result = transpile(
+            "SELECT 'it''s' || name FROM t",
+            Backend::Postgres,
+            Backend::MySql,
+        )
+        .unwrap();
+        assert!(
+            result.sql.contains("CONCAT('it''s', name)"),
+            "{}",
+            result.sql
+        );
+
+        let result = transpile(
+            "SELECT `id` AS `key`, \"unknown\" FROM t",
+            Backend::MySql,
+            Backend::MySql,
+        )
+        .unwrap();
+        assert!(result.sql.contains("\"unknown\""));
+
+        let result = transpile(
+            "SELECT \"it's\", `a b` FROM t",
+            Backend::MySql,
+            Backend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(result.sql, "SELECT 'it''s', \"a b\" FROM t");
+    }
+
+    #[test]
+    fn test_untranslatable_constructs_are_reported() {
+        let result = transpile(
+            "SELECT DISTINCT ON (user_id) user_id FROM events a FULL OUTER JOIN users b ON a.user_id = b.id WHERE name ~ '^a'",
+            Backend::Postgres,
+            Backend::MySql,
+        )
+        .unwrap();
+        let constructs: Vec<&str> = result.issues.iter().map(|i| i.construct.as_str()).collect();
+        assert!(constructs.contains(&"DISTINCT ON"));
+        assert!(constructs.contains(&"FULL OUTER JOIN"));
+        assert!(result.sql.contains("name REGEXP '^a'"));
+
+        let result = transpile(
+            "SELECT 1 WHERE 'a' ~ 'b'",
+            Backend::Postgres,
+            Backend::Sqlite,
+        )
+        .unwrap();
+        assert!(!result.is_lossless());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(
+            transpile("SELEC FROM", Backend::Postgres, Backend::Sqlite),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -6,9 +6,12 @@ use tracing::{error, info};
 
 use crate::error::Error;
 use crate::models::{Action, Workflow};
+use warehouse_conn::{ResultCache, WarehouseRegistry};
 
 pub struct WorkflowEngine {
     workflows: Arc<RwLock<HashMap<String, Workflow>>>,
+    query_handler: Option<Arc<dyn QueryHandler>>,
+    result_cache: Option<Arc<ResultCache>>,
 }
 
 #[async_trait]
@@ -25,9 +28,21 @@ impl WorkflowEngine {
     pub fn new() -> Self {
         Self {
             workflows: Arc::new(RwLock::new(HashMap::new())),
+            query_handler: None,
+            result_cache: None,
         }
     }
 
+    pub fn with_query_handler(mut self, handler: Arc<dyn QueryHandler>) -> Self {
+        self.query_handler = Some(handler);
+        self
+    }
+
+    pub fn with_result_cache(mut self, cache: Arc<ResultCache>) -> Self {
+        self.result_cache = Some(cache);
+        self
+    }
+
     pub async fn register(&self, workflow: Workflow) -> Result<(), Error> {
         let name = workflow.definition.name.clone();
         let mut workflows = self.workflows.write().await;
@@ -53,6 +68,37 @@ impl WorkflowEngine {
         self.execute_workflow(&workflow).await
     }
 
+    /// Runs every enabled workflow with an `event` trigger matching `event`,
+    /// e.g. `schema.drift`. Returns the names of the workflows that ran.
+    pub async fn trigger_event(&self, event: &str) -> Vec<String> {
+        let matching: Vec<Workflow> = self
+            .workflows
+            .read()
+            .await
+            .values()
+            .filter(|w| {
+                w.enabled
+                    && w.definition.trigger.trigger_type == "event"
+                    && w.definition.trigger.event.as_deref() == Some(event)
+            })
+            .cloned()
+            .collect();
+
+        let mut triggered = Vec::new();
+        for workflow in matching {
+            info!(
+                "Event '{}' triggered workflow: {}",
+                event, workflow.definition.name
+            );
+            if let Err(e) = self.execute_workflow(&workflow).await {
+                error!("Workflow {} failed: {}", workflow.definition.name, e);
+            }
+            triggered.push(workflow.definition.name);
+        }
+        triggered.sort();
+        triggered
+    }
+
     pub async fn execute_workflow(&self, workflow: &Workflow) -> Result<String, Error> {
         info!("Executing workflow: {}", workflow.definition.name);
 
@@ -86,10 +132,13 @@ impl WorkflowEngine {
 
     async fn execute_action(&self, action: &Action) -> Result<String, String> {
         match action {
-            Action::Query { sql, database: _ } => Ok(format!(
-                "Query handler not configured. Would execute: {}",
-                sql
-            )),
+            Action::Query { sql, database } => match &self.query_handler {
+                Some(handler) => handler.execute(sql, database.as_deref()).await,
+                None => Ok(format!(
+                    "Query handler not configured. Would execute: {}",
+                    sql
+                )),
+            },
             Action::Transform { input, script } => {
                 Ok(format!("Transform: {} with {}", input, script))
             }
@@ -101,10 +150,45 @@ impl WorkflowEngine {
                 tokio::time::sleep(tokio::time::Duration::from_secs(*duration)).await;
                 Ok(format!("Slept for {} seconds", duration))
             }
+            Action::InvalidateCache { tables, database } => {
+                let Some(cache) = &self.result_cache else {
+                    return Ok("Result cache not configured".to_string());
+                };
+                let mut removed = 0;
+                match (tables.is_empty(), database) {
+                    (false, Some(database)) => {
+                        for table in tables {
+                            removed += cache.invalidate_table_on(database, table).await;
+                        }
+                    }
+                    (false, None) => {
+                        for table in tables {
+                            removed += cache.invalidate_table(table).await;
+                        }
+                    }
+                    (true, Some(database)) => {
+                        removed = cache.invalidate_connection(database).await;
+                    }
+                    (true, None) => {
+                        removed = cache.stats().await.entries;
+                        cache.clear().await;
+                    }
+                }
+                Ok(format!("Invalidated {} cached results", removed))
+            }
         }
     }
 }
 
+#[async_trait]
+impl QueryHandler for WarehouseRegistry {
+    async fn execute(&self, sql: &str, database: Option<&str>) -> Result<String, String> {
+        let warehouse = self.get(database).map_err(|e| e.to_string())?;
+        let result = warehouse.execute(sql).await.map_err(|e| e.to_string())?;
+        serde_json::to_string(&result).map_err(|e| e.to_string())
+    }
+}
+
 impl Default for WorkflowEngine {
     fn default() -> Self {
         Self::new()
@@ -172,4 +256,93 @@ steps:
         let result = engine.execute("test-workflow").await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_query_step_uses_named_database() {
+        let config = warehouse_conn::RegistryConfig::single("analytics", "sqlite::memory:");
+        let registry = WarehouseRegistry::from_config(&config).unwrap();
+        let engine = WorkflowEngine::new().with_query_handler(Arc::new(registry));
+
+        let yaml = r#"
+name: query-workflow
+version: "1.0"
+trigger:
+  type: manual
+steps:
+  - name: count
+    action:
+      type: query
+      sql: SELECT 42 AS answer
+      database: analytics
+  - name: missing
+    action:
+      type: query
+      sql: SELECT 1
+      database: finance
+    on_error: notify
+"#;
+
+        let definition = crate::models::WorkflowDefinition::from_yaml(yaml).unwrap();
+        engine.register(Workflow::new(definition)).await.unwrap();
+
+        let output = engine.execute("query-workflow").await.unwrap();
+        assert!(output.contains("42"));
+        assert!(output.contains("missing: Error handled by 'notify'"));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_cache_step() {
+        let cache = Arc::new(ResultCache::new(std::time::Duration::from_secs(60)));
+        let result = warehouse_conn::QueryResult {
+            columns: vec![],
+            rows: vec![],
+            row_count: 0,
+        };
+        cache
+            .put("analytics", "SELECT * FROM orders", &result)
+            .await;
+        cache.put("analytics", "SELECT * FROM users", &result).await;
+        cache.put("billing", "SELECT * FROM orders", &result).await;
+        let engine = WorkflowEngine::new().with_result_cache(cache.clone());
+
+        let yaml = r#"
+name: reload-orders
+version: "1.0"
+trigger:
+  type: manual
+steps:
+  - name: invalidate
+    action:
+      type: invalidate_cache
+      tables: [orders]
+      database: analytics
+"#;
+        let definition = crate::models::WorkflowDefinition::from_yaml(yaml).unwrap();
+        engine.register(Workflow::new(definition)).await.unwrap();
+
+        let output = engine.execute("reload-orders").await.unwrap();
+        assert!(output.contains("Invalidated 1 cached results"));
+        assert_eq!(cache.stats().await.entries, 2);
+        assert!(cache.get("billing", "SELECT * FROM orders").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_trigger_event() {
+        let engine = WorkflowEngine::new();
+        for (name, trigger) in [
+            ("on-drift", "type: event\n  event: schema.drift"),
+            ("on-sync", "type: event\n  event: schema.synced"),
+            ("manual", "type: manual"),
+        ] {
+            let yaml = format!(
+                "name: {}\nversion: \"1.0\"\ntrigger:\n  {}\nsteps:\n  - name: wait\n    action:\n      type: sleep\n      duration: 0\n",
+                name, trigger
+            );
+            let definition = crate::models::WorkflowDefinition::from_yaml(&yaml).unwrap();
+            engine.register(Workflow::new(definition)).await.unwrap();
+        }
+
+        assert_eq!(engine.trigger_event("schema.drift").await, vec!["on-drift"]);
+        assert!(engine.trigger_event("unknown").await.is_empty());
+    }
 }
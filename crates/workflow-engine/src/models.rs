@@ -39,6 +39,14 @@ pub enum Action {
     Notify { channel: String, message: String },
     #[serde(rename = "sleep")]
     Sleep { duration: u64 },
+    /// Drops cached results reading `tables`, limited to the `database`
+    /// connection when both are set; with neither, clears the whole cache.
+    #[serde(rename = "invalidate_cache")]
+    InvalidateCache {
+        #[serde(default)]
+        tables: Vec<String>,
+        database: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]